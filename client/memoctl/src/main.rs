@@ -1,18 +1,34 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
+use memo_app::app::events::RESET_EVENT;
+use memo_app::app::model::{
+    CreateShareLinkInput, CreatedShareLink, GraphFormat, GraphQuery, LoginOutput,
+    LoginTwoFactorInput, LogoutInput, NoteDiff, NoteDiffQuery, NoteEventMessage, NotePage,
+    RefreshInput, SearchNotesQuery, ShareNoteInput, TwoFactorChallenge,
+};
 use memo_app::client::HttpClient;
-use memo_app::domain::model::Note;
+use memo_app::domain::diff::DiffOp;
+use memo_app::domain::graph::NoteGraph;
+use memo_app::domain::model::{
+    Attachment, Note, NoteLink, NoteRevisionSummary, NoteSearchHit, NoteShare, NoteSharing,
+    Notebook, SharePermission, TagCount, TrashedNote,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 struct Config {
     api_base: String,
     token: Option<String>,
+    #[serde(default)]
+    refresh_token: Option<String>,
 }
 
 fn load_cfg() -> Config {
     confy::load("memoctl", None).unwrap_or_else(|_| Config {
         api_base: std::env::var("MEMO_API_BASE").unwrap_or_else(|_| "http://localhost:8080".into()),
         token: None,
+        refresh_token: None,
     })
 }
 
@@ -40,20 +56,172 @@ enum Command {
         email: String,
         #[arg(short, long)]
         password: String,
+        /// 2 段階認証のコード（認証アプリの 6 桁のコード、またはリカバリーコード）
+        #[arg(long)]
+        otp: Option<String>,
     },
+    /// 保存したリフレッシュトークンでアクセストークンを更新する
+    Refresh,
+    /// トークンを失効させ、保存したトークンを削除する
+    Logout,
     Note {
         #[command(subcommand)]
         command: NoteCommand,
     },
+    Tag {
+        #[command(subcommand)]
+        command: TagCommand,
+    },
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+    Notebook {
+        #[command(subcommand)]
+        command: NotebookCommand,
+    },
+    /// 自分のメモのリンクと共通のタグのグラフを出力する
+    Graph {
+        /// json または dot（Graphviz）
+        #[arg(long, default_value = "json")]
+        format: String,
+        /// このメモの周辺だけを出力する
+        #[arg(long)]
+        note: Option<i64>,
+        /// `--note` からたどる深さ（既定 1）
+        #[arg(long)]
+        depth: Option<u32>,
+    },
+    /// メモの変更を受け取って表示し続ける（Ctrl-C で終了）
+    Events {
+        /// この ID の次の変更から受け取る
+        #[arg(long)]
+        last_event_id: Option<u64>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
-enum NoteCommand {
+enum NotebookCommand {
+    /// ノートブックを階層で表示する
+    Tree {
+        /// 各ノートブックに入っているメモも表示する
+        #[arg(long)]
+        notes: bool,
+    },
+    Create {
+        #[arg(short, long)]
+        name: String,
+        /// 親のノートブックの ID（省略時はトップレベル）
+        #[arg(long)]
+        parent: Option<i64>,
+    },
+    Rename {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(short, long)]
+        name: String,
+    },
+    /// 別のノートブックの下に移す（--parent を省略するとトップレベルに移す）
+    Move {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        parent: Option<i64>,
+    },
+    /// ノートブックを削除する（中身は親に移す。--cascade なら子孫ごと削除してメモをゴミ箱へ）
+    Delete {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        cascade: bool,
+    },
+    /// ノートブックに入っているメモの一覧
+    Notes {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        limit: Option<i64>,
+        /// 前回出力された next_cursor
+        #[arg(long)]
+        cursor: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashCommand {
+    /// ゴミ箱内のメモ一覧
+    List,
+    /// ゴミ箱からメモを戻す
+    Restore {
+        #[arg(short, long)]
+        id: i64,
+    },
+    /// ゴミ箱内のメモを完全に削除する
+    Purge {
+        #[arg(short, long)]
+        id: i64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TagCommand {
+    /// 自分のタグ一覧（メモの件数付き）
     List,
+}
+
+#[derive(Subcommand, Debug)]
+enum NoteCommand {
+    List {
+        #[arg(long)]
+        limit: Option<i64>,
+        /// 前回出力された next_cursor
+        #[arg(long)]
+        cursor: Option<String>,
+        /// 作成者のユーザー ID で絞り込む
+        #[arg(long)]
+        author: Option<i64>,
+        /// created_at | updated_at | title
+        #[arg(long)]
+        sort: Option<String>,
+        /// asc | desc
+        #[arg(long)]
+        order: Option<String>,
+        /// タグで絞り込む（複数指定可）
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// all | any（既定 all）
+        #[arg(long)]
+        tag_mode: Option<String>,
+        /// ノートブックの ID で絞り込む
+        #[arg(long)]
+        notebook: Option<i64>,
+        /// スターを付けたメモだけを表示する
+        #[arg(long)]
+        starred: bool,
+        /// アーカイブしたメモだけを表示する
+        #[arg(long)]
+        archived: bool,
+    },
+    /// メモを表示する（--html ならサーバーで HTML に変換した本文を表示する）
+    Show {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        html: bool,
+    },
     Create {
         #[arg(short, long)]
         title: String,
         content: String,
+        /// private | unlisted | public（省略時は private）
+        #[arg(long)]
+        visibility: Option<String>,
+        /// 付けるタグ（複数指定可）
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// 入れるノートブックの ID
+        #[arg(long)]
+        notebook: Option<i64>,
     },
     Update {
         #[arg(short, long)]
@@ -61,11 +229,150 @@ enum NoteCommand {
         #[arg(short, long)]
         title: Option<String>,
         content: Option<String>,
+        /// private | unlisted | public
+        #[arg(long)]
+        visibility: Option<String>,
+        /// タグを置き換える（複数指定可）
+        #[arg(long = "tag")]
+        tags: Option<Vec<String>>,
     },
     Delete {
         #[arg(short, long)]
         id: i64,
     },
+    /// タイトル・本文を全文検索する
+    Search {
+        query: String,
+        #[arg(long)]
+        limit: Option<i64>,
+    },
+    /// 変更履歴（リビジョン一覧）を表示する
+    History {
+        #[arg(short, long)]
+        id: i64,
+    },
+    /// 本文中のリンク（[[タイトル]] / [[#ID]]）を表示する（--dangling ならリンク切れだけ）
+    Links {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        dangling: bool,
+    },
+    /// このメモにリンクしているメモを表示する
+    Backlinks {
+        #[arg(short, long)]
+        id: i64,
+    },
+    /// ファイルを添付する
+    Attach {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(short, long)]
+        file: PathBuf,
+    },
+    /// 添付ファイルの一覧を表示する
+    Attachments {
+        #[arg(short, long)]
+        id: i64,
+    },
+    /// 添付ファイルをダウンロードする（--out を省略すると元のファイル名で保存する）
+    Download {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        attachment: i64,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// 添付ファイルを削除する
+    Detach {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        attachment: i64,
+    },
+    /// 2 つのリビジョン間の本文の差分を表示する
+    Diff {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        from: i64,
+        #[arg(long)]
+        to: i64,
+    },
+    /// リビジョンの内容にメモを戻す
+    Restore {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        rev: i64,
+    },
+    /// ユーザーとメモを共有する（--email）、または共有リンクを作成する（--link）
+    #[command(group(ArgGroup::new("target").required(true).args(["email", "link"])))]
+    Share {
+        #[arg(short, long)]
+        id: i64,
+        /// 共有するユーザーのメールアドレス
+        #[arg(short, long)]
+        email: Option<String>,
+        /// 共有リンクを作成する
+        #[arg(long)]
+        link: bool,
+        /// read | write（既定 read）
+        #[arg(long, default_value = "read")]
+        permission: String,
+        /// 共有リンクの有効期限（日数、1〜365。省略時は無期限）
+        #[arg(long, requires = "link")]
+        expires_in_days: Option<i64>,
+        /// 共有リンクのパスワード
+        #[arg(long, requires = "link")]
+        password: Option<String>,
+    },
+    /// 共有をやめる（--user）、または共有リンクを取り消す（--link）
+    #[command(group(ArgGroup::new("target").required(true).args(["user", "link"])))]
+    Unshare {
+        #[arg(short, long)]
+        id: i64,
+        /// 共有をやめるユーザーの ID
+        #[arg(short, long)]
+        user: Option<i64>,
+        /// 取り消す共有リンクの ID
+        #[arg(long)]
+        link: Option<i64>,
+    },
+    /// 共有しているユーザーと共有リンクの一覧
+    Shares {
+        #[arg(short, long)]
+        id: i64,
+    },
+    /// メモをノートブックに入れる（--notebook を省略するとノートブックから出す）
+    File {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        notebook: Option<i64>,
+    },
+    /// メモを固定して一覧の先頭に表示する（--off で外す）
+    Pin {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        off: bool,
+    },
+    /// メモにスターを付ける（--off で外す）
+    Star {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        off: bool,
+    },
+    /// メモをアーカイブして一覧から隠す（--off で戻す）
+    Archive {
+        #[arg(short, long)]
+        id: i64,
+        #[arg(long)]
+        off: bool,
+    },
 }
 
 #[actix_rt::main]
@@ -95,13 +402,17 @@ async fn main() {
                 .expect("request failed");
             println!("{} {}", status, text);
         }
-        Command::Login { email, password } => {
+        Command::Login {
+            email,
+            password,
+            otp,
+        } => {
             #[derive(Serialize)]
             struct Body<'a> {
                 email: &'a str,
                 password: &'a str,
             }
-            let (status, text) = http
+            let (mut status, mut text) = http
                 .post_json(
                     "/auth/login",
                     &Body {
@@ -112,36 +423,174 @@ async fn main() {
                 )
                 .await
                 .expect("request failed");
+            if status == 202
+                && let Ok(challenge) = serde_json::from_str::<TwoFactorChallenge>(&text)
+            {
+                let Some(code) = otp else {
+                    eprintln!("Two-factor authentication is enabled. Re-run with --otp <code>.");
+                    return;
+                };
+                (status, text) = http
+                    .post_json(
+                        "/auth/login/2fa",
+                        &LoginTwoFactorInput {
+                            challenge_token: challenge.challenge_token,
+                            code,
+                        },
+                        None,
+                    )
+                    .await
+                    .expect("request failed");
+            }
             if status == 200
-                && let Ok(v) = serde_json::from_str::<serde_json::Value>(&text)
-                && let Some(token) = v.get("token").and_then(|t| t.as_str())
+                && let Ok(out) = serde_json::from_str::<LoginOutput>(&text)
             {
-                cfg.token = Some(token.to_string());
+                cfg.token = Some(out.token);
+                cfg.refresh_token = Some(out.refresh_token);
                 store_cfg(&cfg);
                 println!("Logged in. Token saved.");
                 return;
             }
             println!("{} {}", status, text);
         }
+        Command::Refresh => {
+            let Some(refresh_token) = cfg.refresh_token.clone() else {
+                eprintln!("Not logged in.");
+                return;
+            };
+            let (status, text) = http
+                .post_json("/auth/refresh", &RefreshInput { refresh_token }, None)
+                .await
+                .expect("request failed");
+            if status == 200
+                && let Ok(out) = serde_json::from_str::<LoginOutput>(&text)
+            {
+                cfg.token = Some(out.token);
+                cfg.refresh_token = Some(out.refresh_token);
+                store_cfg(&cfg);
+                println!("Token refreshed.");
+                return;
+            }
+            println!("{} {}", status, text);
+        }
+        Command::Logout => {
+            let (status, text) = http
+                .post_json(
+                    "/auth/logout",
+                    &LogoutInput {
+                        refresh_token: cfg.refresh_token.clone(),
+                    },
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            cfg.token = None;
+            cfg.refresh_token = None;
+            store_cfg(&cfg);
+            println!("{} {}", status, text);
+        }
         Command::Note {
-            command: NoteCommand::List,
+            command:
+                NoteCommand::List {
+                    limit,
+                    cursor,
+                    author,
+                    sort,
+                    order,
+                    tags,
+                    tag_mode,
+                    notebook,
+                    starred,
+                    archived,
+                },
         } => {
-            let notes: Vec<Note> = http
-                .get_json("/notes", cfg.token.as_deref())
+            let mut params: Vec<(&str, String)> = vec![];
+            if let Some(limit) = limit {
+                params.push(("limit", limit.to_string()));
+            }
+            if let Some(cursor) = cursor {
+                params.push(("cursor", cursor));
+            }
+            if let Some(author) = author {
+                params.push(("author", author.to_string()));
+            }
+            if let Some(sort) = sort {
+                params.push(("sort", sort));
+            }
+            if let Some(order) = order {
+                params.push(("order", order));
+            }
+            for tag in tags {
+                params.push(("tag", tag));
+            }
+            if let Some(tag_mode) = tag_mode {
+                params.push(("tag_mode", tag_mode));
+            }
+            if let Some(notebook) = notebook {
+                params.push(("notebook", notebook.to_string()));
+            }
+            if starred {
+                params.push(("starred", "true".into()));
+            }
+            if archived {
+                params.push(("archived", "true".into()));
+            }
+            let page: NotePage = http
+                .get_json_with_query("/notes", &params, cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&page.items).unwrap_or_default()
+            );
+            if let Some(next) = page.next_cursor {
+                eprintln!("next_cursor: {}", next);
+            }
+        }
+        Command::Note {
+            command: NoteCommand::Show { id, html: true },
+        } => {
+            let (status, text) = http
+                .get(&format!("/notes/{}?format=html", id), cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            if status == 200 {
+                print!("{}", text);
+            } else {
+                println!("{} {}", status, text);
+            }
+        }
+        Command::Note {
+            command: NoteCommand::Show { id, html: false },
+        } => {
+            let note: Note = http
+                .get_json(&format!("/notes/{}", id), cfg.token.as_deref())
                 .await
                 .expect("request failed");
             println!(
                 "{}",
-                serde_json::to_string_pretty(&notes).unwrap_or_default()
+                serde_json::to_string_pretty(&note).unwrap_or_default()
             );
         }
         Command::Note {
-            command: NoteCommand::Create { title, content },
+            command:
+                NoteCommand::Create {
+                    title,
+                    content,
+                    visibility,
+                    tags,
+                    notebook,
+                },
         } => {
             #[derive(Serialize)]
             struct Body<'a> {
                 title: &'a str,
                 content: &'a str,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                visibility: Option<&'a str>,
+                tags: &'a [String],
+                #[serde(skip_serializing_if = "Option::is_none")]
+                notebook_id: Option<i64>,
             }
             let note: Note = http
                 .post_json_typed(
@@ -149,6 +598,9 @@ async fn main() {
                     &Body {
                         title: &title,
                         content: &content,
+                        visibility: visibility.as_deref(),
+                        tags: &tags,
+                        notebook_id: notebook,
                     },
                     cfg.token.as_deref(),
                 )
@@ -160,12 +612,21 @@ async fn main() {
             );
         }
         Command::Note {
-            command: NoteCommand::Update { id, title, content },
+            command:
+                NoteCommand::Update {
+                    id,
+                    title,
+                    content,
+                    visibility,
+                    tags,
+                },
         } => {
             #[derive(Serialize)]
             struct Body<'a> {
                 title: Option<&'a str>,
                 content: Option<&'a str>,
+                visibility: Option<&'a str>,
+                tags: Option<&'a [String]>,
             }
             let note: Note = http
                 .put_json_typed(
@@ -173,6 +634,8 @@ async fn main() {
                     &Body {
                         title: title.as_deref(),
                         content: content.as_deref(),
+                        visibility: visibility.as_deref(),
+                        tags: tags.as_deref(),
                     },
                     cfg.token.as_deref(),
                 )
@@ -192,5 +655,618 @@ async fn main() {
                 .expect("request failed");
             println!("{} {}", status, text);
         }
+        Command::Note {
+            command: NoteCommand::Search { query, limit },
+        } => {
+            let hits: Vec<NoteSearchHit> = http
+                .get_json_with_query(
+                    "/notes/search",
+                    &SearchNotesQuery { q: query, limit },
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            for hit in hits {
+                println!("#{} {} ({:.3})", hit.note.id, hit.note.title, hit.rank);
+                println!("    {}", hit.snippet.replace('\n', " "));
+            }
+        }
+        Command::Note {
+            command: NoteCommand::History { id },
+        } => {
+            let revisions: Vec<NoteRevisionSummary> = http
+                .get_json(&format!("/notes/{}/revisions", id), cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            for rev in revisions {
+                println!("{}\t{}\t{}", rev.revision, rev.created_at, rev.title);
+            }
+        }
+        Command::Note {
+            command: NoteCommand::Links { id, dangling },
+        } => {
+            let links: Vec<NoteLink> = http
+                .get_json_with_query(
+                    &format!("/notes/{}/links", id),
+                    &[("dangling", dangling)],
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            for link in links {
+                match (link.note_id, link.title) {
+                    (Some(note_id), Some(title)) => {
+                        println!("[[{}]]\t#{} {}", link.target, note_id, title)
+                    }
+                    _ => println!("[[{}]]\t(リンク切れ)", link.target),
+                }
+            }
+        }
+        Command::Note {
+            command: NoteCommand::Backlinks { id },
+        } => {
+            let notes: Vec<Note> = http
+                .get_json(&format!("/notes/{}/backlinks", id), cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            for note in notes {
+                println!("#{}\t{}", note.id, note.title);
+            }
+        }
+        Command::Note {
+            command: NoteCommand::Attach { id, file },
+        } => {
+            let data = match std::fs::read(&file) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("failed to read {}: {}", file.display(), e);
+                    return;
+                }
+            };
+            let filename = file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let (status, text) = http
+                .post_file(
+                    &format!("/notes/{}/attachments", id),
+                    "file",
+                    &filename,
+                    &data,
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!("{} {}", status, text);
+        }
+        Command::Note {
+            command: NoteCommand::Attachments { id },
+        } => {
+            let attachments: Vec<Attachment> = http
+                .get_json(&format!("/notes/{}/attachments", id), cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            for attachment in attachments {
+                let dimensions = match (attachment.width, attachment.height) {
+                    (Some(width), Some(height)) => format!("{}x{}", width, height),
+                    _ => "-".to_string(),
+                };
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    attachment.id,
+                    attachment.size,
+                    attachment.content_type,
+                    dimensions,
+                    attachment.filename
+                );
+            }
+        }
+        Command::Note {
+            command:
+                NoteCommand::Download {
+                    id,
+                    attachment,
+                    out,
+                },
+        } => {
+            let path = format!("/notes/{}/attachments/{}", id, attachment);
+            let out = match out {
+                Some(out) => out,
+                None => {
+                    let attachments: Vec<Attachment> = http
+                        .get_json(&format!("/notes/{}/attachments", id), cfg.token.as_deref())
+                        .await
+                        .expect("request failed");
+                    match attachments.into_iter().find(|a| a.id == attachment) {
+                        Some(a) => PathBuf::from(a.filename),
+                        None => {
+                            eprintln!("attachment not found.");
+                            return;
+                        }
+                    }
+                }
+            };
+            let (status, data) = http
+                .get_bytes(&path, cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            if status != 200 {
+                println!("{}", status);
+                return;
+            }
+            match std::fs::write(&out, &data) {
+                Ok(()) => println!("saved {} ({} bytes)", out.display(), data.len()),
+                Err(e) => eprintln!("failed to write {}: {}", out.display(), e),
+            }
+        }
+        Command::Note {
+            command: NoteCommand::Detach { id, attachment },
+        } => {
+            let (status, text) = http
+                .delete(
+                    &format!("/notes/{}/attachments/{}", id, attachment),
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!("{} {}", status, text);
+        }
+        Command::Note {
+            command: NoteCommand::Diff { id, from, to },
+        } => {
+            let diff: NoteDiff = http
+                .get_json_with_query(
+                    &format!("/notes/{}/diff", id),
+                    &NoteDiffQuery { from, to },
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!("--- r{} {}", diff.from, diff.from_title);
+            println!("+++ r{} {}", diff.to, diff.to_title);
+            for line in diff.lines {
+                let prefix = match line.op {
+                    DiffOp::Equal => ' ',
+                    DiffOp::Insert => '+',
+                    DiffOp::Delete => '-',
+                };
+                println!("{}{}", prefix, line.text);
+            }
+        }
+        Command::Note {
+            command: NoteCommand::Restore { id, rev },
+        } => {
+            let note: Note = http
+                .post_json_typed(
+                    &format!("/notes/{}/revisions/{}/restore", id, rev),
+                    &(),
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&note).unwrap_or_default()
+            );
+        }
+        Command::Note {
+            command:
+                NoteCommand::Share {
+                    id,
+                    email,
+                    link,
+                    permission,
+                    expires_in_days,
+                    password,
+                },
+        } => {
+            let Ok(permission) = SharePermission::try_from(permission) else {
+                eprintln!("--permission must be read or write.");
+                return;
+            };
+            if link {
+                let created: CreatedShareLink = http
+                    .post_json_typed(
+                        &format!("/notes/{}/share-links", id),
+                        &CreateShareLinkInput {
+                            permission,
+                            expires_in_days,
+                            password,
+                        },
+                        cfg.token.as_deref(),
+                    )
+                    .await
+                    .expect("request failed");
+                println!(
+                    "{}/notes/{}?share={}",
+                    http.base_url.trim_end_matches('/'),
+                    id,
+                    created.token
+                );
+                eprintln!(
+                    "link id: {} (the token is shown only once)",
+                    created.link.id
+                );
+            } else if let Some(email) = email {
+                let share: NoteShare = http
+                    .post_json_typed(
+                        &format!("/notes/{}/shares", id),
+                        &ShareNoteInput { email, permission },
+                        cfg.token.as_deref(),
+                    )
+                    .await
+                    .expect("request failed");
+                println!(
+                    "{}\t{}\t{}",
+                    share.user_id,
+                    share.permission.as_str(),
+                    share.email
+                );
+            }
+        }
+        Command::Note {
+            command: NoteCommand::Unshare { id, user, link },
+        } => {
+            let path = match (user, link) {
+                (Some(user_id), _) => format!("/notes/{}/shares/{}", id, user_id),
+                (None, Some(link_id)) => format!("/notes/{}/share-links/{}", id, link_id),
+                (None, None) => unreachable!("clap requires --user or --link"),
+            };
+            let (status, text) = http
+                .delete(&path, cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            println!("{} {}", status, text);
+        }
+        Command::Note {
+            command: NoteCommand::Shares { id },
+        } => {
+            let sharing: NoteSharing = http
+                .get_json(&format!("/notes/{}/shares", id), cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            for share in sharing.users {
+                println!(
+                    "user #{}\t{}\t{}",
+                    share.user_id,
+                    share.permission.as_str(),
+                    share.email
+                );
+            }
+            for link in sharing.links {
+                let expires = link
+                    .expires_at
+                    .map_or_else(|| "never".to_string(), |at| at.to_string());
+                let password = if link.has_password { "password" } else { "-" };
+                println!(
+                    "link #{}\t{}\texpires: {}\t{}",
+                    link.id,
+                    link.permission.as_str(),
+                    expires,
+                    password
+                );
+            }
+        }
+        Command::Note {
+            command: NoteCommand::File { id, notebook },
+        } => {
+            #[derive(Serialize)]
+            struct Body {
+                notebook_id: Option<i64>,
+            }
+            let note: Note = http
+                .put_json_typed(
+                    &format!("/notes/{}/notebook", id),
+                    &Body {
+                        notebook_id: notebook,
+                    },
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&note).unwrap_or_default()
+            );
+        }
+        Command::Note {
+            command: NoteCommand::Pin { id, off },
+        } => set_note_state(&http, cfg.token.as_deref(), id, "pin", off).await,
+        Command::Note {
+            command: NoteCommand::Star { id, off },
+        } => set_note_state(&http, cfg.token.as_deref(), id, "star", off).await,
+        Command::Note {
+            command: NoteCommand::Archive { id, off },
+        } => set_note_state(&http, cfg.token.as_deref(), id, "archive", off).await,
+        Command::Notebook {
+            command: NotebookCommand::Tree { notes },
+        } => {
+            let notebooks: Vec<Notebook> = http
+                .get_json("/notebooks", cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            let mut contents: HashMap<i64, Vec<Note>> = HashMap::new();
+            if notes {
+                for notebook in &notebooks {
+                    let page: NotePage = http
+                        .get_json_with_query(
+                            &format!("/notebooks/{}/notes", notebook.id),
+                            &[("limit", "100"), ("sort", "title"), ("order", "asc")],
+                            cfg.token.as_deref(),
+                        )
+                        .await
+                        .expect("request failed");
+                    contents.insert(notebook.id, page.items);
+                }
+            }
+            print_notebook_tree(&notebooks, &contents, None, "");
+        }
+        Command::Notebook {
+            command: NotebookCommand::Create { name, parent },
+        } => {
+            #[derive(Serialize)]
+            struct Body<'a> {
+                name: &'a str,
+                parent_id: Option<i64>,
+            }
+            let notebook: Notebook = http
+                .post_json_typed(
+                    "/notebooks",
+                    &Body {
+                        name: &name,
+                        parent_id: parent,
+                    },
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&notebook).unwrap_or_default()
+            );
+        }
+        Command::Notebook {
+            command: NotebookCommand::Rename { id, name },
+        } => {
+            #[derive(Serialize)]
+            struct Body<'a> {
+                name: &'a str,
+            }
+            let notebook: Notebook = http
+                .put_json_typed(
+                    &format!("/notebooks/{}", id),
+                    &Body { name: &name },
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&notebook).unwrap_or_default()
+            );
+        }
+        Command::Notebook {
+            command: NotebookCommand::Move { id, parent },
+        } => {
+            #[derive(Serialize)]
+            struct Body {
+                parent_id: Option<i64>,
+            }
+            let notebook: Notebook = http
+                .put_json_typed(
+                    &format!("/notebooks/{}/parent", id),
+                    &Body { parent_id: parent },
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&notebook).unwrap_or_default()
+            );
+        }
+        Command::Notebook {
+            command: NotebookCommand::Delete { id, cascade },
+        } => {
+            let mode = if cascade { "cascade" } else { "reparent" };
+            let (status, text) = http
+                .delete(
+                    &format!("/notebooks/{}?mode={}", id, mode),
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!("{} {}", status, text);
+        }
+        Command::Notebook {
+            command: NotebookCommand::Notes { id, limit, cursor },
+        } => {
+            let mut params: Vec<(&str, String)> = vec![];
+            if let Some(limit) = limit {
+                params.push(("limit", limit.to_string()));
+            }
+            if let Some(cursor) = cursor {
+                params.push(("cursor", cursor));
+            }
+            let page: NotePage = http
+                .get_json_with_query(
+                    &format!("/notebooks/{}/notes", id),
+                    &params,
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&page.items).unwrap_or_default()
+            );
+            if let Some(next) = page.next_cursor {
+                eprintln!("next_cursor: {}", next);
+            }
+        }
+        Command::Graph {
+            format,
+            note,
+            depth,
+        } => {
+            let format = match format.as_str() {
+                "json" => GraphFormat::Json,
+                "dot" => GraphFormat::Dot,
+                _ => {
+                    eprintln!("--format must be json or dot.");
+                    return;
+                }
+            };
+            let (status, text) = http
+                .get_with_query(
+                    "/graph",
+                    &GraphQuery {
+                        format,
+                        note,
+                        depth,
+                    },
+                    cfg.token.as_deref(),
+                )
+                .await
+                .expect("request failed");
+            match (status, format) {
+                (200, GraphFormat::Dot) => print!("{}", text),
+                (200, GraphFormat::Json) => {
+                    let graph: NoteGraph = serde_json::from_str(&text).unwrap_or_default();
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&graph).unwrap_or_default()
+                    );
+                }
+                _ => println!("{} {}", status, text),
+            }
+        }
+        Command::Events { last_event_id } => {
+            let last_event_id = last_event_id.map(|id| id.to_string());
+            let result = http
+                .get_events(
+                    "/events",
+                    cfg.token.as_deref(),
+                    last_event_id.as_deref(),
+                    |event| {
+                        if event.event == RESET_EVENT {
+                            eprintln!("Missed some changes. Fetch notes again.");
+                            return;
+                        }
+                        let Ok(message) = serde_json::from_str::<NoteEventMessage>(&event.data)
+                        else {
+                            return;
+                        };
+                        match message.note {
+                            Some(note) => println!(
+                                "{}\t{}\t#{} {}",
+                                message.id, message.kind, message.note_id, note.title
+                            ),
+                            None => {
+                                println!("{}\t{}\t#{}", message.id, message.kind, message.note_id)
+                            }
+                        }
+                    },
+                )
+                .await;
+            match result {
+                Ok(()) => eprintln!("Connection closed."),
+                Err(e) => println!("{}", e),
+            }
+        }
+        Command::Tag {
+            command: TagCommand::List,
+        } => {
+            let tags: Vec<TagCount> = http
+                .get_json("/tags", cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            for tag in tags {
+                println!("{}\t{}", tag.count, tag.name);
+            }
+        }
+        Command::Trash {
+            command: TrashCommand::List,
+        } => {
+            let notes: Vec<TrashedNote> = http
+                .get_json("/trash", cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            for trashed in notes {
+                println!(
+                    "#{}\t{}\t{}",
+                    trashed.note.id, trashed.deleted_at, trashed.note.title
+                );
+            }
+        }
+        Command::Trash {
+            command: TrashCommand::Restore { id },
+        } => {
+            let note: Note = http
+                .post_json_typed(&format!("/notes/{}/restore", id), &(), cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&note).unwrap_or_default()
+            );
+        }
+        Command::Trash {
+            command: TrashCommand::Purge { id },
+        } => {
+            let (status, text) = http
+                .delete(&format!("/trash/{}", id), cfg.token.as_deref())
+                .await
+                .expect("request failed");
+            println!("{} {}", status, text);
+        }
+    }
+}
+
+/// `parent` 直下のノートブック（と `contents` に入れたメモ）を罫線付きで表示する。
+/// `PUT /notes/{id}/{state}`（`off` なら `DELETE`）で固定・スター・アーカイブを切り替える。
+async fn set_note_state(http: &HttpClient, token: Option<&str>, id: i64, state: &str, off: bool) {
+    let path = format!("/notes/{}/{}", id, state);
+    let (status, text) = if off {
+        http.delete(&path, token).await
+    } else {
+        http.put_json(&path, &(), token).await
+    }
+    .expect("request failed");
+    println!("{} {}", status, text);
+}
+
+fn print_notebook_tree(
+    notebooks: &[Notebook],
+    contents: &HashMap<i64, Vec<Note>>,
+    parent: Option<i64>,
+    indent: &str,
+) {
+    let children: Vec<&Notebook> = notebooks.iter().filter(|n| n.parent_id == parent).collect();
+    let notes = parent
+        .and_then(|id| contents.get(&id))
+        .map_or(&[][..], |notes| notes.as_slice());
+    let count = children.len() + notes.len();
+    for (i, notebook) in children.iter().enumerate() {
+        let last = i + 1 == count;
+        println!(
+            "{}{}{} #{}",
+            indent,
+            if last { "└── " } else { "├── " },
+            notebook.name,
+            notebook.id
+        );
+        let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+        print_notebook_tree(notebooks, contents, Some(notebook.id), &indent);
+    }
+    for (i, note) in notes.iter().enumerate() {
+        let last = children.len() + i + 1 == count;
+        println!(
+            "{}{}- {} (note #{})",
+            indent,
+            if last { "└── " } else { "├── " },
+            note.title,
+            note.id
+        );
     }
 }
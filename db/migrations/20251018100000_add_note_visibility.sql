@@ -0,0 +1,10 @@
+-- notes.visibility: private（既定） / unlisted / public
+ALTER TABLE notes
+  ADD COLUMN IF NOT EXISTS visibility TEXT NOT NULL DEFAULT 'private';
+
+ALTER TABLE notes
+  ADD CONSTRAINT chk_notes_visibility
+    CHECK (visibility IN ('private', 'unlisted', 'public'));
+
+CREATE INDEX IF NOT EXISTS idx_notes_visibility_created
+  ON notes(visibility, created_at DESC);
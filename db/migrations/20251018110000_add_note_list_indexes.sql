@@ -0,0 +1,7 @@
+-- GET /notes のキーセットページング用インデックス
+-- created_at 順は既存の idx_notes_user_created を使う
+CREATE INDEX IF NOT EXISTS idx_notes_user_updated
+  ON notes(user_id, updated_at DESC, id DESC);
+
+CREATE INDEX IF NOT EXISTS idx_notes_user_title
+  ON notes(user_id, title, id);
@@ -0,0 +1,11 @@
+-- 全文検索用の tsvector（title を重み A、content を重み B として生成）
+-- 生成列なので INSERT / UPDATE に追従し、DELETE で行ごと消える
+ALTER TABLE notes
+  ADD COLUMN IF NOT EXISTS search_vector tsvector
+    GENERATED ALWAYS AS (
+      setweight(to_tsvector('simple', coalesce(title, '')), 'A') ||
+      setweight(to_tsvector('simple', coalesce(content, '')), 'B')
+    ) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_notes_search_vector
+  ON notes USING GIN (search_vector);
@@ -0,0 +1,24 @@
+-- tags: ユーザーごとのタグ
+CREATE TABLE IF NOT EXISTS tags (
+  id          BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  user_id     BIGINT NOT NULL,
+  name        TEXT   NOT NULL,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT fk_tags_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT uq_tags_user_name UNIQUE (user_id, name)
+);
+
+-- note_tags: メモとタグの多対多
+CREATE TABLE IF NOT EXISTS note_tags (
+  note_id  BIGINT NOT NULL,
+  tag_id   BIGINT NOT NULL,
+  PRIMARY KEY (note_id, tag_id),
+  CONSTRAINT fk_note_tags_note
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+  CONSTRAINT fk_note_tags_tag
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_note_tags_tag
+  ON note_tags(tag_id, note_id);
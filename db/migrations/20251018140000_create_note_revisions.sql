@@ -0,0 +1,20 @@
+-- note_revisions: メモのタイトル・本文の変更履歴（作成時を revision 1 とする連番）
+CREATE TABLE IF NOT EXISTS note_revisions (
+  id          BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  note_id     BIGINT NOT NULL,
+  revision    BIGINT NOT NULL,
+  title       TEXT   NOT NULL,
+  content     TEXT   NOT NULL,
+  editor_id   BIGINT,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT fk_note_revisions_note
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+  CONSTRAINT fk_note_revisions_editor
+    FOREIGN KEY (editor_id) REFERENCES users(id) ON DELETE SET NULL,
+  CONSTRAINT uq_note_revisions_note_revision UNIQUE (note_id, revision)
+);
+
+-- 既存のメモは現在の内容を revision 1 とする
+INSERT INTO note_revisions (note_id, revision, title, content, editor_id, created_at)
+SELECT id, 1, title, content, user_id, updated_at FROM notes
+ON CONFLICT DO NOTHING;
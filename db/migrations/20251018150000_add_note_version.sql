@@ -0,0 +1,3 @@
+-- notes.version: 更新のたびに 1 ずつ増える楽観的排他制御用のバージョン（ETag として返す）
+ALTER TABLE notes
+  ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
@@ -0,0 +1,8 @@
+-- notes.deleted_at: ゴミ箱に移動した日時（NULL なら通常のメモ）
+ALTER TABLE notes
+  ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- ゴミ箱の一覧と期限切れの削除用
+CREATE INDEX IF NOT EXISTS idx_notes_user_deleted_at
+  ON notes(user_id, deleted_at)
+  WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,24 @@
+-- refresh_tokens: ローテーションされるリフレッシュトークン（平文は保存せず SHA-256 のみ）
+-- 同じログインから発行されたトークンは family_id を共有する
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+  id          BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  user_id     BIGINT NOT NULL,
+  family_id   TEXT   NOT NULL,
+  token_hash  TEXT   NOT NULL,
+  expires_at  TIMESTAMPTZ NOT NULL,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+  used_at     TIMESTAMPTZ,  -- ローテーション済み
+  revoked_at  TIMESTAMPTZ,  -- ログアウト・再利用検知で失効
+  CONSTRAINT fk_refresh_tokens_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT uq_refresh_tokens_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family
+  ON refresh_tokens(family_id);
+
+-- revoked_tokens: 失効させたアクセストークンの jti（期限切れ後は不要）
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+  jti         TEXT PRIMARY KEY,
+  expires_at  TIMESTAMPTZ NOT NULL
+);
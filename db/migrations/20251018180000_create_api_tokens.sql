@@ -0,0 +1,17 @@
+-- api_tokens: 自動化用のパーソナルアクセストークン（平文は保存せず SHA-256 のみ）
+CREATE TABLE IF NOT EXISTS api_tokens (
+  id            BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  user_id       BIGINT NOT NULL,
+  name          TEXT   NOT NULL,
+  token_hash    TEXT   NOT NULL,
+  scopes        JSONB  NOT NULL DEFAULT '[]'::jsonb, -- 例: ["notes:read", "notes:write"]
+  created_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+  expires_at    TIMESTAMPTZ,  -- NULL なら無期限
+  last_used_at  TIMESTAMPTZ,
+  CONSTRAINT fk_api_tokens_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT uq_api_tokens_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user
+  ON api_tokens(user_id);
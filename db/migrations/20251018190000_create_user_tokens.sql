@@ -0,0 +1,19 @@
+-- users.email_verified_at: サインアップ後のメール確認が済んだ日時（NULL なら未確認）
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMPTZ;
+
+-- user_tokens: パスワード再設定・メール確認用の 1 回限りのトークン（平文は保存せず SHA-256 のみ）
+CREATE TABLE IF NOT EXISTS user_tokens (
+  id          BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  user_id     BIGINT NOT NULL,
+  purpose     TEXT   NOT NULL, -- 'password_reset' / 'email_verification'
+  token_hash  TEXT   NOT NULL,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+  expires_at  TIMESTAMPTZ NOT NULL,
+  used_at     TIMESTAMPTZ,
+  CONSTRAINT fk_user_tokens_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT uq_user_tokens_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_tokens_user
+  ON user_tokens(user_id, purpose);
@@ -0,0 +1,23 @@
+-- user_totp: TOTP による 2 段階認証の設定（確認コードで有効化するまで enabled_at は NULL）
+CREATE TABLE IF NOT EXISTS user_totp (
+  user_id         BIGINT PRIMARY KEY,
+  secret          TEXT   NOT NULL, -- base32
+  created_at      TIMESTAMPTZ NOT NULL DEFAULT now(),
+  enabled_at      TIMESTAMPTZ,
+  last_used_step  BIGINT, -- 最後に使われたコードの時刻ステップ（同じコードの再利用を防ぐ）
+  CONSTRAINT fk_user_totp_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- totp_recovery_codes: 認証アプリを使えないときのリカバリーコード（各 1 回限り、SHA-256 のみ保存）
+CREATE TABLE IF NOT EXISTS totp_recovery_codes (
+  id         BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  user_id    BIGINT NOT NULL,
+  code_hash  TEXT   NOT NULL,
+  used_at    TIMESTAMPTZ,
+  CONSTRAINT fk_totp_recovery_codes_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT uq_totp_recovery_codes_user_hash UNIQUE (user_id, code_hash)
+);
+
+-- user_tokens.purpose には 'login_challenge'（パスワード確認後、2 段階認証のコード入力待ちのログイン）も入る
@@ -0,0 +1,4 @@
+-- users.failed_login_count: 連続したログイン失敗の回数（成功すると 0 に戻す）
+-- users.locked_until: この日時までログインを受け付けない（NULL ならロックなし）
+ALTER TABLE users ADD COLUMN IF NOT EXISTS failed_login_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
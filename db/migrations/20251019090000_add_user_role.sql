@@ -0,0 +1,6 @@
+-- users.role: 'user' または 'admin'（管理用 API を使える）
+-- users.disabled_at: 管理者が無効にした日時（NULL なら有効）。無効なアカウントはログインできない
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role TEXT NOT NULL DEFAULT 'user'
+    CHECK (role IN ('user', 'admin'));
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_users_role ON users(role) WHERE role = 'admin';
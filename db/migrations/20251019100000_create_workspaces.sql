@@ -0,0 +1,43 @@
+-- workspaces: チームで共有するメモの置き場所
+CREATE TABLE IF NOT EXISTS workspaces (
+  id          BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  name        TEXT   NOT NULL,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- workspace_members: メンバーと役割（owner: 管理、editor: 編集、viewer: 閲覧のみ）
+CREATE TABLE IF NOT EXISTS workspace_members (
+  workspace_id  BIGINT NOT NULL,
+  user_id       BIGINT NOT NULL,
+  role          TEXT   NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+  created_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (workspace_id, user_id),
+  CONSTRAINT fk_workspace_members_workspace
+    FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+  CONSTRAINT fk_workspace_members_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_workspace_members_user ON workspace_members(user_id);
+
+-- workspace_invitations: メールで送った招待（1 回限り、SHA-256 のみ保存）
+CREATE TABLE IF NOT EXISTS workspace_invitations (
+  id            BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  workspace_id  BIGINT NOT NULL,
+  email         TEXT   NOT NULL,
+  role          TEXT   NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+  token_hash    TEXT   NOT NULL UNIQUE,
+  invited_by    BIGINT,
+  created_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+  expires_at    TIMESTAMPTZ NOT NULL,
+  accepted_at   TIMESTAMPTZ,
+  CONSTRAINT fk_workspace_invitations_workspace
+    FOREIGN KEY (workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE,
+  CONSTRAINT fk_workspace_invitations_invited_by
+    FOREIGN KEY (invited_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+-- notes.workspace_id: ワークスペースのメモ（NULL なら個人のメモ）。
+-- notes.user_id は作成者として残し、権限はメンバーの役割で判定する
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS workspace_id BIGINT
+  REFERENCES workspaces(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS idx_notes_workspace ON notes(workspace_id) WHERE workspace_id IS NOT NULL;
@@ -0,0 +1,33 @@
+-- note_shares: メモを個別のユーザーと共有する（read: 閲覧のみ、write: 閲覧と編集）
+CREATE TABLE IF NOT EXISTS note_shares (
+  note_id     BIGINT NOT NULL,
+  user_id     BIGINT NOT NULL,
+  permission  TEXT   NOT NULL CHECK (permission IN ('read', 'write')),
+  granted_by  BIGINT,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (note_id, user_id),
+  CONSTRAINT fk_note_shares_note
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+  CONSTRAINT fk_note_shares_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT fk_note_shares_granted_by
+    FOREIGN KEY (granted_by) REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_note_shares_user ON note_shares(user_id);
+
+-- note_share_links: 共有リンク（SHA-256 のみ保存）。取り消すと行を削除する
+CREATE TABLE IF NOT EXISTS note_share_links (
+  id             BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  note_id        BIGINT NOT NULL,
+  token_hash     TEXT   NOT NULL UNIQUE,
+  permission     TEXT   NOT NULL CHECK (permission IN ('read', 'write')),
+  password_hash  TEXT,  -- Argon2id（NULL ならパスワードなし）
+  created_by     BIGINT,
+  created_at     TIMESTAMPTZ NOT NULL DEFAULT now(),
+  expires_at     TIMESTAMPTZ,  -- NULL なら無期限
+  CONSTRAINT fk_note_share_links_note
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+  CONSTRAINT fk_note_share_links_created_by
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_note_share_links_note ON note_share_links(note_id);
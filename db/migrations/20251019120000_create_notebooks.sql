@@ -0,0 +1,20 @@
+-- notebooks: メモを整理するフォルダー（ユーザーごと、parent_id で入れ子にできる）
+CREATE TABLE IF NOT EXISTS notebooks (
+  id          BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  user_id     BIGINT NOT NULL,
+  parent_id   BIGINT,  -- NULL ならトップレベル
+  name        TEXT   NOT NULL,
+  created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT fk_notebooks_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  CONSTRAINT fk_notebooks_parent
+    FOREIGN KEY (parent_id) REFERENCES notebooks(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_notebooks_user ON notebooks(user_id);
+CREATE INDEX IF NOT EXISTS idx_notebooks_parent ON notebooks(parent_id) WHERE parent_id IS NOT NULL;
+
+-- notes.notebook_id: メモを入れているノートブック（NULL ならどこにも入れていない）
+ALTER TABLE notes ADD COLUMN IF NOT EXISTS notebook_id BIGINT
+  REFERENCES notebooks(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_notes_notebook ON notes(notebook_id) WHERE notebook_id IS NOT NULL;
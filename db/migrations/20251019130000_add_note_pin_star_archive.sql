@@ -0,0 +1,12 @@
+-- notes.pinned: 一覧の先頭に固定する
+-- notes.starred: スター（`GET /notes?starred=true` で絞り込む）
+-- notes.archived_at: アーカイブした日時（NULL なら通常のメモ。一覧には `?archived=true` のときだけ出す）
+ALTER TABLE notes
+  ADD COLUMN IF NOT EXISTS pinned BOOLEAN NOT NULL DEFAULT false,
+  ADD COLUMN IF NOT EXISTS starred BOOLEAN NOT NULL DEFAULT false,
+  ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+
+-- アーカイブの一覧用
+CREATE INDEX IF NOT EXISTS idx_notes_user_archived_at
+  ON notes(user_id, archived_at)
+  WHERE archived_at IS NOT NULL;
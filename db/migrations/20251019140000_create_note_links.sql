@@ -0,0 +1,15 @@
+-- note_links: メモ本文中のリンク（`[[#123]]` なら target_id、`[[タイトル]]` なら target_title）
+-- 参照先は取得時に解決するので、存在しない（リンク切れの）参照もそのまま保存する
+CREATE TABLE IF NOT EXISTS note_links (
+  source_id     BIGINT  NOT NULL,
+  position      INTEGER NOT NULL,  -- 本文中の出現順（0 から）
+  target_id     BIGINT,
+  target_title  TEXT,
+  PRIMARY KEY (source_id, position),
+  CONSTRAINT fk_note_links_source
+    FOREIGN KEY (source_id) REFERENCES notes(id) ON DELETE CASCADE,
+  CONSTRAINT chk_note_links_target
+    CHECK ((target_id IS NULL) <> (target_title IS NULL))
+);
+CREATE INDEX IF NOT EXISTS idx_note_links_target_id ON note_links(target_id) WHERE target_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_note_links_target_title ON note_links(target_title) WHERE target_title IS NOT NULL;
@@ -0,0 +1,20 @@
+-- note_attachments: メモの添付ファイルのメタデータ（本体は BlobStore の storage_key に保存する）
+-- メモを完全に削除すると note_id が NULL になり、ゴミ箱の定期削除のときに本体ごと削除する
+CREATE TABLE IF NOT EXISTS note_attachments (
+  id            BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  note_id       BIGINT,
+  user_id       BIGINT,  -- アップロードしたユーザー（容量の上限はこのユーザーに数える）
+  filename      TEXT   NOT NULL,
+  content_type  TEXT   NOT NULL,  -- 中身から判定した MIME タイプ
+  size          BIGINT NOT NULL,
+  sha256        TEXT   NOT NULL,
+  storage_key   TEXT   NOT NULL UNIQUE,
+  created_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT fk_note_attachments_note
+    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE SET NULL,
+  CONSTRAINT fk_note_attachments_user
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_note_attachments_note ON note_attachments(note_id);
+CREATE INDEX IF NOT EXISTS idx_note_attachments_user ON note_attachments(user_id);
+CREATE INDEX IF NOT EXISTS idx_note_attachments_orphan ON note_attachments(id) WHERE note_id IS NULL;
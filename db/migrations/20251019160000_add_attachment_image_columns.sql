@@ -0,0 +1,7 @@
+-- 画像の添付ファイルの大きさ（EXIF の向きを反映した表示上の幅・高さ。画像でなければ NULL）と
+-- サムネイルを作り終えた日時（バックグラウンドの処理が未処理のものを探すのに使う）
+ALTER TABLE note_attachments ADD COLUMN IF NOT EXISTS width INTEGER;
+ALTER TABLE note_attachments ADD COLUMN IF NOT EXISTS height INTEGER;
+ALTER TABLE note_attachments ADD COLUMN IF NOT EXISTS thumbnails_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_note_attachments_pending_thumbnails
+  ON note_attachments(id) WHERE width IS NOT NULL AND thumbnails_at IS NULL;
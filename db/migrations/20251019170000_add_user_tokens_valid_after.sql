@@ -0,0 +1,3 @@
+-- users.tokens_valid_after: この日時以前に発行したアクセストークンを受け付けない
+-- （管理者による無効化・パスワードの強制リセットで発行済みのトークンをまとめて失効させる）
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tokens_valid_after TIMESTAMPTZ;
@@ -0,0 +1,8 @@
+-- SQLite 用の全文検索インデックス（FTS5）
+-- rowid = notes.id。SqliteNoteRepository が create/update/delete のたびに同期する。
+CREATE VIRTUAL TABLE IF NOT EXISTS notes_fts USING fts5(title, content);
+
+-- 既存データの取り込み
+INSERT INTO notes_fts (rowid, title, content)
+  SELECT id, title, content FROM notes
+  WHERE id NOT IN (SELECT rowid FROM notes_fts);
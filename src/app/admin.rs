@@ -0,0 +1,124 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, web};
+
+use crate::app::events::publish;
+use crate::app::model::{ListUsersQuery, UserPage, UserSummary};
+use crate::middleware::auth::extractor::AdminUser;
+use crate::service::admin::{AdminError, AdminService};
+use crate::service::events::NoteEventKind;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// ユーザー一覧（ID 順）。管理者のみ。
+///
+/// クエリ: `q`（メールアドレスの部分一致）, `limit`（1..=200, 既定 50）, `after`（前ページの `next_after`）
+#[get("/admin/users")]
+pub async fn list_users(
+    _admin: AdminUser,
+    admin_service: web::Data<AdminService>,
+    query: web::Query<ListUsersQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return HttpResponse::BadRequest().finish();
+    }
+    let q = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    // 1 件多く取得して次ページの有無を判定する
+    match admin_service.list_users(q, query.after, limit + 1).await {
+        Ok(mut users) => {
+            let next_after = if users.len() as i64 > limit {
+                users.truncate(limit as usize);
+                users.last().map(|u| u.id)
+            } else {
+                None
+            };
+            HttpResponse::Ok().json(UserPage {
+                items: users.into_iter().map(UserSummary::from).collect(),
+                next_after,
+            })
+        }
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[get("/admin/users/{id}")]
+pub async fn get_user(
+    _admin: AdminUser,
+    admin_service: web::Data<AdminService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match admin_service.get_user(path.into_inner()).await {
+        Ok(user) => HttpResponse::Ok().json(UserSummary::from(user)),
+        Err(e) => error_response(e),
+    }
+}
+
+/// アカウントを無効にする。ログインできなくなり、リフレッシュトークンも失効する。
+/// 自分自身は無効にできない（400）。
+#[post("/admin/users/{id}/disable")]
+pub async fn disable_user(
+    admin: AdminUser,
+    admin_service: web::Data<AdminService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match admin_service
+        .set_disabled(admin.0.id, path.into_inner(), true)
+        .await
+    {
+        Ok(user) => HttpResponse::Ok().json(UserSummary::from(user)),
+        Err(e) => error_response(e),
+    }
+}
+
+#[post("/admin/users/{id}/enable")]
+pub async fn enable_user(
+    admin: AdminUser,
+    admin_service: web::Data<AdminService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match admin_service
+        .set_disabled(admin.0.id, path.into_inner(), false)
+        .await
+    {
+        Ok(user) => HttpResponse::Ok().json(UserSummary::from(user)),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 現在のパスワードを使えなくし、再設定用のトークンをメールで送る。
+#[post("/admin/users/{id}/password-reset")]
+pub async fn force_password_reset(
+    _admin: AdminUser,
+    admin_service: web::Data<AdminService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match admin_service.force_password_reset(path.into_inner()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 任意のユーザーのメモを完全に削除する（ゴミ箱には移さない）。
+#[delete("/admin/notes/{id}")]
+pub async fn delete_any_note(
+    req: HttpRequest,
+    _admin: AdminUser,
+    admin_service: web::Data<AdminService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match admin_service.delete_note(path.into_inner()).await {
+        Ok(note) => {
+            publish(&req, NoteEventKind::Deleted, note);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(e: AdminError) -> HttpResponse {
+    match e {
+        AdminError::NotFound => HttpResponse::NotFound().finish(),
+        AdminError::SelfTarget => HttpResponse::BadRequest().finish(),
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
@@ -0,0 +1,418 @@
+use actix_multipart::Multipart;
+use actix_web::http::header::{
+    self, ByteRangeSpec, Charset, ContentDisposition, DispositionParam, DispositionType, ETag,
+    EntityTag, ExtendedValue, Header, IfRange,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, web};
+use futures_util::StreamExt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::app::model::ThumbnailQuery;
+use crate::app::notes::{authorize, authorize_write, shared_permission};
+use crate::domain::attachment::is_inline_type;
+use crate::domain::image::thumbnail_content_type;
+use crate::domain::model::{Attachment, Note, Scope};
+use crate::domain::policy::NoteAction;
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::repository::note::NoteRepository;
+use crate::repository::workspace::WorkspaceRepository;
+use crate::service::attachment::{AttachmentError, AttachmentService};
+use crate::service::share::ShareService;
+
+// 添付ファイルはメモ本体と同じ権限で扱う（閲覧できる人が一覧・ダウンロードでき、
+// 編集できる人が追加・削除できる）。共有リンク（`?share=<token>`）も使える。
+
+/// `?w=` を省略したときのサムネイルの幅。
+const DEFAULT_THUMBNAIL_WIDTH: u32 = 320;
+
+/// `multipart/form-data` の `file` フィールドのファイルを添付する。
+/// 1 ファイルの上限を超えれば 413、アップロードしたユーザーの合計の上限を超えれば 507。
+#[post("/notes/{id}/attachments")]
+pub async fn upload_attachment(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    attachments: web::Data<AttachmentService>,
+    mut payload: Multipart,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    // 引数を増やさないよう、メモの ID はリクエストのパスから読む
+    let Ok(note_id) = req.match_info().query("id").parse::<i64>() else {
+        return HttpResponse::NotFound().finish();
+    };
+    let note = match find_note(&note_repo, note_id).await {
+        Ok(note) => note,
+        Err(resp) => return resp,
+    };
+    let share = match shared_permission(shares.as_ref(), &req, note_id, Some(user.0.sub)).await {
+        Ok(share) => share,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = authorize_write(
+        workspaces.as_ref(),
+        &note,
+        user.0.sub,
+        share,
+        NoteAction::Update,
+    )
+    .await
+    {
+        return resp;
+    }
+
+    let limit = usize::try_from(attachments.max_bytes()).unwrap_or(usize::MAX);
+    while let Some(field) = payload.next().await {
+        let Ok(mut field) = field else {
+            return HttpResponse::BadRequest().finish();
+        };
+        if field.name() != Some("file") {
+            continue;
+        }
+        let Some(filename) = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .map(str::to_string)
+        else {
+            return HttpResponse::BadRequest().finish();
+        };
+        let data = match field.bytes(limit).await {
+            Ok(Ok(data)) => data,
+            Ok(Err(_)) => return HttpResponse::BadRequest().finish(),
+            Err(_) => return HttpResponse::PayloadTooLarge().finish(),
+        };
+        return match attachments
+            .upload(note_id, user.0.sub, &filename, &data)
+            .await
+        {
+            Ok(attachment) => HttpResponse::Created().json(attachment),
+            Err(e) => error_response(e),
+        };
+    }
+    HttpResponse::BadRequest().finish()
+}
+
+/// メモの添付ファイルを追加した順に返す。
+#[get("/notes/{id}/attachments")]
+pub async fn list_attachments(
+    req: HttpRequest,
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    attachments: web::Data<AttachmentService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let note_id = path.into_inner();
+    let viewer = user.map(|u| u.0.sub);
+    if let Err(resp) = find_readable_note(
+        &req,
+        &note_repo,
+        workspaces.as_ref(),
+        shares.as_ref(),
+        note_id,
+        viewer,
+    )
+    .await
+    {
+        return resp;
+    }
+    match attachments.list(note_id).await {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 添付ファイルの本体。`Range: bytes=...`（範囲は 1 つだけ）で一部を取得でき、
+/// `If-Range` が `ETag`（中身の SHA-256）と一致しなければ全体を返す。
+/// 画像はそのまま表示できるように `inline`、それ以外は `attachment` で返す。
+#[get("/notes/{id}/attachments/{attachment_id}")]
+pub async fn download_attachment(
+    req: HttpRequest,
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    attachments: web::Data<AttachmentService>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (note_id, attachment_id) = path.into_inner();
+    let viewer = user.map(|u| u.0.sub);
+    if let Err(resp) = find_readable_note(
+        &req,
+        &note_repo,
+        workspaces.as_ref(),
+        shares.as_ref(),
+        note_id,
+        viewer,
+    )
+    .await
+    {
+        return resp;
+    }
+    let attachment = match attachments.find(note_id, attachment_id).await {
+        Ok(attachment) => attachment,
+        Err(e) => return error_response(e),
+    };
+
+    let size = attachment.size as u64;
+    let etag = EntityTag::new_strong(attachment.sha256.clone());
+    let range = match requested_range(&req, size, &etag) {
+        Ok(range) => range,
+        Err(()) => {
+            return HttpResponse::RangeNotSatisfiable()
+                .insert_header((header::CONTENT_RANGE, format!("bytes */{size}")))
+                .finish();
+        }
+    };
+    let data = match attachments.read(&attachment, range.clone()).await {
+        Ok(data) => data,
+        Err(e) => return error_response(e),
+    };
+    let mut resp = match &range {
+        Some(range) => {
+            let mut resp = HttpResponse::PartialContent();
+            resp.insert_header((
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{size}", range.start, range.end - 1),
+            ));
+            resp
+        }
+        None => HttpResponse::Ok(),
+    };
+    resp.insert_header(ETag(etag))
+        .insert_header((header::ACCEPT_RANGES, "bytes"))
+        .insert_header(content_disposition(&attachment))
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .insert_header((
+            header::CONTENT_SECURITY_POLICY,
+            "default-src 'none'; sandbox",
+        ))
+        .content_type(attachment.content_type.as_str())
+        .body(data)
+}
+
+#[delete("/notes/{id}/attachments/{attachment_id}")]
+pub async fn delete_attachment(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    attachments: web::Data<AttachmentService>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (note_id, attachment_id) = path.into_inner();
+    let note = match find_note(&note_repo, note_id).await {
+        Ok(note) => note,
+        Err(resp) => return resp,
+    };
+    let share = match shared_permission(shares.as_ref(), &req, note_id, Some(user.0.sub)).await {
+        Ok(share) => share,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = authorize_write(
+        workspaces.as_ref(),
+        &note,
+        user.0.sub,
+        share,
+        NoteAction::Update,
+    )
+    .await
+    {
+        return resp;
+    }
+    match attachments.delete(note_id, attachment_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 画像の添付ファイルのサムネイル（`?w=` の幅に近いもの）。画像でなければ 404。
+/// まだ作っていなければ元の画像（`/notes/{id}/attachments/{attachment_id}`）にリダイレクトする。
+#[get("/attachments/{id}/thumb")]
+pub async fn attachment_thumbnail(
+    req: HttpRequest,
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    attachments: web::Data<AttachmentService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let Ok(query) = web::Query::<ThumbnailQuery>::from_query(req.query_string()) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    let requested = query.w.unwrap_or(DEFAULT_THUMBNAIL_WIDTH);
+    if requested == 0 {
+        return HttpResponse::BadRequest().finish();
+    }
+    let attachment = match attachments.get(path.into_inner()).await {
+        Ok(attachment) => attachment,
+        Err(e) => return error_response(e),
+    };
+    let viewer = user.map(|u| u.0.sub);
+    if let Err(resp) = find_readable_note(
+        &req,
+        &note_repo,
+        workspaces.as_ref(),
+        shares.as_ref(),
+        attachment.note_id,
+        viewer,
+    )
+    .await
+    {
+        return resp;
+    }
+    match attachments.thumbnail(&attachment, requested).await {
+        Ok(Some((width, data))) => HttpResponse::Ok()
+            .insert_header(ETag(EntityTag::new_strong(format!(
+                "{}-w{width}",
+                attachment.sha256
+            ))))
+            .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+            .insert_header((
+                header::CONTENT_SECURITY_POLICY,
+                "default-src 'none'; sandbox",
+            ))
+            .content_type(thumbnail_content_type(&attachment.content_type))
+            .body(data),
+        Ok(None) => {
+            // 共有リンクのトークンなどのクエリはそのまま渡す
+            let mut location = format!(
+                "/notes/{}/attachments/{}",
+                attachment.note_id, attachment.id
+            );
+            if !req.query_string().is_empty() {
+                location.push('?');
+                location.push_str(req.query_string());
+            }
+            HttpResponse::TemporaryRedirect()
+                .insert_header((header::LOCATION, location))
+                .insert_header((header::CACHE_CONTROL, "no-store"))
+                .finish()
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+async fn find_note(
+    note_repo: &Arc<dyn NoteRepository>,
+    note_id: i64,
+) -> Result<Note, HttpResponse> {
+    match note_repo.find_by_id(note_id).await {
+        Ok(Some(note)) => Ok(note),
+        Ok(None) => Err(HttpResponse::NotFound().finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+/// 閲覧できるメモを読み込む。閲覧できなければ 404（存在自体を秘匿する）。
+async fn find_readable_note(
+    req: &HttpRequest,
+    note_repo: &Arc<dyn NoteRepository>,
+    workspaces: Option<&web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<&web::Data<ShareService>>,
+    note_id: i64,
+    viewer: Option<i64>,
+) -> Result<Note, HttpResponse> {
+    let note = find_note(note_repo, note_id).await?;
+    let share = shared_permission(shares, req, note_id, viewer).await?;
+    match authorize(workspaces, &note, viewer, share, NoteAction::Read).await {
+        Ok(true) => Ok(note),
+        Ok(false) => Err(HttpResponse::NotFound().finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+/// `Range` で要求された範囲（終端は含まない）。`None` なら全体を返す。
+/// 範囲が 1 つでない・解釈できない・`If-Range` が一致しない場合は `Range` を無視し、
+/// 範囲が本体の外なら `Err`（416）。
+fn requested_range(
+    req: &HttpRequest,
+    size: u64,
+    etag: &EntityTag,
+) -> Result<Option<Range<u64>>, ()> {
+    let Ok(header::Range::Bytes(specs)) = header::Range::parse(req) else {
+        return Ok(None);
+    };
+    let [spec]: [ByteRangeSpec; 1] = match specs.try_into() {
+        Ok(specs) => specs,
+        Err(_) => return Ok(None),
+    };
+    if req.headers().contains_key(header::IF_RANGE) {
+        match IfRange::parse(req) {
+            Ok(IfRange::EntityTag(tag)) if tag.strong_eq(etag) => {}
+            _ => return Ok(None),
+        }
+    }
+    match spec.to_satisfiable_range(size) {
+        Some((start, end)) => Ok(Some(start..end + 1)),
+        None => Err(()),
+    }
+}
+
+/// 元のファイル名を UTF-8 で渡し、古いクライアント向けに ASCII にしたものも付ける。
+fn content_disposition(attachment: &Attachment) -> ContentDisposition {
+    let ascii: String = attachment
+        .filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    ContentDisposition {
+        disposition: if is_inline_type(&attachment.content_type) {
+            DispositionType::Inline
+        } else {
+            DispositionType::Attachment
+        },
+        parameters: vec![
+            DispositionParam::Filename(ascii),
+            DispositionParam::FilenameExt(ExtendedValue {
+                charset: Charset::Ext("UTF-8".into()),
+                language_tag: None,
+                value: attachment.filename.as_bytes().to_vec(),
+            }),
+        ],
+    }
+}
+
+fn error_response(e: AttachmentError) -> HttpResponse {
+    match e {
+        AttachmentError::NotFound => HttpResponse::NotFound().finish(),
+        AttachmentError::TooLarge => HttpResponse::PayloadTooLarge().finish(),
+        AttachmentError::QuotaExceeded => HttpResponse::InsufficientStorage().finish(),
+        AttachmentError::InvalidFilename => HttpResponse::BadRequest().finish(),
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
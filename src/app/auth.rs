@@ -1,23 +1,38 @@
-use actix_web::{HttpResponse, Responder, post, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, get, http::header, post, web};
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::app::model::{LoginInput, LoginOutput, SignupInput};
+use crate::app::model::{
+    ChangePasswordInput, LoginInput, LoginOutput, LoginTwoFactorInput, LogoutInput,
+    PasswordResetConfirmInput, PasswordResetRequestInput, RefreshInput, SignupInput,
+    TwoFactorChallenge, VerifyEmailInput,
+};
 use crate::middleware::auth::extractor::AuthenticatedUser;
 use crate::middleware::auth::token::JwtTokenService;
-use crate::service::auth::AuthService;
+use crate::service::account::{AccountError, AccountService};
+use crate::service::auth::{AuthService, AuthServiceError, LoginOutcome};
+use crate::service::login_throttle::LoginThrottle;
+use crate::service::session::{SessionError, SessionService};
 
+/// 登録後、`AccountService` が登録されていれば確認メールを送る。
+/// 送信に失敗しても登録は成功扱い（`POST /me/email-verification` で再送できる）。
 #[post("/auth/signup")]
 pub async fn signup(
     auth_service: web::Data<Arc<dyn AuthService>>,
+    accounts: Option<web::Data<AccountService>>,
     payload: web::Json<SignupInput>,
 ) -> impl Responder {
     match auth_service.signup(&payload.email, &payload.password).await {
-        Ok(Some(_user)) => HttpResponse::Created().finish(),
+        Ok(Some(user)) => {
+            if let Some(accounts) = accounts {
+                let _ = accounts.send_email_verification(&user).await;
+            }
+            HttpResponse::Created().finish()
+        }
         Ok(None) => HttpResponse::Conflict().finish(),
-        Err(
-            crate::service::auth::AuthServiceError::InvalidEmail
-            | crate::service::auth::AuthServiceError::InvalidPassword,
-        ) => HttpResponse::BadRequest().finish(),
+        Err(AuthServiceError::InvalidEmail | AuthServiceError::InvalidPassword) => {
+            HttpResponse::BadRequest().finish()
+        }
         Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
@@ -27,20 +42,233 @@ pub async fn me(user: AuthenticatedUser) -> impl Responder {
     HttpResponse::Ok().json(user.0)
 }
 
+/// 2 段階認証が有効なユーザーはトークンの代わりにチャレンジトークンを返す（202）。
+/// 続けて `POST /auth/login/2fa` で確認コードを送るとログインが完了する。
+///
+/// 失敗が続くと、`LoginThrottle` が登録されていれば IP アドレス・メールアドレスごとに
+/// 次の試行まで待たせ、さらに続くとアカウントを一時的にロックする（いずれも 429 と `Retry-After`）。
+/// 管理者が無効にしたアカウントは、パスワードが正しくても 403。
 #[post("/auth/login")]
 pub async fn login(
+    req: HttpRequest,
     auth_service: web::Data<Arc<dyn AuthService>>,
     jwt: web::Data<JwtTokenService>,
+    sessions: web::Data<SessionService>,
+    throttle: Option<web::Data<LoginThrottle>>,
     payload: web::Json<LoginInput>,
 ) -> impl Responder {
-    match auth_service.login(&payload.email, &payload.password).await {
-        Ok(Some(user)) => match jwt.generate(user.id) {
-            Ok(token) => HttpResponse::Ok().json(LoginOutput { token }),
+    let email_key = format!("email:{}", payload.email.trim().to_lowercase());
+    let keys: Vec<String> = ip_key(&req)
+        .into_iter()
+        .chain([email_key.clone()])
+        .collect();
+    if let Some(throttle) = &throttle
+        && let Err(wait) = throttle.check(&keys)
+    {
+        return too_many_requests(wait);
+    }
+    let result = auth_service.login(&payload.email, &payload.password).await;
+    if let Some(throttle) = &throttle {
+        match &result {
+            Ok(Some(_)) => throttle.reset(&email_key),
+            Err(AuthServiceError::InvalidCredentials) => throttle.record_failure(&keys),
+            _ => {}
+        }
+    }
+    match result {
+        Ok(Some(LoginOutcome::Authenticated(user))) => issue_tokens(&jwt, &sessions, user.id).await,
+        Ok(Some(LoginOutcome::TwoFactorRequired { challenge_token })) => {
+            HttpResponse::Accepted().json(TwoFactorChallenge { challenge_token })
+        }
+        Err(AuthServiceError::InvalidCredentials) => HttpResponse::Unauthorized().finish(),
+        Err(AuthServiceError::Locked { retry_after }) => too_many_requests(retry_after),
+        Err(AuthServiceError::Disabled) => HttpResponse::Forbidden().finish(),
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// チャレンジトークンと確認コード（認証アプリのコードまたはリカバリーコード）でログインを完了する。
+/// コードが誤っているとチャレンジトークンも使えなくなる（`POST /auth/login` からやり直す）。
+#[post("/auth/login/2fa")]
+pub async fn login_2fa(
+    req: HttpRequest,
+    auth_service: web::Data<Arc<dyn AuthService>>,
+    jwt: web::Data<JwtTokenService>,
+    sessions: web::Data<SessionService>,
+    throttle: Option<web::Data<LoginThrottle>>,
+    payload: web::Json<LoginTwoFactorInput>,
+) -> impl Responder {
+    let keys: Vec<String> = ip_key(&req).into_iter().collect();
+    if let Some(throttle) = &throttle
+        && let Err(wait) = throttle.check(&keys)
+    {
+        return too_many_requests(wait);
+    }
+    match auth_service
+        .login_2fa(&payload.challenge_token, &payload.code)
+        .await
+    {
+        Ok(user) => issue_tokens(&jwt, &sessions, user.id).await,
+        Err(AuthServiceError::InvalidCredentials) => {
+            if let Some(throttle) = &throttle {
+                throttle.record_failure(&keys);
+            }
+            HttpResponse::Unauthorized().finish()
+        }
+        Err(AuthServiceError::Disabled) => HttpResponse::Forbidden().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// 接続元の IP アドレス（プロキシのヘッダーは偽装できるので使わない）。
+pub(crate) fn ip_key(req: &HttpRequest) -> Option<String> {
+    req.peer_addr().map(|addr| format!("ip:{}", addr.ip()))
+}
+
+pub(crate) fn too_many_requests(wait: Duration) -> HttpResponse {
+    let secs = (wait.as_secs() + u64::from(wait.subsec_nanos() > 0)).max(1);
+    HttpResponse::TooManyRequests()
+        .insert_header((header::RETRY_AFTER, secs.to_string()))
+        .finish()
+}
+
+async fn issue_tokens(
+    jwt: &JwtTokenService,
+    sessions: &SessionService,
+    user_id: i64,
+) -> HttpResponse {
+    match (jwt.generate(user_id), sessions.start(user_id).await) {
+        (Ok(token), Ok(refresh_token)) => HttpResponse::Ok().json(LoginOutput {
+            token,
+            refresh_token,
+        }),
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// リフレッシュトークンを新しいアクセストークン・リフレッシュトークンと交換する。
+/// 交換済みのトークンを再び使うと、そのログインのトークンはすべて失効する（401）。
+#[post("/auth/refresh")]
+pub async fn refresh(
+    jwt: web::Data<JwtTokenService>,
+    sessions: web::Data<SessionService>,
+    payload: web::Json<RefreshInput>,
+) -> impl Responder {
+    match sessions.refresh(&payload.refresh_token).await {
+        Ok((user_id, refresh_token)) => match jwt.generate(user_id) {
+            Ok(token) => HttpResponse::Ok().json(LoginOutput {
+                token,
+                refresh_token,
+            }),
             Err(_) => HttpResponse::InternalServerError().finish(),
         },
-        Err(crate::service::auth::AuthServiceError::InvalidCredentials) => {
+        Err(SessionError::InvalidToken | SessionError::TokenReused) => {
             HttpResponse::Unauthorized().finish()
         }
-        _ => HttpResponse::InternalServerError().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
+
+/// 使用中のアクセストークンを失効させる。
+/// 本文で `refresh_token` を渡すと、そのログインのリフレッシュトークンも失効する。
+#[post("/auth/logout")]
+pub async fn logout(
+    user: AuthenticatedUser,
+    sessions: web::Data<SessionService>,
+    payload: Option<web::Json<LogoutInput>>,
+) -> impl Responder {
+    let refresh_token = payload.and_then(|p| p.into_inner().refresh_token);
+    match sessions.end(&user.0, refresh_token.as_deref()).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// パスワードを変更する。API トークンでは変更できない（403）。
+#[post("/me/password")]
+pub async fn change_password(
+    user: AuthenticatedUser,
+    accounts: web::Data<AccountService>,
+    payload: web::Json<ChangePasswordInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match accounts
+        .change_password(user.0.sub, &payload.current_password, &payload.new_password)
+        .await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(AccountError::InvalidCredentials) => HttpResponse::Forbidden().finish(),
+        Err(AccountError::InvalidPassword) => HttpResponse::BadRequest().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// 確認メールを再送する。確認済みなら 409。
+#[post("/me/email-verification")]
+pub async fn resend_email_verification(
+    user: AuthenticatedUser,
+    accounts: web::Data<AccountService>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match accounts.resend_email_verification(user.0.sub).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(AccountError::AlreadyVerified) => HttpResponse::Conflict().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[post("/auth/verify-email")]
+pub async fn verify_email(
+    accounts: web::Data<AccountService>,
+    payload: web::Json<VerifyEmailInput>,
+) -> impl Responder {
+    match accounts.verify_email(&payload.token).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(AccountError::InvalidToken) => HttpResponse::BadRequest().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// 再設定用のトークンをメールで送る。
+/// 登録の有無を推測させないため、未登録のメールアドレスでも 202 を返す。
+#[post("/auth/password-reset/request")]
+pub async fn request_password_reset(
+    accounts: web::Data<AccountService>,
+    payload: web::Json<PasswordResetRequestInput>,
+) -> impl Responder {
+    match accounts.request_password_reset(&payload.email).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// メールで届いたトークンで新しいパスワードを設定する（トークンは 1 回限り）。
+#[post("/auth/password-reset/confirm")]
+pub async fn confirm_password_reset(
+    accounts: web::Data<AccountService>,
+    payload: web::Json<PasswordResetConfirmInput>,
+) -> impl Responder {
+    match accounts
+        .confirm_password_reset(&payload.token, &payload.new_password)
+        .await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(AccountError::InvalidToken | AccountError::InvalidPassword) => {
+            HttpResponse::BadRequest().finish()
+        }
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// 他のサービスがトークンを検証するための公開鍵（JWKS）。
+/// `JWT_SECRET`（HS256）だけで運用している場合は空の `keys` を返す。
+#[get("/.well-known/jwks.json")]
+pub async fn jwks(jwt: web::Data<JwtTokenService>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "public, max-age=300"))
+        .json(jwt.jwks())
+}
@@ -0,0 +1,291 @@
+use actix_web::rt::time::{Instant, Interval, interval_at, sleep_until};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, get, http::header, web};
+use actix_ws::{CloseCode, CloseReason, Message};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app::model::{EventsQuery, NoteEventMessage};
+use crate::domain::model::SharePermission;
+use crate::domain::model::{Note, Scope, Visibility};
+use crate::domain::policy::{NoteAction, can_access_note, lists_note};
+use crate::middleware::auth::extractor::{AuthenticatedUser, TokenCheck};
+use crate::repository::workspace::WorkspaceRepository;
+use crate::service::events::{EventBus, NoteEvent, NoteEventKind, Received, Subscription};
+use crate::service::share::ShareService;
+
+/// 通知が無くても接続を保つために送る間隔（SSE のコメント / WebSocket の ping）。
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 通知を取りこぼしたときに送る通知の種類。受け取ったら `GET /notes` で取得し直す。
+pub const RESET_EVENT: &str = "reset";
+
+/// `EventBus` が登録されていれば、メモの変更を流す。
+pub(crate) fn publish(req: &HttpRequest, kind: NoteEventKind, note: Note) {
+    if let Some(events) = req.app_data::<web::Data<EventBus>>() {
+        events.publish(kind, note);
+    }
+}
+
+/// `EventBus` が登録されていれば、`before` から `after` へのメモの更新を流す。
+pub(crate) fn publish_update(req: &HttpRequest, before: &Note, after: Note) {
+    if let Some(events) = req.app_data::<web::Data<EventBus>>() {
+        events.publish_update(before, after);
+    }
+}
+
+/// ログインしているユーザーの一覧（`GET /notes`）に出るメモと、ユーザーに個別に共有された
+/// メモの変更を Server-Sent Events で送る。
+///
+/// 各通知の `event` は `note.created` / `note.updated` / `note.deleted`、`data` は
+/// `{"id", "type", "note_id", "note"}`（`note.deleted` では `note` を省く）。
+/// 更新で一覧に出なくなったメモ（`public` でなくなった他人のメモ）は、共有されていなければ
+/// `note.deleted` として届く。
+/// 再接続時に `Last-Event-ID`（または `?last_event_id=`）を渡すと続きから受け取れる。
+/// 続きを送れない場合は `reset` を送るので、メモを取得し直すこと。
+/// ブラウザの `EventSource` のためにトークンは `?access_token=` でも渡せる。トークンの期限が切れると接続を閉じる。
+/// キープアライブのたびにトークンを確かめ直し、失効・削除された（アカウントが無効にされた）場合も閉じる。
+#[get("/events")]
+pub async fn note_events(
+    req: HttpRequest,
+    events: Option<web::Data<EventBus>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+) -> HttpResponse {
+    let mut last_event_id = None;
+    if let Some(value) = req.headers().get("Last-Event-ID") {
+        match value.to_str().ok().and_then(|v| v.parse().ok()) {
+            Some(id) => last_event_id = Some(id),
+            None => return HttpResponse::BadRequest().finish(),
+        }
+    }
+    let feed = match Feed::open(&req, events, workspaces, shares, last_event_id).await {
+        Ok(feed) => feed,
+        Err(resp) => return resp,
+    };
+    let frames = futures_util::stream::unfold(feed, |mut feed| async move {
+        let frame = match feed.next().await? {
+            FeedItem::Event(message) => format!(
+                "id: {}\nevent: {}\ndata: {}\n\n",
+                message.id,
+                message.kind,
+                serde_json::to_string(&message).unwrap_or_default()
+            ),
+            FeedItem::Reset => format!("event: {RESET_EVENT}\ndata: {}\n\n", reset_json()),
+            FeedItem::KeepAlive => ": keepalive\n\n".to_string(),
+        };
+        Some((Ok::<_, Infallible>(Bytes::from(frame)), feed))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        // リバースプロキシ（nginx）にバッファーさせない
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(frames)
+}
+
+/// `GET /events` と同じ通知を WebSocket のテキストメッセージ（`data` と同じ JSON）で送る。
+/// 取りこぼした場合は `{"type": "reset"}` を送る。続きから受け取るには `?last_event_id=` を渡す。
+/// クライアントからのメッセージは使わない。トークンの期限が切れるか失効すると 1008 で閉じる。
+#[get("/events/ws")]
+pub async fn note_events_ws(
+    req: HttpRequest,
+    body: web::Payload,
+    events: Option<web::Data<EventBus>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+) -> HttpResponse {
+    let mut feed = match Feed::open(&req, events, workspaces, shares, None).await {
+        Ok(feed) => feed,
+        Err(resp) => return resp,
+    };
+    let (response, mut session, mut messages) = match actix_ws::handle(&req, body) {
+        Ok(handshake) => handshake,
+        Err(e) => return HttpResponse::from_error(e),
+    };
+
+    // 受信は別のタスクで行う（通知の絞り込みの途中で中断しないように）
+    let mut pong = session.clone();
+    let mut receiver = actix_web::rt::spawn(async move {
+        while let Some(Ok(message)) = messages.recv().await {
+            match message {
+                Message::Ping(bytes) if pong.pong(&bytes).await.is_err() => break,
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+    });
+    actix_web::rt::spawn(async move {
+        let reason = loop {
+            let item = tokio::select! {
+                item = feed.next() => item,
+                _ = &mut receiver => return,
+            };
+            let sent = match item {
+                Some(FeedItem::Event(message)) => {
+                    session
+                        .text(serde_json::to_string(&message).unwrap_or_default())
+                        .await
+                }
+                Some(FeedItem::Reset) => session.text(reset_json()).await,
+                Some(FeedItem::KeepAlive) => session.ping(b"").await,
+                None => {
+                    break CloseReason {
+                        code: CloseCode::Policy,
+                        description: Some("token expired or revoked".to_string()),
+                    };
+                }
+            };
+            if sent.is_err() {
+                return;
+            }
+        };
+        receiver.abort();
+        let _ = session.close(Some(reason)).await;
+    });
+    response
+}
+
+fn reset_json() -> String {
+    serde_json::json!({ "type": RESET_EVENT }).to_string()
+}
+
+enum FeedItem {
+    Event(Box<NoteEventMessage>),
+    Reset,
+    KeepAlive,
+}
+
+/// 1 人の購読者に届ける通知を選ぶ。
+struct Feed {
+    subscription: Subscription,
+    user_id: i64,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    /// トークンの期限（API トークンで期限が無ければ `None`）
+    expires_at: Option<Instant>,
+    token: TokenCheck,
+    keepalive: Interval,
+}
+
+impl Feed {
+    /// 認証して購読を始める。`last_event_id` が無ければ `?last_event_id=` を使う。
+    /// `EventBus` が未登録なら 404。
+    async fn open(
+        req: &HttpRequest,
+        events: Option<web::Data<EventBus>>,
+        workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+        shares: Option<web::Data<ShareService>>,
+        last_event_id: Option<u64>,
+    ) -> Result<Self, HttpResponse> {
+        let user = AuthenticatedUser::from_header_or_query(req)
+            .await
+            .map_err(HttpResponse::from_error)?;
+        if !user.0.has_scope(Scope::NotesRead) {
+            return Err(HttpResponse::Forbidden().finish());
+        }
+        let Some(events) = events else {
+            return Err(HttpResponse::NotFound().finish());
+        };
+        let Ok(query) = web::Query::<EventsQuery>::from_query(req.query_string()) else {
+            return Err(HttpResponse::BadRequest().finish());
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        let remaining = Duration::from_secs(user.0.exp.saturating_sub(now).max(0) as u64);
+        let Some(token) = TokenCheck::new(req, &user) else {
+            return Err(HttpResponse::Unauthorized().finish());
+        };
+        Ok(Self {
+            subscription: events.subscribe(last_event_id.or(query.last_event_id)),
+            user_id: user.0.sub,
+            workspaces,
+            shares,
+            expires_at: Instant::now().checked_add(remaining),
+            token,
+            keepalive: interval_at(Instant::now() + KEEPALIVE_INTERVAL, KEEPALIVE_INTERVAL),
+        })
+    }
+
+    /// 次に送るもの。トークンの期限が切れるか、キープアライブの時点で使えなくなっていたら `None`。
+    async fn next(&mut self) -> Option<FeedItem> {
+        loop {
+            let expired = async {
+                match self.expires_at {
+                    Some(at) => sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let received = tokio::select! {
+                received = self.subscription.recv() => Some(received),
+                _ = self.keepalive.tick() => None,
+                _ = expired => return None,
+            };
+            let Some(received) = received else {
+                return self.token.is_valid().await.then_some(FeedItem::KeepAlive);
+            };
+            match received {
+                Received::Missed => return Some(FeedItem::Reset),
+                Received::Event(event) => {
+                    if let Some(message) = self.message_for(&event).await {
+                        return Some(FeedItem::Event(Box::new(message)));
+                    }
+                }
+            }
+        }
+    }
+
+    /// `event` をこのユーザーに届ける形にする。一覧に出ず、共有もされていないメモなら `None`。
+    async fn message_for(&self, event: &NoteEvent) -> Option<NoteEventMessage> {
+        let note = &event.note;
+        // 役割が関係するのは `public` でないワークスペースのメモだけ。調べられなければ届けない
+        let role = match (note.workspace_id, &self.workspaces) {
+            (Some(workspace_id), Some(repo)) if note.visibility != Visibility::Public => {
+                repo.member_role(workspace_id, self.user_id).await.ok()?
+            }
+            _ => None,
+        };
+        let kind = if lists_note(note, note.visibility, self.user_id, role) {
+            event.kind
+        } else if self.shared_permission(note.id).await?.is_some_and(|share| {
+            can_access_note(
+                note,
+                Some(self.user_id),
+                role,
+                Some(share),
+                NoteAction::Read,
+            )
+        }) {
+            // `GET /notes/{id}` で閲覧できる共有されたメモ
+            event.kind
+        } else if event
+            .previous_visibility
+            .is_some_and(|visibility| lists_note(note, visibility, self.user_id, role))
+        {
+            NoteEventKind::Deleted
+        } else {
+            return None;
+        };
+        Some(NoteEventMessage {
+            id: event.id,
+            kind: kind.as_str().to_string(),
+            note_id: note.id,
+            note: (kind != NoteEventKind::Deleted).then(|| note.clone()),
+        })
+    }
+
+    /// このユーザーへの個別の共有の権限。`ShareService` が未登録なら共有は無いものとして扱い、
+    /// 調べられなければ `None`（届けない）。
+    async fn shared_permission(&self, note_id: i64) -> Option<Option<SharePermission>> {
+        let Some(shares) = &self.shares else {
+            return Some(None);
+        };
+        shares
+            .permission_for(note_id, Some(self.user_id), None, None)
+            .await
+            .ok()
+    }
+}
@@ -0,0 +1,45 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use std::sync::Arc;
+
+use crate::app::model::{GraphFormat, GraphQuery};
+use crate::domain::model::Scope;
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::repository::link::LinkRepository;
+
+const DEFAULT_GRAPH_DEPTH: u32 = 1;
+const MAX_GRAPH_DEPTH: u32 = 5;
+
+/// 自分が作成したメモのグラフ（ノードはメモ、エッジはリンクと共通のタグ）。
+/// `?note=<id>` を指定すると、そのメモから `depth` 本以内でたどれるメモだけを返す
+/// （自分のメモでなければ 404）。`?format=dot` なら Graphviz の DOT 形式で返す。
+#[get("/graph")]
+pub async fn note_graph(
+    user: AuthenticatedUser,
+    link_repo: web::Data<Arc<dyn LinkRepository>>,
+    query: web::Query<GraphQuery>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesRead) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let depth = query.depth.unwrap_or(DEFAULT_GRAPH_DEPTH);
+    if depth > MAX_GRAPH_DEPTH {
+        return HttpResponse::BadRequest().finish();
+    }
+    let graph = match link_repo.note_graph(user.0.sub).await {
+        Ok(graph) => graph,
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    let graph = match query.note {
+        Some(note_id) => match graph.neighborhood(note_id, depth) {
+            Some(graph) => graph,
+            None => return HttpResponse::NotFound().finish(),
+        },
+        None => graph,
+    };
+    match query.format {
+        GraphFormat::Json => HttpResponse::Ok().json(graph),
+        GraphFormat::Dot => HttpResponse::Ok()
+            .content_type("text/vnd.graphviz; charset=utf-8")
+            .body(graph.to_dot()),
+    }
+}
@@ -0,0 +1,70 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use std::sync::Arc;
+
+use crate::app::model::NoteLinksQuery;
+use crate::app::revisions::find_visible_note;
+use crate::domain::model::Scope;
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::repository::link::LinkRepository;
+use crate::repository::note::NoteRepository;
+use crate::repository::workspace::WorkspaceRepository;
+
+/// メモ本文中のリンク（`[[タイトル]]` / `[[#123]]`）を出現順に返す。
+/// リンク先が無いか閲覧できないリンク（リンク切れ）は `note_id` が `null`。
+/// `?dangling=true` ならリンク切れだけを返す。
+#[get("/notes/{id}/links")]
+pub async fn list_links(
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    link_repo: web::Data<Arc<dyn LinkRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    path: web::Path<i64>,
+    query: web::Query<NoteLinksQuery>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let note_id = path.into_inner();
+    let viewer = user.map(|u| u.0.sub);
+    if let Err(resp) = find_visible_note(&note_repo, workspaces.as_ref(), note_id, viewer).await {
+        return resp;
+    }
+    match link_repo.links_from(note_id, viewer).await {
+        Ok(mut links) => {
+            if query.dangling {
+                links.retain(|link| link.note_id.is_none());
+            }
+            HttpResponse::Ok().json(links)
+        }
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// このメモにリンクしているメモ（閲覧範囲は `GET /notes` と同じ）を更新日時の新しい順に返す。
+#[get("/notes/{id}/backlinks")]
+pub async fn list_backlinks(
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    link_repo: web::Data<Arc<dyn LinkRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let note_id = path.into_inner();
+    let viewer = user.map(|u| u.0.sub);
+    if let Err(resp) = find_visible_note(&note_repo, workspaces.as_ref(), note_id, viewer).await {
+        return resp;
+    }
+    match link_repo.backlinks(note_id, viewer).await {
+        Ok(notes) => HttpResponse::Ok().json(notes),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
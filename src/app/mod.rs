@@ -1,3 +1,16 @@
+pub mod admin;
+pub mod attachments;
 pub mod auth;
+pub mod events;
+pub mod graph;
+pub mod links;
 pub mod model;
+pub mod notebooks;
 pub mod notes;
+pub mod revisions;
+pub mod shares;
+pub mod tags;
+pub mod tokens;
+pub mod trash;
+pub mod two_factor;
+pub mod workspaces;
@@ -1,30 +1,364 @@
 use serde::{Deserialize, Serialize};
 
+use crate::domain::diff::DiffLine;
+use crate::domain::model::{
+    ApiToken, Note, Role, Scope, ShareLink, SharePermission, User, Visibility, WorkspaceRole,
+};
+use crate::repository::note::{NoteSort, SortOrder, TagMatch};
+use crate::repository::notebook::NotebookDeleteMode;
+
 #[derive(Deserialize, Serialize)]
 pub struct SignupInput {
     pub email: String,
     pub password: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct LoginInput {
     pub email: String,
     pub password: String,
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct LoginOutput {
-    pub token: String, // JWT
+    pub token: String,         // JWT（アクセストークン）
+    pub refresh_token: String, // `POST /auth/refresh` で新しいトークンと交換する
+}
+
+/// 2 段階認証が有効なユーザーの `POST /auth/login` の応答（202）。
+#[derive(Deserialize, Serialize)]
+pub struct TwoFactorChallenge {
+    pub challenge_token: String, // `POST /auth/login/2fa` に渡す（5 分間・1 回限り）
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct LoginTwoFactorInput {
+    pub challenge_token: String,
+    pub code: String, // 認証アプリの 6 桁のコード、またはリカバリーコード
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ConfirmTotpInput {
+    pub code: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RecoveryCodesOutput {
+    pub recovery_codes: Vec<String>, // 各 1 回限り。このレスポンスでしか返さない
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RefreshInput {
+    pub refresh_token: String,
+}
+
+/// `POST /auth/logout` の本文（省略可）。
+#[derive(Deserialize, Serialize, Default)]
+pub struct LogoutInput {
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ChangePasswordInput {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PasswordResetRequestInput {
+    pub email: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PasswordResetConfirmInput {
+    pub token: String, // メールで届いたトークン
+    pub new_password: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct VerifyEmailInput {
+    pub token: String, // メールで届いたトークン
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct CreateNoteInput {
     pub title: String,
     pub content: String,
+    #[serde(default)]
+    pub visibility: Visibility, // 省略時は private
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub workspace_id: Option<i64>, // 指定時はワークスペースのメモ（オーナー・編集者のみ）
+    pub notebook_id: Option<i64>,  // 指定時は自分のノートブックに入れる（個人のメモのみ）
 }
 
 #[derive(Deserialize, Serialize)]
 pub struct UpdateNoteInput {
     pub title: Option<String>,
     pub content: Option<String>,
+    pub visibility: Option<Visibility>,
+    pub tags: Option<Vec<String>>, // 指定時はタグ一覧を置き換える
+}
+
+/// `GET /notes` のクエリパラメータ。
+/// `tag` は繰り返し指定できるため `serde_html_form` でデコードする。
+#[derive(Deserialize, Serialize, Default)]
+pub struct ListNotesQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>, // 前ページの `next_cursor`
+    pub author: Option<i64>,
+    pub workspace: Option<i64>,
+    pub notebook: Option<i64>,
+    #[serde(default, rename = "tag")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub tag_mode: TagMatch, // all（既定） | any
+    #[serde(default)]
+    pub sort: NoteSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    #[serde(default)]
+    pub starred: bool, // true ならスター付きのメモだけ
+    #[serde(default)]
+    pub archived: bool, // true ならアーカイブしたメモだけ
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct NotePage {
+    pub items: Vec<Note>,
+    pub next_cursor: Option<String>, // 次ページが無ければ null
+}
+
+/// `GET /notes/search` のクエリパラメータ。
+#[derive(Deserialize, Serialize)]
+pub struct SearchNotesQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RenameTagInput {
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MergeTagsInput {
+    pub sources: Vec<String>,
+    pub target: String,
+}
+
+/// `GET /notes/{id}/links` のクエリパラメータ。
+#[derive(Deserialize, Serialize, Default)]
+pub struct NoteLinksQuery {
+    #[serde(default)]
+    pub dangling: bool, // true ならリンク切れだけ
+}
+
+/// `GET /graph` のレスポンスの形式（`?format=`）。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    #[default]
+    Json,
+    Dot, // Graphviz
+}
+
+/// `GET /graph` のクエリパラメータ。
+#[derive(Deserialize, Serialize, Default)]
+pub struct GraphQuery {
+    #[serde(default)]
+    pub format: GraphFormat,
+    pub note: Option<i64>,  // 指定するとこのメモの周辺だけ
+    pub depth: Option<u32>, // `note` からたどる深さ（0..=5、既定 1）
+}
+
+/// `GET /attachments/{id}/thumb` のクエリパラメータ。
+#[derive(Deserialize, Serialize, Default)]
+pub struct ThumbnailQuery {
+    pub w: Option<u32>, // 幅（ピクセル、既定 320）。用意してある幅のうち近いものに丸める
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share: Option<String>, // 共有リンクのトークン
+}
+
+/// `GET /notes/{id}/diff` のクエリパラメータ（リビジョン番号）。
+#[derive(Deserialize, Serialize)]
+pub struct NoteDiffQuery {
+    pub from: i64,
+    pub to: i64,
+}
+
+/// 2 つのリビジョン間の本文の行差分。
+#[derive(Deserialize, Serialize)]
+pub struct NoteDiff {
+    pub from: i64,
+    pub to: i64,
+    pub from_title: String,
+    pub to_title: String,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreateApiTokenInput {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_in_days: Option<i64>, // 省略時は無期限
+}
+
+/// 発行直後のみトークン本体（`token`）を返す。
+#[derive(Deserialize, Serialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub api_token: ApiToken,
+    pub token: String,
+}
+
+/// `GET /admin/users` のクエリパラメータ。
+#[derive(Deserialize, Serialize, Default)]
+pub struct ListUsersQuery {
+    pub q: Option<String>, // メールアドレスの部分一致
+    pub limit: Option<i64>,
+    pub after: Option<i64>, // 前ページの `next_after`
+}
+
+/// 管理用 API で返すユーザー（パスワードのハッシュは含めない）。
+#[derive(Deserialize, Serialize)]
+pub struct UserSummary {
+    pub id: i64,
+    pub email: String,
+    pub role: Role,
+    pub created_at: i64,
+    pub email_verified_at: Option<i64>,
+    pub locked_until: Option<i64>,
+    pub disabled_at: Option<i64>,
+}
+
+impl From<User> for UserSummary {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            email: user.email,
+            role: user.role,
+            created_at: user.created_at,
+            email_verified_at: user.email_verified_at,
+            locked_until: user.locked_until,
+            disabled_at: user.disabled_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct UserPage {
+    pub items: Vec<UserSummary>,
+    pub next_after: Option<i64>, // 次ページが無ければ null
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreateWorkspaceInput {
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SetMemberRoleInput {
+    pub role: WorkspaceRole,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct InviteMemberInput {
+    pub email: String,
+    pub role: WorkspaceRole,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AcceptInvitationInput {
+    pub token: String, // メールで届いたトークン
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AcceptedInvitation {
+    pub workspace_id: i64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ShareNoteInput {
+    pub email: String,
+    pub permission: SharePermission,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreateShareLinkInput {
+    pub permission: SharePermission,
+    pub expires_in_days: Option<i64>, // 省略時は無期限
+    pub password: Option<String>,     // 指定すると閲覧時に `X-Share-Password` が必要
+}
+
+/// 作成直後のみリンクのトークン本体（`token`）を返す。
+#[derive(Deserialize, Serialize)]
+pub struct CreatedShareLink {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    pub token: String,
+}
+
+/// `GET /notes/{id}` / `PUT /notes/{id}` の共有リンクのトークン（`?share=<token>`）。
+#[derive(Deserialize, Serialize, Default)]
+pub struct ShareLinkQuery {
+    pub share: Option<String>,
+}
+
+/// `GET /notes/{id}` のレスポンスの形式（`?format=`）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteFormat {
+    Json,
+    Html, // 本文を HTML に変換したもの
+}
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct NoteFormatQuery {
+    pub format: Option<NoteFormat>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreateNotebookInput {
+    pub name: String,
+    pub parent_id: Option<i64>, // 省略時はトップレベル
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RenameNotebookInput {
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MoveNotebookInput {
+    pub parent_id: Option<i64>, // null ならトップレベルに移す
+}
+
+/// `DELETE /notebooks/{id}` のクエリパラメータ。
+#[derive(Deserialize, Serialize, Default)]
+pub struct DeleteNotebookQuery {
+    #[serde(default)]
+    pub mode: NotebookDeleteMode, // reparent（既定） | cascade
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FileNoteInput {
+    pub notebook_id: Option<i64>, // null ならノートブックから出す
+}
+
+/// `GET /events` と `GET /events/ws` のクエリパラメータ。
+#[derive(Deserialize, Serialize, Default)]
+pub struct EventsQuery {
+    pub last_event_id: Option<u64>, // 最後に受け取った通知の ID（SSE は `Last-Event-ID` ヘッダーでもよい）
+}
+
+/// `GET /events` と `GET /events/ws` で送るメモの変更の通知。
+#[derive(Deserialize, Serialize)]
+pub struct NoteEventMessage {
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub kind: String, // note.created | note.updated | note.deleted
+    pub note_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<Note>, // note.deleted では省く
 }
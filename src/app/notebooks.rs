@@ -0,0 +1,182 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, put, web};
+use std::sync::Arc;
+
+use crate::app::events::publish;
+use crate::app::model::{
+    CreateNotebookInput, DeleteNotebookQuery, FileNoteInput, ListNotesQuery, MoveNotebookInput,
+    RenameNotebookInput,
+};
+use crate::app::notes::note_page;
+use crate::domain::model::Scope;
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::repository::note::NoteRepository;
+use crate::repository::notebook::NotebookDeleteMode;
+use crate::service::events::NoteEventKind;
+use crate::service::notebook::{NotebookError, NotebookService};
+
+// ノートブックは作成したユーザーだけが使え、他人のノートブックは 404。
+// 参照には `notes:read`、変更には `notes:write` のスコープが必要。
+
+#[post("/notebooks")]
+pub async fn create_notebook(
+    user: AuthenticatedUser,
+    notebooks: web::Data<NotebookService>,
+    payload: web::Json<CreateNotebookInput>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match notebooks
+        .create(user.0.sub, payload.parent_id, &payload.name)
+        .await
+    {
+        Ok(notebook) => HttpResponse::Created().json(notebook),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 自分のノートブックをすべて名前順に返す（階層は `parent_id` で組み立てる）。
+#[get("/notebooks")]
+pub async fn list_notebooks(
+    user: AuthenticatedUser,
+    notebooks: web::Data<NotebookService>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesRead) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match notebooks.list(user.0.sub).await {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => error_response(e),
+    }
+}
+
+#[put("/notebooks/{id}")]
+pub async fn rename_notebook(
+    user: AuthenticatedUser,
+    notebooks: web::Data<NotebookService>,
+    path: web::Path<i64>,
+    payload: web::Json<RenameNotebookInput>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match notebooks
+        .rename(user.0.sub, path.into_inner(), &payload.name)
+        .await
+    {
+        Ok(notebook) => HttpResponse::Ok().json(notebook),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 別のノートブックの下（`parent_id: null` ならトップレベル）に移す。
+/// 自分自身や子孫の下には移せない（409）。
+#[put("/notebooks/{id}/parent")]
+pub async fn move_notebook(
+    user: AuthenticatedUser,
+    notebooks: web::Data<NotebookService>,
+    path: web::Path<i64>,
+    payload: web::Json<MoveNotebookInput>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match notebooks
+        .move_to(user.0.sub, path.into_inner(), payload.parent_id)
+        .await
+    {
+        Ok(notebook) => HttpResponse::Ok().json(notebook),
+        Err(e) => error_response(e),
+    }
+}
+
+/// `?mode=reparent`（既定）なら中の子ノートブックとメモを親に移し、
+/// `?mode=cascade` なら子孫のノートブックごと削除して中のメモをゴミ箱に移動する。
+#[delete("/notebooks/{id}")]
+pub async fn delete_notebook(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    notebooks: web::Data<NotebookService>,
+    path: web::Path<i64>,
+    query: web::Query<DeleteNotebookQuery>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match notebooks
+        .delete(user.0.sub, path.into_inner(), query.mode)
+        .await
+    {
+        Ok(notes) => {
+            let kind = match query.mode {
+                NotebookDeleteMode::Reparent => NoteEventKind::Updated,
+                NotebookDeleteMode::Cascade => NoteEventKind::Deleted,
+            };
+            for note in notes {
+                publish(&req, kind, note);
+            }
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+/// ノートブックに直接入っているメモ（子のノートブックのメモは含めない）。
+/// クエリとレスポンスは `GET /notes` と同じ（`notebook` は指定しても無視する）。
+#[get("/notebooks/{id}/notes")]
+pub async fn list_notebook_notes(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    notebooks: web::Data<NotebookService>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesRead) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let Ok(mut query) = serde_html_form::from_str::<ListNotesQuery>(req.query_string()) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    let notebook = match notebooks.find(user.0.sub, path.into_inner()).await {
+        Ok(notebook) => notebook,
+        Err(e) => return error_response(e),
+    };
+    query.notebook = Some(notebook.id);
+    note_page(note_repo.get_ref().as_ref(), Some(user.0.sub), query).await
+}
+
+/// メモをノートブックに入れる（`notebook_id: null` ならノートブックから出す）。
+/// 自分が作成した個人のメモだけが対象で、それ以外は 404（ワークスペースのメモは 400）。
+#[put("/notes/{id}/notebook")]
+pub async fn file_note(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    notebooks: web::Data<NotebookService>,
+    path: web::Path<i64>,
+    payload: web::Json<FileNoteInput>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match notebooks
+        .file_note(user.0.sub, path.into_inner(), payload.notebook_id)
+        .await
+    {
+        Ok(note) => {
+            publish(&req, NoteEventKind::Updated, note.clone());
+            HttpResponse::Ok().json(note)
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(e: NotebookError) -> HttpResponse {
+    match e {
+        NotebookError::NotFound => HttpResponse::NotFound().finish(),
+        NotebookError::Cycle => HttpResponse::Conflict().finish(),
+        NotebookError::InvalidName | NotebookError::WorkspaceNote => {
+            HttpResponse::BadRequest().finish()
+        }
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
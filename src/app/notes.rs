@@ -1,78 +1,478 @@
-use actix_web::{HttpResponse, Responder, delete, get, post, put, web};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, Accept, ETag, EntityTag, Header, IfMatch};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, put, web};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use std::collections::HashSet;
 use std::sync::Arc;
 
+use crate::app::auth::{ip_key, too_many_requests};
+use crate::app::events::{publish, publish_update};
 use crate::app::model::CreateNoteInput;
 use crate::app::model::UpdateNoteInput;
+use crate::app::model::{
+    ListNotesQuery, NoteFormat, NoteFormatQuery, NotePage, SearchNotesQuery, ShareLinkQuery,
+    ThumbnailQuery,
+};
+use crate::app::shares::error_response as share_error_response;
+use crate::domain::image::RENDERED_THUMBNAIL_WIDTH;
+use crate::domain::markdown::{render_html, render_html_with};
+use crate::domain::model::{Note, Scope, SharePermission};
+use crate::domain::policy::{NoteAction, can_access_note};
+use crate::domain::tag::normalize_tags;
 use crate::middleware::auth::extractor::AuthenticatedUser;
-use crate::repository::note::NoteRepository;
+use crate::repository::note::{
+    NewNote, NoteChanges, NoteCursor, NoteListQuery, NoteRepository, NoteSort, NoteSortKey,
+    UpdatedNote,
+};
+use crate::repository::user::RepoError;
+use crate::repository::workspace::WorkspaceRepository;
+use crate::service::attachment::AttachmentService;
+use crate::service::events::NoteEventKind;
+use crate::service::login_throttle::LoginThrottle;
+use crate::service::notebook::{NotebookError, NotebookService};
+use crate::service::share::{ShareError, ShareService};
 
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+
+/// パスワード付きの共有リンクで、パスワードを渡すヘッダー。
+pub const SHARE_PASSWORD_HEADER: &str = "X-Share-Password";
+
+/// `viewer` が `note` に対して `action` を行えるか（`domain::policy::can_access_note`）。
+/// ワークスペースのメモなら `viewer` の役割を調べる（`WorkspaceRepository` が未登録なら非メンバー扱い）。
+/// `share` は `shared_permission` で求めた共有の権限。
+pub(crate) async fn authorize(
+    workspaces: Option<&web::Data<Arc<dyn WorkspaceRepository>>>,
+    note: &Note,
+    viewer: Option<i64>,
+    share: Option<SharePermission>,
+    action: NoteAction,
+) -> Result<bool, RepoError> {
+    let role = match (note.workspace_id, viewer, workspaces) {
+        (Some(workspace_id), Some(user_id), Some(repo)) => {
+            repo.member_role(workspace_id, user_id).await?
+        }
+        _ => None,
+    };
+    Ok(can_access_note(note, viewer, role, share, action))
+}
+
+/// 個別の共有と共有リンク（`?share=<token>`）で `viewer` に与えられた権限。
+/// `ShareService` が未登録なら共有は無いものとして扱う。
+/// パスワード付きのリンクで `X-Share-Password` が無いか一致しなければ 401。
+/// `LoginThrottle` が登録されていれば、パスワードの誤りが続くとリンク・IP アドレスごとに
+/// 次の試行まで待たせる（429 と `Retry-After`）。
+pub(crate) async fn shared_permission(
+    shares: Option<&web::Data<ShareService>>,
+    req: &HttpRequest,
+    note_id: i64,
+    viewer: Option<i64>,
+) -> Result<Option<SharePermission>, HttpResponse> {
+    let Some(shares) = shares else {
+        return Ok(None);
+    };
+    let Ok(query) = web::Query::<ShareLinkQuery>::from_query(req.query_string()) else {
+        return Err(HttpResponse::BadRequest().finish());
+    };
+    let password = req
+        .headers()
+        .get(SHARE_PASSWORD_HEADER)
+        .and_then(|v| v.to_str().ok());
+    let throttle = req.app_data::<web::Data<LoginThrottle>>();
+    let link_key = query.share.as_deref().map(|token| format!("share:{token}"));
+    let keys: Vec<String> = ip_key(req).into_iter().chain(link_key.clone()).collect();
+    if let (Some(throttle), Some(_)) = (throttle, &link_key)
+        && let Err(wait) = throttle.check(&keys)
+    {
+        return Err(too_many_requests(wait));
+    }
+    let result = shares
+        .permission_for(note_id, viewer, query.share.as_deref(), password)
+        .await;
+    if let (Some(throttle), Some(link_key), Some(_)) = (throttle, &link_key, password) {
+        match &result {
+            Ok(_) => throttle.reset(link_key),
+            Err(ShareError::PasswordRequired) => throttle.record_failure(&keys),
+            Err(_) => {}
+        }
+    }
+    result.map_err(share_error_response)
+}
+
+/// 読み込んだメモに `action` の権限があるか確かめる。
+/// 閲覧もできないメモは 404（存在自体を秘匿する）、閲覧はできるが操作できないメモは 403。
+pub(crate) async fn authorize_write(
+    workspaces: Option<&web::Data<Arc<dyn WorkspaceRepository>>>,
+    note: &Note,
+    user_id: i64,
+    share: Option<SharePermission>,
+    action: NoteAction,
+) -> Result<(), HttpResponse> {
+    let allowed = |action| async move {
+        authorize(workspaces, note, Some(user_id), share, action)
+            .await
+            .map_err(|_| HttpResponse::InternalServerError().finish())
+    };
+    if allowed(action).await? {
+        Ok(())
+    } else if allowed(NoteAction::Read).await? {
+        Err(HttpResponse::Forbidden().finish())
+    } else {
+        Err(HttpResponse::NotFound().finish())
+    }
+}
+
+/// 未ログインでも呼び出せる。閲覧できないメモ（`private` なメモを作成者・ワークスペースの
+/// メンバー・共有された人以外が見ようとした場合）は 404 を返す（存在自体を秘匿するため 403 にはしない）。
+/// `?share=<token>` で共有リンクのトークンを渡すと、リンクの権限で閲覧できる。
+/// レスポンスの `ETag` は更新・削除時の `If-Match` に使う。
+/// `?format=html`（または `Accept: text/html`）なら本文を無害化した HTML に変換して返す
+/// （このメモの添付画像はサムネイルで表示する）。
 #[get("/notes/{id}")]
 pub async fn get_note(
+    req: HttpRequest,
+    user: Option<AuthenticatedUser>,
     note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    attachments: Option<web::Data<AttachmentService>>,
     path: web::Path<i64>,
 ) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let Some(format) = negotiate_format(&req) else {
+        return HttpResponse::BadRequest().finish();
+    };
     let note_id = path.into_inner();
-    match note_repo.find_by_id(note_id).await {
-        Ok(Some(note)) => HttpResponse::Ok().json(note),
-        Ok(None) => HttpResponse::NotFound().finish(),
+    let viewer = user.map(|u| u.0.sub);
+    let note = match note_repo.find_by_id(note_id).await {
+        Ok(Some(note)) => note,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    let share = match shared_permission(shares.as_ref(), &req, note_id, viewer).await {
+        Ok(share) => share,
+        Err(resp) => return resp,
+    };
+    match authorize(workspaces.as_ref(), &note, viewer, share, NoteAction::Read).await {
+        Ok(true) if format == NoteFormat::Html => {
+            let html = render_note_html(&req, &note, attachments.as_ref()).await;
+            HttpResponse::Ok()
+                .insert_header(note_etag(&note))
+                .insert_header((header::VARY, "Accept"))
+                .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+                // 無害化に漏れがあってもスクリプトは動かないようにしておく
+                .insert_header((
+                    header::CONTENT_SECURITY_POLICY,
+                    "default-src 'none'; img-src * data:; style-src 'unsafe-inline'",
+                ))
+                .content_type("text/html; charset=utf-8")
+                .body(html)
+        }
+        Ok(true) => HttpResponse::Ok()
+            .insert_header(note_etag(&note))
+            .insert_header((header::VARY, "Accept"))
+            .json(note),
+        Ok(false) => HttpResponse::NotFound().finish(),
         Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
 
+/// `workspace_id` を指定するとワークスペースのメモになる（オーナー・編集者のみ。
+/// メンバーでなければ 404、閲覧者なら 403）。
+/// `notebook_id` を指定すると自分のノートブックに入れる（無ければ 404。
+/// ワークスペースのメモには指定できない）。
 #[post("/notes")]
 pub async fn create_note(
+    req: HttpRequest,
     user: AuthenticatedUser,
     note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    notebooks: Option<web::Data<NotebookService>>,
     payload: web::Json<CreateNoteInput>,
 ) -> impl Responder {
-    match note_repo
-        .create_note(user.0.sub, &payload.title, &payload.content)
-        .await
-    {
-        Ok(note) => HttpResponse::Created().json(note),
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let Ok(tags) = normalize_tags(&payload.tags) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    if let Some(notebook_id) = payload.notebook_id {
+        if payload.workspace_id.is_some() {
+            return HttpResponse::BadRequest().finish();
+        }
+        let Some(notebooks) = notebooks else {
+            return HttpResponse::NotFound().finish();
+        };
+        match notebooks.find(user.0.sub, notebook_id).await {
+            Ok(_) => {}
+            Err(NotebookError::NotFound) => return HttpResponse::NotFound().finish(),
+            Err(_) => return HttpResponse::InternalServerError().finish(),
+        }
+    }
+    if let Some(workspace_id) = payload.workspace_id {
+        let Some(workspaces) = workspaces else {
+            return HttpResponse::NotFound().finish();
+        };
+        match workspaces.member_role(workspace_id, user.0.sub).await {
+            Ok(Some(role)) if role.can_edit() => {}
+            Ok(Some(_)) => return HttpResponse::Forbidden().finish(),
+            Ok(None) => return HttpResponse::NotFound().finish(),
+            Err(_) => return HttpResponse::InternalServerError().finish(),
+        }
+    }
+    let new_note = NewNote {
+        title: &payload.title,
+        content: &payload.content,
+        visibility: payload.visibility,
+        tags: &tags,
+        workspace_id: payload.workspace_id,
+        notebook_id: payload.notebook_id,
+    };
+    match note_repo.create_note(user.0.sub, &new_note).await {
+        Ok(note) => {
+            publish(&req, NoteEventKind::Created, note.clone());
+            HttpResponse::Created().json(note)
+        }
         Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
 
+/// 作成者（ワークスペースのメモならオーナー・編集者）と `write` で共有された人のみ更新できる。
+/// `write` の共有リンク（`?share=<token>`）でも更新できるが、編集者を履歴に残すためログインが必要。
+/// `If-Match` を指定した場合、保存されているバージョンと異なれば 412 を返す。
 #[put("/notes/{id}")]
 pub async fn update_note(
+    req: HttpRequest,
     user: AuthenticatedUser,
     note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
     path: web::Path<i64>,
     payload: web::Json<UpdateNoteInput>,
 ) -> impl Responder {
-    let note_id = path.into_inner();
-    let user_id = user.0.sub;
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let tags = match payload.tags.as_deref().map(normalize_tags) {
+        Some(Ok(tags)) => Some(tags),
+        Some(Err(_)) => return HttpResponse::BadRequest().finish(),
+        None => None,
+    };
+    let changes = NoteChanges {
+        title: payload.title.as_deref(),
+        content: payload.content.as_deref(),
+        visibility: payload.visibility,
+        tags: tags.as_deref(),
+        ..Default::default()
+    };
+    change_note(
+        &req,
+        user.0.sub,
+        note_repo.get_ref().as_ref(),
+        workspaces.as_ref(),
+        shares.as_ref(),
+        path.into_inner(),
+        changes,
+    )
+    .await
+}
+
+/// メモを固定して `GET /notes` の先頭に表示する。
+/// 固定・スター・アーカイブは更新と同じ権限で変更でき、`If-Match` も同じように扱う。
+/// どれもメモ自体の状態なので、変更は閲覧できる全員に反映される。
+#[put("/notes/{id}/pin")]
+pub async fn pin_note(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let changes = NoteChanges {
+        pinned: Some(true),
+        ..Default::default()
+    };
+    set_state(req, user, note_repo, workspaces, shares, path, changes).await
+}
+
+#[delete("/notes/{id}/pin")]
+pub async fn unpin_note(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let changes = NoteChanges {
+        pinned: Some(false),
+        ..Default::default()
+    };
+    set_state(req, user, note_repo, workspaces, shares, path, changes).await
+}
+
+/// スターを付ける（`GET /notes?starred=true` で絞り込める）。
+#[put("/notes/{id}/star")]
+pub async fn star_note(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let changes = NoteChanges {
+        starred: Some(true),
+        ..Default::default()
+    };
+    set_state(req, user, note_repo, workspaces, shares, path, changes).await
+}
+
+#[delete("/notes/{id}/star")]
+pub async fn unstar_note(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let changes = NoteChanges {
+        starred: Some(false),
+        ..Default::default()
+    };
+    set_state(req, user, note_repo, workspaces, shares, path, changes).await
+}
+
+/// アーカイブする。アーカイブしたメモは `GET /notes` に出なくなり、
+/// `?archived=true` でだけ一覧できる（ID 指定の取得や検索には影響しない）。
+#[put("/notes/{id}/archive")]
+pub async fn archive_note(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let changes = NoteChanges {
+        archived: Some(true),
+        ..Default::default()
+    };
+    set_state(req, user, note_repo, workspaces, shares, path, changes).await
+}
+
+#[delete("/notes/{id}/archive")]
+pub async fn unarchive_note(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let changes = NoteChanges {
+        archived: Some(false),
+        ..Default::default()
+    };
+    set_state(req, user, note_repo, workspaces, shares, path, changes).await
+}
+
+/// 固定・スター・アーカイブの各ハンドラーの共通部分。
+async fn set_state(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    path: web::Path<i64>,
+    changes: NoteChanges<'_>,
+) -> HttpResponse {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    change_note(
+        &req,
+        user.0.sub,
+        note_repo.get_ref().as_ref(),
+        workspaces.as_ref(),
+        shares.as_ref(),
+        path.into_inner(),
+        changes,
+    )
+    .await
+}
+
+/// 権限と `If-Match` を確かめてから `changes` を適用し、更新後のメモを ETag 付きで返す。
+pub(crate) async fn change_note(
+    req: &HttpRequest,
+    user_id: i64,
+    note_repo: &dyn NoteRepository,
+    workspaces: Option<&web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<&web::Data<ShareService>>,
+    note_id: i64,
+    mut changes: NoteChanges<'_>,
+) -> HttpResponse {
     let note = match note_repo.find_by_id(note_id).await {
         Ok(Some(note)) => note,
         Ok(None) => return HttpResponse::NotFound().finish(),
         Err(_) => return HttpResponse::InternalServerError().finish(),
     };
-    if !note.is_owner(user_id) {
-        return HttpResponse::Forbidden().finish();
+    let share = match shared_permission(shares, req, note_id, Some(user_id)).await {
+        Ok(share) => share,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = authorize_write(workspaces, &note, user_id, share, NoteAction::Update).await
+    {
+        return resp;
     }
+    changes.expected_version = match expected_version(req, note.version) {
+        Ok(version) => version,
+        Err(status) => return HttpResponse::new(status),
+    };
 
-    match note_repo
-        .update_note(
-            note_id,
-            user_id,
-            payload.title.as_deref(),
-            payload.content.as_deref(),
-        )
-        .await
-    {
-        Ok(Some(note)) => HttpResponse::Ok().json(note),
+    match note_repo.update_note(note_id, user_id, &changes).await {
+        Ok(Some(UpdatedNote {
+            note: updated,
+            relinked,
+        })) => {
+            publish_update(req, &note, updated.clone());
+            // リンクを書き換えた他のメモも更新として流す
+            for source in relinked {
+                publish(req, NoteEventKind::Updated, source);
+            }
+            HttpResponse::Ok()
+                .insert_header(note_etag(&updated))
+                .json(updated)
+        }
         Ok(None) => HttpResponse::NotFound().finish(),
+        Err(RepoError::VersionMismatch) => HttpResponse::PreconditionFailed().finish(),
         Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
 
+/// メモを作成者のゴミ箱に移動する（作成者が `POST /notes/{id}/restore` で戻せる）。
+/// 作成者（ワークスペースのメモならオーナーと、作成者本人の編集者）のみ削除できる。
+/// `If-Match` の扱いは `update_note` と同じ。
 #[delete("/notes/{id}")]
 pub async fn delete_note(
+    req: HttpRequest,
     user: AuthenticatedUser,
     note_repo: web::Data<Arc<dyn NoteRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
     path: web::Path<i64>,
 ) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
     let note_id = path.into_inner();
     let user_id = user.0.sub;
     let note = match note_repo.find_by_id(note_id).await {
@@ -80,20 +480,260 @@ pub async fn delete_note(
         Ok(None) => return HttpResponse::NotFound().finish(),
         Err(_) => return HttpResponse::InternalServerError().finish(),
     };
-    if !note.is_owner(user_id) {
-        return HttpResponse::Forbidden().finish();
+    // 共有では削除できないが、共有された人には 404 ではなく 403 を返す
+    let share = match shared_permission(shares.as_ref(), &req, note_id, Some(user_id)).await {
+        Ok(share) => share,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = authorize_write(
+        workspaces.as_ref(),
+        &note,
+        user_id,
+        share,
+        NoteAction::Delete,
+    )
+    .await
+    {
+        return resp;
     }
+    let expected_version = match expected_version(&req, note.version) {
+        Ok(version) => version,
+        Err(status) => return HttpResponse::new(status),
+    };
 
-    match note_repo.delete_note(note_id, user_id).await {
-        Ok(true) => HttpResponse::NoContent().finish(),
+    match note_repo.delete_note(note_id, expected_version).await {
+        Ok(true) => {
+            publish(&req, NoteEventKind::Deleted, note);
+            HttpResponse::NoContent().finish()
+        }
         Ok(false) => HttpResponse::NotFound().finish(),
+        Err(RepoError::VersionMismatch) => HttpResponse::PreconditionFailed().finish(),
         Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
+
+/// 自分のメモ、参加しているワークスペースのメモ、`public` なメモを返す
+/// （`unlisted` は ID 指定でのみ閲覧可能）。
+///
+/// クエリ: `limit`（1..=100, 既定 20）, `cursor`, `author`, `workspace`, `notebook`,
+/// `tag`（複数可）, `tag_mode=all|any`, `starred=true`, `archived=true`,
+/// `sort=created_at|updated_at|title`, `order=asc|desc`
+///
+/// 固定したメモは `sort` / `order` に関係なく先頭に並ぶ。
+/// アーカイブしたメモは `archived=true` のときだけ（それだけが）返る。
 #[get("/notes")]
-pub async fn list_notes(note_repo: web::Data<Arc<dyn NoteRepository>>) -> impl Responder {
-    match note_repo.list_notes().await {
-        Ok(notes) => HttpResponse::Ok().json(notes),
+pub async fn list_notes(
+    req: HttpRequest,
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let Ok(query) = serde_html_form::from_str::<ListNotesQuery>(req.query_string()) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    note_page(note_repo.get_ref().as_ref(), user.map(|u| u.0.sub), query).await
+}
+
+/// `GET /notes` の 1 ページ分を返す（`GET /notebooks/{id}/notes` と共通）。
+pub(crate) async fn note_page(
+    note_repo: &dyn NoteRepository,
+    viewer: Option<i64>,
+    query: ListNotesQuery,
+) -> HttpResponse {
+    let Ok(tags) = normalize_tags(&query.tags) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return HttpResponse::BadRequest().finish();
+    }
+    let after = match query
+        .cursor
+        .as_deref()
+        .map(|c| decode_cursor(c, query.sort))
+    {
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => return HttpResponse::BadRequest().finish(),
+        None => None,
+    };
+
+    // 1 件多く取得して次ページの有無を判定する
+    let repo_query = NoteListQuery {
+        viewer,
+        author: query.author,
+        workspace: query.workspace,
+        notebook: query.notebook,
+        archived: query.archived,
+        starred: query.starred,
+        tags,
+        tag_match: query.tag_mode,
+        sort: query.sort,
+        order: query.order,
+        after,
+        limit: limit + 1,
+    };
+    match note_repo.list_notes(&repo_query).await {
+        Ok(mut items) => {
+            let next_cursor = if items.len() as i64 > limit {
+                items.truncate(limit as usize);
+                items
+                    .last()
+                    .map(|n| encode_cursor(&NoteCursor::of(n, query.sort)))
+            } else {
+                None
+            };
+            HttpResponse::Ok().json(NotePage { items, next_cursor })
+        }
         Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
+
+/// タイトル・本文の全文検索。閲覧範囲は `GET /notes` と同じ。
+/// ルーティング上 `GET /notes/{id}` より先に登録すること。
+#[get("/notes/search")]
+pub async fn search_notes(
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    query: web::Query<SearchNotesQuery>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if query.q.trim().is_empty() || !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return HttpResponse::BadRequest().finish();
+    }
+    let viewer = user.map(|u| u.0.sub);
+    match note_repo.search(viewer, &query.q, limit).await {
+        Ok(hits) => HttpResponse::Ok().json(hits),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// 本文を HTML に変換する。このメモの画像の添付ファイルを参照する画像
+/// （`![...](/notes/{id}/attachments/{attachment_id})`）はサムネイルの URL に置き換える。
+/// 共有リンクで閲覧している場合は、サムネイルの URL にもトークンを付ける。
+async fn render_note_html(
+    req: &HttpRequest,
+    note: &Note,
+    attachments: Option<&web::Data<AttachmentService>>,
+) -> String {
+    let images: HashSet<i64> = match attachments {
+        Some(attachments) => attachments
+            .list(note.id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|attachment| attachment.width.is_some())
+            .map(|attachment| attachment.id)
+            .collect(),
+        None => HashSet::new(),
+    };
+    if images.is_empty() {
+        return render_html(&note.content);
+    }
+    let share = web::Query::<ShareLinkQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.into_inner().share);
+    let prefix = format!("/notes/{}/attachments/", note.id);
+    render_html_with(&note.content, |url| {
+        let attachment_id: i64 = url.strip_prefix(&prefix)?.parse().ok()?;
+        if !images.contains(&attachment_id) {
+            return None;
+        }
+        let query = serde_html_form::to_string(ThumbnailQuery {
+            w: Some(RENDERED_THUMBNAIL_WIDTH),
+            share: share.clone(),
+        })
+        .ok()?;
+        Some(format!("/attachments/{attachment_id}/thumb?{query}"))
+    })
+}
+
+/// `GET /notes/{id}` のレスポンスの形式。`?format=` が優先で、無ければ `Accept` で
+/// `application/json` より `text/html` を優先していれば HTML にする。
+/// `format` の値が不正なら `None`。
+fn negotiate_format(req: &HttpRequest) -> Option<NoteFormat> {
+    let query = web::Query::<NoteFormatQuery>::from_query(req.query_string()).ok()?;
+    if let Some(format) = query.format {
+        return Some(format);
+    }
+    let Ok(accept) = Accept::parse(req) else {
+        return Some(NoteFormat::Json);
+    };
+    let preferred = accept.ranked().into_iter().find_map(|mime| {
+        match (mime.type_().as_str(), mime.subtype().as_str()) {
+            ("text", "html") => Some(NoteFormat::Html),
+            ("application", "json") | ("*", "*") | ("application", "*") => Some(NoteFormat::Json),
+            _ => None,
+        }
+    });
+    Some(preferred.unwrap_or(NoteFormat::Json))
+}
+
+fn note_etag(note: &Note) -> ETag {
+    ETag(EntityTag::new_strong(note.version.to_string()))
+}
+
+/// `If-Match` から更新・削除の前提となるバージョンを決める。
+/// - ヘッダーなし・`*`: 検査しない
+/// - 読み込んだ時点のバージョン（`current`）を含む: そのバージョンを SQL の条件にして
+///   読み込み後の更新も検出する
+/// - それ以外（弱い ETag を含む）: 412
+fn expected_version(req: &HttpRequest, current: i64) -> Result<Option<i64>, StatusCode> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Ok(None);
+    }
+    match IfMatch::parse(req) {
+        Ok(IfMatch::Any) => Ok(None),
+        Ok(IfMatch::Items(tags)) => {
+            let current_tag = EntityTag::new_strong(current.to_string());
+            if tags.iter().any(|tag| tag.strong_eq(&current_tag)) {
+                Ok(Some(current))
+            } else {
+                Err(StatusCode::PRECONDITION_FAILED)
+            }
+        }
+        Err(_) => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+// カーソルはクライアントにとって不透明な文字列として扱わせる。
+// 中身は `pinned|sort_key|id`（タイトルに `|` が含まれても両端から区切れる）
+fn encode_cursor(cursor: &NoteCursor) -> String {
+    let key = match &cursor.key {
+        NoteSortKey::Time(time) => time.to_string(),
+        NoteSortKey::Title(title) => title.clone(),
+    };
+    URL_SAFE_NO_PAD.encode(format!("{}|{key}|{}", u8::from(cursor.pinned), cursor.id))
+}
+
+/// `sort` の並び替えキーとして読めないカーソルは `None`（400 にする）。
+fn decode_cursor(cursor: &str, sort: NoteSort) -> Option<NoteCursor> {
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+    let text = std::str::from_utf8(&bytes).ok()?;
+    let (pinned, rest) = text.split_once('|')?;
+    let (key, id) = rest.rsplit_once('|')?;
+    let pinned = match pinned {
+        "0" => false,
+        "1" => true,
+        _ => return None,
+    };
+    let key = match sort {
+        NoteSort::Title => NoteSortKey::Title(key.to_string()),
+        NoteSort::CreatedAt | NoteSort::UpdatedAt => NoteSortKey::Time(key.parse().ok()?),
+    };
+    Some(NoteCursor {
+        pinned,
+        key,
+        id: id.parse().ok()?,
+    })
+}
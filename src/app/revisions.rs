@@ -0,0 +1,162 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, web};
+use std::sync::Arc;
+
+use crate::app::model::{NoteDiff, NoteDiffQuery};
+use crate::app::notes::{authorize, change_note};
+use crate::domain::diff::line_diff;
+use crate::domain::model::{Note, Scope};
+use crate::domain::policy::NoteAction;
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::repository::note::{NoteChanges, NoteRepository};
+use crate::repository::revision::RevisionRepository;
+use crate::repository::workspace::WorkspaceRepository;
+use crate::service::share::ShareService;
+
+/// 履歴（とリンク）はメモ本体を閲覧できる人にだけ見せる（見えないメモは 404）。
+pub(crate) async fn find_visible_note(
+    note_repo: &Arc<dyn NoteRepository>,
+    workspaces: Option<&web::Data<Arc<dyn WorkspaceRepository>>>,
+    note_id: i64,
+    viewer: Option<i64>,
+) -> Result<Note, HttpResponse> {
+    let note = match note_repo.find_by_id(note_id).await {
+        Ok(Some(note)) => note,
+        Ok(None) => return Err(HttpResponse::NotFound().finish()),
+        Err(_) => return Err(HttpResponse::InternalServerError().finish()),
+    };
+    // 履歴・リンクは共有（`note_shares`・共有リンク）の対象外
+    match authorize(workspaces, &note, viewer, None, NoteAction::Read).await {
+        Ok(true) => Ok(note),
+        Ok(false) => Err(HttpResponse::NotFound().finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+/// メモのリビジョン一覧（新しい順、本文なし）。
+#[get("/notes/{id}/revisions")]
+pub async fn list_revisions(
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    revision_repo: web::Data<Arc<dyn RevisionRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let note_id = path.into_inner();
+    let viewer = user.map(|u| u.0.sub);
+    if let Err(resp) = find_visible_note(&note_repo, workspaces.as_ref(), note_id, viewer).await {
+        return resp;
+    }
+    match revision_repo.list_revisions(note_id).await {
+        Ok(revisions) => HttpResponse::Ok().json(revisions),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[get("/notes/{id}/revisions/{rev}")]
+pub async fn get_revision(
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    revision_repo: web::Data<Arc<dyn RevisionRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (note_id, rev) = path.into_inner();
+    let viewer = user.map(|u| u.0.sub);
+    if let Err(resp) = find_visible_note(&note_repo, workspaces.as_ref(), note_id, viewer).await {
+        return resp;
+    }
+    match revision_repo.find_revision(note_id, rev).await {
+        Ok(Some(revision)) => HttpResponse::Ok().json(revision),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// リビジョン `from` から `to` への本文の行差分。
+#[get("/notes/{id}/diff")]
+pub async fn diff_revisions(
+    user: Option<AuthenticatedUser>,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    revision_repo: web::Data<Arc<dyn RevisionRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    path: web::Path<i64>,
+    query: web::Query<NoteDiffQuery>,
+) -> impl Responder {
+    if user
+        .as_ref()
+        .is_some_and(|u| !u.0.has_scope(Scope::NotesRead))
+    {
+        return HttpResponse::Forbidden().finish();
+    }
+    let note_id = path.into_inner();
+    let viewer = user.map(|u| u.0.sub);
+    if let Err(resp) = find_visible_note(&note_repo, workspaces.as_ref(), note_id, viewer).await {
+        return resp;
+    }
+    let (from, to) = match (
+        revision_repo.find_revision(note_id, query.from).await,
+        revision_repo.find_revision(note_id, query.to).await,
+    ) {
+        (Ok(Some(from)), Ok(Some(to))) => (from, to),
+        (Err(_), _) | (_, Err(_)) => return HttpResponse::InternalServerError().finish(),
+        _ => return HttpResponse::NotFound().finish(),
+    };
+    HttpResponse::Ok().json(NoteDiff {
+        from: from.revision,
+        to: to.revision,
+        lines: line_diff(&from.content, &to.content),
+        from_title: from.title,
+        to_title: to.title,
+    })
+}
+
+/// リビジョンの内容でメモを更新する。権限（`write` の共有を含む）と `If-Match` の扱いは
+/// `PUT /notes/{id}` と同じ。復元自体も新しいリビジョンとして記録される。
+#[post("/notes/{id}/revisions/{rev}/restore")]
+pub async fn restore_revision(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    revision_repo: web::Data<Arc<dyn RevisionRepository>>,
+    workspaces: Option<web::Data<Arc<dyn WorkspaceRepository>>>,
+    shares: Option<web::Data<ShareService>>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (note_id, rev) = path.into_inner();
+    // 見えないメモのリビジョンも 404 になるので、リビジョンを先に読んでも存在は漏れない
+    let revision = match revision_repo.find_revision(note_id, rev).await {
+        Ok(Some(revision)) => revision,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish(),
+    };
+    let changes = NoteChanges {
+        title: Some(&revision.title),
+        content: Some(&revision.content),
+        ..Default::default()
+    };
+    change_note(
+        &req,
+        user.0.sub,
+        note_repo.get_ref().as_ref(),
+        workspaces.as_ref(),
+        shares.as_ref(),
+        note_id,
+        changes,
+    )
+    .await
+}
@@ -0,0 +1,119 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, web};
+
+use crate::app::model::{CreateShareLinkInput, CreatedShareLink, ShareNoteInput};
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::service::share::{ShareError, ShareService};
+
+// 共有の管理はログイン（JWT）でのみ可能で、API トークンでは 403。
+// 作成者（ワークスペースのメモならオーナーと作成者本人の編集者）以外は、
+// 閲覧できるメモなら 403、閲覧もできないメモなら 404。
+
+/// 共有しているユーザーと共有リンクの一覧（トークン本体は含まない）。
+#[get("/notes/{id}/shares")]
+pub async fn list_shares(
+    user: AuthenticatedUser,
+    shares: web::Data<ShareService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match shares.sharing(user.0.sub, path.into_inner()).await {
+        Ok(sharing) => HttpResponse::Ok().json(sharing),
+        Err(e) => error_response(e),
+    }
+}
+
+/// メールアドレスで指定したユーザーとメモを共有する。既に共有していれば権限を置き換える。
+#[post("/notes/{id}/shares")]
+pub async fn share_note(
+    user: AuthenticatedUser,
+    shares: web::Data<ShareService>,
+    path: web::Path<i64>,
+    payload: web::Json<ShareNoteInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match shares
+        .share_with(
+            user.0.sub,
+            path.into_inner(),
+            &payload.email,
+            payload.permission,
+        )
+        .await
+    {
+        Ok(share) => HttpResponse::Ok().json(share),
+        Err(e) => error_response(e),
+    }
+}
+
+#[delete("/notes/{id}/shares/{user_id}")]
+pub async fn unshare_note(
+    user: AuthenticatedUser,
+    shares: web::Data<ShareService>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (note_id, target_id) = path.into_inner();
+    match shares.unshare(user.0.sub, note_id, target_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 共有リンクを作成する。トークン本体はこのレスポンスでしか返さない。
+#[post("/notes/{id}/share-links")]
+pub async fn create_share_link(
+    user: AuthenticatedUser,
+    shares: web::Data<ShareService>,
+    path: web::Path<i64>,
+    payload: web::Json<CreateShareLinkInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match shares
+        .create_link(
+            user.0.sub,
+            path.into_inner(),
+            payload.permission,
+            payload.expires_in_days,
+            payload.password.as_deref(),
+        )
+        .await
+    {
+        Ok((link, token)) => HttpResponse::Created().json(CreatedShareLink { link, token }),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 共有リンクを取り消す。以降そのトークンでは閲覧・編集できない。
+#[delete("/notes/{id}/share-links/{link_id}")]
+pub async fn revoke_share_link(
+    user: AuthenticatedUser,
+    shares: web::Data<ShareService>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (note_id, link_id) = path.into_inner();
+    match shares.revoke_link(user.0.sub, note_id, link_id).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+pub(crate) fn error_response(e: ShareError) -> HttpResponse {
+    match e {
+        ShareError::NotFound => HttpResponse::NotFound().finish(),
+        ShareError::Forbidden => HttpResponse::Forbidden().finish(),
+        ShareError::PasswordRequired => HttpResponse::Unauthorized().finish(),
+        ShareError::UnknownUser | ShareError::InvalidInput => HttpResponse::BadRequest().finish(),
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
@@ -0,0 +1,88 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, put, web};
+use std::sync::Arc;
+
+use crate::app::model::{MergeTagsInput, RenameTagInput};
+use crate::domain::model::Scope;
+use crate::domain::tag::{normalize_tag, normalize_tags};
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::repository::tag::TagRepository;
+use crate::repository::user::RepoError;
+
+/// 自分のタグ一覧（メモの件数付き）。
+#[get("/tags")]
+pub async fn list_tags(
+    user: AuthenticatedUser,
+    tag_repo: web::Data<Arc<dyn TagRepository>>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesRead) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match tag_repo.list_tags(user.0.sub).await {
+        Ok(tags) => HttpResponse::Ok().json(tags),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// タグ名の変更。変更先が既に存在する場合は 409（統合は `POST /tags/merge`）。
+#[put("/tags/{name}")]
+pub async fn rename_tag(
+    user: AuthenticatedUser,
+    tag_repo: web::Data<Arc<dyn TagRepository>>,
+    path: web::Path<String>,
+    payload: web::Json<RenameTagInput>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let from = path.into_inner();
+    let Ok(to) = normalize_tag(&payload.name) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    match tag_repo.rename_tag(user.0.sub, &from, &to).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(RepoError::Conflict) => HttpResponse::Conflict().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// `sources` のタグを `target` に統合する。
+#[post("/tags/merge")]
+pub async fn merge_tags(
+    user: AuthenticatedUser,
+    tag_repo: web::Data<Arc<dyn TagRepository>>,
+    payload: web::Json<MergeTagsInput>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (Ok(sources), Ok(target)) = (
+        normalize_tags(&payload.sources),
+        normalize_tag(&payload.target),
+    ) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    if sources.is_empty() {
+        return HttpResponse::BadRequest().finish();
+    }
+    match tag_repo.merge_tags(user.0.sub, &sources, &target).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[delete("/tags/{name}")]
+pub async fn delete_tag(
+    user: AuthenticatedUser,
+    tag_repo: web::Data<Arc<dyn TagRepository>>,
+    path: web::Path<String>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match tag_repo.delete_tag(user.0.sub, &path.into_inner()).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
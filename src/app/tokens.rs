@@ -0,0 +1,81 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, web};
+use std::sync::Arc;
+
+use crate::app::model::{CreateApiTokenInput, CreatedApiToken};
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::middleware::auth::token::{API_TOKEN_PREFIX, hash_token, random_token};
+use crate::repository::user::ApiTokenRepository;
+
+const MAX_TOKEN_NAME_LEN: usize = 100;
+const MAX_EXPIRES_IN_DAYS: i64 = 365;
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// パーソナルアクセストークンを発行する。トークン本体はこのレスポンスでしか返さない
+/// （DB にはハッシュのみ保存する）。
+/// トークンの発行・一覧・削除はログイン（JWT）でのみ可能で、アクセストークン自身では 403。
+#[post("/me/tokens")]
+pub async fn create_api_token(
+    user: AuthenticatedUser,
+    api_tokens: web::Data<Arc<dyn ApiTokenRepository>>,
+    payload: web::Json<CreateApiTokenInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TOKEN_NAME_LEN || payload.scopes.is_empty() {
+        return HttpResponse::BadRequest().finish();
+    }
+    let ttl_secs = match payload.expires_in_days {
+        Some(days @ 1..=MAX_EXPIRES_IN_DAYS) => Some(days * SECS_PER_DAY),
+        Some(_) => return HttpResponse::BadRequest().finish(),
+        None => None,
+    };
+    let mut scopes = payload.scopes.clone();
+    scopes.sort();
+    scopes.dedup();
+
+    let token = format!("{API_TOKEN_PREFIX}{}", random_token(32));
+    match api_tokens
+        .create_api_token(user.0.sub, name, &hash_token(&token), &scopes, ttl_secs)
+        .await
+    {
+        Ok(api_token) => HttpResponse::Created().json(CreatedApiToken { api_token, token }),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// 自分のアクセストークン一覧（トークン本体は含まない）。
+#[get("/me/tokens")]
+pub async fn list_api_tokens(
+    user: AuthenticatedUser,
+    api_tokens: web::Data<Arc<dyn ApiTokenRepository>>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match api_tokens.list_api_tokens(user.0.sub).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// アクセストークンを削除する（以降そのトークンでは認証できない）。
+#[delete("/me/tokens/{id}")]
+pub async fn delete_api_token(
+    user: AuthenticatedUser,
+    api_tokens: web::Data<Arc<dyn ApiTokenRepository>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match api_tokens
+        .delete_api_token(user.0.sub, path.into_inner())
+        .await
+    {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
@@ -0,0 +1,64 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, web};
+use std::sync::Arc;
+
+use crate::app::events::publish;
+use crate::domain::model::Scope;
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::repository::note::NoteRepository;
+use crate::service::events::NoteEventKind;
+
+/// 自分のゴミ箱（ゴミ箱に移動した日時の新しい順）。
+/// ゴミ箱内のメモは保持期間（`TRASH_RETENTION_DAYS`）を過ぎると自動で完全に削除される。
+#[get("/trash")]
+pub async fn list_trash(
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesRead) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match note_repo.list_trash(user.0.sub).await {
+        Ok(notes) => HttpResponse::Ok().json(notes),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// ゴミ箱からメモを戻す。自分のゴミ箱に無ければ 404。
+#[post("/notes/{id}/restore")]
+pub async fn restore_note(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let note_id = path.into_inner();
+    match note_repo.restore_note(note_id, user.0.sub).await {
+        Ok(Some(note)) => {
+            publish(&req, NoteEventKind::Created, note.clone());
+            HttpResponse::Ok().json(note)
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// ゴミ箱内のメモを完全に削除する（元に戻せない）。
+#[delete("/trash/{id}")]
+pub async fn purge_note(
+    user: AuthenticatedUser,
+    note_repo: web::Data<Arc<dyn NoteRepository>>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesWrite) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let note_id = path.into_inner();
+    match note_repo.purge_note(note_id, user.0.sub).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
@@ -0,0 +1,43 @@
+use actix_web::{HttpResponse, Responder, post, web};
+
+use crate::app::model::{ConfirmTotpInput, RecoveryCodesOutput};
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::service::two_factor::{TwoFactorError, TwoFactorService};
+
+/// TOTP の登録を始める。返した `otpauth_uri` を認証アプリに登録し、
+/// `POST /me/2fa/totp/confirm` でコードを送ると有効になる。既に有効なら 409。
+/// 2 段階認証の設定はログイン（JWT）でのみ可能で、API トークンでは 403。
+#[post("/me/2fa/totp")]
+pub async fn enroll_totp(
+    user: AuthenticatedUser,
+    two_factor: web::Data<TwoFactorService>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match two_factor.enroll(user.0.sub).await {
+        Ok(enrollment) => HttpResponse::Created().json(enrollment),
+        Err(TwoFactorError::AlreadyEnabled) => HttpResponse::Conflict().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// 認証アプリのコードで登録を確認し、リカバリーコードを返す（このレスポンスでしか返さない）。
+#[post("/me/2fa/totp/confirm")]
+pub async fn confirm_totp(
+    user: AuthenticatedUser,
+    two_factor: web::Data<TwoFactorService>,
+    payload: web::Json<ConfirmTotpInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match two_factor.confirm(user.0.sub, &payload.code).await {
+        Ok(recovery_codes) => HttpResponse::Ok().json(RecoveryCodesOutput { recovery_codes }),
+        Err(TwoFactorError::InvalidCode | TwoFactorError::NotEnrolled) => {
+            HttpResponse::BadRequest().finish()
+        }
+        Err(TwoFactorError::AlreadyEnabled) => HttpResponse::Conflict().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
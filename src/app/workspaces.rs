@@ -0,0 +1,154 @@
+use actix_web::{HttpResponse, Responder, delete, get, post, put, web};
+
+use crate::app::model::{
+    AcceptInvitationInput, AcceptedInvitation, CreateWorkspaceInput, InviteMemberInput,
+    SetMemberRoleInput,
+};
+use crate::domain::model::Scope;
+use crate::middleware::auth::extractor::AuthenticatedUser;
+use crate::service::workspace::{WorkspaceError, WorkspaceService};
+
+// ワークスペースとメンバーの変更はログイン（JWT）でのみ可能で、API トークンでは 403。
+// 参照は `notes:read` スコープのトークンでもできる。
+
+#[post("/workspaces")]
+pub async fn create_workspace(
+    user: AuthenticatedUser,
+    workspaces: web::Data<WorkspaceService>,
+    payload: web::Json<CreateWorkspaceInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match workspaces.create(user.0.sub, &payload.name).await {
+        Ok(workspace) => HttpResponse::Created().json(workspace),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 自分が参加しているワークスペースと、そこでの役割。
+#[get("/workspaces")]
+pub async fn list_workspaces(
+    user: AuthenticatedUser,
+    workspaces: web::Data<WorkspaceService>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesRead) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match workspaces.list(user.0.sub).await {
+        Ok(items) => HttpResponse::Ok().json(items),
+        Err(e) => error_response(e),
+    }
+}
+
+/// メンバー一覧。メンバー以外には 404。
+#[get("/workspaces/{id}/members")]
+pub async fn list_members(
+    user: AuthenticatedUser,
+    workspaces: web::Data<WorkspaceService>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if !user.0.has_scope(Scope::NotesRead) {
+        return HttpResponse::Forbidden().finish();
+    }
+    match workspaces.members(user.0.sub, path.into_inner()).await {
+        Ok(members) => HttpResponse::Ok().json(members),
+        Err(e) => error_response(e),
+    }
+}
+
+/// メンバーの役割を変更する（オーナーのみ）。最後のオーナーは降格できない（409）。
+#[put("/workspaces/{id}/members/{user_id}")]
+pub async fn set_member_role(
+    user: AuthenticatedUser,
+    workspaces: web::Data<WorkspaceService>,
+    path: web::Path<(i64, i64)>,
+    payload: web::Json<SetMemberRoleInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (workspace_id, member_id) = path.into_inner();
+    match workspaces
+        .set_member_role(user.0.sub, workspace_id, member_id, payload.role)
+        .await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// メンバーを外す（オーナーのみ。自分自身なら誰でも抜けられる）。
+/// 最後のオーナーは外せない（409）。
+#[delete("/workspaces/{id}/members/{user_id}")]
+pub async fn remove_member(
+    user: AuthenticatedUser,
+    workspaces: web::Data<WorkspaceService>,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (workspace_id, member_id) = path.into_inner();
+    match workspaces
+        .remove_member(user.0.sub, workspace_id, member_id)
+        .await
+    {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// メールで招待する（オーナーのみ）。トークンはメールでのみ送り、レスポンスには含めない。
+#[post("/workspaces/{id}/invitations")]
+pub async fn invite_member(
+    user: AuthenticatedUser,
+    workspaces: web::Data<WorkspaceService>,
+    path: web::Path<i64>,
+    payload: web::Json<InviteMemberInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    if payload.email.trim().is_empty() {
+        return HttpResponse::BadRequest().finish();
+    }
+    match workspaces
+        .invite(user.0.sub, path.into_inner(), &payload.email, payload.role)
+        .await
+    {
+        Ok(invitation) => HttpResponse::Created().json(invitation),
+        Err(e) => error_response(e),
+    }
+}
+
+/// 招待を承諾する。招待の宛先と同じメールアドレスのユーザーでログインしていること。
+#[post("/workspaces/invitations/accept")]
+pub async fn accept_invitation(
+    user: AuthenticatedUser,
+    workspaces: web::Data<WorkspaceService>,
+    payload: web::Json<AcceptInvitationInput>,
+) -> impl Responder {
+    if user.0.scope.is_some() {
+        return HttpResponse::Forbidden().finish();
+    }
+    match workspaces
+        .accept_invitation(user.0.sub, &payload.token)
+        .await
+    {
+        Ok(workspace_id) => HttpResponse::Ok().json(AcceptedInvitation { workspace_id }),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(e: WorkspaceError) -> HttpResponse {
+    match e {
+        WorkspaceError::NotFound => HttpResponse::NotFound().finish(),
+        WorkspaceError::Forbidden => HttpResponse::Forbidden().finish(),
+        WorkspaceError::LastOwner => HttpResponse::Conflict().finish(),
+        WorkspaceError::InvalidName | WorkspaceError::InvalidInvitation => {
+            HttpResponse::BadRequest().finish()
+        }
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
@@ -1,7 +1,9 @@
 use awc::Client;
-use awc::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use awc::http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use futures_util::StreamExt;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug)]
@@ -17,6 +19,14 @@ impl std::error::Error for ClientError {}
 
 pub type ClientResult<T> = Result<T, ClientError>;
 
+/// Server-Sent Events の 1 件。
+#[derive(Debug, Default)]
+pub struct ServerSentEvent {
+    pub id: Option<String>,
+    pub event: String,
+    pub data: String,
+}
+
 #[derive(Clone)]
 pub struct HttpClient {
     pub base_url: String,
@@ -44,6 +54,105 @@ impl HttpClient {
         Ok((status, text))
     }
 
+    /// 本文をバイト列のまま返す GET（添付ファイルのダウンロードなど）。
+    pub async fn get_bytes(
+        &self,
+        path: &str,
+        bearer_token: Option<&str>,
+    ) -> ClientResult<(u16, Vec<u8>)> {
+        let url = join_url(&self.base_url, path);
+        let mut req = self.client.get(url);
+        if let Some(token) = bearer_token {
+            req = req.insert_header((AUTHORIZATION, format!("Bearer {}", token)));
+        }
+        let mut res = req.send().await.map_err(|e| ClientError(e.to_string()))?;
+        let status = res.status().as_u16();
+        // 既定の上限（256 KiB）では添付ファイルが収まらない
+        let body = res
+            .body()
+            .limit(usize::MAX)
+            .await
+            .map_err(|e| ClientError(e.to_string()))?;
+        Ok((status, body.to_vec()))
+    }
+
+    /// Server-Sent Events を受け取り、1 件ごとに `on_event` を呼ぶ（接続が切れるまで返らない）。
+    /// `last_event_id` は `Last-Event-ID` で渡す。コメント行（キープアライブ）は読み飛ばす。
+    pub async fn get_events(
+        &self,
+        path: &str,
+        bearer_token: Option<&str>,
+        last_event_id: Option<&str>,
+        mut on_event: impl FnMut(ServerSentEvent),
+    ) -> ClientResult<()> {
+        let url = join_url(&self.base_url, path);
+        let mut req = self
+            .client
+            .get(url)
+            .insert_header((ACCEPT, "text/event-stream"));
+        if let Some(token) = bearer_token {
+            req = req.insert_header((AUTHORIZATION, format!("Bearer {}", token)));
+        }
+        if let Some(id) = last_event_id {
+            req = req.insert_header(("Last-Event-ID", id));
+        }
+        let mut res = req.send().await.map_err(|e| ClientError(e.to_string()))?;
+        let status = res.status().as_u16();
+        if !(200..300).contains(&status) {
+            let body = res.body().await.map_err(|e| ClientError(e.to_string()))?;
+            return Err(ClientError(format!(
+                "{} {}",
+                status,
+                String::from_utf8_lossy(&body)
+            )));
+        }
+        let mut buf = String::new();
+        while let Some(chunk) = res.next().await {
+            let chunk = chunk.map_err(|e| ClientError(e.to_string()))?;
+            buf.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some(end) = buf.find("\n\n") {
+                let block: String = buf.drain(..end + 2).collect();
+                let mut event = ServerSentEvent::default();
+                for line in block.lines() {
+                    let (field, value) = line.split_once(": ").unwrap_or((line, ""));
+                    match field {
+                        "id" => event.id = Some(value.to_string()),
+                        "event" => event.event = value.to_string(),
+                        "data" => event.data = value.to_string(),
+                        _ => {}
+                    }
+                }
+                if !event.data.is_empty() {
+                    on_event(event);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// クエリパラメータを URL エンコードして GET する。
+    pub async fn get_with_query<Q: Serialize>(
+        &self,
+        path: &str,
+        query: &Q,
+        bearer_token: Option<&str>,
+    ) -> ClientResult<(u16, String)> {
+        let url = join_url(&self.base_url, path);
+        let mut req = self
+            .client
+            .get(url)
+            .query(query)
+            .map_err(|e| ClientError(e.to_string()))?;
+        if let Some(token) = bearer_token {
+            req = req.insert_header((AUTHORIZATION, format!("Bearer {}", token)));
+        }
+        let mut res = req.send().await.map_err(|e| ClientError(e.to_string()))?;
+        let status = res.status().as_u16();
+        let body = res.body().await.map_err(|e| ClientError(e.to_string()))?;
+        let text = String::from_utf8(body.to_vec()).unwrap_or_default();
+        Ok((status, text))
+    }
+
     pub async fn post_json<T: Serialize>(
         &self,
         path: &str,
@@ -82,6 +191,42 @@ impl HttpClient {
         }
     }
 
+    /// `multipart/form-data` の 1 つのフィールドにファイルを入れて POST する。
+    pub async fn post_file(
+        &self,
+        path: &str,
+        field: &str,
+        filename: &str,
+        data: &[u8],
+        bearer_token: Option<&str>,
+    ) -> ClientResult<(u16, String)> {
+        let url = join_url(&self.base_url, path);
+        let boundary = format!("memo-{:x}", Sha256::digest(data));
+        let filename = filename.replace(['"', '\\', '\r', '\n'], "_");
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{field}\"; \
+             filename=\"{filename}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+        let mut req = self.client.post(url).insert_header((
+            CONTENT_TYPE,
+            format!("multipart/form-data; boundary={boundary}"),
+        ));
+        if let Some(token) = bearer_token {
+            req = req.insert_header((AUTHORIZATION, format!("Bearer {}", token)));
+        }
+        let mut res = req
+            .send_body(body)
+            .await
+            .map_err(|e| ClientError(e.to_string()))?;
+        let status = res.status().as_u16();
+        let body = res.body().await.map_err(|e| ClientError(e.to_string()))?;
+        let text = String::from_utf8(body.to_vec()).unwrap_or_default();
+        Ok((status, text))
+    }
+
     pub async fn put_json<T: Serialize>(
         &self,
         path: &str,
@@ -149,6 +294,20 @@ impl HttpClient {
             Err(ClientError(format!("{} {}", status, text)))
         }
     }
+
+    pub async fn get_json_with_query<Q: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &Q,
+        bearer_token: Option<&str>,
+    ) -> ClientResult<T> {
+        let (status, text) = self.get_with_query(path, query, bearer_token).await?;
+        if (200..300).contains(&status) {
+            serde_json::from_str::<T>(&text).map_err(|e| ClientError(e.to_string()))
+        } else {
+            Err(ClientError(format!("{} {}", status, text)))
+        }
+    }
 }
 
 fn join_url(base: &str, path: &str) -> String {
@@ -0,0 +1,58 @@
+/// ファイル名として保存する最大文字数。
+const MAX_FILENAME_CHARS: usize = 255;
+
+/// 中身の決まった位置（先頭からのバイト数）にあるべきバイト列の組。
+type Magic = &'static [(usize, &'static [u8])];
+
+/// マジックナンバーと MIME タイプ。
+const SIGNATURES: [(Magic, &str); 8] = [
+    (&[(0, b"\x89PNG\r\n\x1a\n")], "image/png"),
+    (&[(0, b"\xff\xd8\xff")], "image/jpeg"),
+    (&[(0, b"GIF87a")], "image/gif"),
+    (&[(0, b"GIF89a")], "image/gif"),
+    (&[(0, b"RIFF"), (8, b"WEBP")], "image/webp"),
+    (&[(0, b"%PDF-")], "application/pdf"),
+    (&[(0, b"PK\x03\x04")], "application/zip"),
+    (&[(0, b"\x1f\x8b")], "application/gzip"),
+];
+
+/// 添付ファイルの中身から MIME タイプを判定する（アップロード時の申告は信用しない）。
+/// 既知の画像・PDF・アーカイブ以外は、UTF-8 のテキストなら `text/plain`
+/// （HTML・SVG もブラウザーで実行されないようにテキストとして扱う）、それ以外は `application/octet-stream`。
+pub fn sniff_content_type(data: &[u8]) -> &'static str {
+    let matched = SIGNATURES.iter().find(|(magic, _)| {
+        magic
+            .iter()
+            .all(|&(offset, bytes)| data.get(offset..offset + bytes.len()) == Some(bytes))
+    });
+    match matched {
+        Some((_, content_type)) => content_type,
+        None if is_text(data) => "text/plain; charset=utf-8",
+        None => "application/octet-stream",
+    }
+}
+
+/// ブラウザーで直接表示してよい（`Content-Disposition: inline` で返す）タイプか。
+pub fn is_inline_type(content_type: &str) -> bool {
+    content_type.starts_with("image/")
+}
+
+fn is_text(data: &[u8]) -> bool {
+    std::str::from_utf8(data).is_ok_and(|text| {
+        !text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\u{c}'))
+    })
+}
+
+/// アップロードされたファイル名からディレクトリ部分と制御文字を除き、前後の空白を削って
+/// 255 文字に切り詰める。何も残らなければ `None`。
+pub fn normalize_filename(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base.chars().filter(|c| !c.is_control()).collect();
+    let trimmed = cleaned.trim();
+    if trimmed.is_empty() || trimmed == "." || trimmed == ".." {
+        return None;
+    }
+    Some(trimmed.chars().take(MAX_FILENAME_CHARS).collect())
+}
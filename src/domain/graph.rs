@@ -0,0 +1,167 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+/// メモのグラフ（`GET /graph`）。ノードはメモ、エッジはリンクと共通のタグ。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: i64,
+    pub title: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphEdgeKind {
+    Link, // `source` の本文から `target` へのリンク
+    Tag,  // 共通のタグ（向きは無く `source < target`）
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: i64,
+    pub target: i64,
+    pub kind: GraphEdgeKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>, // `tag` のエッジの共通のタグ（名前順）
+}
+
+impl NoteGraph {
+    /// ノードと、リンク（リンク元, リンク先）の組からグラフを作る。
+    /// ノードに無いメモへのリンクと自分自身へのリンクは除き、共通のタグを持つメモの組にはタグのエッジを張る。
+    pub fn new(mut nodes: Vec<GraphNode>, links: &[(i64, i64)]) -> Self {
+        nodes.sort_by_key(|node| node.id);
+        let ids: BTreeSet<i64> = nodes.iter().map(|node| node.id).collect();
+        let links: BTreeSet<(i64, i64)> = links
+            .iter()
+            .copied()
+            .filter(|(source, target)| {
+                source != target && ids.contains(source) && ids.contains(target)
+            })
+            .collect();
+        let mut edges: Vec<GraphEdge> = links
+            .into_iter()
+            .map(|(source, target)| GraphEdge {
+                source,
+                target,
+                kind: GraphEdgeKind::Link,
+                tags: vec![],
+            })
+            .collect();
+
+        let mut tagged: BTreeMap<&str, Vec<i64>> = BTreeMap::new();
+        for node in &nodes {
+            for tag in &node.tags {
+                tagged.entry(tag).or_default().push(node.id);
+            }
+        }
+        let mut shared: BTreeMap<(i64, i64), Vec<String>> = BTreeMap::new();
+        for (tag, ids) in tagged {
+            for (i, &source) in ids.iter().enumerate() {
+                for &target in &ids[i + 1..] {
+                    shared
+                        .entry((source, target))
+                        .or_default()
+                        .push(tag.to_string());
+                }
+            }
+        }
+        edges.extend(
+            shared
+                .into_iter()
+                .map(|((source, target), tags)| GraphEdge {
+                    source,
+                    target,
+                    kind: GraphEdgeKind::Tag,
+                    tags,
+                }),
+        );
+        Self { nodes, edges }
+    }
+
+    /// `center` から（エッジの向きと種類を問わず）`depth` 本以内でたどれるメモだけの部分グラフ。
+    /// `center` がグラフに無ければ `None`。
+    pub fn neighborhood(&self, center: i64, depth: u32) -> Option<NoteGraph> {
+        if !self.nodes.iter().any(|node| node.id == center) {
+            return None;
+        }
+        let mut adjacent: HashMap<i64, Vec<i64>> = HashMap::new();
+        for edge in &self.edges {
+            adjacent.entry(edge.source).or_default().push(edge.target);
+            adjacent.entry(edge.target).or_default().push(edge.source);
+        }
+        let mut reached = BTreeSet::from([center]);
+        let mut queue = VecDeque::from([(center, 0)]);
+        while let Some((id, distance)) = queue.pop_front() {
+            if distance == depth {
+                continue;
+            }
+            for &next in adjacent.get(&id).into_iter().flatten() {
+                if reached.insert(next) {
+                    queue.push_back((next, distance + 1));
+                }
+            }
+        }
+        Some(NoteGraph {
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|node| reached.contains(&node.id))
+                .cloned()
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .filter(|edge| reached.contains(&edge.source) && reached.contains(&edge.target))
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// Graphviz の DOT 形式。リンクは矢印、共通のタグはタグ名を付けた破線で表す。
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph notes {\n");
+        for node in &self.nodes {
+            let _ = writeln!(dot, "  n{} [label={}];", node.id, dot_string(&node.title));
+        }
+        for edge in &self.edges {
+            let _ = match edge.kind {
+                GraphEdgeKind::Link => writeln!(dot, "  n{} -> n{};", edge.source, edge.target),
+                GraphEdgeKind::Tag => writeln!(
+                    dot,
+                    "  n{} -> n{} [dir=none, style=dashed, label={}];",
+                    edge.source,
+                    edge.target,
+                    dot_string(&edge.tags.join(", "))
+                ),
+            };
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// DOT の二重引用符で囲んだ文字列。
+fn dot_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' | '\r' => quoted.push(' '),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,273 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult, Limits};
+
+/// サムネイルの幅（ピクセル）。要求された幅はこのどれかに丸める。
+pub const THUMBNAIL_WIDTHS: [u32; 4] = [160, 320, 640, 1280];
+
+/// HTML に変換したメモの本文で画像に使うサムネイルの幅。
+pub const RENDERED_THUMBNAIL_WIDTH: u32 = 640;
+
+/// 縦・横それぞれの上限。これより大きい画像はサムネイルを作らない。
+const MAX_DIMENSION: u32 = 16_384;
+/// 展開に使うメモリの上限（小さなファイルが巨大な画像に展開される場合に備える）。
+const MAX_ALLOC: u64 = 256 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 80;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// 要求された幅 `requested` に対して返すサムネイルの幅。
+/// 拡大はしないので、幅 `image_width` の画像がそのまま収まる幅より大きくはしない。
+pub fn thumbnail_width(requested: u32, image_width: u32) -> u32 {
+    let largest = THUMBNAIL_WIDTHS[THUMBNAIL_WIDTHS.len() - 1];
+    let fitting = |width: u32| {
+        THUMBNAIL_WIDTHS
+            .into_iter()
+            .find(|&preset| preset >= width)
+            .unwrap_or(largest)
+    };
+    fitting(requested).min(fitting(image_width))
+}
+
+/// 幅 `image_width` の画像について作るサムネイルの幅。
+pub fn thumbnail_widths(image_width: u32) -> impl Iterator<Item = u32> {
+    let widest = thumbnail_width(u32::MAX, image_width);
+    THUMBNAIL_WIDTHS
+        .into_iter()
+        .filter(move |&width| width <= widest)
+}
+
+/// サムネイルの MIME タイプ。JPEG は JPEG のまま、それ以外（透過やアニメーションを含みうる）は PNG にする。
+pub fn thumbnail_content_type(content_type: &str) -> &'static str {
+    if content_type == "image/jpeg" {
+        "image/jpeg"
+    } else {
+        "image/png"
+    }
+}
+
+/// 表示したときの画像の幅と高さ（EXIF の向きを反映する）。読み込めない・大きすぎる画像は `None`。
+pub fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut decoder = decoder(data).ok()?;
+    let (width, height) = decoder.dimensions();
+    let rotated = matches!(
+        decoder.orientation().ok()?,
+        Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Rotate90FlipH
+            | Orientation::Rotate270FlipH
+    );
+    Some(if rotated {
+        (height, width)
+    } else {
+        (width, height)
+    })
+}
+
+/// `widths` のそれぞれの幅以下に縮小したサムネイルを作る（形式は `thumbnail_content_type` のとおり）。
+/// EXIF の向きは画素に反映し、メタデータは一切書き出さない。アニメーションは最初のコマだけ。
+pub fn render_thumbnails(data: &[u8], widths: &[u32]) -> ImageResult<Vec<Vec<u8>>> {
+    let format = image::guess_format(data)?;
+    let mut decoder = decoder(data)?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let mut thumbnails = Vec::with_capacity(widths.len());
+    for &width in widths {
+        let resized;
+        let thumbnail = if image.width() > width {
+            resized = image.resize(width, u32::MAX, FilterType::Triangle);
+            &resized
+        } else {
+            &image
+        };
+        let mut out = Vec::new();
+        if format == ImageFormat::Jpeg {
+            JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+                .encode_image(&thumbnail.to_rgb8())?;
+        } else {
+            thumbnail.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)?;
+        }
+        thumbnails.push(out);
+    }
+    Ok(thumbnails)
+}
+
+fn decoder(data: &[u8]) -> ImageResult<impl ImageDecoder + '_> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_ALLOC);
+    let mut reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    reader.limits(limits);
+    reader.into_decoder()
+}
+
+/// JPEG・PNG・WebP から EXIF などのメタデータ（撮影日時・位置情報・機種など）を取り除く。
+/// 画素のデータには手を付けない（再圧縮しない）。ただし向きの指定は表示が崩れないように、
+/// 向きだけを持つ EXIF に置き換えて残す。それ以外の形式や解釈できないファイルはそのまま返す。
+pub fn strip_metadata(data: &[u8]) -> Cow<'_, [u8]> {
+    let stripped = if data.starts_with(b"\xff\xd8") {
+        strip_jpeg(data)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        strip_png(data)
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        strip_webp(data)
+    } else {
+        None
+    };
+    stripped.map_or(Cow::Borrowed(data), Cow::Owned)
+}
+
+/// EXIF（TIFF 形式）の向きの指定。向きを変えない（1）か、指定が無ければ `None`。
+fn kept_orientation(tiff: &[u8]) -> Option<u8> {
+    Orientation::from_exif_chunk(tiff)
+        .map(Orientation::to_exif)
+        .filter(|&orientation| orientation != 1)
+}
+
+/// 向き（タグ 0x0112）だけを持つ EXIF（TIFF 形式）。
+fn minimal_exif(orientation: u8) -> [u8; 26] {
+    let mut exif = [0; 26];
+    // バイト順（ビッグエンディアン）・TIFF の識別子・最初の IFD の位置
+    exif[..8].copy_from_slice(b"MM\0\x2a\0\0\0\x08");
+    // エントリーは Orientation（SHORT × 1）の 1 つだけ。残り（次の IFD の位置）は 0
+    exif[8..20].copy_from_slice(&[0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation]);
+    exif
+}
+
+/// APP1（EXIF・XMP）と APP13（IPTC）のセグメントを取り除く。
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+    loop {
+        // マーカーの前の詰め物（0xff の並び）は読み飛ばす
+        while data.get(pos..pos + 2) == Some(&[0xff, 0xff]) {
+            pos += 1;
+        }
+        let &[0xff, marker] = data.get(pos..pos + 2)? else {
+            return None;
+        };
+        match marker {
+            // 画像データの開始（SOS）・終了（EOI）以降はそのまま
+            0xda | 0xd9 => {
+                out.extend_from_slice(&data[pos..]);
+                return Some(out);
+            }
+            // 長さを持たないマーカー
+            0x01 | 0xd0..=0xd7 => {
+                out.extend_from_slice(&data[pos..pos + 2]);
+                pos += 2;
+            }
+            _ => {
+                let len = usize::from(u16::from_be_bytes([
+                    *data.get(pos + 2)?,
+                    *data.get(pos + 3)?,
+                ]));
+                let segment = data.get(pos..pos + 2 + len)?;
+                match marker {
+                    0xe1 => {
+                        let orientation = segment
+                            .get(4..)
+                            .and_then(|payload| payload.strip_prefix(b"Exif\0\0"))
+                            .and_then(kept_orientation);
+                        if let Some(orientation) = orientation {
+                            let exif = minimal_exif(orientation);
+                            out.extend_from_slice(&[0xff, 0xe1]);
+                            out.extend_from_slice(&(2 + 6 + exif.len() as u16).to_be_bytes());
+                            out.extend_from_slice(b"Exif\0\0");
+                            out.extend_from_slice(&exif);
+                        }
+                    }
+                    0xed => {}
+                    _ => out.extend_from_slice(segment),
+                }
+                pos += 2 + len;
+            }
+        }
+    }
+}
+
+/// `eXIf` とテキストのチャンク（XMP やコメントを含む `tEXt` / `zTXt` / `iTXt`）、`tIME` を取り除く。
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(PNG_SIGNATURE);
+    let mut pos = PNG_SIGNATURE.len();
+    while pos < data.len() {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let end = pos.checked_add(12)?.checked_add(len)?;
+        let chunk = data.get(pos..end)?;
+        match &chunk[4..8] {
+            b"eXIf" => {
+                if let Some(orientation) = kept_orientation(&chunk[8..8 + len]) {
+                    let exif = minimal_exif(orientation);
+                    let mut crc = crc32fast::Hasher::new();
+                    crc.update(b"eXIf");
+                    crc.update(&exif);
+                    out.extend_from_slice(&(exif.len() as u32).to_be_bytes());
+                    out.extend_from_slice(b"eXIf");
+                    out.extend_from_slice(&exif);
+                    out.extend_from_slice(&crc.finalize().to_be_bytes());
+                }
+            }
+            b"tEXt" | b"zTXt" | b"iTXt" | b"tIME" => {}
+            _ => out.extend_from_slice(chunk),
+        }
+        pos = end;
+    }
+    Some(out)
+}
+
+/// `EXIF` と `XMP ` のチャンクを取り除き、`VP8X` のフラグと RIFF の大きさを合わせる。
+fn strip_webp(data: &[u8]) -> Option<Vec<u8>> {
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..12]);
+    let mut pos = 12;
+    let mut flags_at = None;
+    let mut kept_exif = false;
+    while pos < data.len() {
+        let len = u32::from_le_bytes(data.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        // チャンクは偶数バイトに揃えられている
+        let end = pos.checked_add(8)?.checked_add(len)?.checked_add(len % 2)?;
+        let chunk = data.get(pos..end)?;
+        match &chunk[..4] {
+            b"EXIF" => {
+                let payload = &chunk[8..8 + len];
+                let tiff = payload.strip_prefix(b"Exif\0\0").unwrap_or(payload);
+                if let Some(orientation) = kept_orientation(tiff) {
+                    let exif = minimal_exif(orientation);
+                    out.extend_from_slice(b"EXIF");
+                    out.extend_from_slice(&(exif.len() as u32).to_le_bytes());
+                    out.extend_from_slice(&exif);
+                    kept_exif = true;
+                }
+            }
+            b"XMP " => {}
+            b"VP8X" => {
+                flags_at = Some(out.len() + 8);
+                out.extend_from_slice(chunk);
+            }
+            _ => out.extend_from_slice(chunk),
+        }
+        pos = end;
+    }
+    if let Some(flags_at) = flags_at {
+        let flags = out.get_mut(flags_at)?;
+        *flags &= !XMP_FLAG;
+        if !kept_exif {
+            *flags &= !EXIF_FLAG;
+        }
+    }
+    let riff_size = u32::try_from(out.len() - 8).ok()?;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(out)
+}
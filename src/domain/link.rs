@@ -0,0 +1,78 @@
+use std::ops::Range;
+
+/// メモ本文中のリンク（`[[タイトル]]` / `[[#123]]`）の参照先。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    Id(i64),
+    Title(String),
+}
+
+impl LinkTarget {
+    /// `[[` と `]]` の間の文字列を参照先として解釈する。前後の空白は無視する。
+    fn parse(inner: &str) -> Option<Self> {
+        let inner = inner.trim();
+        if inner.is_empty() || inner.contains(['[', ']', '\n']) {
+            return None;
+        }
+        match inner.strip_prefix('#').map(str::parse::<i64>) {
+            Some(Ok(id)) => Some(LinkTarget::Id(id)),
+            _ => Some(LinkTarget::Title(inner.to_string())),
+        }
+    }
+}
+
+/// 本文中のリンクを出現順に返す（同じ参照先は最初の 1 つだけ）。
+pub fn parse_links(content: &str) -> Vec<LinkTarget> {
+    let mut targets: Vec<LinkTarget> = vec![];
+    for (_, target) in link_spans(content) {
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    targets
+}
+
+/// `[[old]]` へのリンクを `[[new]]` に書き換えた本文を返す。
+/// 書き換える箇所が無いか、`new` がリンクとして書けないタイトルなら `None`。
+pub fn rename_links(content: &str, old: &str, new: &str) -> Option<String> {
+    if LinkTarget::parse(new) != Some(LinkTarget::Title(new.to_string())) {
+        return None;
+    }
+    let old = LinkTarget::Title(old.to_string());
+    let mut renamed = String::with_capacity(content.len());
+    let mut rest = 0;
+    for (span, target) in link_spans(content) {
+        if target == old {
+            renamed.push_str(&content[rest..span.start]);
+            renamed.push_str("[[");
+            renamed.push_str(new);
+            renamed.push_str("]]");
+            rest = span.end;
+        }
+    }
+    if rest == 0 {
+        return None;
+    }
+    renamed.push_str(&content[rest..]);
+    Some(renamed)
+}
+
+/// 本文中のリンク（`[[` から `]]` まで）の位置と参照先。
+fn link_spans(content: &str) -> Vec<(Range<usize>, LinkTarget)> {
+    let mut spans = vec![];
+    let mut from = 0;
+    while let Some(start) = content[from..].find("[[").map(|i| from + i) {
+        let Some(end) = content[start + 2..].find("]]").map(|i| start + 2 + i) else {
+            break;
+        };
+        match LinkTarget::parse(&content[start + 2..end]) {
+            Some(target) => {
+                spans.push((start..end + 2, target));
+                from = end + 2;
+            }
+            // `[[[a]]` のように `[` が続く場合は 1 文字ずらして探し直す
+            None => from = start + 1,
+        }
+    }
+    spans
+}
@@ -0,0 +1,61 @@
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use ammonia::Builder;
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, html};
+
+/// 表の列揃えとして `<th>` / `<td>` に残してよい `style` の値。
+const TEXT_ALIGN_STYLES: [&str; 3] = [
+    "text-align: left",
+    "text-align: center",
+    "text-align: right",
+];
+
+// 許可するタグ・属性は ammonia の既定（スクリプト・イベントハンドラー・`javascript:` の
+// URL などは含まれない）に、タスクリストのチェックボックスだけを加える
+static SANITIZER: LazyLock<Builder<'static>> = LazyLock::new(|| {
+    let mut builder = Builder::default();
+    builder
+        .add_tags(["input"])
+        .add_tag_attribute_values("input", "type", ["checkbox"])
+        .add_tag_attributes("input", ["checked", "disabled"])
+        .add_tag_attributes("th", ["style"])
+        .add_tag_attributes("td", ["style"])
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            ("th" | "td", "style") => TEXT_ALIGN_STYLES
+                .contains(&value)
+                .then_some(Cow::Borrowed(value)),
+            _ => Some(Cow::Borrowed(value)),
+        });
+    builder
+});
+
+/// メモの本文（CommonMark + GFM の表・タスクリスト・取り消し線）を HTML に変換する。
+/// 本文中の生の HTML も含めて無害化するので、結果はそのままページに埋め込める。
+pub fn render_html(markdown: &str) -> String {
+    render_html_with(markdown, |_| None)
+}
+
+/// `render_html` と同じだが、Markdown の画像（`![alt](url)`）の URL を `rewrite_image` が
+/// `Some` を返したものに置き換える（添付画像をサムネイルで表示するためなど）。
+pub fn render_html_with(markdown: &str, rewrite_image: impl Fn(&str) -> Option<String>) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH;
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: rewrite_image(&dest_url).map_or(dest_url, CowStr::from),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    let mut unsafe_html = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, events);
+    SANITIZER.clean(&unsafe_html).to_string()
+}
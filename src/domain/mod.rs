@@ -1,2 +1,14 @@
+pub mod attachment;
+pub mod diff;
+pub mod graph;
+pub mod image;
+pub mod link;
+pub mod markdown;
 pub mod model;
 pub mod note;
+pub mod policy;
+pub mod share;
+pub mod tag;
+pub mod totp;
+pub mod user;
+pub mod workspace;
@@ -7,14 +7,292 @@ pub struct User {
     pub email: String,
     pub password_hash: String, // Argon2id PHC string
     pub created_at: i64,
+    pub email_verified_at: Option<i64>, // メール確認前は None
+    pub failed_login_count: i64,        // 連続したログイン失敗の回数
+    pub locked_until: Option<i64>,      // この日時までログインを受け付けない
+    #[sqlx(try_from = "String")]
+    pub role: Role,
+    pub disabled_at: Option<i64>, // 管理者が無効にしたアカウントは Some
+}
+
+/// ユーザーの権限。`Admin` は `/admin` 以下の管理用 API を使える。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Admin,
+}
+
+/// メモの公開範囲。
+/// - `Private`: 作成者のみ閲覧可能（既定）
+/// - `Unlisted`: ID を知っていれば誰でも閲覧可能（一覧には出さない）
+/// - `Public`: 誰でも閲覧可能（一覧にも出す）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Private,
+    Unlisted,
+    Public,
 }
 
 #[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
     pub id: i64,
     pub author_id: i64,
+    #[serde(default)]
+    pub workspace_id: Option<i64>, // ワークスペースのメモなら Some（メンバーの役割で権限が決まる）
+    #[serde(default)]
+    pub notebook_id: Option<i64>, // 作成者が入れたノートブック（どこにも入れていなければ None）
     pub title: String,
     pub content: String,
+    #[sqlx(try_from = "String")]
+    pub visibility: Visibility,
     pub created_at: i64,
     pub updated_at: i64,
+    pub version: i64, // 更新のたびに増える（ETag）
+    #[sqlx(json)]
+    #[serde(default)]
+    pub tags: Vec<String>, // 名前順
+    #[serde(default)]
+    pub pinned: bool, // 一覧の先頭に固定する
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default)]
+    pub archived_at: Option<i64>, // アーカイブしていれば Some（一覧には `?archived=true` でのみ出す）
+}
+
+/// メモを整理するノートブック（フォルダー）。作成したユーザーだけが使える。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Notebook {
+    pub id: i64,
+    pub parent_id: Option<i64>, // トップレベルなら null
+    pub name: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// ワークスペースでの役割。
+/// - `Owner`: メンバーの招待・役割の変更・削除、メモの編集・削除
+/// - `Editor`: メモの作成・編集（削除は自分が作成したメモのみ）
+/// - `Viewer`: メモの閲覧のみ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+/// チームで共有するメモの置き場所。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+    pub created_at: i64,
+}
+
+/// 自分が参加しているワークスペースと、そこでの役割。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMembership {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub workspace: Workspace,
+    #[sqlx(try_from = "String")]
+    pub role: WorkspaceRole,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMember {
+    pub user_id: i64,
+    pub email: String,
+    #[sqlx(try_from = "String")]
+    pub role: WorkspaceRole,
+    pub joined_at: i64,
+}
+
+/// メールで送ったワークスペースへの招待（トークン本体はハッシュのみ保存する）。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceInvitation {
+    pub id: i64,
+    pub workspace_id: i64,
+    pub email: String,
+    #[sqlx(try_from = "String")]
+    pub role: WorkspaceRole,
+    pub invited_by: Option<i64>, // 招待したユーザーが退会していれば null
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+/// メモを個別に共有したときの権限（`Read` < `Write`）。
+/// - `Read`: 閲覧のみ
+/// - `Write`: 閲覧と編集（削除・再共有はできない）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SharePermission {
+    Read,
+    Write,
+}
+
+/// メモを共有しているユーザー。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct NoteShare {
+    pub user_id: i64,
+    pub email: String,
+    #[sqlx(try_from = "String")]
+    pub permission: SharePermission,
+    pub created_at: i64,
+}
+
+/// メモの共有リンク（トークン本体・パスワードはハッシュのみ保存する）。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct ShareLink {
+    pub id: i64,
+    pub note_id: i64,
+    #[sqlx(try_from = "String")]
+    pub permission: SharePermission,
+    pub has_password: bool,
+    pub created_by: Option<i64>, // 作成したユーザーが退会していれば null
+    pub created_at: i64,
+    pub expires_at: Option<i64>, // null なら無期限
+}
+
+/// メモの共有状況（`GET /notes/{id}/shares`）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteSharing {
+    pub users: Vec<NoteShare>,
+    pub links: Vec<ShareLink>,
+}
+
+/// ユーザーのタグと、そのタグが付いたメモの件数。
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
+}
+
+/// 全文検索のヒット。`snippet` は一致箇所を `<mark>`〜`</mark>` で囲んだ抜粋
+/// （それ以外の部分はエスケープされていない本文そのもの）。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct NoteSearchHit {
+    #[sqlx(flatten)]
+    pub note: Note,
+    pub rank: f64, // 大きいほど関連度が高い
+    pub snippet: String,
+}
+
+/// メモのある時点のタイトル・本文。`revision` はメモごとの連番（作成時が 1）。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct NoteRevision {
+    pub note_id: i64,
+    pub revision: i64,
+    pub title: String,
+    pub content: String,
+    pub editor_id: Option<i64>, // 編集者が退会していれば null
+    pub created_at: i64,
+}
+
+/// 履歴一覧用の要約（本文は含めない）。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct NoteRevisionSummary {
+    pub revision: i64,
+    pub title: String,
+    pub editor_id: Option<i64>,
+    pub created_at: i64,
+}
+
+/// メモ本文中のリンク（`[[タイトル]]` / `[[#123]]`）とその参照先。
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteLink {
+    pub target: String,       // 本文に書かれた参照先（`#123` またはタイトル）
+    pub note_id: Option<i64>, // リンク切れ（参照先が無いか閲覧できない）なら null
+    pub title: Option<String>,
+}
+
+/// メモの添付ファイル。`content_type` はアップロード時の申告ではなく中身から判定したもの。
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub note_id: i64,
+    pub user_id: Option<i64>, // アップロードしたユーザー（退会していれば null）
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub sha256: String,      // 中身の SHA-256（16 進）
+    pub width: Option<i32>,  // 画像の表示上の幅（画像でなければ null）
+    pub height: Option<i32>, // 画像の表示上の高さ（画像でなければ null）
+    pub created_at: i64,
+    #[serde(skip)]
+    pub storage_key: String, // `BlobStore` のキー
+}
+
+/// ゴミ箱内のメモ。`deleted_at` はゴミ箱に移動した日時。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedNote {
+    #[sqlx(flatten)]
+    pub note: Note,
+    pub deleted_at: i64,
+}
+
+/// リフレッシュトークンの保存内容（トークン本体はハッシュのみ保存する）。
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct RefreshToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub family_id: String, // 同じログインから続くトークンで共通
+    pub expires_at: i64,
+    pub used_at: Option<i64>, // ローテーション済みなら Some
+    pub revoked_at: Option<i64>,
+}
+
+/// `user_tokens` に保存する 1 回限りのトークンの用途。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserTokenPurpose {
+    PasswordReset,
+    EmailVerification,
+    /// パスワード確認後、2 段階認証のコード入力を待つログイン
+    LoginChallenge,
+}
+
+impl UserTokenPurpose {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserTokenPurpose::PasswordReset => "password_reset",
+            UserTokenPurpose::EmailVerification => "email_verification",
+            UserTokenPurpose::LoginChallenge => "login_challenge",
+        }
+    }
+}
+
+/// ユーザーの TOTP 設定。`enabled_at` が None の間は登録途中（ログインには使わない）。
+#[allow(dead_code)]
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TotpCredential {
+    pub user_id: i64,
+    pub secret: String, // base32
+    pub enabled_at: Option<i64>,
+    pub last_used_step: Option<i64>,
+}
+
+/// API トークンに付与できる権限。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "notes:read")]
+    NotesRead,
+    #[serde(rename = "notes:write")]
+    NotesWrite,
+}
+
+/// ユーザーが発行した API トークン（トークン本体はハッシュのみ保存する）。
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    #[sqlx(json)]
+    pub scopes: Vec<Scope>,
+    pub created_at: i64,
+    pub expires_at: Option<i64>, // null なら無期限
+    pub last_used_at: Option<i64>,
 }
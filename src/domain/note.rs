@@ -1,7 +1,30 @@
-use crate::domain::model::Note;
+use thiserror::Error;
 
-impl Note {
-    pub fn is_owner(&self, user_id: i64) -> bool {
-        self.author_id == user_id
+use crate::domain::model::Visibility;
+
+#[derive(Debug, Error)]
+#[error("invalid visibility: {0}")]
+pub struct InvalidVisibility(pub String);
+
+impl Visibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Visibility::Private => "private",
+            Visibility::Unlisted => "unlisted",
+            Visibility::Public => "public",
+        }
+    }
+}
+
+impl TryFrom<String> for Visibility {
+    type Error = InvalidVisibility;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "private" => Ok(Visibility::Private),
+            "unlisted" => Ok(Visibility::Unlisted),
+            "public" => Ok(Visibility::Public),
+            _ => Err(InvalidVisibility(value)),
+        }
     }
 }
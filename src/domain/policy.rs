@@ -0,0 +1,71 @@
+use crate::domain::model::{Note, SharePermission, Visibility, WorkspaceRole};
+
+/// メモに対する操作。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteAction {
+    Read,
+    Update,
+    Delete,
+    /// 他のユーザーとの共有・共有リンクの管理
+    Share,
+}
+
+/// `viewer`（未ログインなら `None`）が `note` に対して `action` を行えるか。
+///
+/// `role` は `note.workspace_id` のワークスペースでの `viewer` の役割（メンバーでなければ `None`。
+/// 個人のメモでは使わない）。`share` は個別の共有や共有リンクで `viewer` に与えられた権限。
+/// - 個人のメモ: 作成者だけが編集・削除・共有できる
+/// - ワークスペースのメモ: オーナー・編集者が編集でき、削除・共有はオーナーか作成者本人の編集者
+/// - `public` / `unlisted` なメモは誰でも閲覧でき、`private` なメモは上記で編集できる人と
+///   ワークスペースのメンバー（閲覧者を含む）だけが閲覧できる
+/// - 共有された人は `read` なら閲覧、`write` なら閲覧と編集ができる（削除・再共有はできない）
+pub fn can_access_note(
+    note: &Note,
+    viewer: Option<i64>,
+    role: Option<WorkspaceRole>,
+    share: Option<SharePermission>,
+    action: NoteAction,
+) -> bool {
+    let shared = match action {
+        NoteAction::Read => share.is_some(),
+        NoteAction::Update => share == Some(SharePermission::Write),
+        NoteAction::Delete | NoteAction::Share => false,
+    };
+    if shared {
+        return true;
+    }
+    let is_author = viewer == Some(note.author_id);
+    if note.workspace_id.is_none() {
+        return match action {
+            NoteAction::Read => note.visibility != Visibility::Private || is_author,
+            NoteAction::Update | NoteAction::Delete | NoteAction::Share => is_author,
+        };
+    }
+    let Some(role) = role.filter(|_| viewer.is_some()) else {
+        return action == NoteAction::Read && note.visibility != Visibility::Private;
+    };
+    match action {
+        NoteAction::Read => true,
+        NoteAction::Update => role.can_edit(),
+        NoteAction::Delete | NoteAction::Share => {
+            role == WorkspaceRole::Owner || (role == WorkspaceRole::Editor && is_author)
+        }
+    }
+}
+
+/// 公開範囲を `visibility` としたとき、`viewer` の `GET /notes` に `note` が出るか
+/// （ゴミ箱・アーカイブは考えない）。`role` は `can_access_note` と同じ。
+/// `public` なメモ、自分の個人のメモ、メンバーになっているワークスペースのメモが出る。
+/// 共有されただけのメモや `unlisted` なメモは出ない。
+pub fn lists_note(
+    note: &Note,
+    visibility: Visibility,
+    viewer: i64,
+    role: Option<WorkspaceRole>,
+) -> bool {
+    visibility == Visibility::Public
+        || match note.workspace_id {
+            None => note.author_id == viewer,
+            Some(_) => role.is_some(),
+        }
+}
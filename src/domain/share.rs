@@ -0,0 +1,28 @@
+use thiserror::Error;
+
+use crate::domain::model::SharePermission;
+
+#[derive(Debug, Error)]
+#[error("invalid share permission: {0}")]
+pub struct InvalidSharePermission(pub String);
+
+impl SharePermission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SharePermission::Read => "read",
+            SharePermission::Write => "write",
+        }
+    }
+}
+
+impl TryFrom<String> for SharePermission {
+    type Error = InvalidSharePermission;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "read" => Ok(SharePermission::Read),
+            "write" => Ok(SharePermission::Write),
+            _ => Err(InvalidSharePermission(value)),
+        }
+    }
+}
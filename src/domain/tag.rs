@@ -0,0 +1,49 @@
+use thiserror::Error;
+
+pub const MAX_TAG_LEN: usize = 64;
+pub const MAX_TAGS_PER_NOTE: usize = 32;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvalidTag {
+    #[error("tag must not be empty")]
+    Empty,
+    #[error("tag is too long: {0}")]
+    TooLong(String),
+    #[error("tag contains invalid characters: {0}")]
+    InvalidChar(String),
+    #[error("too many tags")]
+    TooMany,
+}
+
+/// タグ名を正規化する。
+/// - 前後の空白を除去
+/// - 空文字・制御文字・`/`・`,` を含むものは不可（URL パスやクエリでそのまま扱えるように）
+/// - 長さは 1..=64 文字
+pub fn normalize_tag(tag: &str) -> Result<String, InvalidTag> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(InvalidTag::Empty);
+    }
+    if tag.chars().count() > MAX_TAG_LEN {
+        return Err(InvalidTag::TooLong(tag.to_string()));
+    }
+    if tag.chars().any(|c| c.is_control() || c == '/' || c == ',') {
+        return Err(InvalidTag::InvalidChar(tag.to_string()));
+    }
+    Ok(tag.to_string())
+}
+
+/// メモに付けるタグ一覧を正規化する（重複は最初の 1 つだけ残す）。
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, InvalidTag> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = normalize_tag(tag)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS_PER_NOTE {
+        return Err(InvalidTag::TooMany);
+    }
+    Ok(normalized)
+}
@@ -0,0 +1,107 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+
+/// 1 ステップの長さ（秒）。
+pub const TOTP_PERIOD_SECS: i64 = 30;
+/// コードの桁数。
+pub const TOTP_DIGITS: u32 = 6;
+/// 時計のずれを許容するステップ数（前後）。
+pub const TOTP_SKEW_STEPS: i64 = 1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 の base32（パディングなし）。認証アプリに渡すシークレットの形式。
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for &b in bytes {
+        buffer = (buffer << 8) | b as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// `base32_encode` の逆。大文字・小文字と `=` パディングは問わない。
+pub fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for c in s.trim_end_matches('=').bytes() {
+        let v = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | v;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// 時刻 `unix_secs` のステップ番号。
+pub fn totp_step(unix_secs: i64) -> i64 {
+    unix_secs.div_euclid(TOTP_PERIOD_SECS)
+}
+
+/// RFC 6238（HMAC-SHA1、6 桁）のコード。
+pub fn totp_code(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let bin = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    bin % 10u32.pow(TOTP_DIGITS)
+}
+
+/// `code` が時刻 `unix_secs` の前後 `TOTP_SKEW_STEPS` ステップのいずれかと一致すれば、そのステップを返す。
+/// 同じコードの再利用を防ぐため、呼び出し側で使用済みのステップと比較すること。
+pub fn verify_totp(secret: &[u8], code: &str, unix_secs: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let current = totp_step(unix_secs);
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS).find(|&step| {
+        let expected = format!(
+            "{:0width$}",
+            totp_code(secret, step),
+            width = TOTP_DIGITS as usize
+        );
+        bool::from(expected.as_bytes().ct_eq(code.as_bytes()))
+    })
+}
+
+/// 認証アプリに登録するための `otpauth://` URI（QR コードにして読み取らせる）。
+pub fn otpauth_uri(issuer: &str, account: &str, secret_base32: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={secret_base32}&issuer={}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_PERIOD_SECS}",
+        percent_encode(issuer),
+        percent_encode(account),
+        percent_encode(issuer),
+    )
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
@@ -0,0 +1,29 @@
+use thiserror::Error;
+
+use crate::domain::model::{Role, User};
+
+impl User {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled_at.is_some()
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid role: {0}")]
+pub struct InvalidRole(pub String);
+
+impl TryFrom<String> for Role {
+    type Error = InvalidRole;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            _ => Err(InvalidRole(value)),
+        }
+    }
+}
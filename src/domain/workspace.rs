@@ -0,0 +1,35 @@
+use thiserror::Error;
+
+use crate::domain::model::WorkspaceRole;
+
+#[derive(Debug, Error)]
+#[error("invalid workspace role: {0}")]
+pub struct InvalidWorkspaceRole(pub String);
+
+impl WorkspaceRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Owner => "owner",
+            WorkspaceRole::Editor => "editor",
+            WorkspaceRole::Viewer => "viewer",
+        }
+    }
+
+    /// メモを作成・編集できるか。
+    pub fn can_edit(&self) -> bool {
+        matches!(self, WorkspaceRole::Owner | WorkspaceRole::Editor)
+    }
+}
+
+impl TryFrom<String> for WorkspaceRole {
+    type Error = InvalidWorkspaceRole;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "owner" => Ok(WorkspaceRole::Owner),
+            "editor" => Ok(WorkspaceRole::Editor),
+            "viewer" => Ok(WorkspaceRole::Viewer),
+            _ => Err(InvalidWorkspaceRole(value)),
+        }
+    }
+}
@@ -10,46 +10,367 @@ use sqlx::{PgPool, postgres::PgPoolOptions};
 #[cfg(not(feature = "postgres"))]
 use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
 use std::sync::Arc;
+use std::time::Duration;
 
-use app::auth::{login, me, signup};
-use app::notes::{create_note, delete_note, get_note, list_notes, update_note};
+use app::admin::{
+    delete_any_note, disable_user, enable_user, force_password_reset, get_user, list_users,
+};
+use app::attachments::{
+    attachment_thumbnail, delete_attachment, download_attachment, list_attachments,
+    upload_attachment,
+};
+use app::auth::{
+    change_password, confirm_password_reset, jwks, login, login_2fa, logout, me, refresh,
+    request_password_reset, resend_email_verification, signup, verify_email,
+};
+use app::events::{note_events, note_events_ws};
+use app::graph::note_graph;
+use app::links::{list_backlinks, list_links};
+use app::notebooks::{
+    create_notebook, delete_notebook, file_note, list_notebook_notes, list_notebooks,
+    move_notebook, rename_notebook,
+};
+use app::notes::{
+    archive_note, create_note, delete_note, get_note, list_notes, pin_note, search_notes,
+    star_note, unarchive_note, unpin_note, unstar_note, update_note,
+};
+use app::revisions::{diff_revisions, get_revision, list_revisions, restore_revision};
+use app::shares::{create_share_link, list_shares, revoke_share_link, share_note, unshare_note};
+use app::tags::{delete_tag, list_tags, merge_tags, rename_tag};
+use app::tokens::{create_api_token, delete_api_token, list_api_tokens};
+use app::trash::{list_trash, purge_note, restore_note};
+use app::two_factor::{confirm_totp, enroll_totp};
+use app::workspaces::{
+    accept_invitation, create_workspace, invite_member, list_members, list_workspaces,
+    remove_member, set_member_role,
+};
 use middleware::auth::token::JwtTokenService;
+use middleware::rate_limit::{
+    InMemoryRateLimitStore, RateLimitPolicy, RateLimitStore, RateLimiter,
+};
+use repository::attachment::AttachmentRepository;
+use repository::link::LinkRepository;
 use repository::note::NoteRepository;
-use repository::user::UserRepository;
+use repository::notebook::NotebookRepository;
+use repository::revision::RevisionRepository;
+use repository::share::ShareRepository;
+use repository::tag::TagRepository;
+use repository::token::TokenRepository;
+use repository::user::{
+    ApiTokenRepository, TwoFactorRepository, UserAdminRepository, UserRepository,
+    UserTokenRepository,
+};
+use repository::workspace::WorkspaceRepository;
 #[cfg(feature = "postgres")]
-use repository::{note::PgNoteRepository, user::PgUserRepository};
+use repository::{
+    attachment::PgAttachmentRepository, link::PgLinkRepository, note::PgNoteRepository,
+    notebook::PgNotebookRepository, revision::PgRevisionRepository, share::PgShareRepository,
+    tag::PgTagRepository, token::PgTokenRepository, user::PgUserRepository,
+    workspace::PgWorkspaceRepository,
+};
 #[cfg(not(feature = "postgres"))]
-use repository::{note::SqliteNoteRepository, user::SqliteUserRepository};
+use repository::{
+    attachment::SqliteAttachmentRepository, link::SqliteLinkRepository, note::SqliteNoteRepository,
+    notebook::SqliteNotebookRepository, revision::SqliteRevisionRepository,
+    share::SqliteShareRepository, tag::SqliteTagRepository, token::SqliteTokenRepository,
+    user::SqliteUserRepository, workspace::SqliteWorkspaceRepository,
+};
+use service::account::AccountService;
+use service::admin::AdminService;
+use service::attachment::AttachmentService;
 use service::auth::{AuthService, AuthServiceImpl};
+use service::blob::blob_store_from_env;
+use service::events::EventBus;
+use service::login_throttle::{LockoutPolicy, LoginThrottle};
+use service::mailer::mailer_from_env;
+use service::notebook::NotebookService;
+use service::session::{SessionService, TokenPurger};
+use service::share::ShareService;
+use service::thumbnail::ThumbnailWorker;
+use service::trash::TrashPurger;
+use service::two_factor::TwoFactorService;
+use service::workspace::WorkspaceService;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let pool: AppPool = create_pool(&database_url).await;
 
-    let (user_repo, note_repo) = create_repositories(pool.clone());
-    let auth_service: Arc<dyn AuthService> = Arc::new(AuthServiceImpl::new(user_repo.clone()));
+    let repos = create_repositories(pool.clone());
+    let two_factor = web::Data::new(TwoFactorService::from_env(
+        repos.two_factor.clone(),
+        repos.user.clone(),
+        repos.user_token.clone(),
+    ));
+    let auth_service: Arc<dyn AuthService> = Arc::new(
+        AuthServiceImpl::new(repos.user.clone())
+            .with_two_factor(two_factor.clone().into_inner())
+            .with_lockout(LockoutPolicy::from_env().expect("login lockout config")),
+    );
+    let login_throttle = web::Data::new(LoginThrottle::from_env().expect("login throttle config"));
     let jwt = web::Data::new(JwtTokenService::from_env().expect("JWT config"));
+    let sessions = web::Data::new(
+        SessionService::from_env(repos.token.clone()).expect("refresh token config"),
+    );
+    let mailer = mailer_from_env().expect("mailer config");
+    let accounts = web::Data::new(AccountService::new(
+        repos.user.clone(),
+        repos.user_token.clone(),
+        mailer.clone(),
+    ));
+    let workspace_service = web::Data::new(WorkspaceService::new(
+        repos.workspace.clone(),
+        repos.user.clone(),
+        mailer,
+    ));
+    let share_service = web::Data::new(ShareService::new(
+        repos.share.clone(),
+        repos.note.clone(),
+        repos.workspace.clone(),
+        repos.user.clone(),
+    ));
+    let notebook_service = web::Data::new(NotebookService::new(
+        repos.notebook.clone(),
+        repos.note.clone(),
+    ));
+    let admin_service = web::Data::new(AdminService::new(
+        repos.user.clone(),
+        repos.admin.clone(),
+        repos.token.clone(),
+        repos.api_token.clone(),
+        repos.note.clone(),
+        accounts.clone().into_inner(),
+    ));
+    let attachment_service = web::Data::new(
+        AttachmentService::from_env(
+            repos.attachment.clone(),
+            blob_store_from_env().expect("blob store config"),
+        )
+        .expect("attachment config"),
+    );
+    let event_bus = web::Data::new(EventBus::default());
+    let rate_limits = RateLimits::from_env();
+    let rate_limit_store: Arc<dyn RateLimitStore> = Arc::new(InMemoryRateLimitStore::new());
+    let trash_purger = TrashPurger::from_env(repos.note.clone())
+        .expect("trash config")
+        .with_attachments(attachment_service.clone().into_inner());
+    actix_web::rt::spawn(trash_purger.run());
+    let token_purger = TokenPurger::from_env(repos.token.clone()).expect("token purge config");
+    actix_web::rt::spawn(token_purger.run());
+    let thumbnail_worker = ThumbnailWorker::new(attachment_service.clone().into_inner());
+    actix_web::rt::spawn(thumbnail_worker.run());
 
     HttpServer::new(move || {
         App::new()
+            .wrap(rate_limits.limiter(rate_limit_store.clone()))
             .app_data(web::Data::new(auth_service.clone()))
-            .app_data(web::Data::new(note_repo.clone()))
+            .app_data(web::Data::new(repos.note.clone()))
+            .app_data(web::Data::new(repos.tag.clone()))
+            .app_data(web::Data::new(repos.revision.clone()))
+            .app_data(web::Data::new(repos.link.clone()))
+            .app_data(web::Data::new(repos.token.clone()))
+            .app_data(web::Data::new(repos.api_token.clone()))
+            .app_data(web::Data::new(repos.user.clone()))
+            .app_data(web::Data::new(repos.workspace.clone()))
             .app_data(jwt.clone())
+            .app_data(sessions.clone())
+            .app_data(accounts.clone())
+            .app_data(two_factor.clone())
+            .app_data(login_throttle.clone())
+            .app_data(admin_service.clone())
+            .app_data(workspace_service.clone())
+            .app_data(share_service.clone())
+            .app_data(notebook_service.clone())
+            .app_data(attachment_service.clone())
+            .app_data(event_bus.clone())
             .service(signup)
             .service(login)
+            .service(login_2fa)
+            .service(refresh)
+            .service(logout)
+            .service(verify_email)
+            .service(request_password_reset)
+            .service(confirm_password_reset)
             .service(me)
+            .service(change_password)
+            .service(resend_email_verification)
+            .service(enroll_totp)
+            .service(confirm_totp)
+            .service(jwks)
+            .service(create_api_token)
+            .service(list_api_tokens)
+            .service(delete_api_token)
+            .service(search_notes) // `/notes/{id}` より先に登録する
             .service(get_note)
             .service(create_note)
             .service(update_note)
             .service(delete_note)
+            .service(restore_note)
             .service(list_notes)
+            .service(list_revisions)
+            .service(get_revision)
+            .service(diff_revisions)
+            .service(restore_revision)
+            .service(list_links)
+            .service(list_backlinks)
+            .service(note_graph)
+            .service(list_tags)
+            .service(merge_tags)
+            .service(rename_tag)
+            .service(delete_tag)
+            .service(list_trash)
+            .service(purge_note)
+            .service(list_users)
+            .service(get_user)
+            .service(disable_user)
+            .service(enable_user)
+            .service(force_password_reset)
+            .service(delete_any_note)
+            .service(accept_invitation)
+            .service(create_workspace)
+            .service(list_workspaces)
+            .service(list_members)
+            .service(set_member_role)
+            .service(remove_member)
+            .service(invite_member)
+            .service(list_shares)
+            .service(share_note)
+            .service(unshare_note)
+            .service(create_share_link)
+            .service(revoke_share_link)
+            .service(create_notebook)
+            .service(list_notebooks)
+            .service(rename_notebook)
+            .service(move_notebook)
+            .service(delete_notebook)
+            .service(list_notebook_notes)
+            .service(file_note)
+            .service(pin_note)
+            .service(unpin_note)
+            .service(star_note)
+            .service(unstar_note)
+            .service(archive_note)
+            .service(unarchive_note)
+            .service(upload_attachment)
+            .service(list_attachments)
+            .service(download_attachment)
+            .service(delete_attachment)
+            .service(attachment_thumbnail)
+            .service(note_events)
+            .service(note_events_ws)
     })
     .bind(("0.0.0.0", 8080))?
     .run()
     .await
 }
 
+/// ルートのグループごとのレート制限（`RATE_LIMIT_*` に `<requests>/<seconds>` で指定する）。
+#[derive(Clone, Copy)]
+struct RateLimits {
+    auth: RateLimitPolicy,
+    write: RateLimitPolicy,
+    read: RateLimitPolicy,
+    default: RateLimitPolicy,
+}
+
+impl RateLimits {
+    fn from_env() -> Self {
+        let policy = |name, requests, secs| {
+            RateLimitPolicy::from_env(
+                name,
+                RateLimitPolicy::new(requests, Duration::from_secs(secs)),
+            )
+            .expect("rate limit config")
+        };
+        Self {
+            auth: policy("RATE_LIMIT_AUTH", 10, 60),
+            write: policy("RATE_LIMIT_WRITE", 60, 60),
+            read: policy("RATE_LIMIT_READ", 300, 60),
+            default: policy("RATE_LIMIT_DEFAULT", 120, 60),
+        }
+    }
+
+    fn limiter(&self, store: Arc<dyn RateLimitStore>) -> RateLimiter {
+        use actix_web::http::Method;
+        // ログインは `LoginThrottle` で別に制限している
+        RateLimiter::new(store)
+            .group(
+                "auth",
+                self.auth,
+                [
+                    (Method::POST, "/auth/signup"),
+                    (Method::POST, "/auth/password-reset/request"),
+                    (Method::POST, "/me/email-verification"),
+                    (Method::POST, "/workspaces/{id}/invitations"),
+                ],
+            )
+            .group(
+                "write",
+                self.write,
+                [
+                    (Method::POST, "/notes"),
+                    (Method::PUT, "/notes/{id}"),
+                    (Method::DELETE, "/notes/{id}"),
+                    (Method::POST, "/notes/{id}/restore"),
+                    (Method::POST, "/notes/{id}/revisions/{rev}/restore"),
+                    (Method::PUT, "/tags/{name}"),
+                    (Method::POST, "/tags/merge"),
+                    (Method::DELETE, "/tags/{name}"),
+                    (Method::DELETE, "/trash/{id}"),
+                    (Method::POST, "/workspaces"),
+                    (Method::PUT, "/workspaces/{id}/members/{user_id}"),
+                    (Method::DELETE, "/workspaces/{id}/members/{user_id}"),
+                    (Method::POST, "/workspaces/invitations/accept"),
+                    (Method::POST, "/notes/{id}/shares"),
+                    (Method::DELETE, "/notes/{id}/shares/{user_id}"),
+                    (Method::POST, "/notes/{id}/share-links"),
+                    (Method::DELETE, "/notes/{id}/share-links/{link_id}"),
+                    (Method::PUT, "/notes/{id}/notebook"),
+                    (Method::POST, "/notebooks"),
+                    (Method::PUT, "/notebooks/{id}"),
+                    (Method::PUT, "/notebooks/{id}/parent"),
+                    (Method::DELETE, "/notebooks/{id}"),
+                    (Method::PUT, "/notes/{id}/pin"),
+                    (Method::DELETE, "/notes/{id}/pin"),
+                    (Method::PUT, "/notes/{id}/star"),
+                    (Method::DELETE, "/notes/{id}/star"),
+                    (Method::PUT, "/notes/{id}/archive"),
+                    (Method::DELETE, "/notes/{id}/archive"),
+                    (Method::POST, "/notes/{id}/attachments"),
+                    (Method::DELETE, "/notes/{id}/attachments/{attachment_id}"),
+                ],
+            )
+            .group(
+                "read",
+                self.read,
+                [
+                    (Method::GET, "/notes"),
+                    (Method::GET, "/notes/search"),
+                    (Method::GET, "/notes/{id}"),
+                    (Method::GET, "/notes/{id}/revisions"),
+                    (Method::GET, "/notes/{id}/revisions/{rev}"),
+                    (Method::GET, "/notes/{id}/diff"),
+                    (Method::GET, "/notes/{id}/links"),
+                    (Method::GET, "/notes/{id}/backlinks"),
+                    (Method::GET, "/graph"),
+                    (Method::GET, "/tags"),
+                    (Method::GET, "/trash"),
+                    (Method::GET, "/workspaces"),
+                    (Method::GET, "/workspaces/{id}/members"),
+                    (Method::GET, "/notes/{id}/shares"),
+                    (Method::GET, "/notebooks"),
+                    (Method::GET, "/notebooks/{id}/notes"),
+                    (Method::GET, "/notes/{id}/attachments"),
+                    (Method::GET, "/notes/{id}/attachments/{attachment_id}"),
+                    (Method::GET, "/attachments/{id}/thumb"),
+                    (Method::GET, "/events"),
+                    (Method::GET, "/events/ws"),
+                ],
+            )
+            .default_policy(self.default)
+    }
+}
+
 #[cfg(feature = "postgres")]
 type AppPool = PgPool;
 #[cfg(not(feature = "postgres"))]
@@ -73,18 +394,62 @@ async fn create_pool(database_url: &str) -> AppPool {
         .expect("db connect")
 }
 
+/// アプリケーションが使うリポジトリ一式。
+struct Repositories {
+    user: Arc<dyn UserRepository>,
+    note: Arc<dyn NoteRepository>,
+    tag: Arc<dyn TagRepository>,
+    revision: Arc<dyn RevisionRepository>,
+    link: Arc<dyn LinkRepository>,
+    attachment: Arc<dyn AttachmentRepository>,
+    token: Arc<dyn TokenRepository>,
+    api_token: Arc<dyn ApiTokenRepository>,
+    user_token: Arc<dyn UserTokenRepository>,
+    two_factor: Arc<dyn TwoFactorRepository>,
+    admin: Arc<dyn UserAdminRepository>,
+    workspace: Arc<dyn WorkspaceRepository>,
+    share: Arc<dyn ShareRepository>,
+    notebook: Arc<dyn NotebookRepository>,
+}
+
 #[cfg(feature = "postgres")]
-fn create_repositories(pool: AppPool) -> (Arc<dyn UserRepository>, Arc<dyn NoteRepository>) {
-    (
-        Arc::new(PgUserRepository::new(pool.clone())) as Arc<dyn UserRepository>,
-        Arc::new(PgNoteRepository::new(pool)) as Arc<dyn NoteRepository>,
-    )
+fn create_repositories(pool: AppPool) -> Repositories {
+    let user = Arc::new(PgUserRepository::new(pool.clone()));
+    Repositories {
+        user: user.clone(),
+        api_token: user.clone(),
+        user_token: user.clone(),
+        two_factor: user.clone(),
+        admin: user,
+        note: Arc::new(PgNoteRepository::new(pool.clone())),
+        tag: Arc::new(PgTagRepository::new(pool.clone())),
+        revision: Arc::new(PgRevisionRepository::new(pool.clone())),
+        link: Arc::new(PgLinkRepository::new(pool.clone())),
+        attachment: Arc::new(PgAttachmentRepository::new(pool.clone())),
+        workspace: Arc::new(PgWorkspaceRepository::new(pool.clone())),
+        share: Arc::new(PgShareRepository::new(pool.clone())),
+        notebook: Arc::new(PgNotebookRepository::new(pool.clone())),
+        token: Arc::new(PgTokenRepository::new(pool)),
+    }
 }
 
 #[cfg(not(feature = "postgres"))]
-fn create_repositories(pool: AppPool) -> (Arc<dyn UserRepository>, Arc<dyn NoteRepository>) {
-    (
-        Arc::new(SqliteUserRepository::new(pool.clone())) as Arc<dyn UserRepository>,
-        Arc::new(SqliteNoteRepository::new(pool)) as Arc<dyn NoteRepository>,
-    )
+fn create_repositories(pool: AppPool) -> Repositories {
+    let user = Arc::new(SqliteUserRepository::new(pool.clone()));
+    Repositories {
+        user: user.clone(),
+        api_token: user.clone(),
+        user_token: user.clone(),
+        two_factor: user.clone(),
+        admin: user,
+        note: Arc::new(SqliteNoteRepository::new(pool.clone())),
+        tag: Arc::new(SqliteTagRepository::new(pool.clone())),
+        revision: Arc::new(SqliteRevisionRepository::new(pool.clone())),
+        link: Arc::new(SqliteLinkRepository::new(pool.clone())),
+        attachment: Arc::new(SqliteAttachmentRepository::new(pool.clone())),
+        workspace: Arc::new(SqliteWorkspaceRepository::new(pool.clone())),
+        share: Arc::new(SqliteShareRepository::new(pool.clone())),
+        notebook: Arc::new(SqliteNotebookRepository::new(pool.clone())),
+        token: Arc::new(SqliteTokenRepository::new(pool)),
+    }
 }
@@ -1,36 +1,243 @@
-use actix_web::{FromRequest, HttpRequest, dev::Payload, http::header, web};
-use std::future::{Ready, ready};
+use actix_web::{FromRequest, HttpMessage, HttpRequest, dev::Payload, http::header, web};
+use serde::Deserialize;
+use std::future::{Future, ready};
+use std::pin::Pin;
+use std::sync::Arc;
 
-use super::{model::JWTClaim, token::JwtTokenService};
+use super::model::JWTClaim;
+use super::token::{API_TOKEN_PREFIX, JwtTokenService, hash_token};
+use crate::domain::model::{ApiToken, User};
+use crate::repository::token::TokenRepository;
+use crate::repository::user::{ApiTokenRepository, UserRepository};
 
+/// `Authorization: Bearer` で認証したユーザー。
+/// ログインで得た JWT と、`memo_pat_` で始まる API トークンのどちらも受け付ける。
+/// API トークンの場合は `scope` に許可された権限が入るので、ハンドラ側で
+/// `JWTClaim::has_scope` を確認すること。
 pub struct AuthenticatedUser(pub JWTClaim);
 
+/// ミドルウェア（レート制限など）で検証済みのクレーム。
+/// リクエストの extensions に入っていれば、ハンドラでの検証を省く。
+pub(crate) struct VerifiedClaim(pub(crate) JWTClaim);
+
 impl FromRequest for AuthenticatedUser {
     type Error = actix_web::Error;
-    type Future = Ready<Result<Self, Self::Error>>;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
 
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        let jwt = req.app_data::<web::Data<JwtTokenService>>();
-        let auth = req
-            .headers()
-            .get(header::AUTHORIZATION)
-            .and_then(|h| h.to_str().ok());
-
-        let Some(jwt) = jwt else {
-            return ready(Err(actix_web::error::ErrorUnauthorized("missing jwt")));
-        };
-        let Some(auth) = auth else {
-            return ready(Err(actix_web::error::ErrorUnauthorized("missing header")));
-        };
+        if let Some(VerifiedClaim(claim)) = req.extensions().get::<VerifiedClaim>() {
+            return Box::pin(ready(Ok(AuthenticatedUser(claim.clone()))));
+        }
+        match bearer_token(req) {
+            Ok(token) => authenticate(req, token.to_string()),
+            Err(e) => Box::pin(ready(Err(e))),
+        }
+    }
+}
+
+/// ヘッダーを付けられないクライアント（ブラウザの `EventSource` / `WebSocket`）がトークンを渡すクエリ。
+#[derive(Deserialize)]
+struct AccessTokenQuery {
+    access_token: Option<String>,
+}
 
-        let token = auth.strip_prefix("Bearer ").unwrap_or("");
-        if token.is_empty() {
-            return ready(Err(actix_web::error::ErrorUnauthorized("invalid header")));
+impl AuthenticatedUser {
+    /// `Authorization` ヘッダーが無ければ `?access_token=<token>` のトークンで認証する（検証の内容は同じ）。
+    /// URL はアクセスログなどに残りやすいので、ヘッダーを付けられない接続（変更の通知）以外では使わない。
+    pub async fn from_header_or_query(req: &HttpRequest) -> Result<Self, actix_web::Error> {
+        if req.headers().contains_key(header::AUTHORIZATION)
+            || req.extensions().contains::<VerifiedClaim>()
+        {
+            return Self::extract(req).await;
         }
+        match query_token(req) {
+            Some(token) => authenticate(req, token).await,
+            None => Err(actix_web::error::ErrorUnauthorized("missing token")),
+        }
+    }
+}
+
+fn query_token(req: &HttpRequest) -> Option<String> {
+    web::Query::<AccessTokenQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| query.into_inner().access_token)
+        .filter(|token| !token.is_empty())
+}
+
+/// 認証に使ったトークンがまだ使えるかを確かめ直す。
+/// 長く続く接続（変更の通知）で、ログアウト・API トークンの削除・アカウントの無効化などの後に
+/// 接続を閉じるために使う。
+pub(crate) enum TokenCheck {
+    Jwt {
+        claim: JWTClaim,
+        tokens: Option<web::Data<Arc<dyn TokenRepository>>>,
+    },
+    ApiToken {
+        token_hash: String,
+        api_tokens: Option<web::Data<Arc<dyn ApiTokenRepository>>>,
+    },
+}
 
-        match jwt.verify(token) {
-            Ok(claim) => ready(Ok(AuthenticatedUser(claim))),
-            Err(_) => ready(Err(actix_web::error::ErrorUnauthorized("invalid token"))),
+impl TokenCheck {
+    /// `user` を認証したリクエストのトークン（ヘッダーか `?access_token=`）から作る。
+    pub(crate) fn new(req: &HttpRequest, user: &AuthenticatedUser) -> Option<Self> {
+        let token = match bearer_token(req) {
+            Ok(token) => token.to_string(),
+            Err(_) => query_token(req)?,
+        };
+        if token.starts_with(API_TOKEN_PREFIX) {
+            return Some(Self::ApiToken {
+                token_hash: hash_token(&token),
+                api_tokens: req
+                    .app_data::<web::Data<Arc<dyn ApiTokenRepository>>>()
+                    .cloned(),
+            });
         }
+        Some(Self::Jwt {
+            claim: user.0.clone(),
+            tokens: req
+                .app_data::<web::Data<Arc<dyn TokenRepository>>>()
+                .cloned(),
+        })
+    }
+
+    /// 認証したときと同じ確認をもう一度行う（署名と期限は変わらないので確かめない）。
+    /// 調べられなかった場合も使えないものとして扱う。
+    pub(crate) async fn is_valid(&self) -> bool {
+        match self {
+            Self::Jwt { claim, tokens } => check_revocation(tokens.as_ref(), claim).await.is_ok(),
+            Self::ApiToken {
+                token_hash,
+                api_tokens: Some(api_tokens),
+            } => matches!(
+                api_tokens.authenticate_api_token(token_hash).await,
+                Ok(Some(_))
+            ),
+            Self::ApiToken { .. } => false,
+        }
+    }
+}
+
+/// JWT か API トークンを検証する。
+fn authenticate(
+    req: &HttpRequest,
+    token: String,
+) -> Pin<Box<dyn Future<Output = Result<AuthenticatedUser, actix_web::Error>>>> {
+    if token.starts_with(API_TOKEN_PREFIX) {
+        let api_tokens = req
+            .app_data::<web::Data<Arc<dyn ApiTokenRepository>>>()
+            .cloned();
+        return Box::pin(async move {
+            let Some(api_tokens) = api_tokens else {
+                return Err(actix_web::error::ErrorUnauthorized("api tokens disabled"));
+            };
+            match api_tokens.authenticate_api_token(&hash_token(&token)).await {
+                Ok(Some(api_token)) => Ok(AuthenticatedUser(api_token_claim(api_token))),
+                Ok(None) => Err(actix_web::error::ErrorUnauthorized("invalid token")),
+                Err(_) => Err(actix_web::error::ErrorInternalServerError(
+                    "api token lookup failed",
+                )),
+            }
+        });
+    }
+
+    // JWT は署名と期限を検証したうえで、`TokenRepository` が登録されていれば
+    // `jti` が失効リストに載っていないこと、ユーザーが無効にされていないこと、
+    // ユーザーのトークンをまとめて失効させた後に発行されたことも確認する。
+    let claim = verify_jwt(req, &token);
+    let tokens = req
+        .app_data::<web::Data<Arc<dyn TokenRepository>>>()
+        .cloned();
+    Box::pin(async move {
+        let claim = claim?;
+        check_revocation(tokens.as_ref(), &claim).await?;
+        Ok(AuthenticatedUser(claim))
+    })
+}
+
+async fn check_revocation(
+    tokens: Option<&web::Data<Arc<dyn TokenRepository>>>,
+    claim: &JWTClaim,
+) -> Result<(), actix_web::Error> {
+    let Some(tokens) = tokens.filter(|_| !claim.jti.is_empty()) else {
+        return Ok(());
+    };
+    match tokens
+        .is_access_token_revoked(&claim.jti, claim.sub, claim.iat)
+        .await
+    {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(actix_web::error::ErrorUnauthorized("revoked token")),
+        Err(_) => Err(actix_web::error::ErrorInternalServerError(
+            "revocation check failed",
+        )),
+    }
+}
+
+/// 管理者（`Role::Admin`）としてログインしているユーザー。
+/// 権限はトークンに含めず、リクエストのたびに `UserRepository` で確認する
+/// （降格・無効化がすぐに反映される）。API トークンでは使えない（403）。
+pub struct AdminUser(pub User);
+
+impl FromRequest for AdminUser {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let authenticated = AuthenticatedUser::from_request(req, payload);
+        let users = req
+            .app_data::<web::Data<Arc<dyn UserRepository>>>()
+            .cloned();
+        Box::pin(async move {
+            let claim = authenticated.await?.0;
+            if claim.scope.is_some() {
+                return Err(actix_web::error::ErrorForbidden("api token"));
+            }
+            let Some(users) = users else {
+                return Err(actix_web::error::ErrorForbidden("admin api disabled"));
+            };
+            match users.find_by_id(claim.sub).await {
+                Ok(Some(user)) if user.is_admin() && !user.is_disabled() => Ok(AdminUser(user)),
+                Ok(_) => Err(actix_web::error::ErrorForbidden("admin only")),
+                Err(_) => Err(actix_web::error::ErrorInternalServerError(
+                    "user lookup failed",
+                )),
+            }
+        })
+    }
+}
+
+fn bearer_token(req: &HttpRequest) -> Result<&str, actix_web::Error> {
+    let Some(auth) = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+    else {
+        return Err(actix_web::error::ErrorUnauthorized("missing header"));
+    };
+
+    let token = auth.strip_prefix("Bearer ").unwrap_or("");
+    if token.is_empty() {
+        return Err(actix_web::error::ErrorUnauthorized("invalid header"));
+    }
+    Ok(token)
+}
+
+fn verify_jwt(req: &HttpRequest, token: &str) -> Result<JWTClaim, actix_web::Error> {
+    let Some(jwt) = req.app_data::<web::Data<JwtTokenService>>() else {
+        return Err(actix_web::error::ErrorUnauthorized("missing jwt"));
+    };
+    jwt.verify(token)
+        .map_err(|_| actix_web::error::ErrorUnauthorized("invalid token"))
+}
+
+/// API トークンを JWT と同じ形のクレームとして扱う（失効は `DELETE /me/tokens/{id}` で行うため jti は持たない）。
+fn api_token_claim(api_token: ApiToken) -> JWTClaim {
+    JWTClaim {
+        sub: api_token.user_id,
+        iat: api_token.created_at,
+        exp: api_token.expires_at.unwrap_or(i64::MAX),
+        jti: String::new(),
+        scope: Some(api_token.scopes),
     }
 }
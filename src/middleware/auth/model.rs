@@ -1,8 +1,23 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize)]
+use crate::domain::model::Scope;
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct JWTClaim {
     pub sub: i64, // user id
     pub iat: i64, // issued at
     pub exp: i64, // expire
+    #[serde(default)]
+    pub jti: String, // token id（ログアウト時の失効に使う）
+    /// API トークンで認証した場合に許可された権限。ログインで得たトークンは `None`（すべて許可）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Vec<Scope>>,
+}
+
+impl JWTClaim {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|scopes| scopes.contains(&scope))
+    }
 }
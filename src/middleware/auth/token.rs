@@ -1,6 +1,20 @@
+use std::path::PathBuf;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+};
+use rand_core::{OsRng, RngCore};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use spki::der::{Decode, asn1::ObjectIdentifier};
+use spki::{Document, SubjectPublicKeyInfoRef};
 use thiserror::Error;
 
 use super::model::JWTClaim;
@@ -15,11 +29,162 @@ pub enum TokenError {
     MissingSecret,
     #[error("invalid JWT_EXP_SECS env")]
     InvalidExpiration,
+    #[error("invalid JWT key: {0}")]
+    InvalidKey(String),
+    #[error("no active signing key")]
+    NoSigningKey,
 }
 
-pub struct JwtTokenService {
-    encoding: EncodingKey,
+/// API トークン（`POST /me/tokens` で発行）の接頭辞。JWT と区別するために使う。
+pub const API_TOKEN_PREFIX: &str = "memo_pat_";
+
+const RSA_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const ED25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+/// `JWT_KEYS_FILE`（JSON の配列）の 1 要素。パスは起動時のカレントディレクトリからの相対パス。
+///
+/// - `private_key` を省略した鍵は検証専用（ローテーションで退役させた鍵など）
+/// - `active_from` 以降は署名に使える鍵のうち `active_from` が最も新しいものが使われる
+/// - `retire_at` 以降はその鍵で署名されたトークンを受け付けず、JWKS からも外す
+#[derive(Debug, Deserialize)]
+pub struct JwtKeyConfig {
+    pub kid: String,
+    pub alg: Algorithm,
+    pub private_key: Option<PathBuf>,
+    pub public_key: PathBuf,
+    #[serde(default)]
+    pub active_from: i64,
+    pub retire_at: Option<i64>,
+}
+
+/// 署名・検証に使う鍵。非対称鍵（RS256 / EdDSA）は `kid` で区別し、JWKS で公開する。
+pub struct JwtKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding: Option<EncodingKey>,
     decoding: DecodingKey,
+    jwk: Option<Jwk>,
+    active_from: i64,
+    retire_at: Option<i64>,
+}
+
+impl JwtKey {
+    /// HS256 の共有鍵。`kid` は付けない。
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self {
+            kid: None,
+            algorithm: Algorithm::HS256,
+            encoding: Some(EncodingKey::from_secret(secret)),
+            decoding: DecodingKey::from_secret(secret),
+            jwk: None,
+            active_from: 0,
+            retire_at: None,
+        }
+    }
+
+    /// PEM（秘密鍵は PKCS#8、公開鍵は SubjectPublicKeyInfo）から RS256 / EdDSA の鍵を作る。
+    /// `private_pem` が `None` なら検証専用。
+    pub fn from_pem(
+        kid: &str,
+        algorithm: Algorithm,
+        private_pem: Option<&[u8]>,
+        public_pem: &[u8],
+    ) -> Result<Self, TokenError> {
+        let invalid = |e: &dyn std::fmt::Display| TokenError::InvalidKey(format!("{kid}: {e}"));
+        let jwk = public_jwk(kid, algorithm, public_pem).map_err(|e| invalid(&e))?;
+        let decoding = DecodingKey::from_jwk(&jwk).map_err(|e| invalid(&e))?;
+        let encoding = match (algorithm, private_pem) {
+            (_, None) => None,
+            (Algorithm::RS256, Some(pem)) => {
+                Some(EncodingKey::from_rsa_pem(pem).map_err(|e| invalid(&e))?)
+            }
+            (_, Some(pem)) => Some(EncodingKey::from_ed_pem(pem).map_err(|e| invalid(&e))?),
+        };
+        Ok(Self {
+            kid: Some(kid.to_string()),
+            algorithm,
+            encoding,
+            decoding,
+            jwk: Some(jwk),
+            active_from: 0,
+            retire_at: None,
+        })
+    }
+
+    /// 鍵の有効期間を設定する（`JwtKeyConfig` の `active_from` / `retire_at` と同じ意味）。
+    pub fn with_schedule(mut self, active_from: i64, retire_at: Option<i64>) -> Self {
+        self.active_from = active_from;
+        self.retire_at = retire_at;
+        self
+    }
+
+    fn load(config: &JwtKeyConfig) -> Result<Self, TokenError> {
+        let read = |path: &PathBuf| {
+            std::fs::read(path)
+                .map_err(|e| TokenError::InvalidKey(format!("{}: {e}", path.display())))
+        };
+        let private_pem = config.private_key.as_ref().map(read).transpose()?;
+        let public_pem = read(&config.public_key)?;
+        Ok(
+            Self::from_pem(&config.kid, config.alg, private_pem.as_deref(), &public_pem)?
+                .with_schedule(config.active_from, config.retire_at),
+        )
+    }
+
+    fn is_retired(&self, now: i64) -> bool {
+        self.retire_at.is_some_and(|t| t <= now)
+    }
+
+    fn can_sign(&self, now: i64) -> bool {
+        self.encoding.is_some() && self.active_from <= now && !self.is_retired(now)
+    }
+}
+
+/// 公開鍵の PEM から JWKS に載せる JWK を作る。
+fn public_jwk(kid: &str, algorithm: Algorithm, public_pem: &[u8]) -> Result<Jwk, String> {
+    let pem = std::str::from_utf8(public_pem).map_err(|e| e.to_string())?;
+    let (_, doc) = Document::from_pem(pem).map_err(|e| e.to_string())?;
+    let spki = SubjectPublicKeyInfoRef::try_from(doc.as_bytes()).map_err(|e| e.to_string())?;
+    let key = spki.subject_public_key.raw_bytes();
+
+    let (key_algorithm, parameters) = match algorithm {
+        Algorithm::RS256 if spki.algorithm.oid == RSA_OID => {
+            let rsa = pkcs1::RsaPublicKey::from_der(key).map_err(|e| e.to_string())?;
+            (
+                KeyAlgorithm::RS256,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(rsa.modulus.as_bytes()),
+                    e: URL_SAFE_NO_PAD.encode(rsa.public_exponent.as_bytes()),
+                }),
+            )
+        }
+        Algorithm::EdDSA if spki.algorithm.oid == ED25519_OID => (
+            KeyAlgorithm::EdDSA,
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(key),
+            }),
+        ),
+        Algorithm::RS256 | Algorithm::EdDSA => {
+            return Err(format!("public key does not match {algorithm:?}"));
+        }
+        _ => return Err(format!("unsupported algorithm {algorithm:?}")),
+    };
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+pub struct JwtTokenService {
+    keys: Vec<JwtKey>,
     expiration_secs: u64,
 }
 
@@ -27,52 +192,128 @@ impl JwtTokenService {
     const DEFAULT_EXPIRATION_SECS: u64 = 3600;
     pub fn from_secret(secret: &[u8], expiration_secs: u64) -> Self {
         Self {
-            encoding: EncodingKey::from_secret(secret),
-            decoding: DecodingKey::from_secret(secret),
+            keys: vec![JwtKey::from_secret(secret)],
             expiration_secs,
         }
     }
 
+    /// 複数の鍵を使う。署名には有効期間内で `active_from` が最も新しい鍵、
+    /// 検証にはトークンのヘッダーの `kid` が一致する退役前の鍵を使う。
+    pub fn from_keys(keys: Vec<JwtKey>, expiration_secs: u64) -> Result<Self, TokenError> {
+        if keys.is_empty() {
+            return Err(TokenError::InvalidKey("no keys".into()));
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].iter().any(|k| k.kid == key.kid) {
+                return Err(TokenError::InvalidKey(format!(
+                    "duplicate kid {:?}",
+                    key.kid.as_deref().unwrap_or_default()
+                )));
+            }
+        }
+        Ok(Self {
+            keys,
+            expiration_secs,
+        })
+    }
+
+    /// `JWT_KEYS_FILE` があればその鍵（RS256 / EdDSA）を、無ければ `JWT_SECRET`（HS256）を使う。
     pub fn from_env() -> Result<Self, TokenError> {
-        let secret = std::env::var("JWT_SECRET").map_err(|_| TokenError::MissingSecret)?;
         let exp = match std::env::var("JWT_EXP_SECS") {
             Ok(v) => v
                 .parse::<u64>()
                 .map_err(|_| TokenError::InvalidExpiration)?,
             Err(_) => Self::DEFAULT_EXPIRATION_SECS,
         };
+        if let Ok(path) = std::env::var("JWT_KEYS_FILE") {
+            let file =
+                std::fs::read(&path).map_err(|e| TokenError::InvalidKey(format!("{path}: {e}")))?;
+            let configs: Vec<JwtKeyConfig> = serde_json::from_slice(&file)
+                .map_err(|e| TokenError::InvalidKey(format!("{path}: {e}")))?;
+            let keys = configs
+                .iter()
+                .map(JwtKey::load)
+                .collect::<Result<Vec<_>, _>>()?;
+            return Self::from_keys(keys, exp);
+        }
+        let secret = std::env::var("JWT_SECRET").map_err(|_| TokenError::MissingSecret)?;
         Ok(Self::from_secret(secret.as_bytes(), exp))
     }
 
     pub fn generate(&self, user_id: i64) -> Result<String, TokenError> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or(Duration::from_secs(0))
-            .as_secs() as i64;
+        let now = now();
+        let key = self
+            .keys
+            .iter()
+            .filter(|k| k.can_sign(now))
+            .max_by_key(|k| k.active_from)
+            .ok_or(TokenError::NoSigningKey)?;
+        let encoding = key.encoding.as_ref().ok_or(TokenError::NoSigningKey)?;
+        let mut header = Header::new(key.algorithm);
+        header.kid = key.kid.clone();
         let claims = JWTClaim {
             sub: user_id,
             iat: now,
             exp: now + self.expiration_secs as i64,
+            jti: random_token(16),
+            scope: None,
         };
-        let header = Header::default();
-        encode(&header, &claims, &self.encoding).map_err(|_| TokenError::Encode)
+        encode(&header, &claims, encoding).map_err(|_| TokenError::Encode)
     }
 
     pub fn verify(&self, token: &str) -> Result<JWTClaim, TokenError> {
-        let mut validation = Validation::default();
+        let header = decode_header(token).map_err(|_| TokenError::Decode)?;
+        let now = now();
+        let Some(key) = self
+            .keys
+            .iter()
+            .find(|k| k.kid == header.kid && k.algorithm == header.alg && !k.is_retired(now))
+        else {
+            return Err(TokenError::Decode);
+        };
+        let mut validation = Validation::new(key.algorithm);
         validation.validate_exp = true;
-        let claims = decode::<JWTClaim>(token, &self.decoding, &validation)
+        let claims = decode::<JWTClaim>(token, &key.decoding, &validation)
             .map(|data| data.claims)
             .map_err(|_| TokenError::Decode)?;
 
         // 念のため手動でも exp を検証（バージョン差異対策）
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or(Duration::from_secs(0))
-            .as_secs() as i64;
         if claims.exp < now {
             return Err(TokenError::Decode);
         }
         Ok(claims)
     }
+
+    /// 退役前の公開鍵の一覧（`active_from` 前の鍵も含めて事前に公開する）。
+    /// HS256 の共有鍵は含めない。
+    pub fn jwks(&self) -> JwkSet {
+        let now = now();
+        JwkSet {
+            keys: self
+                .keys
+                .iter()
+                .filter(|k| !k.is_retired(now))
+                .filter_map(|k| k.jwk.clone())
+                .collect(),
+        }
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs() as i64
+}
+
+/// `len` バイトの乱数を URL セーフな文字列にする（jti やリフレッシュトークンに使う）。
+pub fn random_token(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// 保存用のハッシュ。トークン自体が十分な乱数なのでソルトは付けない。
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
 }
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::future::{Future, Ready, ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::http::{Method, header};
+use actix_web::{FromRequest, HttpMessage, HttpResponse};
+use thiserror::Error;
+
+use crate::middleware::auth::extractor::{AuthenticatedUser, VerifiedClaim};
+
+#[derive(Debug, Error)]
+pub enum RateLimitError {
+    #[error("invalid rate limit policy {0:?} (expected `<requests>/<seconds>`)")]
+    InvalidPolicy(String),
+
+    /// `RateLimitStore` の実装（Redis など）が使う
+    #[allow(dead_code)]
+    #[error("rate limit store error: {0}")]
+    Store(String),
+}
+
+/// トークンバケットの設定。`requests` 個まで連続して使え、`period` で `requests` 個補充される。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitPolicy {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl RateLimitPolicy {
+    pub fn new(requests: u32, period: Duration) -> Self {
+        Self { requests, period }
+    }
+
+    /// `<requests>/<seconds>` 形式（例: `60/60`）。
+    pub fn parse(s: &str) -> Result<Self, RateLimitError> {
+        let invalid = || RateLimitError::InvalidPolicy(s.to_string());
+        let (requests, secs) = s.split_once('/').ok_or_else(invalid)?;
+        let requests = requests.trim().parse::<u32>().map_err(|_| invalid())?;
+        let secs = secs.trim().parse::<u64>().map_err(|_| invalid())?;
+        if requests == 0 || secs == 0 {
+            return Err(invalid());
+        }
+        Ok(Self::new(requests, Duration::from_secs(secs)))
+    }
+
+    /// 環境変数 `name` があればそれを、なければ `default` を使う。
+    pub fn from_env(name: &str, default: Self) -> Result<Self, RateLimitError> {
+        match std::env::var(name) {
+            Ok(v) => Self::parse(&v),
+            Err(_) => Ok(default),
+        }
+    }
+
+    /// 1 秒あたりの補充数。
+    fn rate(&self) -> f64 {
+        self.requests as f64 / self.period.as_secs_f64()
+    }
+}
+
+/// バケットの状態。ストアの実装はこれを保存し、`take` で 1 つ取り出す。
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    /// 満杯のバケット。
+    pub fn full(policy: &RateLimitPolicy, now: Instant) -> Self {
+        Self {
+            tokens: policy.requests as f64,
+            updated_at: now,
+        }
+    }
+
+    /// 経過時間分を補充してから 1 つ取り出す。
+    pub fn take(&mut self, policy: &RateLimitPolicy, now: Instant) -> RateLimitDecision {
+        let capacity = policy.requests as f64;
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * policy.rate()).min(capacity);
+        self.updated_at = now;
+
+        let allowed = self.tokens >= 1.0;
+        if allowed {
+            self.tokens -= 1.0;
+        }
+        let secs_until = |tokens: f64| Duration::from_secs_f64((tokens / policy.rate()).max(0.0));
+        RateLimitDecision {
+            allowed,
+            limit: policy.requests,
+            remaining: self.tokens.floor() as u32,
+            reset_after: secs_until(capacity - self.tokens),
+            retry_after: (!allowed).then(|| secs_until(1.0 - self.tokens)),
+        }
+    }
+
+    /// 満杯まで補充されていれば（保存しておく必要がなければ）true。
+    pub fn is_full(&self, policy: &RateLimitPolicy, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens + elapsed * policy.rate() >= policy.requests as f64
+    }
+}
+
+/// 1 リクエスト分の判定結果（`RateLimit-*` ヘッダーの値）。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// バケットが満杯に戻るまでの時間
+    pub reset_after: Duration,
+    /// 拒否した場合、次の 1 回が使えるまでの時間
+    pub retry_after: Option<Duration>,
+}
+
+/// バケットの保存先。複数のプロセスで制限を共有する場合は Redis などで実装する。
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync + 'static {
+    /// `key` のバケットから 1 つ取り出す。
+    async fn acquire(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<RateLimitDecision, RateLimitError>;
+}
+
+/// プロセス内のメモリに保持するストア（既定）。
+#[derive(Default)]
+pub struct InMemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, (TokenBucket, RateLimitPolicy)>>,
+}
+
+impl InMemoryRateLimitStore {
+    /// これを超えたら満杯に戻ったバケットを掃除する
+    const MAX_BUCKETS: usize = 100_000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn acquire(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<RateLimitDecision, RateLimitError> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let now = Instant::now();
+        if buckets.len() > Self::MAX_BUCKETS {
+            buckets.retain(|_, (bucket, policy)| !bucket.is_full(policy, now));
+        }
+        let (bucket, _) = buckets
+            .entry(key.to_string())
+            .or_insert_with(|| (TokenBucket::full(policy, now), *policy));
+        Ok(bucket.take(policy, now))
+    }
+}
+
+struct RouteGroup {
+    name: String,
+    policy: RateLimitPolicy,
+    routes: Vec<(Method, String)>,
+}
+
+/// ルートのグループごとにトークンバケットで流量を制限するミドルウェア。
+///
+/// 認証済み（`AuthenticatedUser` を取り出せる）リクエストはユーザー ID ごと、
+/// それ以外は接続元の IP アドレスごとに数える。どのグループにも属さないルートは
+/// `default_policy` があればそれで、なければ制限しない。
+/// 応答には `RateLimit-Limit` / `RateLimit-Remaining` / `RateLimit-Reset` / `RateLimit-Policy` を付け、
+/// 超えた場合は `429 Too Many Requests` と `Retry-After` を返す。
+///
+/// ```ignore
+/// App::new().wrap(
+///     RateLimiter::new(Arc::new(InMemoryRateLimitStore::new()))
+///         .group("notes:write", RateLimitPolicy::new(30, Duration::from_secs(60)), [
+///             (Method::POST, "/notes"),
+///             (Method::PUT, "/notes/{id}"),
+///         ])
+///         .default_policy(RateLimitPolicy::new(120, Duration::from_secs(60))),
+/// )
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter {
+    inner: Rc<RateLimiterConfig>,
+}
+
+struct RateLimiterConfig {
+    store: Arc<dyn RateLimitStore>,
+    groups: Vec<RouteGroup>,
+    default_policy: Option<RateLimitPolicy>,
+}
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn RateLimitStore>) -> Self {
+        Self {
+            inner: Rc::new(RateLimiterConfig {
+                store,
+                groups: Vec::new(),
+                default_policy: None,
+            }),
+        }
+    }
+
+    /// `routes` はメソッドとルート定義のパターン（`/notes/{id}` など）の組。
+    /// バケットはグループごとに別なので、グループ内のルートは同じ枠を共有する。
+    pub fn group<'a>(
+        mut self,
+        name: &str,
+        policy: RateLimitPolicy,
+        routes: impl IntoIterator<Item = (Method, &'a str)>,
+    ) -> Self {
+        self.config_mut().groups.push(RouteGroup {
+            name: name.to_string(),
+            policy,
+            routes: routes
+                .into_iter()
+                .map(|(method, pattern)| (method, pattern.to_string()))
+                .collect(),
+        });
+        self
+    }
+
+    pub fn default_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.config_mut().default_policy = Some(policy);
+        self
+    }
+
+    fn config_mut(&mut self) -> &mut RateLimiterConfig {
+        Rc::get_mut(&mut self.inner).expect("RateLimiter is configured before use")
+    }
+}
+
+impl RateLimiterConfig {
+    /// リクエストが属するグループの名前と設定。
+    fn policy_for(&self, req: &ServiceRequest) -> Option<(&str, RateLimitPolicy)> {
+        let pattern = req.match_pattern();
+        self.groups
+            .iter()
+            .find(|g| {
+                g.routes.iter().any(|(method, route)| {
+                    method == req.method() && pattern.as_deref() == Some(route.as_str())
+                })
+            })
+            .map(|g| (g.name.as_str(), g.policy))
+            .or_else(|| self.default_policy.map(|p| ("default", p)))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            config: self.inner.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    config: Rc<RateLimiterConfig>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let config = self.config.clone();
+        Box::pin(async move {
+            let Some((group, policy)) = config.policy_for(&req) else {
+                return service.call(req).await.map(|res| res.map_into_left_body());
+            };
+            let key = format!("{group}:{}", client_key(&req).await);
+            // ストアの障害でサービス全体を止めないよう、判定できなければ通す
+            let Ok(decision) = config.store.acquire(&key, &policy).await else {
+                return service.call(req).await.map(|res| res.map_into_left_body());
+            };
+            if !decision.allowed {
+                let mut res = HttpResponse::TooManyRequests();
+                insert_headers(&mut res, &decision, &policy);
+                if let Some(retry_after) = decision.retry_after {
+                    res.insert_header((header::RETRY_AFTER, ceil_secs(retry_after).to_string()));
+                }
+                return Ok(req.into_response(res.finish()).map_into_right_body());
+            }
+            let mut res = service.call(req).await?;
+            let headers = res.headers_mut();
+            for (name, value) in header_values(&decision, &policy) {
+                if let Ok(value) = header::HeaderValue::from_str(&value) {
+                    headers.insert(header::HeaderName::from_static(name), value);
+                }
+            }
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+/// 認証できればユーザー ID、できなければ接続元の IP アドレス。
+/// 認証結果はリクエストに保存し、ハンドラの `AuthenticatedUser` で再利用する。
+async fn client_key(req: &ServiceRequest) -> String {
+    if req.headers().contains_key(header::AUTHORIZATION)
+        && let Ok(user) = AuthenticatedUser::from_request(req.request(), &mut Payload::None).await
+    {
+        let key = format!("user:{}", user.0.sub);
+        req.extensions_mut().insert(VerifiedClaim(user.0));
+        return key;
+    }
+    match req.peer_addr() {
+        Some(addr) => format!("ip:{}", addr.ip()),
+        None => "ip:unknown".to_string(),
+    }
+}
+
+fn header_values(
+    decision: &RateLimitDecision,
+    policy: &RateLimitPolicy,
+) -> [(&'static str, String); 4] {
+    [
+        ("ratelimit-limit", decision.limit.to_string()),
+        ("ratelimit-remaining", decision.remaining.to_string()),
+        (
+            "ratelimit-reset",
+            ceil_secs(decision.reset_after).to_string(),
+        ),
+        (
+            "ratelimit-policy",
+            format!("{};w={}", policy.requests, policy.period.as_secs()),
+        ),
+    ]
+}
+
+fn insert_headers(
+    res: &mut actix_web::HttpResponseBuilder,
+    decision: &RateLimitDecision,
+    policy: &RateLimitPolicy,
+) {
+    for header in header_values(decision, policy) {
+        res.insert_header(header);
+    }
+}
+
+fn ceil_secs(d: Duration) -> u64 {
+    d.as_secs() + u64::from(d.subsec_nanos() > 0)
+}
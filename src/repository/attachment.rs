@@ -0,0 +1,383 @@
+use crate::domain::model::Attachment;
+use crate::repository::user::RepoError;
+
+/// 追加する添付ファイルのメタデータ（本体は先に `BlobStore` に保存しておく）。
+pub struct NewAttachment<'a> {
+    pub note_id: i64,
+    pub user_id: i64,
+    pub filename: &'a str,
+    pub content_type: &'a str,
+    pub size: i64,
+    pub sha256: &'a str,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub storage_key: &'a str,
+}
+
+/// メモの添付ファイルのメタデータ。本体の保存・削除は呼び出し側（`AttachmentService`）で行う。
+#[async_trait::async_trait]
+pub trait AttachmentRepository: Send + Sync + 'static {
+    /// アップロードしたユーザーの添付ファイルの合計が `quota` バイト以下に収まる場合だけ追加する。
+    /// 収まらなければ `Ok(None)`。
+    async fn create_attachment(
+        &self,
+        attachment: &NewAttachment<'_>,
+        quota: i64,
+    ) -> Result<Option<Attachment>, RepoError>;
+    /// メモの添付ファイルを追加した順に返す。
+    async fn list_attachments(&self, note_id: i64) -> Result<Vec<Attachment>, RepoError>;
+    async fn find_attachment(
+        &self,
+        note_id: i64,
+        attachment_id: i64,
+    ) -> Result<Option<Attachment>, RepoError>;
+    /// ID だけで探す（完全に削除されたメモのものは除く）。
+    async fn get_attachment(&self, attachment_id: i64) -> Result<Option<Attachment>, RepoError>;
+    /// 削除したメタデータを返す（無ければ `None`）。
+    async fn delete_attachment(
+        &self,
+        note_id: i64,
+        attachment_id: i64,
+    ) -> Result<Option<Attachment>, RepoError>;
+    /// 完全に削除されたメモに付いていた添付ファイルの ID と `storage_key`（古い順に最大 `limit` 件）。
+    async fn orphaned_attachments(&self, limit: i64) -> Result<Vec<(i64, String)>, RepoError>;
+    async fn delete_orphan(&self, attachment_id: i64) -> Result<bool, RepoError>;
+    /// サムネイルをまだ作っていない画像（古い順に最大 `limit` 件）。
+    async fn pending_thumbnails(&self, limit: i64) -> Result<Vec<Attachment>, RepoError>;
+    /// サムネイルを作り終えた（作れなかった場合も含む）ことを記録する。
+    async fn mark_thumbnails_done(&self, attachment_id: i64) -> Result<(), RepoError>;
+}
+
+// SQLite 実装をモジュールにまとめる
+#[cfg(not(feature = "postgres"))]
+pub use sqlite::SqliteAttachmentRepository;
+
+#[cfg(not(feature = "postgres"))]
+pub mod sqlite {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    const ATTACHMENT_COLUMNS: &str = r#"id, note_id, user_id, filename, content_type, size,
+                   sha256, width, height, created_at, storage_key"#;
+
+    pub struct SqliteAttachmentRepository {
+        pub(crate) pool: SqlitePool,
+    }
+
+    impl SqliteAttachmentRepository {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AttachmentRepository for SqliteAttachmentRepository {
+        async fn create_attachment(
+            &self,
+            attachment: &NewAttachment<'_>,
+            quota: i64,
+        ) -> Result<Option<Attachment>, RepoError> {
+            // 合計の確認と追加を 1 つの文で行い、同時のアップロードでも上限を超えないようにする
+            let attachment = sqlx::query_as::<sqlx::Sqlite, Attachment>(&format!(
+                r#"INSERT INTO note_attachments
+                       (note_id, user_id, filename, content_type, size, sha256, storage_key,
+                        width, height, created_at)
+                   SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?9, ?10, strftime('%s','now')
+                   WHERE (SELECT COALESCE(SUM(size), 0) FROM note_attachments
+                          WHERE user_id = ?2) + ?5 <= ?8
+                   RETURNING {ATTACHMENT_COLUMNS}"#
+            ))
+            .bind(attachment.note_id)
+            .bind(attachment.user_id)
+            .bind(attachment.filename)
+            .bind(attachment.content_type)
+            .bind(attachment.size)
+            .bind(attachment.sha256)
+            .bind(attachment.storage_key)
+            .bind(quota)
+            .bind(attachment.width)
+            .bind(attachment.height)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachment)
+        }
+
+        async fn list_attachments(&self, note_id: i64) -> Result<Vec<Attachment>, RepoError> {
+            let attachments = sqlx::query_as::<sqlx::Sqlite, Attachment>(&format!(
+                "SELECT {ATTACHMENT_COLUMNS} FROM note_attachments WHERE note_id = ? ORDER BY id"
+            ))
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachments)
+        }
+
+        async fn find_attachment(
+            &self,
+            note_id: i64,
+            attachment_id: i64,
+        ) -> Result<Option<Attachment>, RepoError> {
+            let attachment = sqlx::query_as::<sqlx::Sqlite, Attachment>(&format!(
+                "SELECT {ATTACHMENT_COLUMNS} FROM note_attachments WHERE id = ? AND note_id = ?"
+            ))
+            .bind(attachment_id)
+            .bind(note_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachment)
+        }
+
+        async fn get_attachment(
+            &self,
+            attachment_id: i64,
+        ) -> Result<Option<Attachment>, RepoError> {
+            let attachment = sqlx::query_as::<sqlx::Sqlite, Attachment>(&format!(
+                "SELECT {ATTACHMENT_COLUMNS} FROM note_attachments WHERE id = ? AND note_id IS NOT NULL"
+            ))
+            .bind(attachment_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachment)
+        }
+
+        async fn delete_attachment(
+            &self,
+            note_id: i64,
+            attachment_id: i64,
+        ) -> Result<Option<Attachment>, RepoError> {
+            let attachment = sqlx::query_as::<sqlx::Sqlite, Attachment>(&format!(
+                r#"DELETE FROM note_attachments WHERE id = ? AND note_id = ?
+                   RETURNING {ATTACHMENT_COLUMNS}"#
+            ))
+            .bind(attachment_id)
+            .bind(note_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachment)
+        }
+
+        async fn orphaned_attachments(&self, limit: i64) -> Result<Vec<(i64, String)>, RepoError> {
+            let orphans = sqlx::query_as::<sqlx::Sqlite, (i64, String)>(
+                r#"SELECT id, storage_key FROM note_attachments
+                   WHERE note_id IS NULL
+                   ORDER BY id
+                   LIMIT ?"#,
+            )
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(orphans)
+        }
+
+        async fn delete_orphan(&self, attachment_id: i64) -> Result<bool, RepoError> {
+            let result =
+                sqlx::query("DELETE FROM note_attachments WHERE id = ? AND note_id IS NULL")
+                    .bind(attachment_id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn pending_thumbnails(&self, limit: i64) -> Result<Vec<Attachment>, RepoError> {
+            let attachments = sqlx::query_as::<sqlx::Sqlite, Attachment>(&format!(
+                r#"SELECT {ATTACHMENT_COLUMNS} FROM note_attachments
+                   WHERE width IS NOT NULL AND thumbnails_at IS NULL AND note_id IS NOT NULL
+                   ORDER BY id
+                   LIMIT ?"#
+            ))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachments)
+        }
+
+        async fn mark_thumbnails_done(&self, attachment_id: i64) -> Result<(), RepoError> {
+            sqlx::query(
+                "UPDATE note_attachments SET thumbnails_at = strftime('%s','now') WHERE id = ?",
+            )
+            .bind(attachment_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+    }
+}
+
+// PostgreSQL 実装をモジュールにまとめる
+#[cfg(feature = "postgres")]
+pub use postgres::PgAttachmentRepository;
+
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use super::*;
+    use sqlx::PgPool;
+
+    const ATTACHMENT_COLUMNS: &str = r#"id, note_id, user_id, filename, content_type, size,
+                   sha256, width, height, EXTRACT(EPOCH FROM created_at)::bigint as created_at,
+                   storage_key"#;
+
+    pub struct PgAttachmentRepository {
+        pub(crate) pool: PgPool,
+    }
+
+    impl PgAttachmentRepository {
+        pub fn new(pool: PgPool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AttachmentRepository for PgAttachmentRepository {
+        async fn create_attachment(
+            &self,
+            attachment: &NewAttachment<'_>,
+            quota: i64,
+        ) -> Result<Option<Attachment>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            // 同じユーザーの同時のアップロードを直列にして、合計が上限を超えないようにする
+            sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+                .bind(attachment.user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?;
+            let created = sqlx::query_as::<sqlx::Postgres, Attachment>(&format!(
+                r#"INSERT INTO note_attachments
+                       (note_id, user_id, filename, content_type, size, sha256, storage_key,
+                        width, height)
+                   SELECT $1, $2, $3, $4, $5, $6, $7, $9, $10
+                   WHERE (SELECT COALESCE(SUM(size), 0) FROM note_attachments
+                          WHERE user_id = $2) + $5 <= $8
+                   RETURNING {ATTACHMENT_COLUMNS}"#
+            ))
+            .bind(attachment.note_id)
+            .bind(attachment.user_id)
+            .bind(attachment.filename)
+            .bind(attachment.content_type)
+            .bind(attachment.size)
+            .bind(attachment.sha256)
+            .bind(attachment.storage_key)
+            .bind(quota)
+            .bind(attachment.width)
+            .bind(attachment.height)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(created)
+        }
+
+        async fn list_attachments(&self, note_id: i64) -> Result<Vec<Attachment>, RepoError> {
+            let attachments = sqlx::query_as::<sqlx::Postgres, Attachment>(&format!(
+                "SELECT {ATTACHMENT_COLUMNS} FROM note_attachments WHERE note_id = $1 ORDER BY id"
+            ))
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachments)
+        }
+
+        async fn find_attachment(
+            &self,
+            note_id: i64,
+            attachment_id: i64,
+        ) -> Result<Option<Attachment>, RepoError> {
+            let attachment = sqlx::query_as::<sqlx::Postgres, Attachment>(&format!(
+                "SELECT {ATTACHMENT_COLUMNS} FROM note_attachments WHERE id = $1 AND note_id = $2"
+            ))
+            .bind(attachment_id)
+            .bind(note_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachment)
+        }
+
+        async fn get_attachment(
+            &self,
+            attachment_id: i64,
+        ) -> Result<Option<Attachment>, RepoError> {
+            let attachment = sqlx::query_as::<sqlx::Postgres, Attachment>(&format!(
+                "SELECT {ATTACHMENT_COLUMNS} FROM note_attachments WHERE id = $1 AND note_id IS NOT NULL"
+            ))
+            .bind(attachment_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachment)
+        }
+
+        async fn delete_attachment(
+            &self,
+            note_id: i64,
+            attachment_id: i64,
+        ) -> Result<Option<Attachment>, RepoError> {
+            let attachment = sqlx::query_as::<sqlx::Postgres, Attachment>(&format!(
+                r#"DELETE FROM note_attachments WHERE id = $1 AND note_id = $2
+                   RETURNING {ATTACHMENT_COLUMNS}"#
+            ))
+            .bind(attachment_id)
+            .bind(note_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachment)
+        }
+
+        async fn orphaned_attachments(&self, limit: i64) -> Result<Vec<(i64, String)>, RepoError> {
+            let orphans = sqlx::query_as::<sqlx::Postgres, (i64, String)>(
+                r#"SELECT id, storage_key FROM note_attachments
+                   WHERE note_id IS NULL
+                   ORDER BY id
+                   LIMIT $1"#,
+            )
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(orphans)
+        }
+
+        async fn delete_orphan(&self, attachment_id: i64) -> Result<bool, RepoError> {
+            let result =
+                sqlx::query("DELETE FROM note_attachments WHERE id = $1 AND note_id IS NULL")
+                    .bind(attachment_id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn pending_thumbnails(&self, limit: i64) -> Result<Vec<Attachment>, RepoError> {
+            let attachments = sqlx::query_as::<sqlx::Postgres, Attachment>(&format!(
+                r#"SELECT {ATTACHMENT_COLUMNS} FROM note_attachments
+                   WHERE width IS NOT NULL AND thumbnails_at IS NULL AND note_id IS NOT NULL
+                   ORDER BY id
+                   LIMIT $1"#
+            ))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(attachments)
+        }
+
+        async fn mark_thumbnails_done(&self, attachment_id: i64) -> Result<(), RepoError> {
+            sqlx::query("UPDATE note_attachments SET thumbnails_at = now() WHERE id = $1")
+                .bind(attachment_id)
+                .execute(&self.pool)
+                .await
+                .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::domain::graph::{GraphNode, NoteGraph};
+use crate::domain::model::{Note, NoteLink};
+use crate::repository::user::RepoError;
+
+/// `note_links l`（リンク元 `s`）のリンク先のメモ ID。
+/// タイトルのリンクは同じ範囲のゴミ箱に無いメモのうち ID の最も小さいものを指す。
+const RESOLVED_TARGET: &str = r#"COALESCE(l.target_id, (
+        SELECT MIN(o.id) FROM notes o
+        WHERE o.deleted_at IS NULL AND o.title = l.target_title
+          AND (o.workspace_id = s.workspace_id
+               OR (s.workspace_id IS NULL AND o.workspace_id IS NULL
+                   AND o.user_id = s.user_id))))"#;
+
+/// メモ間のリンクの参照。リンクの保存は `NoteRepository` の作成・更新時に同じトランザクションで行う。
+///
+/// `[[タイトル]]` は、リンク元と同じ範囲（同じワークスペース、個人のメモなら同じ作成者の
+/// 個人のメモ）で、ゴミ箱に無いそのタイトルのメモ（複数あれば ID の最も小さいもの）を指す。
+/// `[[#123]]` は ID で指す。どちらも参照時に解決するので、後から作成・復元されたメモにも繋がる。
+#[async_trait::async_trait]
+pub trait LinkRepository: Send + Sync + 'static {
+    /// `note_id` の本文中のリンクを出現順に返す。
+    /// 参照先が無いか `viewer` が閲覧できなければ `note_id` / `title` は `None`（リンク切れ）。
+    async fn links_from(
+        &self,
+        note_id: i64,
+        viewer: Option<i64>,
+    ) -> Result<Vec<NoteLink>, RepoError>;
+    /// `note_id` にリンクしているメモのうち、`viewer` が一覧で閲覧できるものを更新日時の新しい順に返す。
+    async fn backlinks(&self, note_id: i64, viewer: Option<i64>) -> Result<Vec<Note>, RepoError>;
+    /// `user_id` が作成した（ゴミ箱に無い）メモと、その間のリンク・共通のタグのグラフ。
+    async fn note_graph(&self, user_id: i64) -> Result<NoteGraph, RepoError>;
+}
+
+fn graph_node(note: Note) -> GraphNode {
+    GraphNode {
+        id: note.id,
+        title: note.title,
+        tags: note.tags,
+    }
+}
+
+// SQLite 実装をモジュールにまとめる
+#[cfg(not(feature = "postgres"))]
+pub use sqlite::SqliteLinkRepository;
+
+#[cfg(not(feature = "postgres"))]
+pub mod sqlite {
+    use super::*;
+    use crate::repository::note::sqlite::SELECT_NOTE;
+    use sqlx::SqlitePool;
+
+    pub struct SqliteLinkRepository {
+        pub(crate) pool: SqlitePool,
+    }
+
+    impl SqliteLinkRepository {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LinkRepository for SqliteLinkRepository {
+        async fn links_from(
+            &self,
+            note_id: i64,
+            viewer: Option<i64>,
+        ) -> Result<Vec<NoteLink>, RepoError> {
+            // ID を知っていれば閲覧できる `unlisted` も、リンク先としては解決する
+            let links = sqlx::query_as::<sqlx::Sqlite, NoteLink>(&format!(
+                r#"SELECT COALESCE(l.target_title, '#' || l.target_id) as target,
+                          t.id as note_id, t.title
+                   FROM note_links l
+                   JOIN notes s ON s.id = l.source_id
+                   LEFT JOIN notes t
+                        ON t.id = {RESOLVED_TARGET}
+                       AND t.deleted_at IS NULL
+                       AND (t.visibility IN ('public', 'unlisted')
+                            OR (t.workspace_id IS NULL AND t.user_id = ?1)
+                            OR t.workspace_id IN (SELECT workspace_id FROM workspace_members
+                                                  WHERE user_id = ?1))
+                   WHERE l.source_id = ?2
+                   ORDER BY l.position"#
+            ))
+            .bind(viewer)
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(links)
+        }
+
+        async fn backlinks(
+            &self,
+            note_id: i64,
+            viewer: Option<i64>,
+        ) -> Result<Vec<Note>, RepoError> {
+            let notes = sqlx::query_as::<sqlx::Sqlite, Note>(&format!(
+                r#"{SELECT_NOTE}
+                   JOIN notes b ON b.id = ?1
+                   WHERE n.deleted_at IS NULL AND n.id <> b.id
+                     AND (n.visibility = 'public'
+                          OR (n.workspace_id IS NULL AND n.user_id = ?2)
+                          OR n.workspace_id IN (SELECT workspace_id FROM workspace_members
+                                                WHERE user_id = ?2))
+                     AND EXISTS (
+                         SELECT 1 FROM note_links l
+                         WHERE l.source_id = n.id
+                           AND (l.target_id = b.id
+                                OR (l.target_title = b.title
+                                    AND (b.workspace_id = n.workspace_id
+                                         OR (n.workspace_id IS NULL AND b.workspace_id IS NULL
+                                             AND b.user_id = n.user_id))
+                                    AND NOT EXISTS (
+                                        SELECT 1 FROM notes o
+                                        WHERE o.deleted_at IS NULL AND o.title = b.title
+                                          AND o.id < b.id
+                                          AND (o.workspace_id = n.workspace_id
+                                               OR (n.workspace_id IS NULL
+                                                   AND o.workspace_id IS NULL
+                                                   AND o.user_id = n.user_id))))))
+                   ORDER BY n.updated_at DESC, n.id DESC"#
+            ))
+            .bind(note_id)
+            .bind(viewer)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notes)
+        }
+
+        async fn note_graph(&self, user_id: i64) -> Result<NoteGraph, RepoError> {
+            let notes = sqlx::query_as::<sqlx::Sqlite, Note>(&format!(
+                "{SELECT_NOTE} WHERE n.user_id = ? AND n.deleted_at IS NULL"
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            let links = sqlx::query_as::<sqlx::Sqlite, (i64, i64)>(&format!(
+                r#"SELECT l.source_id, t.id
+                   FROM note_links l
+                   JOIN notes s ON s.id = l.source_id
+                   JOIN notes t ON t.id = {RESOLVED_TARGET}
+                   WHERE s.user_id = ?1 AND s.deleted_at IS NULL
+                     AND t.user_id = ?1 AND t.deleted_at IS NULL"#
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(NoteGraph::new(
+                notes.into_iter().map(graph_node).collect(),
+                &links,
+            ))
+        }
+    }
+}
+
+// Postgres 実装をモジュールにまとめる
+#[cfg(feature = "postgres")]
+pub use postgres::PgLinkRepository;
+
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use super::*;
+    use crate::repository::note::postgres::SELECT_NOTE;
+    use sqlx::PgPool;
+
+    pub struct PgLinkRepository {
+        pub(crate) pool: PgPool,
+    }
+
+    impl PgLinkRepository {
+        pub fn new(pool: PgPool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LinkRepository for PgLinkRepository {
+        async fn links_from(
+            &self,
+            note_id: i64,
+            viewer: Option<i64>,
+        ) -> Result<Vec<NoteLink>, RepoError> {
+            // ID を知っていれば閲覧できる `unlisted` も、リンク先としては解決する
+            let links = sqlx::query_as::<sqlx::Postgres, NoteLink>(&format!(
+                r#"SELECT COALESCE(l.target_title, '#' || l.target_id::text) as target,
+                          t.id as note_id, t.title
+                   FROM note_links l
+                   JOIN notes s ON s.id = l.source_id
+                   LEFT JOIN notes t
+                        ON t.id = {RESOLVED_TARGET}
+                       AND t.deleted_at IS NULL
+                       AND (t.visibility IN ('public', 'unlisted')
+                            OR (t.workspace_id IS NULL AND t.user_id = $1)
+                            OR t.workspace_id IN (SELECT workspace_id FROM workspace_members
+                                                  WHERE user_id = $1))
+                   WHERE l.source_id = $2
+                   ORDER BY l.position"#
+            ))
+            .bind(viewer)
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(links)
+        }
+
+        async fn backlinks(
+            &self,
+            note_id: i64,
+            viewer: Option<i64>,
+        ) -> Result<Vec<Note>, RepoError> {
+            let notes = sqlx::query_as::<sqlx::Postgres, Note>(&format!(
+                r#"{SELECT_NOTE}
+                   JOIN notes b ON b.id = $1
+                   WHERE n.deleted_at IS NULL AND n.id <> b.id
+                     AND (n.visibility = 'public'
+                          OR (n.workspace_id IS NULL AND n.user_id = $2)
+                          OR n.workspace_id IN (SELECT workspace_id FROM workspace_members
+                                                WHERE user_id = $2))
+                     AND EXISTS (
+                         SELECT 1 FROM note_links l
+                         WHERE l.source_id = n.id
+                           AND (l.target_id = b.id
+                                OR (l.target_title = b.title
+                                    AND (b.workspace_id = n.workspace_id
+                                         OR (n.workspace_id IS NULL AND b.workspace_id IS NULL
+                                             AND b.user_id = n.user_id))
+                                    AND NOT EXISTS (
+                                        SELECT 1 FROM notes o
+                                        WHERE o.deleted_at IS NULL AND o.title = b.title
+                                          AND o.id < b.id
+                                          AND (o.workspace_id = n.workspace_id
+                                               OR (n.workspace_id IS NULL
+                                                   AND o.workspace_id IS NULL
+                                                   AND o.user_id = n.user_id))))))
+                   ORDER BY n.updated_at DESC, n.id DESC"#
+            ))
+            .bind(note_id)
+            .bind(viewer)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notes)
+        }
+
+        async fn note_graph(&self, user_id: i64) -> Result<NoteGraph, RepoError> {
+            let notes = sqlx::query_as::<sqlx::Postgres, Note>(&format!(
+                "{SELECT_NOTE} WHERE n.user_id = $1 AND n.deleted_at IS NULL"
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            let links = sqlx::query_as::<sqlx::Postgres, (i64, i64)>(&format!(
+                r#"SELECT l.source_id, t.id
+                   FROM note_links l
+                   JOIN notes s ON s.id = l.source_id
+                   JOIN notes t ON t.id = {RESOLVED_TARGET}
+                   WHERE s.user_id = $1 AND s.deleted_at IS NULL
+                     AND t.user_id = $1 AND t.deleted_at IS NULL"#
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(NoteGraph::new(
+                notes.into_iter().map(graph_node).collect(),
+                &links,
+            ))
+        }
+    }
+}
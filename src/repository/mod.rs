@@ -1,2 +1,10 @@
+pub mod attachment;
+pub mod link;
 pub mod note;
+pub mod notebook;
+pub mod revision;
+pub mod share;
+pub mod tag;
+pub mod token;
 pub mod user;
+pub mod workspace;
@@ -1,24 +1,226 @@
-use crate::domain::model::Note;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::link::{LinkTarget, parse_links, rename_links};
+use crate::domain::model::{Note, NoteSearchHit, TrashedNote, Visibility};
 use crate::repository::user::RepoError;
 
+/// 一覧の並び替えキー。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSort {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Title,
+}
+
+impl NoteSort {
+    /// SQL に埋め込むカラム名（ユーザー入力は直接埋め込まない）
+    fn column(&self) -> &'static str {
+        match self {
+            NoteSort::CreatedAt => "created_at",
+            NoteSort::UpdatedAt => "updated_at",
+            NoteSort::Title => "title",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    fn keyword(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+
+    fn comparator(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        }
+    }
+}
+
+/// タグ絞り込みの結合方法。
+/// - `All`: 指定したタグをすべて持つメモ（AND）
+/// - `Any`: 指定したタグのいずれかを持つメモ（OR）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    All,
+    Any,
+}
+
+/// キーセット方式のページングの位置。前ページ最後のメモの `(pinned, sort_key, id)` の値を持つ。
+/// 値そのものと比較するので、そのメモが後から更新・削除されても続きの位置は変わらない。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteCursor {
+    pub pinned: bool,
+    pub key: NoteSortKey,
+    pub id: i64,
+}
+
+/// 並び替えキーの値。日時は `Note` と同じ UNIX 秒。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NoteSortKey {
+    Time(i64),
+    Title(String),
+}
+
+impl NoteCursor {
+    /// `sort` で並べたときの `note` の位置。
+    pub fn of(note: &Note, sort: NoteSort) -> Self {
+        let key = match sort {
+            NoteSort::CreatedAt => NoteSortKey::Time(note.created_at),
+            NoteSort::UpdatedAt => NoteSortKey::Time(note.updated_at),
+            NoteSort::Title => NoteSortKey::Title(note.title.clone()),
+        };
+        Self {
+            pinned: note.pinned,
+            key,
+            id: note.id,
+        }
+    }
+}
+
+/// `list_notes` の検索条件。
+///
+/// 固定（`pinned`）したメモを先頭に、それぞれを `sort` / `order` の順に並べる。
+/// ページングはキーセット方式で、`after` には前ページ最後のメモの `NoteCursor` を渡す。
+/// `(pinned, sort_key, id)` の組で比較するため、同値のキーがあっても取りこぼさない。
+#[derive(Debug, Clone, Default)]
+pub struct NoteListQuery {
+    /// 閲覧者（未ログインなら `None`）。自分のメモ + 参加しているワークスペースのメモ +
+    /// `public` なメモが対象。
+    pub viewer: Option<i64>,
+    /// 作成者で絞り込む
+    pub author: Option<i64>,
+    /// ワークスペースで絞り込む
+    pub workspace: Option<i64>,
+    /// ノートブックで絞り込む（子のノートブックのメモは含めない）
+    pub notebook: Option<i64>,
+    /// `true` ならアーカイブしたメモだけ、`false` ならアーカイブしていないメモだけを返す
+    pub archived: bool,
+    /// `true` ならスターを付けたメモだけを返す
+    pub starred: bool,
+    /// タグで絞り込む（空なら絞り込まない）
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    pub sort: NoteSort,
+    pub order: SortOrder,
+    pub after: Option<NoteCursor>,
+    pub limit: i64,
+}
+
+/// `create_note` の入力。タグは正規化済み（`domain::tag::normalize_tags`）であること。
+#[derive(Debug, Clone, Default)]
+pub struct NewNote<'a> {
+    pub title: &'a str,
+    pub content: &'a str,
+    pub visibility: Visibility,
+    pub tags: &'a [String],
+    /// `Some` ならワークスペースのメモとして作成する（メンバーかどうかは呼び出し側で確認する）
+    pub workspace_id: Option<i64>,
+    /// `Some` ならそのノートブックに入れる（作成者のノートブックかどうかは呼び出し側で確認する）
+    pub notebook_id: Option<i64>,
+}
+
+/// `update_note` の入力。`None` の項目は変更しない。
+/// `tags` は `Some` のとき指定したタグ一覧で置き換える。
+/// 固定・スター・アーカイブだけの変更では `updated_at` を変えない（`version` は増える）。
+#[derive(Debug, Clone, Default)]
+pub struct NoteChanges<'a> {
+    pub title: Option<&'a str>,
+    pub content: Option<&'a str>,
+    pub visibility: Option<Visibility>,
+    pub tags: Option<&'a [String]>,
+    pub pinned: Option<bool>,
+    pub starred: Option<bool>,
+    /// `Some(true)` でアーカイブする（アーカイブ済みなら日時は変えない）、`Some(false)` で戻す
+    pub archived: Option<bool>,
+    /// `Some` のとき、保存されているバージョンが一致する場合だけ更新する
+    pub expected_version: Option<i64>,
+}
+
+/// `update_note` の結果。
+#[derive(Debug, Clone)]
+pub struct UpdatedNote {
+    pub note: Note,
+    /// タイトルの変更に合わせて本文の `[[...]]` を書き換えた他のメモ
+    pub relinked: Vec<Note>,
+}
+
+impl From<Note> for UpdatedNote {
+    fn from(note: Note) -> Self {
+        Self {
+            note,
+            relinked: vec![],
+        }
+    }
+}
+
+impl NoteChanges<'_> {
+    /// `updated_at` を更新する変更（タイトル・本文・公開範囲・タグ）を含むか
+    fn edits_note(&self) -> bool {
+        self.title.is_some()
+            || self.content.is_some()
+            || self.visibility.is_some()
+            || self.tags.is_some()
+    }
+}
+
 #[async_trait::async_trait]
 pub trait NoteRepository: Send + Sync + 'static {
-    async fn create_note(
-        &self,
-        user_id: i64,
-        title: &str,
-        content: &str,
-    ) -> Result<Note, RepoError>;
+    async fn create_note(&self, user_id: i64, note: &NewNote<'_>) -> Result<Note, RepoError>;
+    /// ゴミ箱内のメモは返さない（`list_notes` / `search` も同様）。
     async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError>;
+    /// `find_by_id` と同じだが、ゴミ箱内のメモも返す（管理者による完全な削除に使う）。
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError>;
+    /// 更新のたびに `version` を 1 増やす。`user_id` は編集者としてリビジョンに記録する。
+    /// 権限の確認（`domain::policy::can_access_note`）は呼び出し側で行うこと。
+    /// タイトルを変えると、リンクしているメモの `[[旧タイトル]]` も書き換える（`UpdatedNote::relinked`）。
+    /// - Ok(None): メモが存在しない
+    /// - Err(VersionMismatch): `changes.expected_version` と保存されているバージョンが異なる
     async fn update_note(
         &self,
         note_id: i64,
         user_id: i64,
-        title: Option<&str>,
-        content: Option<&str>,
-    ) -> Result<Option<Note>, RepoError>;
-    async fn delete_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError>;
-    async fn list_notes(&self) -> Result<Vec<Note>, RepoError>;
+        changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError>;
+    /// メモをゴミ箱に移動する（論理削除）。
+    /// `expected_version` が `Some` のときは `update_note` と同様にバージョンを検査する。
+    /// 権限の確認は `update_note` と同じく呼び出し側で行う。メモは作成者のゴミ箱に入る。
+    async fn delete_note(
+        &self,
+        note_id: i64,
+        expected_version: Option<i64>,
+    ) -> Result<bool, RepoError>;
+    /// ユーザーのゴミ箱を、ゴミ箱に移動した日時の新しい順に返す。
+    async fn list_trash(&self, user_id: i64) -> Result<Vec<TrashedNote>, RepoError>;
+    /// ゴミ箱からメモを戻す。ゴミ箱に無ければ `None`。
+    async fn restore_note(&self, note_id: i64, user_id: i64) -> Result<Option<Note>, RepoError>;
+    /// ゴミ箱内のメモを完全に削除する。
+    async fn purge_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError>;
+    /// ゴミ箱に移動してから `retention_secs` 秒以上経ったメモを全ユーザー分完全に削除し、件数を返す。
+    async fn purge_trash(&self, retention_secs: i64) -> Result<u64, RepoError>;
+    /// `query.viewer` が一覧で閲覧できるメモを、条件に従って最大 `query.limit` 件返す。
+    async fn list_notes(&self, query: &NoteListQuery) -> Result<Vec<Note>, RepoError>;
+    /// タイトル・本文の全文検索。閲覧範囲は `list_notes` と同じで、関連度の高い順に返す。
+    async fn search(
+        &self,
+        viewer: Option<i64>,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError>;
 }
 // SQLite 実装をモジュールにまとめる
 #[cfg(not(feature = "postgres"))]
@@ -27,7 +229,16 @@ pub use sqlite::SqliteNoteRepository;
 #[cfg(not(feature = "postgres"))]
 pub mod sqlite {
     use super::*;
-    use sqlx::SqlitePool;
+    use sqlx::{QueryBuilder, SqlitePool, Transaction};
+
+    /// メモ 1 件分のカラム（タグは名前順の JSON 配列として集約する）
+    pub(crate) const SELECT_NOTE: &str = r#"SELECT n.id, n.user_id as author_id, n.workspace_id, n.notebook_id,
+                  n.title, n.content, n.visibility, n.created_at, n.updated_at, n.version,
+                  (SELECT json_group_array(name) FROM (
+                       SELECT t.name FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                       WHERE nt.note_id = n.id ORDER BY t.name)) as tags,
+                  n.pinned, n.starred, n.archived_at
+           FROM notes n"#;
 
     pub struct SqliteNoteRepository {
         pub(crate) pool: SqlitePool,
@@ -41,32 +252,36 @@ pub mod sqlite {
 
     #[async_trait::async_trait]
     impl NoteRepository for SqliteNoteRepository {
-        async fn create_note(
-            &self,
-            user_id: i64,
-            title: &str,
-            content: &str,
-        ) -> Result<Note, RepoError> {
-            let inserted = sqlx::query_as::<sqlx::Sqlite, Note>(
-                r#"INSERT INTO notes (user_id, title, content, created_at, updated_at)
-                   VALUES (?, ?, ?, strftime('%s','now'), strftime('%s','now'))
-                   RETURNING id, user_id as author_id, title, content, created_at, updated_at"#,
+        async fn create_note(&self, user_id: i64, note: &NewNote<'_>) -> Result<Note, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let note_id: i64 = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+                r#"INSERT INTO notes (user_id, workspace_id, notebook_id, title, content, visibility,
+                                      created_at, updated_at)
+                   VALUES (?, ?, ?, ?, ?, ?, strftime('%s','now'), strftime('%s','now'))
+                   RETURNING id"#,
             )
             .bind(user_id)
-            .bind(title)
-            .bind(content)
-            .fetch_one(&self.pool)
+            .bind(note.workspace_id)
+            .bind(note.notebook_id)
+            .bind(note.title)
+            .bind(note.content)
+            .bind(note.visibility.as_str())
+            .fetch_one(&mut *tx)
             .await
             .map_err(RepoError::DbError)?;
+            replace_tags(&mut tx, note_id, user_id, note.tags).await?;
+            replace_links(&mut tx, note_id, note.content).await?;
+            record_revision(&mut tx, note_id, user_id).await?;
+            let inserted = fetch_note(&mut tx, note_id).await?;
+            sync_fts(&mut tx, &inserted).await?;
+            tx.commit().await.map_err(RepoError::DbError)?;
 
             Ok(inserted)
         }
         async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
-            let note = sqlx::query_as::<sqlx::Sqlite, Note>(
-                r#"SELECT id, user_id as author_id, title, content, created_at, updated_at
-                   FROM notes
-                   WHERE id = ?"#,
-            )
+            let note = sqlx::query_as::<sqlx::Sqlite, Note>(&format!(
+                "{SELECT_NOTE} WHERE n.id = ? AND n.deleted_at IS NULL"
+            ))
             .bind(note_id)
             .fetch_optional(&self.pool)
             .await
@@ -75,52 +290,511 @@ pub mod sqlite {
             Ok(note)
         }
 
+        async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+            let note =
+                sqlx::query_as::<sqlx::Sqlite, Note>(&format!("{SELECT_NOTE} WHERE n.id = ?"))
+                    .bind(note_id)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(RepoError::DbError)?;
+
+            Ok(note)
+        }
+
         async fn update_note(
             &self,
             note_id: i64,
             user_id: i64,
-            title: Option<&str>,
-            content: Option<&str>,
-        ) -> Result<Option<Note>, RepoError> {
-            let updated = sqlx::query_as::<sqlx::Sqlite, Note>(
+            changes: &NoteChanges<'_>,
+        ) -> Result<Option<UpdatedNote>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let old_title = match changes.title {
+                Some(_) => sqlx::query_scalar::<sqlx::Sqlite, String>(
+                    r#"SELECT title FROM notes WHERE id = ? AND deleted_at IS NULL"#,
+                )
+                .bind(note_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?,
+                None => None,
+            };
+            let author_id = sqlx::query_scalar::<sqlx::Sqlite, i64>(
                 r#"UPDATE notes
                    SET title = COALESCE(?, title),
                        content = COALESCE(?, content),
-                       updated_at = strftime('%s','now')
-                   WHERE id = ? AND user_id = ?
-                   RETURNING id, user_id as author_id, title, content, created_at, updated_at"#,
+                       visibility = COALESCE(?, visibility),
+                       pinned = COALESCE(?, pinned),
+                       starred = COALESCE(?, starred),
+                       archived_at = CASE ?
+                           WHEN 1 THEN COALESCE(archived_at, strftime('%s','now'))
+                           WHEN 0 THEN NULL
+                           ELSE archived_at END,
+                       updated_at = CASE WHEN ? THEN strftime('%s','now') ELSE updated_at END,
+                       version = version + 1
+                   WHERE id = ? AND deleted_at IS NULL
+                     AND (? IS NULL OR version = ?)
+                   RETURNING user_id"#,
             )
-            .bind(title)
-            .bind(content)
+            .bind(changes.title)
+            .bind(changes.content)
+            .bind(changes.visibility.map(|v| v.as_str()))
+            .bind(changes.pinned)
+            .bind(changes.starred)
+            .bind(changes.archived)
+            .bind(changes.edits_note())
             .bind(note_id)
-            .bind(user_id)
-            .fetch_optional(&self.pool)
+            .bind(changes.expected_version)
+            .bind(changes.expected_version)
+            .fetch_optional(&mut *tx)
             .await
             .map_err(RepoError::DbError)?;
+            let Some(author_id) = author_id else {
+                return not_updated(&mut tx, note_id).await.map(|_| None);
+            };
+            // タグは編集者ではなく作成者のタグとして付ける
+            if let Some(tags) = changes.tags {
+                replace_tags(&mut tx, note_id, author_id, tags).await?;
+            }
+            if let Some(content) = changes.content {
+                replace_links(&mut tx, note_id, content).await?;
+            }
+            record_revision(&mut tx, note_id, user_id).await?;
+            let mut note = fetch_note(&mut tx, note_id).await?;
+            let mut relinked = vec![];
+            if let Some(old_title) = old_title.filter(|title| *title != note.title) {
+                relinked = relink_renamed(&mut tx, &note, &old_title, user_id).await?;
+                relinked.retain(|source| source.id != note_id);
+                note = fetch_note(&mut tx, note_id).await?;
+            }
+            sync_fts(&mut tx, &note).await?;
+            tx.commit().await.map_err(RepoError::DbError)?;
 
-            Ok(updated)
+            Ok(Some(UpdatedNote { note, relinked }))
         }
-        async fn delete_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
-            let result =
-                sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM notes WHERE id = ? AND user_id = ?"#)
-                    .bind(note_id)
-                    .bind(user_id)
-                    .execute(&self.pool)
-                    .await
-                    .map_err(RepoError::DbError)?;
-            Ok(result.rows_affected() > 0)
+        async fn delete_note(
+            &self,
+            note_id: i64,
+            expected_version: Option<i64>,
+        ) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE notes
+                   SET deleted_at = strftime('%s','now'),
+                       version = version + 1
+                   WHERE id = ? AND deleted_at IS NULL
+                     AND (? IS NULL OR version = ?)"#,
+            )
+            .bind(note_id)
+            .bind(expected_version)
+            .bind(expected_version)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            if result.rows_affected() == 0 {
+                return not_updated(&mut tx, note_id).await.map(|_| false);
+            }
+            sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM notes_fts WHERE rowid = ?"#)
+                .bind(note_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(true)
         }
-        async fn list_notes(&self) -> Result<Vec<Note>, RepoError> {
-            let notes = sqlx::query_as::<sqlx::Sqlite, Note>(
-                r#"SELECT id, user_id as author_id, title, content, created_at, updated_at
-                   FROM notes
-                   ORDER BY created_at DESC"#,
+        async fn list_notes(&self, query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+            let column = query.sort.column();
+            let mut qb = QueryBuilder::<sqlx::Sqlite>::new(SELECT_NOTE);
+            qb.push(" WHERE n.deleted_at IS NULL AND (n.visibility = 'public'")
+                .push(" OR (n.workspace_id IS NULL AND n.user_id = ")
+                .push_bind(query.viewer)
+                .push(") OR n.workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = ")
+                .push_bind(query.viewer)
+                .push("))");
+            if let Some(author) = query.author {
+                qb.push(" AND n.user_id = ").push_bind(author);
+            }
+            if let Some(workspace) = query.workspace {
+                qb.push(" AND n.workspace_id = ").push_bind(workspace);
+            }
+            if let Some(notebook) = query.notebook {
+                qb.push(" AND n.notebook_id = ").push_bind(notebook);
+            }
+            qb.push(if query.archived {
+                " AND n.archived_at IS NOT NULL"
+            } else {
+                " AND n.archived_at IS NULL"
+            });
+            if query.starred {
+                qb.push(" AND n.starred");
+            }
+            if !query.tags.is_empty() {
+                let tagged = match query.tag_match {
+                    TagMatch::All => " AND (SELECT COUNT(DISTINCT t.name)",
+                    TagMatch::Any => " AND EXISTS (SELECT 1",
+                };
+                qb.push(tagged).push(
+                    " FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                      WHERE nt.note_id = n.id AND t.name IN (",
+                );
+                let mut names = qb.separated(", ");
+                for tag in &query.tags {
+                    names.push_bind(tag);
+                }
+                names.push_unseparated(")");
+                qb.push(")");
+                if query.tag_match == TagMatch::All {
+                    qb.push(" = ").push_bind(query.tags.len() as i64);
+                }
+            }
+            if let Some(after) = &query.after {
+                // 固定したメモは `order` に関係なく常に先頭なので、カーソルより後ろとは
+                // 「固定されていない」か「固定状態が同じでキーが後ろ」のどちらか
+                qb.push(" AND (n.pinned < ")
+                    .push_bind(after.pinned)
+                    .push(" OR (n.pinned = ")
+                    .push_bind(after.pinned)
+                    .push(format_args!(
+                        " AND (n.{column}, n.id) {} (",
+                        query.order.comparator()
+                    ));
+                match &after.key {
+                    NoteSortKey::Time(time) => qb.push_bind(*time),
+                    NoteSortKey::Title(title) => qb.push_bind(title.as_str()),
+                };
+                qb.push(", ").push_bind(after.id).push(")))");
+            }
+            qb.push(format_args!(
+                " ORDER BY n.pinned DESC, n.{column} {order}, n.id {order} LIMIT ",
+                order = query.order.keyword()
+            ))
+            .push_bind(query.limit);
+
+            let notes = qb
+                .build_query_as::<Note>()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(RepoError::DbError)?;
+            Ok(notes)
+        }
+
+        async fn search(
+            &self,
+            viewer: Option<i64>,
+            query: &str,
+            limit: i64,
+        ) -> Result<Vec<NoteSearchHit>, RepoError> {
+            let Some(expr) = fts5_query(query) else {
+                return Ok(vec![]);
+            };
+            // bm25 は小さいほど関連度が高いので符号を反転する（タイトルの一致を重視）
+            let hits = sqlx::query_as::<sqlx::Sqlite, NoteSearchHit>(
+                r#"SELECT n.id, n.user_id as author_id, n.workspace_id, n.notebook_id,
+                          n.title, n.content, n.visibility, n.created_at, n.updated_at, n.version,
+                          (SELECT json_group_array(name) FROM (
+                               SELECT t.name FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                               WHERE nt.note_id = n.id ORDER BY t.name)) as tags,
+                          n.pinned, n.starred, n.archived_at,
+                          -bm25(notes_fts, 10.0, 1.0) as rank,
+                          snippet(notes_fts, -1, '<mark>', '</mark>', '…', 16) as snippet
+                   FROM notes_fts
+                   JOIN notes n ON n.id = notes_fts.rowid
+                   WHERE notes_fts MATCH ?1
+                     AND n.deleted_at IS NULL
+                     AND (n.visibility = 'public'
+                          OR (n.workspace_id IS NULL AND n.user_id = ?2)
+                          OR n.workspace_id IN (
+                              SELECT workspace_id FROM workspace_members WHERE user_id = ?2))
+                   ORDER BY rank DESC, n.id DESC
+                   LIMIT ?3"#,
             )
+            .bind(expr)
+            .bind(viewer)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(hits)
+        }
+
+        async fn list_trash(&self, user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+            let notes = sqlx::query_as::<sqlx::Sqlite, TrashedNote>(&format!(
+                r#"SELECT s.*, n.deleted_at
+                   FROM notes n
+                   JOIN ({SELECT_NOTE}) s ON s.id = n.id
+                   WHERE n.user_id = ? AND n.deleted_at IS NOT NULL
+                   ORDER BY n.deleted_at DESC, n.id DESC"#
+            ))
+            .bind(user_id)
             .fetch_all(&self.pool)
             .await
             .map_err(RepoError::DbError)?;
             Ok(notes)
         }
+
+        async fn restore_note(
+            &self,
+            note_id: i64,
+            user_id: i64,
+        ) -> Result<Option<Note>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let restored = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+                r#"UPDATE notes
+                   SET deleted_at = NULL,
+                       version = version + 1
+                   WHERE id = ? AND user_id = ? AND deleted_at IS NOT NULL
+                   RETURNING id"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            if restored.is_none() {
+                return Ok(None);
+            }
+            let note = fetch_note(&mut tx, note_id).await?;
+            sync_fts(&mut tx, &note).await?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(Some(note))
+        }
+
+        async fn purge_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"DELETE FROM notes WHERE id = ? AND user_id = ? AND deleted_at IS NOT NULL"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn purge_trash(&self, retention_secs: i64) -> Result<u64, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"DELETE FROM notes
+                   WHERE deleted_at IS NOT NULL
+                     AND deleted_at <= strftime('%s','now') - ?"#,
+            )
+            .bind(retention_secs)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected())
+        }
+    }
+
+    pub(crate) async fn fetch_note(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        note_id: i64,
+    ) -> Result<Note, RepoError> {
+        sqlx::query_as::<sqlx::Sqlite, Note>(&format!("{SELECT_NOTE} WHERE n.id = ?"))
+            .bind(note_id)
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)
+    }
+
+    /// 更新・削除の対象行が無かった理由を判定する。
+    /// メモが存在するのにバージョン条件で弾かれた場合だけ `VersionMismatch` を返す。
+    async fn not_updated(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        note_id: i64,
+    ) -> Result<(), RepoError> {
+        let exists = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+            r#"SELECT id FROM notes WHERE id = ? AND deleted_at IS NULL"#,
+        )
+        .bind(note_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        match exists {
+            Some(_) => Err(RepoError::VersionMismatch),
+            None => Ok(()),
+        }
+    }
+
+    /// メモのタグを `tags` で置き換える。未登録のタグはメモの所有者のタグとして作成する。
+    async fn replace_tags(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        note_id: i64,
+        user_id: i64,
+        tags: &[String],
+    ) -> Result<(), RepoError> {
+        sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM note_tags WHERE note_id = ?"#)
+            .bind(note_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        for name in tags {
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO tags (user_id, name, created_at)
+                   VALUES (?, ?, strftime('%s','now'))
+                   ON CONFLICT (user_id, name) DO NOTHING"#,
+            )
+            .bind(user_id)
+            .bind(name)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO note_tags (note_id, tag_id)
+                   SELECT ?, id FROM tags WHERE user_id = ? AND name = ?
+                   ON CONFLICT DO NOTHING"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .bind(name)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        }
+        Ok(())
+    }
+
+    /// 本文中のリンクを `note_links` に保存し直す。
+    async fn replace_links(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        note_id: i64,
+        content: &str,
+    ) -> Result<(), RepoError> {
+        sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM note_links WHERE source_id = ?"#)
+            .bind(note_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        for (position, target) in parse_links(content).into_iter().enumerate() {
+            let (target_id, target_title) = match target {
+                LinkTarget::Id(id) => (Some(id), None),
+                LinkTarget::Title(title) => (None, Some(title)),
+            };
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO note_links (source_id, position, target_id, target_title)
+                   VALUES (?, ?, ?, ?)"#,
+            )
+            .bind(note_id)
+            .bind(position as i64)
+            .bind(target_id)
+            .bind(target_title)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        }
+        Ok(())
+    }
+
+    /// タイトルが `old_title` から変わったメモ `renamed` へのリンクを追従させる。
+    /// 同じ範囲（同じワークスペース、個人のメモなら同じ作成者）のメモの本文の
+    /// `[[old_title]]` を新しいタイトルに書き換える（書き換えたメモもリビジョンを残す）。
+    /// 範囲内にまだ `old_title` のメモがあれば、リンクはそちらを指すので書き換えない。
+    /// 書き換えたメモを返す。
+    async fn relink_renamed(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        renamed: &Note,
+        old_title: &str,
+        editor_id: i64,
+    ) -> Result<Vec<Note>, RepoError> {
+        let sources = sqlx::query_as::<sqlx::Sqlite, (i64, String)>(
+            r#"SELECT n.id, n.content FROM notes n
+               WHERE n.deleted_at IS NULL
+                 AND (n.workspace_id = ?1
+                      OR (?1 IS NULL AND n.workspace_id IS NULL AND n.user_id = ?2))
+                 AND EXISTS (SELECT 1 FROM note_links l
+                             WHERE l.source_id = n.id AND l.target_title = ?3)
+                 AND NOT EXISTS (SELECT 1 FROM notes o
+                                 WHERE o.deleted_at IS NULL AND o.title = ?3
+                                   AND (o.workspace_id = ?1
+                                        OR (?1 IS NULL AND o.workspace_id IS NULL
+                                            AND o.user_id = ?2)))"#,
+        )
+        .bind(renamed.workspace_id)
+        .bind(renamed.author_id)
+        .bind(old_title)
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        let mut relinked = vec![];
+        for (source_id, content) in sources {
+            let Some(content) = rename_links(&content, old_title, &renamed.title) else {
+                continue;
+            };
+            sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE notes
+                   SET content = ?, updated_at = strftime('%s','now'), version = version + 1
+                   WHERE id = ?"#,
+            )
+            .bind(&content)
+            .bind(source_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            replace_links(tx, source_id, &content).await?;
+            record_revision(tx, source_id, editor_id).await?;
+            let source = fetch_note(tx, source_id).await?;
+            sync_fts(tx, &source).await?;
+            relinked.push(source);
+        }
+        Ok(relinked)
+    }
+
+    /// 現在のタイトル・本文を新しいリビジョンとして記録する。
+    /// 直前のリビジョンと同じ内容（公開範囲やタグだけの変更）なら記録しない。
+    async fn record_revision(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        note_id: i64,
+        editor_id: i64,
+    ) -> Result<(), RepoError> {
+        sqlx::query::<sqlx::Sqlite>(
+            r#"INSERT INTO note_revisions (note_id, revision, title, content, editor_id, created_at)
+               SELECT n.id, COALESCE(r.revision, 0) + 1, n.title, n.content, ?, strftime('%s','now')
+               FROM notes n
+               LEFT JOIN note_revisions r ON r.note_id = n.id
+                    AND r.revision = (SELECT MAX(revision) FROM note_revisions WHERE note_id = n.id)
+               WHERE n.id = ?
+                 AND (r.revision IS NULL OR r.title <> n.title OR r.content <> n.content)"#,
+        )
+        .bind(editor_id)
+        .bind(note_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        Ok(())
+    }
+
+    /// FTS5 インデックス（`notes_fts`, rowid = notes.id）を最新の内容に置き換える。
+    async fn sync_fts(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        note: &Note,
+    ) -> Result<(), RepoError> {
+        sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM notes_fts WHERE rowid = ?"#)
+            .bind(note.id)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        sqlx::query::<sqlx::Sqlite>(
+            r#"INSERT INTO notes_fts (rowid, title, content) VALUES (?, ?, ?)"#,
+        )
+        .bind(note.id)
+        .bind(&note.title)
+        .bind(&note.content)
+        .execute(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        Ok(())
+    }
+
+    /// 入力をトークンごとにフレーズとしてクォートし、FTS5 の構文エラーを防ぐ。
+    /// トークン同士は AND 条件になる。
+    fn fts5_query(query: &str) -> Option<String> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+            .collect();
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" "))
+        }
     }
 }
 
@@ -131,7 +805,26 @@ pub use postgres::PgNoteRepository;
 #[cfg(feature = "postgres")]
 pub mod postgres {
     use super::*;
-    use sqlx::PgPool;
+    use sqlx::{PgPool, QueryBuilder, Transaction};
+
+    /// メモ 1 件分のカラム（タグは名前順の JSON 配列として集約する）
+    pub(crate) const SELECT_NOTE: &str = r#"SELECT n.id,
+                  n.user_id as author_id,
+                  n.workspace_id,
+                  n.notebook_id,
+                  n.title,
+                  n.content,
+                  n.visibility,
+                  EXTRACT(EPOCH FROM n.created_at)::bigint as created_at,
+                  EXTRACT(EPOCH FROM n.updated_at)::bigint as updated_at,
+                  n.version,
+                  COALESCE((SELECT json_agg(t.name ORDER BY t.name)
+                            FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                            WHERE nt.note_id = n.id), '[]'::json) as tags,
+                  n.pinned,
+                  n.starred,
+                  EXTRACT(EPOCH FROM n.archived_at)::bigint as archived_at
+           FROM notes n"#;
 
     pub struct PgNoteRepository {
         pub(crate) pool: PgPool,
@@ -145,41 +838,35 @@ pub mod postgres {
 
     #[async_trait::async_trait]
     impl NoteRepository for PgNoteRepository {
-        async fn create_note(
-            &self,
-            user_id: i64,
-            title: &str,
-            content: &str,
-        ) -> Result<Note, RepoError> {
-            let inserted = sqlx::query_as::<sqlx::Postgres, Note>(
-                r#"INSERT INTO notes (user_id, title, content, created_at, updated_at)
-                   VALUES ($1, $2, $3, NOW(), NOW())
-                   RETURNING id,
-                             user_id as author_id,
-                             title,
-                             content,
-                             EXTRACT(EPOCH FROM created_at)::bigint as created_at,
-                             EXTRACT(EPOCH FROM updated_at)::bigint as updated_at"#,
+        async fn create_note(&self, user_id: i64, note: &NewNote<'_>) -> Result<Note, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let note_id: i64 = sqlx::query_scalar::<sqlx::Postgres, i64>(
+                r#"INSERT INTO notes (user_id, workspace_id, notebook_id, title, content, visibility,
+                                      created_at, updated_at)
+                   VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+                   RETURNING id"#,
             )
             .bind(user_id)
-            .bind(title)
-            .bind(content)
-            .fetch_one(&self.pool)
+            .bind(note.workspace_id)
+            .bind(note.notebook_id)
+            .bind(note.title)
+            .bind(note.content)
+            .bind(note.visibility.as_str())
+            .fetch_one(&mut *tx)
             .await
             .map_err(RepoError::DbError)?;
+            replace_tags(&mut tx, note_id, user_id, note.tags).await?;
+            replace_links(&mut tx, note_id, note.content).await?;
+            record_revision(&mut tx, note_id, user_id).await?;
+            let inserted = fetch_note(&mut tx, note_id).await?;
+            tx.commit().await.map_err(RepoError::DbError)?;
             Ok(inserted)
         }
 
         async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
-            let note = sqlx::query_as::<sqlx::Postgres, Note>(
-                r#"SELECT id,
-                          user_id as author_id,
-                          title,
-                          content,
-                          EXTRACT(EPOCH FROM created_at)::bigint as created_at,
-                          EXTRACT(EPOCH FROM updated_at)::bigint as updated_at
-                   FROM notes WHERE id = $1"#,
-            )
+            let note = sqlx::query_as::<sqlx::Postgres, Note>(&format!(
+                "{SELECT_NOTE} WHERE n.id = $1 AND n.deleted_at IS NULL"
+            ))
             .bind(note_id)
             .fetch_optional(&self.pool)
             .await
@@ -187,39 +874,268 @@ pub mod postgres {
             Ok(note)
         }
 
+        async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+            let note =
+                sqlx::query_as::<sqlx::Postgres, Note>(&format!("{SELECT_NOTE} WHERE n.id = $1"))
+                    .bind(note_id)
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(RepoError::DbError)?;
+            Ok(note)
+        }
+
         async fn update_note(
             &self,
             note_id: i64,
             user_id: i64,
-            title: Option<&str>,
-            content: Option<&str>,
-        ) -> Result<Option<Note>, RepoError> {
-            let updated = sqlx::query_as::<sqlx::Postgres, Note>(
+            changes: &NoteChanges<'_>,
+        ) -> Result<Option<UpdatedNote>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let old_title = match changes.title {
+                Some(_) => sqlx::query_scalar::<sqlx::Postgres, String>(
+                    r#"SELECT title FROM notes WHERE id = $1 AND deleted_at IS NULL"#,
+                )
+                .bind(note_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?,
+                None => None,
+            };
+            let author_id = sqlx::query_scalar::<sqlx::Postgres, i64>(
                 r#"UPDATE notes
                    SET title = COALESCE($1, title),
                        content = COALESCE($2, content),
-                       updated_at = NOW()
-                   WHERE id = $3 AND user_id = $4
-                   RETURNING id,
-                             user_id as author_id,
-                             title,
-                             content,
-                             EXTRACT(EPOCH FROM created_at)::bigint as created_at,
-                             EXTRACT(EPOCH FROM updated_at)::bigint as updated_at"#,
-            )
-            .bind(title)
-            .bind(content)
+                       visibility = COALESCE($3, visibility),
+                       pinned = COALESCE($6, pinned),
+                       starred = COALESCE($7, starred),
+                       archived_at = CASE $8::boolean
+                           WHEN true THEN COALESCE(archived_at, NOW())
+                           WHEN false THEN NULL
+                           ELSE archived_at END,
+                       updated_at = CASE WHEN $9 THEN NOW() ELSE updated_at END,
+                       version = version + 1
+                   WHERE id = $4 AND deleted_at IS NULL
+                     AND ($5::bigint IS NULL OR version = $5)
+                   RETURNING user_id"#,
+            )
+            .bind(changes.title)
+            .bind(changes.content)
+            .bind(changes.visibility.map(|v| v.as_str()))
+            .bind(note_id)
+            .bind(changes.expected_version)
+            .bind(changes.pinned)
+            .bind(changes.starred)
+            .bind(changes.archived)
+            .bind(changes.edits_note())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let Some(author_id) = author_id else {
+                return not_updated(&mut tx, note_id).await.map(|_| None);
+            };
+            // タグは編集者ではなく作成者のタグとして付ける
+            if let Some(tags) = changes.tags {
+                replace_tags(&mut tx, note_id, author_id, tags).await?;
+            }
+            if let Some(content) = changes.content {
+                replace_links(&mut tx, note_id, content).await?;
+            }
+            record_revision(&mut tx, note_id, user_id).await?;
+            let mut note = fetch_note(&mut tx, note_id).await?;
+            let mut relinked = vec![];
+            if let Some(old_title) = old_title.filter(|title| *title != note.title) {
+                relinked = relink_renamed(&mut tx, &note, &old_title, user_id).await?;
+                relinked.retain(|source| source.id != note_id);
+                note = fetch_note(&mut tx, note_id).await?;
+            }
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(Some(UpdatedNote { note, relinked }))
+        }
+
+        async fn delete_note(
+            &self,
+            note_id: i64,
+            expected_version: Option<i64>,
+        ) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE notes
+                   SET deleted_at = NOW(),
+                       version = version + 1
+                   WHERE id = $1 AND deleted_at IS NULL
+                     AND ($2::bigint IS NULL OR version = $2)"#,
+            )
+            .bind(note_id)
+            .bind(expected_version)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            if res.rows_affected() == 0 {
+                return not_updated(&mut tx, note_id).await.map(|_| false);
+            }
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(true)
+        }
+
+        async fn list_notes(&self, query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+            // カーソルは `Note` と同じ秒単位の値を持つので、並び順も秒に丸めた値と ID で決める
+            // （timestamptz のまま並べると、同じ秒の中の順序がカーソルとの比較と食い違う）
+            let column = match query.sort {
+                NoteSort::Title => "n.title".to_string(),
+                sort => format!("EXTRACT(EPOCH FROM n.{})::bigint", sort.column()),
+            };
+            let mut qb = QueryBuilder::<sqlx::Postgres>::new(SELECT_NOTE);
+            qb.push(" WHERE n.deleted_at IS NULL AND (n.visibility = 'public'")
+                .push(" OR (n.workspace_id IS NULL AND n.user_id = ")
+                .push_bind(query.viewer)
+                .push(") OR n.workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = ")
+                .push_bind(query.viewer)
+                .push("))");
+            if let Some(author) = query.author {
+                qb.push(" AND n.user_id = ").push_bind(author);
+            }
+            if let Some(workspace) = query.workspace {
+                qb.push(" AND n.workspace_id = ").push_bind(workspace);
+            }
+            if let Some(notebook) = query.notebook {
+                qb.push(" AND n.notebook_id = ").push_bind(notebook);
+            }
+            qb.push(if query.archived {
+                " AND n.archived_at IS NOT NULL"
+            } else {
+                " AND n.archived_at IS NULL"
+            });
+            if query.starred {
+                qb.push(" AND n.starred");
+            }
+            if !query.tags.is_empty() {
+                let tagged = match query.tag_match {
+                    TagMatch::All => " AND (SELECT COUNT(DISTINCT t.name)",
+                    TagMatch::Any => " AND EXISTS (SELECT 1",
+                };
+                qb.push(tagged)
+                    .push(
+                        " FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                          WHERE nt.note_id = n.id AND t.name = ANY(",
+                    )
+                    .push_bind(&query.tags)
+                    .push("))");
+                if query.tag_match == TagMatch::All {
+                    qb.push(" = ").push_bind(query.tags.len() as i64);
+                }
+            }
+            if let Some(after) = &query.after {
+                // 固定したメモは `order` に関係なく常に先頭なので、カーソルより後ろとは
+                // 「固定されていない」か「固定状態が同じでキーが後ろ」のどちらか
+                qb.push(" AND (n.pinned < ")
+                    .push_bind(after.pinned)
+                    .push(" OR (n.pinned = ")
+                    .push_bind(after.pinned)
+                    .push(format_args!(
+                        " AND ({column}, n.id) {} (",
+                        query.order.comparator()
+                    ));
+                match &after.key {
+                    NoteSortKey::Time(time) => qb.push_bind(*time),
+                    NoteSortKey::Title(title) => qb.push_bind(title.as_str()),
+                };
+                qb.push(", ").push_bind(after.id).push(")))");
+            }
+            qb.push(format_args!(
+                " ORDER BY n.pinned DESC, {column} {order}, n.id {order} LIMIT ",
+                order = query.order.keyword()
+            ))
+            .push_bind(query.limit);
+
+            let notes = qb
+                .build_query_as::<Note>()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(RepoError::DbError)?;
+            Ok(notes)
+        }
+
+        async fn search(
+            &self,
+            viewer: Option<i64>,
+            query: &str,
+            limit: i64,
+        ) -> Result<Vec<NoteSearchHit>, RepoError> {
+            if query.trim().is_empty() {
+                return Ok(vec![]);
+            }
+            // search_vector は title（重み A）と content（重み B）から生成される列
+            let hits = sqlx::query_as::<sqlx::Postgres, NoteSearchHit>(&format!(
+                r#"SELECT s.*,
+                          ts_rank(n.search_vector, q)::float8 as rank,
+                          ts_headline('simple', n.title || E'\n' || n.content, q,
+                                      'StartSel=<mark>, StopSel=</mark>, MaxWords=24, MinWords=8')
+                              as snippet
+                   FROM notes n
+                   CROSS JOIN websearch_to_tsquery('simple', $1) q
+                   JOIN ({SELECT_NOTE}) s ON s.id = n.id
+                   WHERE n.search_vector @@ q
+                     AND n.deleted_at IS NULL
+                     AND (n.visibility = 'public'
+                          OR (n.workspace_id IS NULL AND n.user_id = $2)
+                          OR n.workspace_id IN (
+                              SELECT workspace_id FROM workspace_members WHERE user_id = $2))
+                   ORDER BY rank DESC, n.id DESC
+                   LIMIT $3"#
+            ))
+            .bind(query)
+            .bind(viewer)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(hits)
+        }
+
+        async fn list_trash(&self, user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+            let notes = sqlx::query_as::<sqlx::Postgres, TrashedNote>(&format!(
+                r#"SELECT s.*, EXTRACT(EPOCH FROM n.deleted_at)::bigint as deleted_at
+                   FROM notes n
+                   JOIN ({SELECT_NOTE}) s ON s.id = n.id
+                   WHERE n.user_id = $1 AND n.deleted_at IS NOT NULL
+                   ORDER BY n.deleted_at DESC, n.id DESC"#
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notes)
+        }
+
+        async fn restore_note(
+            &self,
+            note_id: i64,
+            user_id: i64,
+        ) -> Result<Option<Note>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let restored = sqlx::query_scalar::<sqlx::Postgres, i64>(
+                r#"UPDATE notes
+                   SET deleted_at = NULL,
+                       version = version + 1
+                   WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL
+                   RETURNING id"#,
+            )
             .bind(note_id)
             .bind(user_id)
-            .fetch_optional(&self.pool)
+            .fetch_optional(&mut *tx)
             .await
             .map_err(RepoError::DbError)?;
-            Ok(updated)
+            if restored.is_none() {
+                return Ok(None);
+            }
+            let note = fetch_note(&mut tx, note_id).await?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(Some(note))
         }
 
-        async fn delete_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
+        async fn purge_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
             let res = sqlx::query::<sqlx::Postgres>(
-                r#"DELETE FROM notes WHERE id = $1 AND user_id = $2"#,
+                r#"DELETE FROM notes WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL"#,
             )
             .bind(note_id)
             .bind(user_id)
@@ -229,20 +1145,194 @@ pub mod postgres {
             Ok(res.rows_affected() > 0)
         }
 
-        async fn list_notes(&self) -> Result<Vec<Note>, RepoError> {
-            let notes = sqlx::query_as::<sqlx::Postgres, Note>(
-                r#"SELECT id,
-                          user_id as author_id,
-                          title,
-                          content,
-                          EXTRACT(EPOCH FROM created_at)::bigint as created_at,
-                          EXTRACT(EPOCH FROM updated_at)::bigint as updated_at
-                   FROM notes ORDER BY created_at DESC"#,
+        async fn purge_trash(&self, retention_secs: i64) -> Result<u64, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM notes
+                   WHERE deleted_at IS NOT NULL
+                     AND deleted_at <= NOW() - make_interval(secs => $1)"#,
             )
-            .fetch_all(&self.pool)
+            .bind(retention_secs as f64)
+            .execute(&self.pool)
             .await
             .map_err(RepoError::DbError)?;
-            Ok(notes)
+            Ok(res.rows_affected())
         }
     }
+
+    pub(crate) async fn fetch_note(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        note_id: i64,
+    ) -> Result<Note, RepoError> {
+        sqlx::query_as::<sqlx::Postgres, Note>(&format!("{SELECT_NOTE} WHERE n.id = $1"))
+            .bind(note_id)
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)
+    }
+
+    /// 更新・削除の対象行が無かった理由を判定する。
+    /// メモが存在するのにバージョン条件で弾かれた場合だけ `VersionMismatch` を返す。
+    async fn not_updated(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        note_id: i64,
+    ) -> Result<(), RepoError> {
+        let exists = sqlx::query_scalar::<sqlx::Postgres, i64>(
+            r#"SELECT id FROM notes WHERE id = $1 AND deleted_at IS NULL"#,
+        )
+        .bind(note_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        match exists {
+            Some(_) => Err(RepoError::VersionMismatch),
+            None => Ok(()),
+        }
+    }
+
+    /// メモのタグを `tags` で置き換える。未登録のタグはメモの所有者のタグとして作成する。
+    async fn replace_tags(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        note_id: i64,
+        user_id: i64,
+        tags: &[String],
+    ) -> Result<(), RepoError> {
+        sqlx::query::<sqlx::Postgres>(r#"DELETE FROM note_tags WHERE note_id = $1"#)
+            .bind(note_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        if tags.is_empty() {
+            return Ok(());
+        }
+        sqlx::query::<sqlx::Postgres>(
+            r#"INSERT INTO tags (user_id, name)
+               SELECT $1, name FROM UNNEST($2::text[]) AS name
+               ON CONFLICT (user_id, name) DO NOTHING"#,
+        )
+        .bind(user_id)
+        .bind(tags)
+        .execute(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        sqlx::query::<sqlx::Postgres>(
+            r#"INSERT INTO note_tags (note_id, tag_id)
+               SELECT $1, id FROM tags WHERE user_id = $2 AND name = ANY($3)
+               ON CONFLICT DO NOTHING"#,
+        )
+        .bind(note_id)
+        .bind(user_id)
+        .bind(tags)
+        .execute(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        Ok(())
+    }
+
+    /// 本文中のリンクを `note_links` に保存し直す。
+    async fn replace_links(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        note_id: i64,
+        content: &str,
+    ) -> Result<(), RepoError> {
+        sqlx::query::<sqlx::Postgres>(r#"DELETE FROM note_links WHERE source_id = $1"#)
+            .bind(note_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        for (position, target) in parse_links(content).into_iter().enumerate() {
+            let (target_id, target_title) = match target {
+                LinkTarget::Id(id) => (Some(id), None),
+                LinkTarget::Title(title) => (None, Some(title)),
+            };
+            sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO note_links (source_id, position, target_id, target_title)
+                   VALUES ($1, $2, $3, $4)"#,
+            )
+            .bind(note_id)
+            .bind(position as i64)
+            .bind(target_id)
+            .bind(target_title)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        }
+        Ok(())
+    }
+
+    /// タイトルが `old_title` から変わったメモ `renamed` へのリンクを追従させる。
+    /// 同じ範囲（同じワークスペース、個人のメモなら同じ作成者）のメモの本文の
+    /// `[[old_title]]` を新しいタイトルに書き換える（書き換えたメモもリビジョンを残す）。
+    /// 範囲内にまだ `old_title` のメモがあれば、リンクはそちらを指すので書き換えない。
+    /// 書き換えたメモを返す。
+    async fn relink_renamed(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        renamed: &Note,
+        old_title: &str,
+        editor_id: i64,
+    ) -> Result<Vec<Note>, RepoError> {
+        let sources = sqlx::query_as::<sqlx::Postgres, (i64, String)>(
+            r#"SELECT n.id, n.content FROM notes n
+               WHERE n.deleted_at IS NULL
+                 AND (n.workspace_id = $1
+                      OR ($1::bigint IS NULL AND n.workspace_id IS NULL AND n.user_id = $2))
+                 AND EXISTS (SELECT 1 FROM note_links l
+                             WHERE l.source_id = n.id AND l.target_title = $3)
+                 AND NOT EXISTS (SELECT 1 FROM notes o
+                                 WHERE o.deleted_at IS NULL AND o.title = $3
+                                   AND (o.workspace_id = $1
+                                        OR ($1::bigint IS NULL AND o.workspace_id IS NULL
+                                            AND o.user_id = $2)))"#,
+        )
+        .bind(renamed.workspace_id)
+        .bind(renamed.author_id)
+        .bind(old_title)
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        let mut relinked = vec![];
+        for (source_id, content) in sources {
+            let Some(content) = rename_links(&content, old_title, &renamed.title) else {
+                continue;
+            };
+            sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE notes SET content = $1, updated_at = NOW(), version = version + 1
+                   WHERE id = $2"#,
+            )
+            .bind(&content)
+            .bind(source_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            replace_links(tx, source_id, &content).await?;
+            record_revision(tx, source_id, editor_id).await?;
+            relinked.push(fetch_note(tx, source_id).await?);
+        }
+        Ok(relinked)
+    }
+
+    /// 現在のタイトル・本文を新しいリビジョンとして記録する。
+    /// 直前のリビジョンと同じ内容（公開範囲やタグだけの変更）なら記録しない。
+    async fn record_revision(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        note_id: i64,
+        editor_id: i64,
+    ) -> Result<(), RepoError> {
+        sqlx::query::<sqlx::Postgres>(
+            r#"INSERT INTO note_revisions (note_id, revision, title, content, editor_id)
+               SELECT n.id, COALESCE(r.revision, 0) + 1, n.title, n.content, $1
+               FROM notes n
+               LEFT JOIN LATERAL (
+                   SELECT revision, title, content FROM note_revisions
+                   WHERE note_id = n.id ORDER BY revision DESC LIMIT 1
+               ) r ON TRUE
+               WHERE n.id = $2
+                 AND (r.revision IS NULL OR r.title <> n.title OR r.content <> n.content)"#,
+        )
+        .bind(editor_id)
+        .bind(note_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        Ok(())
+    }
 }
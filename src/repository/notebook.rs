@@ -0,0 +1,669 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::model::{Note, Notebook};
+use crate::repository::user::RepoError;
+
+/// ノートブックを削除するときの中身の扱い。
+/// - `Reparent`: 子のノートブックとメモを親（トップレベルならどこにも入れない状態）に移す（既定）
+/// - `Cascade`: 子孫のノートブックもまとめて削除し、中のメモはゴミ箱に移動する
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotebookDeleteMode {
+    #[default]
+    Reparent,
+    Cascade,
+}
+
+/// ユーザーのノートブックと、メモの出し入れ。
+/// どのメソッドも `user_id` のノートブックだけを対象にする（他人のノートブックは存在しない扱い）。
+#[async_trait::async_trait]
+pub trait NotebookRepository: Send + Sync + 'static {
+    /// `parent_id` が `user_id` のノートブックかどうかは呼び出し側で確認する。
+    async fn create_notebook(
+        &self,
+        user_id: i64,
+        parent_id: Option<i64>,
+        name: &str,
+    ) -> Result<Notebook, RepoError>;
+    async fn find_notebook(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+    ) -> Result<Option<Notebook>, RepoError>;
+    /// ユーザーのノートブックをすべて名前順に返す（階層は `parent_id` で組み立てる）。
+    async fn list_notebooks(&self, user_id: i64) -> Result<Vec<Notebook>, RepoError>;
+    async fn rename_notebook(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        name: &str,
+    ) -> Result<Option<Notebook>, RepoError>;
+    /// ノートブックを `parent_id` の下に移す（`None` ならトップレベル）。
+    /// - Ok(None): ノートブックまたは移動先が存在しない
+    /// - Err(Conflict): 自分自身か子孫の下に移そうとした
+    async fn move_notebook(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        parent_id: Option<i64>,
+    ) -> Result<Option<Notebook>, RepoError>;
+    /// ノートブックを削除する。中身の扱いは `mode` に従う。
+    /// メモの `notebook_id` が変わるので、対象のメモの `version` を 1 増やす。
+    /// ノートブックが見つからなければ `None`、削除したら移した・ゴミ箱に入れたメモ
+    /// （既にゴミ箱にあったものは除く）を変更後の状態で返す。
+    async fn delete_notebook(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        mode: NotebookDeleteMode,
+    ) -> Result<Option<Vec<Note>>, RepoError>;
+    /// `user_id` が作成した個人のメモを `notebook_id` に入れる（`None` ならノートブックから出す）。
+    /// メモの `version` を 1 増やす。メモかノートブックが見つからなければ `false`。
+    async fn file_note(
+        &self,
+        user_id: i64,
+        note_id: i64,
+        notebook_id: Option<i64>,
+    ) -> Result<bool, RepoError>;
+}
+
+// SQLite 実装をモジュールにまとめる
+#[cfg(not(feature = "postgres"))]
+pub use sqlite::SqliteNotebookRepository;
+
+#[cfg(not(feature = "postgres"))]
+pub mod sqlite {
+    use super::*;
+    use sqlx::{SqlitePool, Transaction};
+
+    const SELECT_NOTEBOOK: &str =
+        r#"SELECT id, parent_id, name, created_at, updated_at FROM notebooks"#;
+
+    /// `?1` のノートブックと、その子孫の ID（`?2` のユーザーのもののみ）
+    const SUBTREE: &str = r#"WITH RECURSIVE subtree(id) AS (
+               SELECT id FROM notebooks WHERE id = ?1 AND user_id = ?2
+               UNION ALL
+               SELECT nb.id FROM notebooks nb JOIN subtree s ON nb.parent_id = s.id)"#;
+
+    pub struct SqliteNotebookRepository {
+        pub(crate) pool: SqlitePool,
+    }
+
+    impl SqliteNotebookRepository {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl NotebookRepository for SqliteNotebookRepository {
+        async fn create_notebook(
+            &self,
+            user_id: i64,
+            parent_id: Option<i64>,
+            name: &str,
+        ) -> Result<Notebook, RepoError> {
+            let notebook = sqlx::query_as::<sqlx::Sqlite, Notebook>(
+                r#"INSERT INTO notebooks (user_id, parent_id, name, created_at, updated_at)
+                   VALUES (?, ?, ?, strftime('%s','now'), strftime('%s','now'))
+                   RETURNING id, parent_id, name, created_at, updated_at"#,
+            )
+            .bind(user_id)
+            .bind(parent_id)
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notebook)
+        }
+
+        async fn find_notebook(
+            &self,
+            user_id: i64,
+            notebook_id: i64,
+        ) -> Result<Option<Notebook>, RepoError> {
+            let notebook = sqlx::query_as::<sqlx::Sqlite, Notebook>(&format!(
+                "{SELECT_NOTEBOOK} WHERE id = ? AND user_id = ?"
+            ))
+            .bind(notebook_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notebook)
+        }
+
+        async fn list_notebooks(&self, user_id: i64) -> Result<Vec<Notebook>, RepoError> {
+            let notebooks = sqlx::query_as::<sqlx::Sqlite, Notebook>(&format!(
+                "{SELECT_NOTEBOOK} WHERE user_id = ? ORDER BY name, id"
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notebooks)
+        }
+
+        async fn rename_notebook(
+            &self,
+            user_id: i64,
+            notebook_id: i64,
+            name: &str,
+        ) -> Result<Option<Notebook>, RepoError> {
+            let notebook = sqlx::query_as::<sqlx::Sqlite, Notebook>(
+                r#"UPDATE notebooks SET name = ?, updated_at = strftime('%s','now')
+                   WHERE id = ? AND user_id = ?
+                   RETURNING id, parent_id, name, created_at, updated_at"#,
+            )
+            .bind(name)
+            .bind(notebook_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notebook)
+        }
+
+        async fn move_notebook(
+            &self,
+            user_id: i64,
+            notebook_id: i64,
+            parent_id: Option<i64>,
+        ) -> Result<Option<Notebook>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            if let Some(parent_id) = parent_id {
+                match parent_is_descendant(&mut tx, user_id, notebook_id, parent_id).await? {
+                    None => return Ok(None),
+                    Some(true) => return Err(RepoError::Conflict),
+                    Some(false) => {}
+                }
+            }
+            let notebook = sqlx::query_as::<sqlx::Sqlite, Notebook>(
+                r#"UPDATE notebooks SET parent_id = ?, updated_at = strftime('%s','now')
+                   WHERE id = ? AND user_id = ?
+                   RETURNING id, parent_id, name, created_at, updated_at"#,
+            )
+            .bind(parent_id)
+            .bind(notebook_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(notebook)
+        }
+
+        async fn delete_notebook(
+            &self,
+            user_id: i64,
+            notebook_id: i64,
+            mode: NotebookDeleteMode,
+        ) -> Result<Option<Vec<Note>>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let parent = sqlx::query_scalar::<sqlx::Sqlite, Option<i64>>(
+                r#"SELECT parent_id FROM notebooks WHERE id = ? AND user_id = ?"#,
+            )
+            .bind(notebook_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let Some(parent) = parent else {
+                return Ok(None);
+            };
+            let note_ids = match mode {
+                NotebookDeleteMode::Reparent => {
+                    let note_ids = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+                        r#"SELECT id FROM notes
+                           WHERE notebook_id = ? AND deleted_at IS NULL ORDER BY id"#,
+                    )
+                    .bind(notebook_id)
+                    .fetch_all(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    sqlx::query::<sqlx::Sqlite>(
+                        r#"UPDATE notebooks SET parent_id = ?, updated_at = strftime('%s','now')
+                           WHERE parent_id = ?"#,
+                    )
+                    .bind(parent)
+                    .bind(notebook_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    sqlx::query::<sqlx::Sqlite>(
+                        r#"UPDATE notes SET notebook_id = ?, version = version + 1
+                           WHERE notebook_id = ?"#,
+                    )
+                    .bind(parent)
+                    .bind(notebook_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM notebooks WHERE id = ?"#)
+                        .bind(notebook_id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(RepoError::DbError)?;
+                    note_ids
+                }
+                NotebookDeleteMode::Cascade => {
+                    let note_ids = sqlx::query_scalar::<sqlx::Sqlite, i64>(&format!(
+                        r#"{SUBTREE}
+                           SELECT id FROM notes
+                           WHERE notebook_id IN (SELECT id FROM subtree) AND deleted_at IS NULL
+                           ORDER BY id"#
+                    ))
+                    .bind(notebook_id)
+                    .bind(user_id)
+                    .fetch_all(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    // ゴミ箱に移すメモは検索の対象から外す（`NoteRepository::delete_note` と同じ）
+                    sqlx::query::<sqlx::Sqlite>(&format!(
+                        r#"{SUBTREE}
+                           DELETE FROM notes_fts WHERE rowid IN (
+                               SELECT id FROM notes
+                               WHERE notebook_id IN (SELECT id FROM subtree)
+                                 AND deleted_at IS NULL)"#
+                    ))
+                    .bind(notebook_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    // ゴミ箱から戻したメモはどのノートブックにも入っていない状態にする
+                    sqlx::query::<sqlx::Sqlite>(&format!(
+                        r#"{SUBTREE}
+                           UPDATE notes
+                           SET deleted_at = COALESCE(deleted_at, strftime('%s','now')),
+                               notebook_id = NULL,
+                               version = version + 1
+                           WHERE notebook_id IN (SELECT id FROM subtree)"#
+                    ))
+                    .bind(notebook_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    sqlx::query::<sqlx::Sqlite>(&format!(
+                        r#"{SUBTREE}
+                           DELETE FROM notebooks WHERE id IN (SELECT id FROM subtree)"#
+                    ))
+                    .bind(notebook_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    note_ids
+                }
+            };
+            let mut notes = Vec::with_capacity(note_ids.len());
+            for note_id in note_ids {
+                notes.push(crate::repository::note::sqlite::fetch_note(&mut tx, note_id).await?);
+            }
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(Some(notes))
+        }
+
+        async fn file_note(
+            &self,
+            user_id: i64,
+            note_id: i64,
+            notebook_id: Option<i64>,
+        ) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE notes SET notebook_id = ?1, version = version + 1
+                   WHERE id = ?2 AND user_id = ?3
+                     AND workspace_id IS NULL AND deleted_at IS NULL
+                     AND (?1 IS NULL
+                          OR EXISTS (SELECT 1 FROM notebooks WHERE id = ?1 AND user_id = ?3))"#,
+            )
+            .bind(notebook_id)
+            .bind(note_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+    }
+
+    /// 移動先 `parent_id` が `notebook_id` 自身か子孫なら `Some(true)`。
+    /// どちらかが `user_id` のノートブックでなければ `None`。
+    async fn parent_is_descendant(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        user_id: i64,
+        notebook_id: i64,
+        parent_id: i64,
+    ) -> Result<Option<bool>, RepoError> {
+        let found = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+            r#"SELECT COUNT(*) FROM notebooks WHERE id IN (?1, ?2) AND user_id = ?3"#,
+        )
+        .bind(notebook_id)
+        .bind(parent_id)
+        .bind(user_id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        let expected = if notebook_id == parent_id { 1 } else { 2 };
+        if found < expected {
+            return Ok(None);
+        }
+        let descendant = sqlx::query_scalar::<sqlx::Sqlite, i64>(&format!(
+            r#"{SUBTREE}
+               SELECT 1 FROM subtree WHERE id = ?3"#
+        ))
+        .bind(notebook_id)
+        .bind(user_id)
+        .bind(parent_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        Ok(Some(descendant.is_some()))
+    }
+}
+
+// PostgreSQL 実装をモジュールにまとめる
+#[cfg(feature = "postgres")]
+pub use postgres::PgNotebookRepository;
+
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use super::*;
+    use sqlx::{PgPool, Transaction};
+
+    const SELECT_NOTEBOOK: &str = r#"SELECT id,
+                  parent_id,
+                  name,
+                  EXTRACT(EPOCH FROM created_at)::bigint as created_at,
+                  EXTRACT(EPOCH FROM updated_at)::bigint as updated_at
+           FROM notebooks"#;
+
+    const RETURNING_NOTEBOOK: &str = r#"RETURNING id,
+                             parent_id,
+                             name,
+                             EXTRACT(EPOCH FROM created_at)::bigint as created_at,
+                             EXTRACT(EPOCH FROM updated_at)::bigint as updated_at"#;
+
+    /// `$1` のノートブックと、その子孫の ID（`$2` のユーザーのもののみ）
+    const SUBTREE: &str = r#"WITH RECURSIVE subtree(id) AS (
+               SELECT id FROM notebooks WHERE id = $1 AND user_id = $2
+               UNION ALL
+               SELECT nb.id FROM notebooks nb JOIN subtree s ON nb.parent_id = s.id)"#;
+
+    pub struct PgNotebookRepository {
+        pub(crate) pool: PgPool,
+    }
+
+    impl PgNotebookRepository {
+        pub fn new(pool: PgPool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl NotebookRepository for PgNotebookRepository {
+        async fn create_notebook(
+            &self,
+            user_id: i64,
+            parent_id: Option<i64>,
+            name: &str,
+        ) -> Result<Notebook, RepoError> {
+            let notebook = sqlx::query_as::<sqlx::Postgres, Notebook>(&format!(
+                r#"INSERT INTO notebooks (user_id, parent_id, name)
+                   VALUES ($1, $2, $3)
+                   {RETURNING_NOTEBOOK}"#
+            ))
+            .bind(user_id)
+            .bind(parent_id)
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notebook)
+        }
+
+        async fn find_notebook(
+            &self,
+            user_id: i64,
+            notebook_id: i64,
+        ) -> Result<Option<Notebook>, RepoError> {
+            let notebook = sqlx::query_as::<sqlx::Postgres, Notebook>(&format!(
+                "{SELECT_NOTEBOOK} WHERE id = $1 AND user_id = $2"
+            ))
+            .bind(notebook_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notebook)
+        }
+
+        async fn list_notebooks(&self, user_id: i64) -> Result<Vec<Notebook>, RepoError> {
+            let notebooks = sqlx::query_as::<sqlx::Postgres, Notebook>(&format!(
+                "{SELECT_NOTEBOOK} WHERE user_id = $1 ORDER BY name, id"
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notebooks)
+        }
+
+        async fn rename_notebook(
+            &self,
+            user_id: i64,
+            notebook_id: i64,
+            name: &str,
+        ) -> Result<Option<Notebook>, RepoError> {
+            let notebook = sqlx::query_as::<sqlx::Postgres, Notebook>(&format!(
+                r#"UPDATE notebooks SET name = $1, updated_at = NOW()
+                   WHERE id = $2 AND user_id = $3
+                   {RETURNING_NOTEBOOK}"#
+            ))
+            .bind(name)
+            .bind(notebook_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(notebook)
+        }
+
+        async fn move_notebook(
+            &self,
+            user_id: i64,
+            notebook_id: i64,
+            parent_id: Option<i64>,
+        ) -> Result<Option<Notebook>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            lock_notebooks(&mut tx, user_id).await?;
+            if let Some(parent_id) = parent_id {
+                match parent_is_descendant(&mut tx, user_id, notebook_id, parent_id).await? {
+                    None => return Ok(None),
+                    Some(true) => return Err(RepoError::Conflict),
+                    Some(false) => {}
+                }
+            }
+            let notebook = sqlx::query_as::<sqlx::Postgres, Notebook>(&format!(
+                r#"UPDATE notebooks SET parent_id = $1, updated_at = NOW()
+                   WHERE id = $2 AND user_id = $3
+                   {RETURNING_NOTEBOOK}"#
+            ))
+            .bind(parent_id)
+            .bind(notebook_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(notebook)
+        }
+
+        async fn delete_notebook(
+            &self,
+            user_id: i64,
+            notebook_id: i64,
+            mode: NotebookDeleteMode,
+        ) -> Result<Option<Vec<Note>>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            lock_notebooks(&mut tx, user_id).await?;
+            let parent = sqlx::query_scalar::<sqlx::Postgres, Option<i64>>(
+                r#"SELECT parent_id FROM notebooks WHERE id = $1 AND user_id = $2"#,
+            )
+            .bind(notebook_id)
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let Some(parent) = parent else {
+                return Ok(None);
+            };
+            let note_ids = match mode {
+                NotebookDeleteMode::Reparent => {
+                    let note_ids = sqlx::query_scalar::<sqlx::Postgres, i64>(
+                        r#"SELECT id FROM notes
+                           WHERE notebook_id = $1 AND deleted_at IS NULL ORDER BY id"#,
+                    )
+                    .bind(notebook_id)
+                    .fetch_all(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    sqlx::query::<sqlx::Postgres>(
+                        r#"UPDATE notebooks SET parent_id = $1, updated_at = NOW()
+                           WHERE parent_id = $2"#,
+                    )
+                    .bind(parent)
+                    .bind(notebook_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    sqlx::query::<sqlx::Postgres>(
+                        r#"UPDATE notes SET notebook_id = $1, version = version + 1
+                           WHERE notebook_id = $2"#,
+                    )
+                    .bind(parent)
+                    .bind(notebook_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    sqlx::query::<sqlx::Postgres>(r#"DELETE FROM notebooks WHERE id = $1"#)
+                        .bind(notebook_id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(RepoError::DbError)?;
+                    note_ids
+                }
+                NotebookDeleteMode::Cascade => {
+                    let note_ids = sqlx::query_scalar::<sqlx::Postgres, i64>(&format!(
+                        r#"{SUBTREE}
+                           SELECT id FROM notes
+                           WHERE notebook_id IN (SELECT id FROM subtree) AND deleted_at IS NULL
+                           ORDER BY id"#
+                    ))
+                    .bind(notebook_id)
+                    .bind(user_id)
+                    .fetch_all(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    // ゴミ箱から戻したメモはどのノートブックにも入っていない状態にする
+                    sqlx::query::<sqlx::Postgres>(&format!(
+                        r#"{SUBTREE}
+                           UPDATE notes
+                           SET deleted_at = COALESCE(deleted_at, NOW()),
+                               notebook_id = NULL,
+                               version = version + 1
+                           WHERE notebook_id IN (SELECT id FROM subtree)"#
+                    ))
+                    .bind(notebook_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                    // 子孫のノートブックは parent_id の ON DELETE CASCADE で削除される
+                    sqlx::query::<sqlx::Postgres>(r#"DELETE FROM notebooks WHERE id = $1"#)
+                        .bind(notebook_id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(RepoError::DbError)?;
+                    note_ids
+                }
+            };
+            let mut notes = Vec::with_capacity(note_ids.len());
+            for note_id in note_ids {
+                notes.push(crate::repository::note::postgres::fetch_note(&mut tx, note_id).await?);
+            }
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(Some(notes))
+        }
+
+        async fn file_note(
+            &self,
+            user_id: i64,
+            note_id: i64,
+            notebook_id: Option<i64>,
+        ) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE notes SET notebook_id = $1, version = version + 1
+                   WHERE id = $2 AND user_id = $3
+                     AND workspace_id IS NULL AND deleted_at IS NULL
+                     AND ($1::bigint IS NULL
+                          OR EXISTS (SELECT 1 FROM notebooks WHERE id = $1 AND user_id = $3))"#,
+            )
+            .bind(notebook_id)
+            .bind(note_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+    }
+
+    /// 同時に 2 つのノートブックを互いの下に移して循環させないよう、ユーザーのノートブックをロックする。
+    async fn lock_notebooks(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        user_id: i64,
+    ) -> Result<(), RepoError> {
+        sqlx::query::<sqlx::Postgres>(r#"SELECT id FROM notebooks WHERE user_id = $1 FOR UPDATE"#)
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(RepoError::DbError)?;
+        Ok(())
+    }
+
+    /// 移動先 `parent_id` が `notebook_id` 自身か子孫なら `Some(true)`。
+    /// どちらかが `user_id` のノートブックでなければ `None`。
+    async fn parent_is_descendant(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        user_id: i64,
+        notebook_id: i64,
+        parent_id: i64,
+    ) -> Result<Option<bool>, RepoError> {
+        let found = sqlx::query_scalar::<sqlx::Postgres, i64>(
+            r#"SELECT COUNT(*) FROM notebooks WHERE id IN ($1, $2) AND user_id = $3"#,
+        )
+        .bind(notebook_id)
+        .bind(parent_id)
+        .bind(user_id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        let expected = if notebook_id == parent_id { 1 } else { 2 };
+        if found < expected {
+            return Ok(None);
+        }
+        let descendant = sqlx::query_scalar::<sqlx::Postgres, i32>(&format!(
+            r#"{SUBTREE}
+               SELECT 1 FROM subtree WHERE id = $3"#
+        ))
+        .bind(notebook_id)
+        .bind(user_id)
+        .bind(parent_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        Ok(Some(descendant.is_some()))
+    }
+}
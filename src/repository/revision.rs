@@ -0,0 +1,138 @@
+use crate::domain::model::{NoteRevision, NoteRevisionSummary};
+use crate::repository::user::RepoError;
+
+/// メモの変更履歴の参照。履歴の記録は `NoteRepository` の作成・更新時に同じトランザクションで行う。
+#[async_trait::async_trait]
+pub trait RevisionRepository: Send + Sync + 'static {
+    /// メモのリビジョンを新しい順に返す。
+    async fn list_revisions(&self, note_id: i64) -> Result<Vec<NoteRevisionSummary>, RepoError>;
+    async fn find_revision(
+        &self,
+        note_id: i64,
+        revision: i64,
+    ) -> Result<Option<NoteRevision>, RepoError>;
+}
+
+// SQLite 実装をモジュールにまとめる
+#[cfg(not(feature = "postgres"))]
+pub use sqlite::SqliteRevisionRepository;
+
+#[cfg(not(feature = "postgres"))]
+pub mod sqlite {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    pub struct SqliteRevisionRepository {
+        pub(crate) pool: SqlitePool,
+    }
+
+    impl SqliteRevisionRepository {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl RevisionRepository for SqliteRevisionRepository {
+        async fn list_revisions(
+            &self,
+            note_id: i64,
+        ) -> Result<Vec<NoteRevisionSummary>, RepoError> {
+            let revisions = sqlx::query_as::<sqlx::Sqlite, NoteRevisionSummary>(
+                r#"SELECT revision, title, editor_id, created_at
+                   FROM note_revisions
+                   WHERE note_id = ?
+                   ORDER BY revision DESC"#,
+            )
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(revisions)
+        }
+
+        async fn find_revision(
+            &self,
+            note_id: i64,
+            revision: i64,
+        ) -> Result<Option<NoteRevision>, RepoError> {
+            let revision = sqlx::query_as::<sqlx::Sqlite, NoteRevision>(
+                r#"SELECT note_id, revision, title, content, editor_id, created_at
+                   FROM note_revisions
+                   WHERE note_id = ? AND revision = ?"#,
+            )
+            .bind(note_id)
+            .bind(revision)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(revision)
+        }
+    }
+}
+
+// PostgreSQL 実装をモジュールにまとめる
+#[cfg(feature = "postgres")]
+pub use postgres::PgRevisionRepository;
+
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use super::*;
+    use sqlx::PgPool;
+
+    pub struct PgRevisionRepository {
+        pub(crate) pool: PgPool,
+    }
+
+    impl PgRevisionRepository {
+        pub fn new(pool: PgPool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl RevisionRepository for PgRevisionRepository {
+        async fn list_revisions(
+            &self,
+            note_id: i64,
+        ) -> Result<Vec<NoteRevisionSummary>, RepoError> {
+            let revisions = sqlx::query_as::<sqlx::Postgres, NoteRevisionSummary>(
+                r#"SELECT revision,
+                          title,
+                          editor_id,
+                          EXTRACT(EPOCH FROM created_at)::bigint as created_at
+                   FROM note_revisions
+                   WHERE note_id = $1
+                   ORDER BY revision DESC"#,
+            )
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(revisions)
+        }
+
+        async fn find_revision(
+            &self,
+            note_id: i64,
+            revision: i64,
+        ) -> Result<Option<NoteRevision>, RepoError> {
+            let revision = sqlx::query_as::<sqlx::Postgres, NoteRevision>(
+                r#"SELECT note_id,
+                          revision,
+                          title,
+                          content,
+                          editor_id,
+                          EXTRACT(EPOCH FROM created_at)::bigint as created_at
+                   FROM note_revisions
+                   WHERE note_id = $1 AND revision = $2"#,
+            )
+            .bind(note_id)
+            .bind(revision)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(revision)
+        }
+    }
+}
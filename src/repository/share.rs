@@ -0,0 +1,414 @@
+use crate::domain::model::{NoteShare, ShareLink, SharePermission};
+use crate::repository::user::RepoError;
+
+/// 共有リンクの作成内容。
+pub struct NewShareLink<'a> {
+    pub permission: SharePermission,
+    pub token_hash: &'a str,
+    pub password_hash: Option<&'a str>,
+    pub ttl_secs: Option<i64>, // None なら無期限
+}
+
+/// トークンから引いた有効な共有リンク。パスワードの照合は呼び出し側で行う。
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ResolvedShareLink {
+    pub note_id: i64,
+    #[sqlx(try_from = "String")]
+    pub permission: SharePermission,
+    pub password_hash: Option<String>,
+}
+
+/// メモの個別の共有と共有リンク。権限の確認は呼び出し側（`ShareService`）で行う。
+#[async_trait::async_trait]
+pub trait ShareRepository: Send + Sync + 'static {
+    /// `user_id` とメモを共有する。既に共有していれば権限を置き換える。
+    async fn share_note(
+        &self,
+        note_id: i64,
+        user_id: i64,
+        permission: SharePermission,
+        granted_by: i64,
+    ) -> Result<NoteShare, RepoError>;
+    /// 共有をやめる。共有していなければ false。
+    async fn unshare_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError>;
+    /// 共有しているユーザーを共有した順に返す。
+    async fn list_shares(&self, note_id: i64) -> Result<Vec<NoteShare>, RepoError>;
+    /// `user_id` に共有されている権限（共有されていなければ `None`）。
+    async fn shared_permission(
+        &self,
+        note_id: i64,
+        user_id: i64,
+    ) -> Result<Option<SharePermission>, RepoError>;
+    async fn create_link(
+        &self,
+        note_id: i64,
+        created_by: i64,
+        link: &NewShareLink<'_>,
+    ) -> Result<ShareLink, RepoError>;
+    /// メモの共有リンクを作成した順に返す（有効期限切れを含む）。
+    async fn list_links(&self, note_id: i64) -> Result<Vec<ShareLink>, RepoError>;
+    /// 共有リンクを取り消す（削除する）。該当するリンクが無ければ false。
+    async fn delete_link(&self, note_id: i64, link_id: i64) -> Result<bool, RepoError>;
+    /// 有効期限内の共有リンクを返す。
+    async fn find_link(&self, token_hash: &str) -> Result<Option<ResolvedShareLink>, RepoError>;
+}
+
+// SQLite 実装をモジュールにまとめる
+#[cfg(not(feature = "postgres"))]
+pub use sqlite::SqliteShareRepository;
+
+#[cfg(not(feature = "postgres"))]
+pub mod sqlite {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    const SELECT_LINK: &str = r#"SELECT id, note_id, permission,
+                                        password_hash IS NOT NULL as has_password,
+                                        created_by, created_at, expires_at
+                                 FROM note_share_links"#;
+
+    pub struct SqliteShareRepository {
+        pub(crate) pool: SqlitePool,
+    }
+
+    impl SqliteShareRepository {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ShareRepository for SqliteShareRepository {
+        async fn share_note(
+            &self,
+            note_id: i64,
+            user_id: i64,
+            permission: SharePermission,
+            granted_by: i64,
+        ) -> Result<NoteShare, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO note_shares (note_id, user_id, permission, granted_by, created_at)
+                   VALUES (?, ?, ?, ?, strftime('%s','now'))
+                   ON CONFLICT (note_id, user_id)
+                   DO UPDATE SET permission = excluded.permission,
+                                 granted_by = excluded.granted_by"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .bind(permission.as_str())
+            .bind(granted_by)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let share = sqlx::query_as::<sqlx::Sqlite, NoteShare>(
+                r#"SELECT s.user_id, u.email, s.permission, s.created_at
+                   FROM note_shares s
+                   JOIN users u ON u.id = s.user_id
+                   WHERE s.note_id = ? AND s.user_id = ?"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(share)
+        }
+
+        async fn unshare_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"DELETE FROM note_shares WHERE note_id = ? AND user_id = ?"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn list_shares(&self, note_id: i64) -> Result<Vec<NoteShare>, RepoError> {
+            let shares = sqlx::query_as::<sqlx::Sqlite, NoteShare>(
+                r#"SELECT s.user_id, u.email, s.permission, s.created_at
+                   FROM note_shares s
+                   JOIN users u ON u.id = s.user_id
+                   WHERE s.note_id = ?
+                   ORDER BY s.created_at, s.user_id"#,
+            )
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(shares)
+        }
+
+        async fn shared_permission(
+            &self,
+            note_id: i64,
+            user_id: i64,
+        ) -> Result<Option<SharePermission>, RepoError> {
+            let permission = sqlx::query_scalar::<sqlx::Sqlite, String>(
+                r#"SELECT permission FROM note_shares WHERE note_id = ? AND user_id = ?"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            permission
+                .map(SharePermission::try_from)
+                .transpose()
+                .map_err(|_| RepoError::Internal)
+        }
+
+        async fn create_link(
+            &self,
+            note_id: i64,
+            created_by: i64,
+            link: &NewShareLink<'_>,
+        ) -> Result<ShareLink, RepoError> {
+            let link = sqlx::query_as::<sqlx::Sqlite, ShareLink>(
+                r#"INSERT INTO note_share_links
+                       (note_id, token_hash, permission, password_hash, created_by,
+                        created_at, expires_at)
+                   VALUES (?, ?, ?, ?, ?, strftime('%s','now'), strftime('%s','now') + ?)
+                   RETURNING id, note_id, permission,
+                             password_hash IS NOT NULL as has_password,
+                             created_by, created_at, expires_at"#,
+            )
+            .bind(note_id)
+            .bind(link.token_hash)
+            .bind(link.permission.as_str())
+            .bind(link.password_hash)
+            .bind(created_by)
+            .bind(link.ttl_secs)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(link)
+        }
+
+        async fn list_links(&self, note_id: i64) -> Result<Vec<ShareLink>, RepoError> {
+            let links = sqlx::query_as::<sqlx::Sqlite, ShareLink>(&format!(
+                "{SELECT_LINK} WHERE note_id = ? ORDER BY id"
+            ))
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(links)
+        }
+
+        async fn delete_link(&self, note_id: i64, link_id: i64) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"DELETE FROM note_share_links WHERE id = ? AND note_id = ?"#,
+            )
+            .bind(link_id)
+            .bind(note_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn find_link(
+            &self,
+            token_hash: &str,
+        ) -> Result<Option<ResolvedShareLink>, RepoError> {
+            let link = sqlx::query_as::<sqlx::Sqlite, ResolvedShareLink>(
+                r#"SELECT note_id, permission, password_hash
+                   FROM note_share_links
+                   WHERE token_hash = ?
+                     AND (expires_at IS NULL OR expires_at > strftime('%s','now'))"#,
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(link)
+        }
+    }
+}
+
+// PostgreSQL 実装をモジュールにまとめる
+#[cfg(feature = "postgres")]
+pub use postgres::PgShareRepository;
+
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use super::*;
+    use sqlx::PgPool;
+
+    const SELECT_LINK: &str = r#"SELECT id, note_id, permission,
+                                        password_hash IS NOT NULL as has_password,
+                                        created_by,
+                                        EXTRACT(EPOCH FROM created_at)::bigint as created_at,
+                                        EXTRACT(EPOCH FROM expires_at)::bigint as expires_at
+                                 FROM note_share_links"#;
+
+    pub struct PgShareRepository {
+        pub(crate) pool: PgPool,
+    }
+
+    impl PgShareRepository {
+        pub fn new(pool: PgPool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ShareRepository for PgShareRepository {
+        async fn share_note(
+            &self,
+            note_id: i64,
+            user_id: i64,
+            permission: SharePermission,
+            granted_by: i64,
+        ) -> Result<NoteShare, RepoError> {
+            let share = sqlx::query_as::<sqlx::Postgres, NoteShare>(
+                r#"WITH s AS (
+                       INSERT INTO note_shares (note_id, user_id, permission, granted_by)
+                       VALUES ($1, $2, $3, $4)
+                       ON CONFLICT (note_id, user_id)
+                       DO UPDATE SET permission = EXCLUDED.permission,
+                                     granted_by = EXCLUDED.granted_by
+                       RETURNING user_id, permission, created_at
+                   )
+                   SELECT s.user_id,
+                          u.email,
+                          s.permission,
+                          EXTRACT(EPOCH FROM s.created_at)::bigint as created_at
+                   FROM s
+                   JOIN users u ON u.id = s.user_id"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .bind(permission.as_str())
+            .bind(granted_by)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(share)
+        }
+
+        async fn unshare_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM note_shares WHERE note_id = $1 AND user_id = $2"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn list_shares(&self, note_id: i64) -> Result<Vec<NoteShare>, RepoError> {
+            let shares = sqlx::query_as::<sqlx::Postgres, NoteShare>(
+                r#"SELECT s.user_id,
+                          u.email,
+                          s.permission,
+                          EXTRACT(EPOCH FROM s.created_at)::bigint as created_at
+                   FROM note_shares s
+                   JOIN users u ON u.id = s.user_id
+                   WHERE s.note_id = $1
+                   ORDER BY s.created_at, s.user_id"#,
+            )
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(shares)
+        }
+
+        async fn shared_permission(
+            &self,
+            note_id: i64,
+            user_id: i64,
+        ) -> Result<Option<SharePermission>, RepoError> {
+            let permission = sqlx::query_scalar::<sqlx::Postgres, String>(
+                r#"SELECT permission FROM note_shares WHERE note_id = $1 AND user_id = $2"#,
+            )
+            .bind(note_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            permission
+                .map(SharePermission::try_from)
+                .transpose()
+                .map_err(|_| RepoError::Internal)
+        }
+
+        async fn create_link(
+            &self,
+            note_id: i64,
+            created_by: i64,
+            link: &NewShareLink<'_>,
+        ) -> Result<ShareLink, RepoError> {
+            let link = sqlx::query_as::<sqlx::Postgres, ShareLink>(
+                r#"INSERT INTO note_share_links
+                       (note_id, token_hash, permission, password_hash, created_by, expires_at)
+                   VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(secs => $6))
+                   RETURNING id,
+                             note_id,
+                             permission,
+                             password_hash IS NOT NULL as has_password,
+                             created_by,
+                             EXTRACT(EPOCH FROM created_at)::bigint as created_at,
+                             EXTRACT(EPOCH FROM expires_at)::bigint as expires_at"#,
+            )
+            .bind(note_id)
+            .bind(link.token_hash)
+            .bind(link.permission.as_str())
+            .bind(link.password_hash)
+            .bind(created_by)
+            .bind(link.ttl_secs.map(|secs| secs as f64))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(link)
+        }
+
+        async fn list_links(&self, note_id: i64) -> Result<Vec<ShareLink>, RepoError> {
+            let links = sqlx::query_as::<sqlx::Postgres, ShareLink>(&format!(
+                "{SELECT_LINK} WHERE note_id = $1 ORDER BY id"
+            ))
+            .bind(note_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(links)
+        }
+
+        async fn delete_link(&self, note_id: i64, link_id: i64) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM note_share_links WHERE id = $1 AND note_id = $2"#,
+            )
+            .bind(link_id)
+            .bind(note_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn find_link(
+            &self,
+            token_hash: &str,
+        ) -> Result<Option<ResolvedShareLink>, RepoError> {
+            let link = sqlx::query_as::<sqlx::Postgres, ResolvedShareLink>(
+                r#"SELECT note_id, permission, password_hash
+                   FROM note_share_links
+                   WHERE token_hash = $1
+                     AND (expires_at IS NULL OR expires_at > NOW())"#,
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(link)
+        }
+    }
+}
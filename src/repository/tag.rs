@@ -0,0 +1,285 @@
+use crate::domain::model::TagCount;
+use crate::repository::user::RepoError;
+
+/// ユーザーごとのタグ管理。メモへのタグ付け自体は `NoteRepository` が行う。
+#[async_trait::async_trait]
+pub trait TagRepository: Send + Sync + 'static {
+    /// ユーザーのタグを名前順に、付いているメモの件数（ゴミ箱内のメモは除く）とともに返す。
+    async fn list_tags(&self, user_id: i64) -> Result<Vec<TagCount>, RepoError>;
+    /// タグ名を変更する。
+    /// - Ok(false): 変更元のタグが存在しない
+    /// - Err(Conflict): 変更先の名前が既に存在する（統合は `merge_tags` を使う）
+    async fn rename_tag(&self, user_id: i64, from: &str, to: &str) -> Result<bool, RepoError>;
+    /// `sources` のタグを `target` に統合し、`sources` を削除する。
+    /// `target` が存在しなければ作成する。削除したタグの数を返す。
+    async fn merge_tags(
+        &self,
+        user_id: i64,
+        sources: &[String],
+        target: &str,
+    ) -> Result<u64, RepoError>;
+    /// タグを削除する（メモからも外れる）。
+    async fn delete_tag(&self, user_id: i64, name: &str) -> Result<bool, RepoError>;
+}
+
+fn map_unique_violation(e: sqlx::Error) -> RepoError {
+    if let sqlx::Error::Database(db_err) = &e
+        && db_err.is_unique_violation()
+    {
+        return RepoError::Conflict;
+    }
+    RepoError::DbError(e)
+}
+
+// SQLite 実装をモジュールにまとめる
+#[cfg(not(feature = "postgres"))]
+pub use sqlite::SqliteTagRepository;
+
+#[cfg(not(feature = "postgres"))]
+pub mod sqlite {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    pub struct SqliteTagRepository {
+        pub(crate) pool: SqlitePool,
+    }
+
+    impl SqliteTagRepository {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TagRepository for SqliteTagRepository {
+        async fn list_tags(&self, user_id: i64) -> Result<Vec<TagCount>, RepoError> {
+            let tags = sqlx::query_as::<sqlx::Sqlite, TagCount>(
+                r#"SELECT t.name, COUNT(n.id) as count
+                   FROM tags t
+                   LEFT JOIN note_tags nt ON nt.tag_id = t.id
+                   LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL
+                   WHERE t.user_id = ?
+                   GROUP BY t.id, t.name
+                   ORDER BY t.name"#,
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(tags)
+        }
+
+        async fn rename_tag(&self, user_id: i64, from: &str, to: &str) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE tags SET name = ? WHERE user_id = ? AND name = ?"#,
+            )
+            .bind(to)
+            .bind(user_id)
+            .bind(from)
+            .execute(&self.pool)
+            .await
+            .map_err(map_unique_violation)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn merge_tags(
+            &self,
+            user_id: i64,
+            sources: &[String],
+            target: &str,
+        ) -> Result<u64, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO tags (user_id, name, created_at)
+                   VALUES (?, ?, strftime('%s','now'))
+                   ON CONFLICT (user_id, name) DO NOTHING"#,
+            )
+            .bind(user_id)
+            .bind(target)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let target_id: i64 = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+                r#"SELECT id FROM tags WHERE user_id = ? AND name = ?"#,
+            )
+            .bind(user_id)
+            .bind(target)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+
+            let mut merged = 0;
+            for source in sources.iter().filter(|s| s.as_str() != target) {
+                let Some(source_id) = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+                    r#"SELECT id FROM tags WHERE user_id = ? AND name = ?"#,
+                )
+                .bind(user_id)
+                .bind(source)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?
+                else {
+                    continue;
+                };
+                sqlx::query::<sqlx::Sqlite>(
+                    r#"INSERT INTO note_tags (note_id, tag_id)
+                       SELECT note_id, ? FROM note_tags WHERE tag_id = ?
+                       ON CONFLICT DO NOTHING"#,
+                )
+                .bind(target_id)
+                .bind(source_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?;
+                sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM note_tags WHERE tag_id = ?"#)
+                    .bind(source_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM tags WHERE id = ?"#)
+                    .bind(source_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+                merged += 1;
+            }
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(merged)
+        }
+
+        async fn delete_tag(&self, user_id: i64, name: &str) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Sqlite>(
+                r#"DELETE FROM note_tags
+                   WHERE tag_id IN (SELECT id FROM tags WHERE user_id = ? AND name = ?)"#,
+            )
+            .bind(user_id)
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let result =
+                sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM tags WHERE user_id = ? AND name = ?"#)
+                    .bind(user_id)
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+    }
+}
+
+// PostgreSQL 実装をモジュールにまとめる
+#[cfg(feature = "postgres")]
+pub use postgres::PgTagRepository;
+
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use super::*;
+    use sqlx::PgPool;
+
+    pub struct PgTagRepository {
+        pub(crate) pool: PgPool,
+    }
+
+    impl PgTagRepository {
+        pub fn new(pool: PgPool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TagRepository for PgTagRepository {
+        async fn list_tags(&self, user_id: i64) -> Result<Vec<TagCount>, RepoError> {
+            let tags = sqlx::query_as::<sqlx::Postgres, TagCount>(
+                r#"SELECT t.name, COUNT(n.id) as count
+                   FROM tags t
+                   LEFT JOIN note_tags nt ON nt.tag_id = t.id
+                   LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL
+                   WHERE t.user_id = $1
+                   GROUP BY t.id, t.name
+                   ORDER BY t.name"#,
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(tags)
+        }
+
+        async fn rename_tag(&self, user_id: i64, from: &str, to: &str) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE tags SET name = $1 WHERE user_id = $2 AND name = $3"#,
+            )
+            .bind(to)
+            .bind(user_id)
+            .bind(from)
+            .execute(&self.pool)
+            .await
+            .map_err(map_unique_violation)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn merge_tags(
+            &self,
+            user_id: i64,
+            sources: &[String],
+            target: &str,
+        ) -> Result<u64, RepoError> {
+            let sources: Vec<&str> = sources
+                .iter()
+                .map(String::as_str)
+                .filter(|s| *s != target)
+                .collect();
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let target_id: i64 = sqlx::query_scalar::<sqlx::Postgres, i64>(
+                r#"INSERT INTO tags (user_id, name) VALUES ($1, $2)
+                   ON CONFLICT (user_id, name) DO UPDATE SET name = EXCLUDED.name
+                   RETURNING id"#,
+            )
+            .bind(user_id)
+            .bind(target)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO note_tags (note_id, tag_id)
+                   SELECT nt.note_id, $1
+                   FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                   WHERE t.user_id = $2 AND t.name = ANY($3)
+                   ON CONFLICT DO NOTHING"#,
+            )
+            .bind(target_id)
+            .bind(user_id)
+            .bind(&sources)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            // note_tags は ON DELETE CASCADE で消える
+            let result = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM tags WHERE user_id = $1 AND name = ANY($2)"#,
+            )
+            .bind(user_id)
+            .bind(&sources)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(result.rows_affected())
+        }
+
+        async fn delete_tag(&self, user_id: i64, name: &str) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM tags WHERE user_id = $1 AND name = $2"#,
+            )
+            .bind(user_id)
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+    }
+}
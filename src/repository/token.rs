@@ -0,0 +1,364 @@
+use crate::domain::model::RefreshToken;
+use crate::repository::user::RepoError;
+
+/// リフレッシュトークンとアクセストークンの失効リスト。
+#[async_trait::async_trait]
+pub trait TokenRepository: Send + Sync + 'static {
+    async fn create_refresh_token(
+        &self,
+        user_id: i64,
+        family_id: &str,
+        token_hash: &str,
+        ttl_secs: i64,
+    ) -> Result<(), RepoError>;
+    async fn find_refresh_token(&self, token_hash: &str)
+    -> Result<Option<RefreshToken>, RepoError>;
+    /// 未使用のトークンを使用済みにする。既に使用済みなら false
+    /// （同時に使われた場合も true になるのは 1 回だけ）。
+    async fn mark_refresh_token_used(&self, id: i64) -> Result<bool, RepoError>;
+    /// 同じファミリーのトークンをすべて失効させ、件数を返す。
+    async fn revoke_token_family(&self, family_id: &str) -> Result<u64, RepoError>;
+    /// ユーザーのリフレッシュトークンをすべて失効させ、件数を返す（全端末からのログアウト）。
+    async fn revoke_user_refresh_tokens(&self, user_id: i64) -> Result<u64, RepoError>;
+    /// アクセストークンを `expires_at`（そのトークンの exp）まで失効リストに載せる。
+    async fn revoke_access_token(&self, jti: &str, expires_at: i64) -> Result<(), RepoError>;
+    /// ユーザーにこれまでに発行したアクセストークンをすべて失効させる
+    /// （`users.tokens_valid_after` を現在時刻の 1 秒後にし、それより前に発行したトークンを受け付けない。
+    /// `iat` は秒単位なので、同じ秒のうちに発行したトークンも失効させる）。
+    async fn revoke_user_access_tokens(&self, user_id: i64) -> Result<(), RepoError>;
+    /// `jti` が失効リストに載っているか、ユーザー `user_id` が無効にされているか、
+    /// `issued_at` が `users.tokens_valid_after` より前なら true。
+    async fn is_access_token_revoked(
+        &self,
+        jti: &str,
+        user_id: i64,
+        issued_at: i64,
+    ) -> Result<bool, RepoError>;
+    /// 有効期限が `now` 以前の失効リストの行とリフレッシュトークンを削除し、件数を返す。
+    async fn purge_expired(&self, now: i64) -> Result<u64, RepoError>;
+}
+
+// SQLite 実装をモジュールにまとめる
+#[cfg(not(feature = "postgres"))]
+pub use sqlite::SqliteTokenRepository;
+
+#[cfg(not(feature = "postgres"))]
+pub mod sqlite {
+    use super::*;
+    use sqlx::SqlitePool;
+
+    pub struct SqliteTokenRepository {
+        pub(crate) pool: SqlitePool,
+    }
+
+    impl SqliteTokenRepository {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TokenRepository for SqliteTokenRepository {
+        async fn create_refresh_token(
+            &self,
+            user_id: i64,
+            family_id: &str,
+            token_hash: &str,
+            ttl_secs: i64,
+        ) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at, created_at)
+                   VALUES (?, ?, ?, strftime('%s','now') + ?, strftime('%s','now'))"#,
+            )
+            .bind(user_id)
+            .bind(family_id)
+            .bind(token_hash)
+            .bind(ttl_secs)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn find_refresh_token(
+            &self,
+            token_hash: &str,
+        ) -> Result<Option<RefreshToken>, RepoError> {
+            let token = sqlx::query_as::<sqlx::Sqlite, RefreshToken>(
+                r#"SELECT id, user_id, family_id, expires_at, used_at, revoked_at
+                   FROM refresh_tokens WHERE token_hash = ?"#,
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(token)
+        }
+
+        async fn mark_refresh_token_used(&self, id: i64) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE refresh_tokens SET used_at = strftime('%s','now')
+                   WHERE id = ? AND used_at IS NULL AND revoked_at IS NULL"#,
+            )
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn revoke_token_family(&self, family_id: &str) -> Result<u64, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE refresh_tokens SET revoked_at = strftime('%s','now')
+                   WHERE family_id = ? AND revoked_at IS NULL"#,
+            )
+            .bind(family_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected())
+        }
+
+        async fn revoke_user_refresh_tokens(&self, user_id: i64) -> Result<u64, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE refresh_tokens SET revoked_at = strftime('%s','now')
+                   WHERE user_id = ? AND revoked_at IS NULL"#,
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected())
+        }
+
+        async fn revoke_access_token(&self, jti: &str, expires_at: i64) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO revoked_tokens (jti, expires_at) VALUES (?, ?)
+                   ON CONFLICT (jti) DO NOTHING"#,
+            )
+            .bind(jti)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn revoke_user_access_tokens(&self, user_id: i64) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE users SET tokens_valid_after = strftime('%s','now') + 1 WHERE id = ?"#,
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn is_access_token_revoked(
+            &self,
+            jti: &str,
+            user_id: i64,
+            issued_at: i64,
+        ) -> Result<bool, RepoError> {
+            let revoked = sqlx::query_scalar::<sqlx::Sqlite, bool>(
+                r#"SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = ?1)
+                       OR EXISTS (SELECT 1 FROM users
+                                  WHERE id = ?2
+                                    AND (disabled_at IS NOT NULL OR tokens_valid_after > ?3))"#,
+            )
+            .bind(jti)
+            .bind(user_id)
+            .bind(issued_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(revoked)
+        }
+
+        async fn purge_expired(&self, now: i64) -> Result<u64, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let revoked =
+                sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM revoked_tokens WHERE expires_at <= ?"#)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+            let refresh =
+                sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM refresh_tokens WHERE expires_at <= ?"#)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(revoked.rows_affected() + refresh.rows_affected())
+        }
+    }
+}
+
+// PostgreSQL 実装をモジュールにまとめる
+#[cfg(feature = "postgres")]
+pub use postgres::PgTokenRepository;
+
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use super::*;
+    use sqlx::PgPool;
+
+    pub struct PgTokenRepository {
+        pub(crate) pool: PgPool,
+    }
+
+    impl PgTokenRepository {
+        pub fn new(pool: PgPool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TokenRepository for PgTokenRepository {
+        async fn create_refresh_token(
+            &self,
+            user_id: i64,
+            family_id: &str,
+            token_hash: &str,
+            ttl_secs: i64,
+        ) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+                   VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))"#,
+            )
+            .bind(user_id)
+            .bind(family_id)
+            .bind(token_hash)
+            .bind(ttl_secs as f64)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn find_refresh_token(
+            &self,
+            token_hash: &str,
+        ) -> Result<Option<RefreshToken>, RepoError> {
+            let token = sqlx::query_as::<sqlx::Postgres, RefreshToken>(
+                r#"SELECT id,
+                          user_id,
+                          family_id,
+                          EXTRACT(EPOCH FROM expires_at)::bigint as expires_at,
+                          EXTRACT(EPOCH FROM used_at)::bigint as used_at,
+                          EXTRACT(EPOCH FROM revoked_at)::bigint as revoked_at
+                   FROM refresh_tokens WHERE token_hash = $1"#,
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(token)
+        }
+
+        async fn mark_refresh_token_used(&self, id: i64) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE refresh_tokens SET used_at = NOW()
+                   WHERE id = $1 AND used_at IS NULL AND revoked_at IS NULL"#,
+            )
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn revoke_token_family(&self, family_id: &str) -> Result<u64, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE refresh_tokens SET revoked_at = NOW()
+                   WHERE family_id = $1 AND revoked_at IS NULL"#,
+            )
+            .bind(family_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected())
+        }
+
+        async fn revoke_user_refresh_tokens(&self, user_id: i64) -> Result<u64, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE refresh_tokens SET revoked_at = NOW()
+                   WHERE user_id = $1 AND revoked_at IS NULL"#,
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected())
+        }
+
+        async fn revoke_access_token(&self, jti: &str, expires_at: i64) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO revoked_tokens (jti, expires_at) VALUES ($1, to_timestamp($2))
+                   ON CONFLICT (jti) DO NOTHING"#,
+            )
+            .bind(jti)
+            .bind(expires_at as f64)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn revoke_user_access_tokens(&self, user_id: i64) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE users SET tokens_valid_after = date_trunc('second', NOW()) + INTERVAL '1 second'
+                   WHERE id = $1"#,
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn is_access_token_revoked(
+            &self,
+            jti: &str,
+            user_id: i64,
+            issued_at: i64,
+        ) -> Result<bool, RepoError> {
+            let revoked = sqlx::query_scalar::<sqlx::Postgres, bool>(
+                r#"SELECT EXISTS (SELECT 1 FROM revoked_tokens WHERE jti = $1)
+                       OR EXISTS (SELECT 1 FROM users
+                                  WHERE id = $2
+                                    AND (disabled_at IS NOT NULL
+                                         OR tokens_valid_after > to_timestamp($3)))"#,
+            )
+            .bind(jti)
+            .bind(user_id)
+            .bind(issued_at as f64)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(revoked)
+        }
+
+        async fn purge_expired(&self, now: i64) -> Result<u64, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let revoked = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM revoked_tokens WHERE expires_at <= to_timestamp($1)"#,
+            )
+            .bind(now as f64)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let refresh = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM refresh_tokens WHERE expires_at <= to_timestamp($1)"#,
+            )
+            .bind(now as f64)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(revoked.rows_affected() + refresh.rows_affected())
+        }
+    }
+}
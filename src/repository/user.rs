@@ -1,4 +1,5 @@
-use crate::domain::model::User;
+use crate::domain::model::{ApiToken, Role, Scope, TotpCredential, User, UserTokenPurpose};
+use sqlx::types::Json;
 use thiserror::Error;
 
 pub const USERS_EMAIL_UNIQUE_CONSTRAINT: &str = "users_email_key"; // unique index/constraint name
@@ -11,6 +12,95 @@ pub trait UserRepository: Send + Sync + 'static {
         password_hash: &str,
     ) -> Result<Option<User>, RepoError>;
     async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError>;
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError>;
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<bool, RepoError>;
+    /// 確認済みにする。既に確認済みなら `false`。
+    async fn mark_email_verified(&self, user_id: i64) -> Result<bool, RepoError>;
+    /// ログインの失敗を記録し、連続した失敗の回数を返す。
+    async fn record_login_failure(&self, user_id: i64) -> Result<i64, RepoError>;
+    /// `locked_until`（UNIX 秒）までログインを受け付けない。
+    async fn lock_user_until(&self, user_id: i64, locked_until: i64) -> Result<(), RepoError>;
+    /// ログインに成功したら失敗の回数とロックを消す。
+    async fn reset_login_failures(&self, user_id: i64) -> Result<(), RepoError>;
+}
+
+/// パスワード再設定・メール確認・2 段階認証のログインに使う 1 回限りのトークン。
+#[async_trait::async_trait]
+pub trait UserTokenRepository: Send + Sync + 'static {
+    /// 同じユーザー・用途の未使用のトークンは使用済みにしてから発行する（最新の 1 つだけが有効）。
+    async fn create_user_token(
+        &self,
+        user_id: i64,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+        ttl_secs: i64,
+    ) -> Result<(), RepoError>;
+    /// 有効期限内の未使用トークンなら使用済みにして `user_id` を返す。
+    async fn consume_user_token(
+        &self,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<i64>, RepoError>;
+}
+
+/// TOTP による 2 段階認証の設定とリカバリーコード。
+#[async_trait::async_trait]
+pub trait TwoFactorRepository: Send + Sync + 'static {
+    /// 登録途中の設定を作る（やり直しなら秘密鍵を差し替える）。既に有効なら `false`。
+    async fn start_totp_enrollment(&self, user_id: i64, secret: &str) -> Result<bool, RepoError>;
+    async fn find_totp(&self, user_id: i64) -> Result<Option<TotpCredential>, RepoError>;
+    /// 登録途中の設定を有効にし、リカバリーコードを置き換える。既に有効なら `false`。
+    async fn enable_totp(
+        &self,
+        user_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<bool, RepoError>;
+    /// `step` が最後に使われたステップより新しければ記録して `true`（同じコードの再利用を防ぐ）。
+    async fn use_totp_step(&self, user_id: i64, step: i64) -> Result<bool, RepoError>;
+    /// 未使用のリカバリーコードなら使用済みにして `true`。
+    async fn use_recovery_code(&self, user_id: i64, code_hash: &str) -> Result<bool, RepoError>;
+}
+
+/// 管理用 API（`/admin`）からのユーザーの検索と無効化。
+#[async_trait::async_trait]
+pub trait UserAdminRepository: Send + Sync + 'static {
+    /// メールアドレスに `query` を含む（大文字・小文字は区別しない）ユーザーを ID 順に返す。
+    /// `after` より大きい ID から最大 `limit` 件（キーセット方式のページング）。
+    async fn list_users(
+        &self,
+        query: Option<&str>,
+        after: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<User>, RepoError>;
+    /// アカウントを無効にする（`disabled` が false なら有効に戻す）。既に同じ状態でもよい。
+    /// ユーザーが存在しなければ `None`。
+    async fn set_user_disabled(
+        &self,
+        user_id: i64,
+        disabled: bool,
+    ) -> Result<Option<User>, RepoError>;
+}
+
+/// ユーザーが発行する API トークン。
+#[async_trait::async_trait]
+pub trait ApiTokenRepository: Send + Sync + 'static {
+    /// `ttl_secs` が `None` なら無期限。
+    async fn create_api_token(
+        &self,
+        user_id: i64,
+        name: &str,
+        token_hash: &str,
+        scopes: &[Scope],
+        ttl_secs: Option<i64>,
+    ) -> Result<ApiToken, RepoError>;
+    async fn list_api_tokens(&self, user_id: i64) -> Result<Vec<ApiToken>, RepoError>;
+    async fn delete_api_token(&self, user_id: i64, token_id: i64) -> Result<bool, RepoError>;
+    /// ユーザーのトークンをすべて削除し、件数を返す。
+    async fn delete_user_api_tokens(&self, user_id: i64) -> Result<u64, RepoError>;
+    /// 有効期限内で、持ち主のアカウントが無効にされていなければ最終使用日時を更新して返す。
+    async fn authenticate_api_token(&self, token_hash: &str)
+    -> Result<Option<ApiToken>, RepoError>;
 }
 
 #[allow(dead_code)]
@@ -19,9 +109,12 @@ pub enum RepoError {
     #[error("database error: {0}")]
     DbError(#[from] sqlx::Error),
 
-    #[error("email already exists")]
+    #[error("already exists")]
     Conflict,
 
+    #[error("version mismatch")]
+    VersionMismatch,
+
     #[error("internal error")]
     Internal,
 }
@@ -52,11 +145,11 @@ pub mod sqlite {
             email: &str,
             password_hash: &str,
         ) -> Result<Option<User>, RepoError> {
-            let inserted = sqlx::query_as::<sqlx::Sqlite, User>(
+            let inserted = sqlx::query_as::<sqlx::Sqlite, User>(&format!(
                 r#"INSERT INTO users (email, password_hash, created_at)
                    VALUES (?, ?, strftime('%s','now'))
-                   RETURNING id, email, password_hash, created_at"#,
-            )
+                   RETURNING {USER_COLUMNS}"#
+            ))
             .bind(email)
             .bind(password_hash)
             .fetch_one(&self.pool)
@@ -77,9 +170,9 @@ pub mod sqlite {
         }
 
         async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError> {
-            let user = sqlx::query_as::<_, User>(
-                r#"SELECT id, email, password_hash, created_at FROM users WHERE email = ?"#,
-            )
+            let user = sqlx::query_as::<_, User>(&format!(
+                r#"SELECT {USER_COLUMNS} FROM users WHERE email = ?"#
+            ))
             .bind(email)
             .fetch_optional(&self.pool)
             .await
@@ -87,6 +180,367 @@ pub mod sqlite {
 
             Ok(user)
         }
+
+        async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+            let user = sqlx::query_as::<_, User>(&format!(
+                r#"SELECT {USER_COLUMNS} FROM users WHERE id = ?"#
+            ))
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(user)
+        }
+
+        async fn update_password(
+            &self,
+            user_id: i64,
+            password_hash: &str,
+        ) -> Result<bool, RepoError> {
+            let result =
+                sqlx::query::<sqlx::Sqlite>(r#"UPDATE users SET password_hash = ? WHERE id = ?"#)
+                    .bind(password_hash)
+                    .bind(user_id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn mark_email_verified(&self, user_id: i64) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE users SET email_verified_at = strftime('%s','now')
+                   WHERE id = ? AND email_verified_at IS NULL"#,
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn record_login_failure(&self, user_id: i64) -> Result<i64, RepoError> {
+            let count = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+                r#"UPDATE users SET failed_login_count = failed_login_count + 1
+                   WHERE id = ?
+                   RETURNING failed_login_count"#,
+            )
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(count)
+        }
+
+        async fn lock_user_until(&self, user_id: i64, locked_until: i64) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Sqlite>(r#"UPDATE users SET locked_until = ? WHERE id = ?"#)
+                .bind(locked_until)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await
+                .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn reset_login_failures(&self, user_id: i64) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE users SET failed_login_count = 0, locked_until = NULL WHERE id = ?"#,
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+    }
+
+    const USER_COLUMNS: &str = "id, email, password_hash, created_at, email_verified_at, \
+        failed_login_count, locked_until, role, disabled_at";
+
+    #[async_trait::async_trait]
+    impl UserAdminRepository for SqliteUserRepository {
+        async fn list_users(
+            &self,
+            query: Option<&str>,
+            after: Option<i64>,
+            limit: i64,
+        ) -> Result<Vec<User>, RepoError> {
+            let users = sqlx::query_as::<sqlx::Sqlite, User>(&format!(
+                r#"SELECT {USER_COLUMNS} FROM users
+                   WHERE (?1 IS NULL OR instr(lower(email), lower(?1)) > 0) AND id > ?2
+                   ORDER BY id
+                   LIMIT ?3"#
+            ))
+            .bind(query)
+            .bind(after.unwrap_or(0))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(users)
+        }
+
+        async fn set_user_disabled(
+            &self,
+            user_id: i64,
+            disabled: bool,
+        ) -> Result<Option<User>, RepoError> {
+            let user = sqlx::query_as::<sqlx::Sqlite, User>(&format!(
+                r#"UPDATE users
+                   SET disabled_at = CASE WHEN ? THEN COALESCE(disabled_at, strftime('%s','now'))
+                                          ELSE NULL END
+                   WHERE id = ?
+                   RETURNING {USER_COLUMNS}"#
+            ))
+            .bind(disabled)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(user)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl UserTokenRepository for SqliteUserRepository {
+        async fn create_user_token(
+            &self,
+            user_id: i64,
+            purpose: UserTokenPurpose,
+            token_hash: &str,
+            ttl_secs: i64,
+        ) -> Result<(), RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE user_tokens SET used_at = strftime('%s','now')
+                   WHERE user_id = ? AND purpose = ? AND used_at IS NULL"#,
+            )
+            .bind(user_id)
+            .bind(purpose.as_str())
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO user_tokens (user_id, purpose, token_hash, created_at, expires_at)
+                   VALUES (?, ?, ?, strftime('%s','now'), strftime('%s','now') + ?)"#,
+            )
+            .bind(user_id)
+            .bind(purpose.as_str())
+            .bind(token_hash)
+            .bind(ttl_secs)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn consume_user_token(
+            &self,
+            purpose: UserTokenPurpose,
+            token_hash: &str,
+        ) -> Result<Option<i64>, RepoError> {
+            let user_id = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+                r#"UPDATE user_tokens SET used_at = strftime('%s','now')
+                   WHERE token_hash = ? AND purpose = ?
+                     AND used_at IS NULL AND expires_at > strftime('%s','now')
+                   RETURNING user_id"#,
+            )
+            .bind(token_hash)
+            .bind(purpose.as_str())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(user_id)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TwoFactorRepository for SqliteUserRepository {
+        async fn start_totp_enrollment(
+            &self,
+            user_id: i64,
+            secret: &str,
+        ) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO user_totp (user_id, secret, created_at)
+                   VALUES (?, ?, strftime('%s','now'))
+                   ON CONFLICT (user_id) DO UPDATE
+                     SET secret = excluded.secret, created_at = excluded.created_at,
+                         last_used_step = NULL
+                     WHERE user_totp.enabled_at IS NULL"#,
+            )
+            .bind(user_id)
+            .bind(secret)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn find_totp(&self, user_id: i64) -> Result<Option<TotpCredential>, RepoError> {
+            let totp = sqlx::query_as::<sqlx::Sqlite, TotpCredential>(
+                r#"SELECT user_id, secret, enabled_at, last_used_step
+                   FROM user_totp WHERE user_id = ?"#,
+            )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(totp)
+        }
+
+        async fn enable_totp(
+            &self,
+            user_id: i64,
+            step: i64,
+            recovery_code_hashes: &[String],
+        ) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let enabled = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE user_totp SET enabled_at = strftime('%s','now'), last_used_step = ?
+                   WHERE user_id = ? AND enabled_at IS NULL"#,
+            )
+            .bind(step)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            if enabled.rows_affected() == 0 {
+                return Ok(false);
+            }
+            sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM totp_recovery_codes WHERE user_id = ?"#)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?;
+            for hash in recovery_code_hashes {
+                sqlx::query::<sqlx::Sqlite>(
+                    r#"INSERT INTO totp_recovery_codes (user_id, code_hash) VALUES (?, ?)"#,
+                )
+                .bind(user_id)
+                .bind(hash)
+                .execute(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?;
+            }
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(true)
+        }
+
+        async fn use_totp_step(&self, user_id: i64, step: i64) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE user_totp SET last_used_step = ?
+                   WHERE user_id = ? AND enabled_at IS NOT NULL
+                     AND (last_used_step IS NULL OR last_used_step < ?)"#,
+            )
+            .bind(step)
+            .bind(user_id)
+            .bind(step)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn use_recovery_code(
+            &self,
+            user_id: i64,
+            code_hash: &str,
+        ) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE totp_recovery_codes SET used_at = strftime('%s','now')
+                   WHERE user_id = ? AND code_hash = ? AND used_at IS NULL"#,
+            )
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+    }
+
+    const API_TOKEN_COLUMNS: &str =
+        "id, user_id, name, scopes, created_at, expires_at, last_used_at";
+
+    #[async_trait::async_trait]
+    impl ApiTokenRepository for SqliteUserRepository {
+        async fn create_api_token(
+            &self,
+            user_id: i64,
+            name: &str,
+            token_hash: &str,
+            scopes: &[Scope],
+            ttl_secs: Option<i64>,
+        ) -> Result<ApiToken, RepoError> {
+            let token = sqlx::query_as::<sqlx::Sqlite, ApiToken>(&format!(
+                r#"INSERT INTO api_tokens (user_id, name, token_hash, scopes, created_at, expires_at)
+                   VALUES (?, ?, ?, ?, strftime('%s','now'), strftime('%s','now') + ?)
+                   RETURNING {API_TOKEN_COLUMNS}"#
+            ))
+            .bind(user_id)
+            .bind(name)
+            .bind(token_hash)
+            .bind(Json(scopes))
+            .bind(ttl_secs)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(token)
+        }
+
+        async fn list_api_tokens(&self, user_id: i64) -> Result<Vec<ApiToken>, RepoError> {
+            let tokens = sqlx::query_as::<sqlx::Sqlite, ApiToken>(&format!(
+                r#"SELECT {API_TOKEN_COLUMNS} FROM api_tokens
+                   WHERE user_id = ?
+                   ORDER BY created_at DESC, id DESC"#
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(tokens)
+        }
+
+        async fn delete_api_token(&self, user_id: i64, token_id: i64) -> Result<bool, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"DELETE FROM api_tokens WHERE id = ? AND user_id = ?"#,
+            )
+            .bind(token_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn delete_user_api_tokens(&self, user_id: i64) -> Result<u64, RepoError> {
+            let result = sqlx::query::<sqlx::Sqlite>(r#"DELETE FROM api_tokens WHERE user_id = ?"#)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await
+                .map_err(RepoError::DbError)?;
+            Ok(result.rows_affected())
+        }
+
+        async fn authenticate_api_token(
+            &self,
+            token_hash: &str,
+        ) -> Result<Option<ApiToken>, RepoError> {
+            let token = sqlx::query_as::<sqlx::Sqlite, ApiToken>(&format!(
+                r#"UPDATE api_tokens SET last_used_at = strftime('%s','now')
+                   WHERE token_hash = ?
+                     AND (expires_at IS NULL OR expires_at > strftime('%s','now'))
+                     AND user_id IN (SELECT id FROM users WHERE disabled_at IS NULL)
+                   RETURNING {API_TOKEN_COLUMNS}"#
+            ))
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(token)
+        }
     }
 }
 
@@ -116,14 +570,11 @@ pub mod postgres {
             email: &str,
             password_hash: &str,
         ) -> Result<Option<User>, RepoError> {
-            let inserted = sqlx::query_as::<sqlx::Postgres, User>(
+            let inserted = sqlx::query_as::<sqlx::Postgres, User>(&format!(
                 r#"INSERT INTO users (email, password_hash)
                    VALUES ($1, $2)
-                   RETURNING id,
-                             email,
-                             password_hash,
-                             EXTRACT(EPOCH FROM created_at)::bigint as created_at"#,
-            )
+                   RETURNING {USER_COLUMNS}"#
+            ))
             .bind(email)
             .bind(password_hash)
             .fetch_one(&self.pool)
@@ -145,19 +596,390 @@ pub mod postgres {
         }
 
         async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError> {
-            let user = sqlx::query_as::<sqlx::Postgres, User>(
-                r#"SELECT id,
-                          email,
-                          password_hash,
-                          EXTRACT(EPOCH FROM created_at)::bigint as created_at
-                   FROM users WHERE email = $1"#,
-            )
+            let user = sqlx::query_as::<sqlx::Postgres, User>(&format!(
+                r#"SELECT {USER_COLUMNS} FROM users WHERE email = $1"#
+            ))
             .bind(email)
             .fetch_optional(&self.pool)
             .await
             .map_err(RepoError::DbError)?;
             Ok(user)
         }
+
+        async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+            let user = sqlx::query_as::<sqlx::Postgres, User>(&format!(
+                r#"SELECT {USER_COLUMNS} FROM users WHERE id = $1"#
+            ))
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(user)
+        }
+
+        async fn update_password(
+            &self,
+            user_id: i64,
+            password_hash: &str,
+        ) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE users SET password_hash = $1 WHERE id = $2"#,
+            )
+            .bind(password_hash)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn mark_email_verified(&self, user_id: i64) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE users SET email_verified_at = NOW()
+                   WHERE id = $1 AND email_verified_at IS NULL"#,
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn record_login_failure(&self, user_id: i64) -> Result<i64, RepoError> {
+            let count = sqlx::query_scalar::<sqlx::Postgres, i64>(
+                r#"UPDATE users SET failed_login_count = failed_login_count + 1
+                   WHERE id = $1
+                   RETURNING failed_login_count"#,
+            )
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(count)
+        }
+
+        async fn lock_user_until(&self, user_id: i64, locked_until: i64) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE users SET locked_until = to_timestamp($1) WHERE id = $2"#,
+            )
+            .bind(locked_until as f64)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn reset_login_failures(&self, user_id: i64) -> Result<(), RepoError> {
+            sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE users SET failed_login_count = 0, locked_until = NULL WHERE id = $1"#,
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(())
+        }
+    }
+
+    const USER_COLUMNS: &str = r#"id,
+        email,
+        password_hash,
+        EXTRACT(EPOCH FROM created_at)::bigint as created_at,
+        EXTRACT(EPOCH FROM email_verified_at)::bigint as email_verified_at,
+        failed_login_count,
+        EXTRACT(EPOCH FROM locked_until)::bigint as locked_until,
+        role,
+        EXTRACT(EPOCH FROM disabled_at)::bigint as disabled_at"#;
+
+    #[async_trait::async_trait]
+    impl UserAdminRepository for PgUserRepository {
+        async fn list_users(
+            &self,
+            query: Option<&str>,
+            after: Option<i64>,
+            limit: i64,
+        ) -> Result<Vec<User>, RepoError> {
+            let users = sqlx::query_as::<sqlx::Postgres, User>(&format!(
+                r#"SELECT {USER_COLUMNS} FROM users
+                   WHERE ($1::text IS NULL OR strpos(lower(email), lower($1)) > 0) AND id > $2
+                   ORDER BY id
+                   LIMIT $3"#
+            ))
+            .bind(query)
+            .bind(after.unwrap_or(0))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(users)
+        }
+
+        async fn set_user_disabled(
+            &self,
+            user_id: i64,
+            disabled: bool,
+        ) -> Result<Option<User>, RepoError> {
+            let user = sqlx::query_as::<sqlx::Postgres, User>(&format!(
+                r#"UPDATE users
+                   SET disabled_at = CASE WHEN $1 THEN COALESCE(disabled_at, now()) ELSE NULL END
+                   WHERE id = $2
+                   RETURNING {USER_COLUMNS}"#
+            ))
+            .bind(disabled)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(user)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl UserTokenRepository for PgUserRepository {
+        async fn create_user_token(
+            &self,
+            user_id: i64,
+            purpose: UserTokenPurpose,
+            token_hash: &str,
+            ttl_secs: i64,
+        ) -> Result<(), RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE user_tokens SET used_at = NOW()
+                   WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL"#,
+            )
+            .bind(user_id)
+            .bind(purpose.as_str())
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+                   VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))"#,
+            )
+            .bind(user_id)
+            .bind(purpose.as_str())
+            .bind(token_hash)
+            .bind(ttl_secs as f64)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(())
+        }
+
+        async fn consume_user_token(
+            &self,
+            purpose: UserTokenPurpose,
+            token_hash: &str,
+        ) -> Result<Option<i64>, RepoError> {
+            let user_id = sqlx::query_scalar::<sqlx::Postgres, i64>(
+                r#"UPDATE user_tokens SET used_at = NOW()
+                   WHERE token_hash = $1 AND purpose = $2
+                     AND used_at IS NULL AND expires_at > NOW()
+                   RETURNING user_id"#,
+            )
+            .bind(token_hash)
+            .bind(purpose.as_str())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(user_id)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TwoFactorRepository for PgUserRepository {
+        async fn start_totp_enrollment(
+            &self,
+            user_id: i64,
+            secret: &str,
+        ) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO user_totp (user_id, secret)
+                   VALUES ($1, $2)
+                   ON CONFLICT (user_id) DO UPDATE
+                     SET secret = EXCLUDED.secret, created_at = NOW(), last_used_step = NULL
+                     WHERE user_totp.enabled_at IS NULL"#,
+            )
+            .bind(user_id)
+            .bind(secret)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn find_totp(&self, user_id: i64) -> Result<Option<TotpCredential>, RepoError> {
+            let totp = sqlx::query_as::<sqlx::Postgres, TotpCredential>(
+                r#"SELECT user_id,
+                          secret,
+                          EXTRACT(EPOCH FROM enabled_at)::bigint as enabled_at,
+                          last_used_step
+                   FROM user_totp WHERE user_id = $1"#,
+            )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(totp)
+        }
+
+        async fn enable_totp(
+            &self,
+            user_id: i64,
+            step: i64,
+            recovery_code_hashes: &[String],
+        ) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let enabled = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE user_totp SET enabled_at = NOW(), last_used_step = $1
+                   WHERE user_id = $2 AND enabled_at IS NULL"#,
+            )
+            .bind(step)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            if enabled.rows_affected() == 0 {
+                return Ok(false);
+            }
+            sqlx::query::<sqlx::Postgres>(r#"DELETE FROM totp_recovery_codes WHERE user_id = $1"#)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO totp_recovery_codes (user_id, code_hash)
+                   SELECT $1, UNNEST($2::text[])"#,
+            )
+            .bind(user_id)
+            .bind(recovery_code_hashes)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(true)
+        }
+
+        async fn use_totp_step(&self, user_id: i64, step: i64) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE user_totp SET last_used_step = $1
+                   WHERE user_id = $2 AND enabled_at IS NOT NULL
+                     AND (last_used_step IS NULL OR last_used_step < $1)"#,
+            )
+            .bind(step)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn use_recovery_code(
+            &self,
+            user_id: i64,
+            code_hash: &str,
+        ) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE totp_recovery_codes SET used_at = NOW()
+                   WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL"#,
+            )
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+    }
+
+    const API_TOKEN_COLUMNS: &str = r#"id,
+        user_id,
+        name,
+        scopes,
+        EXTRACT(EPOCH FROM created_at)::bigint as created_at,
+        EXTRACT(EPOCH FROM expires_at)::bigint as expires_at,
+        EXTRACT(EPOCH FROM last_used_at)::bigint as last_used_at"#;
+
+    #[async_trait::async_trait]
+    impl ApiTokenRepository for PgUserRepository {
+        async fn create_api_token(
+            &self,
+            user_id: i64,
+            name: &str,
+            token_hash: &str,
+            scopes: &[Scope],
+            ttl_secs: Option<i64>,
+        ) -> Result<ApiToken, RepoError> {
+            let token = sqlx::query_as::<sqlx::Postgres, ApiToken>(&format!(
+                r#"INSERT INTO api_tokens (user_id, name, token_hash, scopes, expires_at)
+                   VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5))
+                   RETURNING {API_TOKEN_COLUMNS}"#
+            ))
+            .bind(user_id)
+            .bind(name)
+            .bind(token_hash)
+            .bind(Json(scopes))
+            .bind(ttl_secs.map(|secs| secs as f64))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(token)
+        }
+
+        async fn list_api_tokens(&self, user_id: i64) -> Result<Vec<ApiToken>, RepoError> {
+            let tokens = sqlx::query_as::<sqlx::Postgres, ApiToken>(&format!(
+                r#"SELECT {API_TOKEN_COLUMNS} FROM api_tokens
+                   WHERE user_id = $1
+                   ORDER BY api_tokens.created_at DESC, id DESC"#
+            ))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(tokens)
+        }
+
+        async fn delete_api_token(&self, user_id: i64, token_id: i64) -> Result<bool, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM api_tokens WHERE id = $1 AND user_id = $2"#,
+            )
+            .bind(token_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn delete_user_api_tokens(&self, user_id: i64) -> Result<u64, RepoError> {
+            let res = sqlx::query::<sqlx::Postgres>(r#"DELETE FROM api_tokens WHERE user_id = $1"#)
+                .bind(user_id)
+                .execute(&self.pool)
+                .await
+                .map_err(RepoError::DbError)?;
+            Ok(res.rows_affected())
+        }
+
+        async fn authenticate_api_token(
+            &self,
+            token_hash: &str,
+        ) -> Result<Option<ApiToken>, RepoError> {
+            let token = sqlx::query_as::<sqlx::Postgres, ApiToken>(&format!(
+                r#"UPDATE api_tokens SET last_used_at = NOW()
+                   WHERE token_hash = $1
+                     AND (expires_at IS NULL OR expires_at > NOW())
+                     AND user_id IN (SELECT id FROM users WHERE disabled_at IS NULL)
+                   RETURNING {API_TOKEN_COLUMNS}"#
+            ))
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(token)
+        }
     }
 }
 
@@ -176,6 +998,11 @@ impl UserRepository for MockRepoSuccess {
             email: email.to_string(),
             password_hash: password_hash.to_string(),
             created_at: 0,
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role: Role::User,
+            disabled_at: None,
         }))
     }
 
@@ -185,8 +1012,40 @@ impl UserRepository for MockRepoSuccess {
             email: _email.to_string(),
             password_hash: "x".into(),
             created_at: 0,
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role: Role::User,
+            disabled_at: None,
         }))
     }
+
+    async fn find_by_id(&self, _user_id: i64) -> Result<Option<User>, RepoError> {
+        Ok(None)
+    }
+
+    async fn update_password(
+        &self,
+        _user_id: i64,
+        _password_hash: &str,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn mark_email_verified(&self, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+    async fn record_login_failure(&self, _user_id: i64) -> Result<i64, RepoError> {
+        Ok(1)
+    }
+
+    async fn lock_user_until(&self, _user_id: i64, _locked_until: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
 }
 
 #[allow(dead_code)]
@@ -204,6 +1063,33 @@ impl UserRepository for MockRepoConflict {
     async fn find_by_email(&self, _email: &str) -> Result<Option<User>, RepoError> {
         Ok(None)
     }
+
+    async fn find_by_id(&self, _user_id: i64) -> Result<Option<User>, RepoError> {
+        Ok(None)
+    }
+
+    async fn update_password(
+        &self,
+        _user_id: i64,
+        _password_hash: &str,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn mark_email_verified(&self, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+    async fn record_login_failure(&self, _user_id: i64) -> Result<i64, RepoError> {
+        Ok(1)
+    }
+
+    async fn lock_user_until(&self, _user_id: i64, _locked_until: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
 }
 
 // モック（任意の1ユーザーを保持して検索に応答）
@@ -229,4 +1115,27 @@ impl UserRepository for MockRepoWithUser {
             Ok(None)
         }
     }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+        Ok((self.user.id == user_id).then(|| self.user.clone()))
+    }
+
+    async fn update_password(&self, user_id: i64, _password_hash: &str) -> Result<bool, RepoError> {
+        Ok(self.user.id == user_id)
+    }
+
+    async fn mark_email_verified(&self, user_id: i64) -> Result<bool, RepoError> {
+        Ok(self.user.id == user_id && self.user.email_verified_at.is_none())
+    }
+    async fn record_login_failure(&self, _user_id: i64) -> Result<i64, RepoError> {
+        Ok(1)
+    }
+
+    async fn lock_user_until(&self, _user_id: i64, _locked_until: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
 }
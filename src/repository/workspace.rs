@@ -0,0 +1,543 @@
+use crate::domain::model::{
+    Workspace, WorkspaceInvitation, WorkspaceMember, WorkspaceMembership, WorkspaceRole,
+};
+use crate::repository::user::RepoError;
+
+/// ワークスペースとメンバー・招待。権限の確認は呼び出し側（`WorkspaceService`）で行う。
+#[async_trait::async_trait]
+pub trait WorkspaceRepository: Send + Sync + 'static {
+    /// ワークスペースを作成し、`owner_id` をオーナーとして登録する。
+    async fn create_workspace(&self, owner_id: i64, name: &str) -> Result<Workspace, RepoError>;
+    /// `user_id` が参加しているワークスペースを ID 順に返す。
+    async fn list_workspaces(&self, user_id: i64) -> Result<Vec<WorkspaceMembership>, RepoError>;
+    /// メンバーでなければ `None`。
+    async fn member_role(
+        &self,
+        workspace_id: i64,
+        user_id: i64,
+    ) -> Result<Option<WorkspaceRole>, RepoError>;
+    /// メンバーを参加した順に返す。
+    async fn list_members(&self, workspace_id: i64) -> Result<Vec<WorkspaceMember>, RepoError>;
+    /// メンバーの役割を変更する。
+    /// - Ok(false): メンバーではない
+    /// - Err(Conflict): 最後のオーナーを編集者・閲覧者にしようとした
+    async fn set_member_role(
+        &self,
+        workspace_id: i64,
+        user_id: i64,
+        role: WorkspaceRole,
+    ) -> Result<bool, RepoError>;
+    /// メンバーを外す。
+    /// - Ok(false): メンバーではない
+    /// - Err(Conflict): 最後のオーナーを外そうとした
+    async fn remove_member(&self, workspace_id: i64, user_id: i64) -> Result<bool, RepoError>;
+    /// 招待を作成する。同じメールアドレスへの未承諾の招待は無効にする。
+    async fn create_invitation(
+        &self,
+        workspace_id: i64,
+        email: &str,
+        role: WorkspaceRole,
+        invited_by: i64,
+        token_hash: &str,
+        ttl_secs: i64,
+    ) -> Result<WorkspaceInvitation, RepoError>;
+    /// 有効期限内・未承諾で、宛先が `email` の招待を承諾済みにしてメンバーに加え、
+    /// ワークスペースの ID を返す（該当する招待が無ければ `None`）。
+    /// 既にメンバーなら役割は変更しない。
+    async fn accept_invitation(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        email: &str,
+    ) -> Result<Option<i64>, RepoError>;
+}
+
+// SQLite 実装をモジュールにまとめる
+#[cfg(not(feature = "postgres"))]
+pub use sqlite::SqliteWorkspaceRepository;
+
+#[cfg(not(feature = "postgres"))]
+pub mod sqlite {
+    use super::*;
+    use sqlx::{SqlitePool, Transaction};
+
+    pub struct SqliteWorkspaceRepository {
+        pub(crate) pool: SqlitePool,
+    }
+
+    impl SqliteWorkspaceRepository {
+        pub fn new(pool: SqlitePool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl WorkspaceRepository for SqliteWorkspaceRepository {
+        async fn create_workspace(
+            &self,
+            owner_id: i64,
+            name: &str,
+        ) -> Result<Workspace, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let workspace = sqlx::query_as::<sqlx::Sqlite, Workspace>(
+                r#"INSERT INTO workspaces (name, created_at)
+                   VALUES (?, strftime('%s','now'))
+                   RETURNING id, name, created_at"#,
+            )
+            .bind(name)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO workspace_members (workspace_id, user_id, role, created_at)
+                   VALUES (?, ?, 'owner', strftime('%s','now'))"#,
+            )
+            .bind(workspace.id)
+            .bind(owner_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(workspace)
+        }
+
+        async fn list_workspaces(
+            &self,
+            user_id: i64,
+        ) -> Result<Vec<WorkspaceMembership>, RepoError> {
+            let workspaces = sqlx::query_as::<sqlx::Sqlite, WorkspaceMembership>(
+                r#"SELECT w.id, w.name, w.created_at, m.role
+                   FROM workspace_members m
+                   JOIN workspaces w ON w.id = m.workspace_id
+                   WHERE m.user_id = ?
+                   ORDER BY w.id"#,
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(workspaces)
+        }
+
+        async fn member_role(
+            &self,
+            workspace_id: i64,
+            user_id: i64,
+        ) -> Result<Option<WorkspaceRole>, RepoError> {
+            let role = sqlx::query_scalar::<sqlx::Sqlite, String>(
+                r#"SELECT role FROM workspace_members WHERE workspace_id = ? AND user_id = ?"#,
+            )
+            .bind(workspace_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            role.map(WorkspaceRole::try_from)
+                .transpose()
+                .map_err(|_| RepoError::Internal)
+        }
+
+        async fn list_members(&self, workspace_id: i64) -> Result<Vec<WorkspaceMember>, RepoError> {
+            let members = sqlx::query_as::<sqlx::Sqlite, WorkspaceMember>(
+                r#"SELECT m.user_id, u.email, m.role, m.created_at as joined_at
+                   FROM workspace_members m
+                   JOIN users u ON u.id = m.user_id
+                   WHERE m.workspace_id = ?
+                   ORDER BY m.created_at, m.user_id"#,
+            )
+            .bind(workspace_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(members)
+        }
+
+        async fn set_member_role(
+            &self,
+            workspace_id: i64,
+            user_id: i64,
+            role: WorkspaceRole,
+        ) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            if role != WorkspaceRole::Owner {
+                ensure_not_last_owner(&mut tx, workspace_id, user_id).await?;
+            }
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE workspace_members SET role = ?
+                   WHERE workspace_id = ? AND user_id = ?"#,
+            )
+            .bind(role.as_str())
+            .bind(workspace_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn remove_member(&self, workspace_id: i64, user_id: i64) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            ensure_not_last_owner(&mut tx, workspace_id, user_id).await?;
+            let result = sqlx::query::<sqlx::Sqlite>(
+                r#"DELETE FROM workspace_members WHERE workspace_id = ? AND user_id = ?"#,
+            )
+            .bind(workspace_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn create_invitation(
+            &self,
+            workspace_id: i64,
+            email: &str,
+            role: WorkspaceRole,
+            invited_by: i64,
+            token_hash: &str,
+            ttl_secs: i64,
+        ) -> Result<WorkspaceInvitation, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Sqlite>(
+                r#"UPDATE workspace_invitations SET expires_at = strftime('%s','now')
+                   WHERE workspace_id = ? AND lower(email) = lower(?)
+                     AND accepted_at IS NULL AND expires_at > strftime('%s','now')"#,
+            )
+            .bind(workspace_id)
+            .bind(email)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let invitation = sqlx::query_as::<sqlx::Sqlite, WorkspaceInvitation>(
+                r#"INSERT INTO workspace_invitations
+                       (workspace_id, email, role, token_hash, invited_by, created_at, expires_at)
+                   VALUES (?, ?, ?, ?, ?, strftime('%s','now'), strftime('%s','now') + ?)
+                   RETURNING id, workspace_id, email, role, invited_by, created_at, expires_at"#,
+            )
+            .bind(workspace_id)
+            .bind(email)
+            .bind(role.as_str())
+            .bind(token_hash)
+            .bind(invited_by)
+            .bind(ttl_secs)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(invitation)
+        }
+
+        async fn accept_invitation(
+            &self,
+            token_hash: &str,
+            user_id: i64,
+            email: &str,
+        ) -> Result<Option<i64>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let accepted = sqlx::query_as::<sqlx::Sqlite, (i64, String)>(
+                r#"UPDATE workspace_invitations SET accepted_at = strftime('%s','now')
+                   WHERE token_hash = ? AND lower(email) = lower(?)
+                     AND accepted_at IS NULL AND expires_at > strftime('%s','now')
+                   RETURNING workspace_id, role"#,
+            )
+            .bind(token_hash)
+            .bind(email)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let Some((workspace_id, role)) = accepted else {
+                return Ok(None);
+            };
+            sqlx::query::<sqlx::Sqlite>(
+                r#"INSERT INTO workspace_members (workspace_id, user_id, role, created_at)
+                   VALUES (?, ?, ?, strftime('%s','now'))
+                   ON CONFLICT (workspace_id, user_id) DO NOTHING"#,
+            )
+            .bind(workspace_id)
+            .bind(user_id)
+            .bind(role)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(Some(workspace_id))
+        }
+    }
+
+    /// `user_id` がワークスペースの唯一のオーナーなら `Conflict` を返す。
+    async fn ensure_not_last_owner(
+        tx: &mut Transaction<'_, sqlx::Sqlite>,
+        workspace_id: i64,
+        user_id: i64,
+    ) -> Result<(), RepoError> {
+        let last_owner = sqlx::query_scalar::<sqlx::Sqlite, i64>(
+            r#"SELECT 1 FROM workspace_members
+               WHERE workspace_id = ?1 AND user_id = ?2 AND role = 'owner'
+                 AND (SELECT COUNT(*) FROM workspace_members
+                      WHERE workspace_id = ?1 AND role = 'owner') = 1"#,
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        match last_owner {
+            Some(_) => Err(RepoError::Conflict),
+            None => Ok(()),
+        }
+    }
+}
+
+// PostgreSQL 実装をモジュールにまとめる
+#[cfg(feature = "postgres")]
+pub use postgres::PgWorkspaceRepository;
+
+#[cfg(feature = "postgres")]
+pub mod postgres {
+    use super::*;
+    use sqlx::{PgPool, Transaction};
+
+    pub struct PgWorkspaceRepository {
+        pub(crate) pool: PgPool,
+    }
+
+    impl PgWorkspaceRepository {
+        pub fn new(pool: PgPool) -> Self {
+            Self { pool }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl WorkspaceRepository for PgWorkspaceRepository {
+        async fn create_workspace(
+            &self,
+            owner_id: i64,
+            name: &str,
+        ) -> Result<Workspace, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let workspace = sqlx::query_as::<sqlx::Postgres, Workspace>(
+                r#"INSERT INTO workspaces (name)
+                   VALUES ($1)
+                   RETURNING id, name, EXTRACT(EPOCH FROM created_at)::bigint as created_at"#,
+            )
+            .bind(name)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO workspace_members (workspace_id, user_id, role)
+                   VALUES ($1, $2, 'owner')"#,
+            )
+            .bind(workspace.id)
+            .bind(owner_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(workspace)
+        }
+
+        async fn list_workspaces(
+            &self,
+            user_id: i64,
+        ) -> Result<Vec<WorkspaceMembership>, RepoError> {
+            let workspaces = sqlx::query_as::<sqlx::Postgres, WorkspaceMembership>(
+                r#"SELECT w.id,
+                          w.name,
+                          EXTRACT(EPOCH FROM w.created_at)::bigint as created_at,
+                          m.role
+                   FROM workspace_members m
+                   JOIN workspaces w ON w.id = m.workspace_id
+                   WHERE m.user_id = $1
+                   ORDER BY w.id"#,
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(workspaces)
+        }
+
+        async fn member_role(
+            &self,
+            workspace_id: i64,
+            user_id: i64,
+        ) -> Result<Option<WorkspaceRole>, RepoError> {
+            let role = sqlx::query_scalar::<sqlx::Postgres, String>(
+                r#"SELECT role FROM workspace_members WHERE workspace_id = $1 AND user_id = $2"#,
+            )
+            .bind(workspace_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            role.map(WorkspaceRole::try_from)
+                .transpose()
+                .map_err(|_| RepoError::Internal)
+        }
+
+        async fn list_members(&self, workspace_id: i64) -> Result<Vec<WorkspaceMember>, RepoError> {
+            let members = sqlx::query_as::<sqlx::Postgres, WorkspaceMember>(
+                r#"SELECT m.user_id,
+                          u.email,
+                          m.role,
+                          EXTRACT(EPOCH FROM m.created_at)::bigint as joined_at
+                   FROM workspace_members m
+                   JOIN users u ON u.id = m.user_id
+                   WHERE m.workspace_id = $1
+                   ORDER BY m.created_at, m.user_id"#,
+            )
+            .bind(workspace_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(RepoError::DbError)?;
+            Ok(members)
+        }
+
+        async fn set_member_role(
+            &self,
+            workspace_id: i64,
+            user_id: i64,
+            role: WorkspaceRole,
+        ) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            if role != WorkspaceRole::Owner {
+                ensure_not_last_owner(&mut tx, workspace_id, user_id).await?;
+            }
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE workspace_members SET role = $1
+                   WHERE workspace_id = $2 AND user_id = $3"#,
+            )
+            .bind(role.as_str())
+            .bind(workspace_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn remove_member(&self, workspace_id: i64, user_id: i64) -> Result<bool, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            ensure_not_last_owner(&mut tx, workspace_id, user_id).await?;
+            let res = sqlx::query::<sqlx::Postgres>(
+                r#"DELETE FROM workspace_members WHERE workspace_id = $1 AND user_id = $2"#,
+            )
+            .bind(workspace_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(res.rows_affected() > 0)
+        }
+
+        async fn create_invitation(
+            &self,
+            workspace_id: i64,
+            email: &str,
+            role: WorkspaceRole,
+            invited_by: i64,
+            token_hash: &str,
+            ttl_secs: i64,
+        ) -> Result<WorkspaceInvitation, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            sqlx::query::<sqlx::Postgres>(
+                r#"UPDATE workspace_invitations SET expires_at = NOW()
+                   WHERE workspace_id = $1 AND lower(email) = lower($2)
+                     AND accepted_at IS NULL AND expires_at > NOW()"#,
+            )
+            .bind(workspace_id)
+            .bind(email)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let invitation = sqlx::query_as::<sqlx::Postgres, WorkspaceInvitation>(
+                r#"INSERT INTO workspace_invitations
+                       (workspace_id, email, role, token_hash, invited_by, expires_at)
+                   VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(secs => $6))
+                   RETURNING id,
+                             workspace_id,
+                             email,
+                             role,
+                             invited_by,
+                             EXTRACT(EPOCH FROM created_at)::bigint as created_at,
+                             EXTRACT(EPOCH FROM expires_at)::bigint as expires_at"#,
+            )
+            .bind(workspace_id)
+            .bind(email)
+            .bind(role.as_str())
+            .bind(token_hash)
+            .bind(invited_by)
+            .bind(ttl_secs as f64)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(invitation)
+        }
+
+        async fn accept_invitation(
+            &self,
+            token_hash: &str,
+            user_id: i64,
+            email: &str,
+        ) -> Result<Option<i64>, RepoError> {
+            let mut tx = self.pool.begin().await.map_err(RepoError::DbError)?;
+            let accepted = sqlx::query_as::<sqlx::Postgres, (i64, String)>(
+                r#"UPDATE workspace_invitations SET accepted_at = NOW()
+                   WHERE token_hash = $1 AND lower(email) = lower($2)
+                     AND accepted_at IS NULL AND expires_at > NOW()
+                   RETURNING workspace_id, role"#,
+            )
+            .bind(token_hash)
+            .bind(email)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            let Some((workspace_id, role)) = accepted else {
+                return Ok(None);
+            };
+            sqlx::query::<sqlx::Postgres>(
+                r#"INSERT INTO workspace_members (workspace_id, user_id, role)
+                   VALUES ($1, $2, $3)
+                   ON CONFLICT (workspace_id, user_id) DO NOTHING"#,
+            )
+            .bind(workspace_id)
+            .bind(user_id)
+            .bind(role)
+            .execute(&mut *tx)
+            .await
+            .map_err(RepoError::DbError)?;
+            tx.commit().await.map_err(RepoError::DbError)?;
+            Ok(Some(workspace_id))
+        }
+    }
+
+    /// `user_id` がワークスペースの唯一のオーナーなら `Conflict` を返す。
+    /// 同時に 2 人のオーナーが互いを降格させても最後の 1 人が残るよう、オーナーの行をロックする。
+    async fn ensure_not_last_owner(
+        tx: &mut Transaction<'_, sqlx::Postgres>,
+        workspace_id: i64,
+        user_id: i64,
+    ) -> Result<(), RepoError> {
+        let owners = sqlx::query_scalar::<sqlx::Postgres, i64>(
+            r#"SELECT user_id FROM workspace_members
+               WHERE workspace_id = $1 AND role = 'owner'
+               FOR UPDATE"#,
+        )
+        .bind(workspace_id)
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(RepoError::DbError)?;
+        if owners == [user_id] {
+            Err(RepoError::Conflict)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::domain::model::{User, UserTokenPurpose};
+use crate::middleware::auth::token::{hash_token, random_token};
+use crate::repository::user::{RepoError, UserRepository, UserTokenRepository};
+use crate::service::auth::{AuthServiceError, hash_password, is_valid_password, verify_password};
+use crate::service::mailer::{Email, Mailer, MailerError};
+
+#[derive(Debug, Error)]
+pub enum AccountError {
+    /// 存在しない・期限切れ・使用済みのトークン
+    #[error("invalid token")]
+    InvalidToken,
+
+    /// 現在のパスワードが一致しない
+    #[error("invalid credentials")]
+    InvalidCredentials,
+
+    /// 新しいパスワードがポリシーを満たさない（`AuthService::signup` と同じポリシー）
+    #[error("invalid password")]
+    InvalidPassword,
+
+    #[error("email already verified")]
+    AlreadyVerified,
+
+    #[error("password hashing failed")]
+    HashError,
+
+    #[error(transparent)]
+    Mail(#[from] MailerError),
+
+    #[error(transparent)]
+    Repo(#[from] RepoError),
+}
+
+impl From<AuthServiceError> for AccountError {
+    fn from(e: AuthServiceError) -> Self {
+        match e {
+            AuthServiceError::InvalidCredentials => AccountError::InvalidCredentials,
+            AuthServiceError::InvalidPassword => AccountError::InvalidPassword,
+            AuthServiceError::Repo(e) => AccountError::Repo(e),
+            _ => AccountError::HashError,
+        }
+    }
+}
+
+/// パスワードの変更・再設定とメールアドレスの確認。
+///
+/// 再設定・確認に使うトークンは 1 回限りで、メールで送った平文は保存せずハッシュのみ保存する。
+pub struct AccountService {
+    users: Arc<dyn UserRepository>,
+    tokens: Arc<dyn UserTokenRepository>,
+    mailer: Arc<dyn Mailer>,
+}
+
+impl AccountService {
+    const PASSWORD_RESET_EXP_SECS: i64 = 60 * 60;
+    const EMAIL_VERIFICATION_EXP_SECS: i64 = 24 * 60 * 60;
+    const FORCED_RESET_EXP_SECS: i64 = 24 * 60 * 60;
+    /// どのパスワードとも一致しないハッシュ（PHC 文字列として解釈できない）
+    const UNUSABLE_PASSWORD_HASH: &str = "!";
+
+    pub fn new(
+        users: Arc<dyn UserRepository>,
+        tokens: Arc<dyn UserTokenRepository>,
+        mailer: Arc<dyn Mailer>,
+    ) -> Self {
+        Self {
+            users,
+            tokens,
+            mailer,
+        }
+    }
+
+    /// 確認用のトークンをメールで送る（サインアップ直後と再送時）。
+    pub async fn send_email_verification(&self, user: &User) -> Result<(), AccountError> {
+        if user.email_verified_at.is_some() {
+            return Err(AccountError::AlreadyVerified);
+        }
+        let token = self
+            .issue(
+                user.id,
+                UserTokenPurpose::EmailVerification,
+                Self::EMAIL_VERIFICATION_EXP_SECS,
+            )
+            .await?;
+        self.mailer
+            .send(&Email {
+                to: user.email.clone(),
+                subject: "メールアドレスの確認".into(),
+                body: format!(
+                    "メールアドレスを確認するには、24 時間以内に次のトークンを\n\
+                     POST /auth/verify-email に送ってください。\n\n{token}\n"
+                ),
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn resend_email_verification(&self, user_id: i64) -> Result<(), AccountError> {
+        let Some(user) = self.users.find_by_id(user_id).await? else {
+            return Err(AccountError::InvalidCredentials);
+        };
+        self.send_email_verification(&user).await
+    }
+
+    pub async fn verify_email(&self, token: &str) -> Result<(), AccountError> {
+        let Some(user_id) = self
+            .tokens
+            .consume_user_token(UserTokenPurpose::EmailVerification, &hash_token(token))
+            .await?
+        else {
+            return Err(AccountError::InvalidToken);
+        };
+        self.users.mark_email_verified(user_id).await?;
+        Ok(())
+    }
+
+    /// ログイン中のユーザーのパスワードを変更する。現在のパスワードの確認が必要。
+    pub async fn change_password(
+        &self,
+        user_id: i64,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), AccountError> {
+        let Some(user) = self.users.find_by_id(user_id).await? else {
+            return Err(AccountError::InvalidCredentials);
+        };
+        verify_password(&user, current_password)?;
+        self.set_password(user_id, new_password).await
+    }
+
+    /// 登録済みのメールアドレスなら再設定用のトークンを送る。
+    /// 未登録でも `Ok` を返す（登録の有無を推測させないため）。
+    pub async fn request_password_reset(&self, email: &str) -> Result<(), AccountError> {
+        let Some(user) = self.users.find_by_email(email).await? else {
+            return Ok(());
+        };
+        let token = self
+            .issue(
+                user.id,
+                UserTokenPurpose::PasswordReset,
+                Self::PASSWORD_RESET_EXP_SECS,
+            )
+            .await?;
+        self.mailer
+            .send(&Email {
+                to: user.email,
+                subject: "パスワードの再設定".into(),
+                body: format!(
+                    "パスワードを再設定するには、1 時間以内に次のトークンと新しいパスワードを\n\
+                     POST /auth/password-reset/confirm に送ってください。\n\
+                     心当たりがない場合はこのメールを無視してください。\n\n{token}\n"
+                ),
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// 管理者による強制的な再設定。現在のパスワードを使えなくし、再設定用のトークンを送る。
+    pub async fn force_password_reset(&self, user: &User) -> Result<(), AccountError> {
+        self.users
+            .update_password(user.id, Self::UNUSABLE_PASSWORD_HASH)
+            .await?;
+        let token = self
+            .issue(
+                user.id,
+                UserTokenPurpose::PasswordReset,
+                Self::FORCED_RESET_EXP_SECS,
+            )
+            .await?;
+        self.mailer
+            .send(&Email {
+                to: user.email.clone(),
+                subject: "パスワードの再設定のお願い".into(),
+                body: format!(
+                    "管理者がパスワードの再設定を求めました。現在のパスワードではログインできません。\n\
+                     24 時間以内に次のトークンと新しいパスワードを\n\
+                     POST /auth/password-reset/confirm に送ってください。\n\
+                     期限が切れた場合は POST /auth/password-reset/request で再送できます。\n\n{token}\n"
+                ),
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn confirm_password_reset(
+        &self,
+        token: &str,
+        new_password: &str,
+    ) -> Result<(), AccountError> {
+        // ポリシー違反でトークンを使い切らないよう先に検証する
+        if !is_valid_password(new_password) {
+            return Err(AccountError::InvalidPassword);
+        }
+        let Some(user_id) = self
+            .tokens
+            .consume_user_token(UserTokenPurpose::PasswordReset, &hash_token(token))
+            .await?
+        else {
+            return Err(AccountError::InvalidToken);
+        };
+        self.set_password(user_id, new_password).await
+    }
+
+    async fn set_password(&self, user_id: i64, new_password: &str) -> Result<(), AccountError> {
+        if !is_valid_password(new_password) {
+            return Err(AccountError::InvalidPassword);
+        }
+        let hash = hash_password(new_password)?;
+        if !self.users.update_password(user_id, &hash).await? {
+            return Err(AccountError::InvalidCredentials);
+        }
+        Ok(())
+    }
+
+    async fn issue(
+        &self,
+        user_id: i64,
+        purpose: UserTokenPurpose,
+        ttl_secs: i64,
+    ) -> Result<String, AccountError> {
+        let token = random_token(32);
+        self.tokens
+            .create_user_token(user_id, purpose, &hash_token(&token), ttl_secs)
+            .await?;
+        Ok(token)
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::domain::model::{Note, User};
+use crate::repository::note::NoteRepository;
+use crate::repository::token::TokenRepository;
+use crate::repository::user::{ApiTokenRepository, RepoError, UserAdminRepository, UserRepository};
+use crate::service::account::{AccountError, AccountService};
+
+#[derive(Debug, Error)]
+pub enum AdminError {
+    #[error("not found")]
+    NotFound,
+
+    /// 管理者が自分自身を無効にしようとした（管理者がいなくなるのを防ぐ）
+    #[error("cannot disable yourself")]
+    SelfTarget,
+
+    #[error(transparent)]
+    Account(#[from] AccountError),
+
+    #[error(transparent)]
+    Repo(#[from] RepoError),
+}
+
+/// 管理用 API（`/admin`）のユースケース。呼び出し側で管理者であることを確認済みとする。
+pub struct AdminService {
+    users: Arc<dyn UserRepository>,
+    admin: Arc<dyn UserAdminRepository>,
+    tokens: Arc<dyn TokenRepository>,
+    api_tokens: Arc<dyn ApiTokenRepository>,
+    notes: Arc<dyn NoteRepository>,
+    accounts: Arc<AccountService>,
+}
+
+impl AdminService {
+    pub fn new(
+        users: Arc<dyn UserRepository>,
+        admin: Arc<dyn UserAdminRepository>,
+        tokens: Arc<dyn TokenRepository>,
+        api_tokens: Arc<dyn ApiTokenRepository>,
+        notes: Arc<dyn NoteRepository>,
+        accounts: Arc<AccountService>,
+    ) -> Self {
+        Self {
+            users,
+            admin,
+            tokens,
+            api_tokens,
+            notes,
+            accounts,
+        }
+    }
+
+    pub async fn list_users(
+        &self,
+        query: Option<&str>,
+        after: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<User>, AdminError> {
+        Ok(self.admin.list_users(query, after, limit).await?)
+    }
+
+    pub async fn get_user(&self, user_id: i64) -> Result<User, AdminError> {
+        self.users
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AdminError::NotFound)
+    }
+
+    /// アカウントを無効にする（`disabled` が false なら有効に戻す）。
+    /// 無効にすると発行済みのトークンをすべて失効させ、API トークンを削除する（有効に戻しても復活しない）。
+    pub async fn set_disabled(
+        &self,
+        admin_id: i64,
+        user_id: i64,
+        disabled: bool,
+    ) -> Result<User, AdminError> {
+        if disabled && admin_id == user_id {
+            return Err(AdminError::SelfTarget);
+        }
+        let user = self
+            .admin
+            .set_user_disabled(user_id, disabled)
+            .await?
+            .ok_or(AdminError::NotFound)?;
+        if disabled {
+            self.revoke_all_tokens(user_id).await?;
+        }
+        Ok(user)
+    }
+
+    /// 現在のパスワードを使えなくし、ログイン中の端末からもログアウトさせたうえで
+    /// （API トークンも削除する）、再設定用のトークンをメールで送る。
+    pub async fn force_password_reset(&self, user_id: i64) -> Result<(), AdminError> {
+        let user = self.get_user(user_id).await?;
+        self.accounts.force_password_reset(&user).await?;
+        self.revoke_all_tokens(user_id).await?;
+        Ok(())
+    }
+
+    /// リフレッシュトークン・発行済みのアクセストークン・API トークンをすべて使えなくする。
+    async fn revoke_all_tokens(&self, user_id: i64) -> Result<(), AdminError> {
+        self.tokens.revoke_user_refresh_tokens(user_id).await?;
+        self.tokens.revoke_user_access_tokens(user_id).await?;
+        self.api_tokens.delete_user_api_tokens(user_id).await?;
+        Ok(())
+    }
+
+    /// 任意のユーザーのメモを完全に削除し、削除したメモを返す（作成者がゴミ箱から戻せないようにする）。
+    /// ゴミ箱内のメモもそのまま削除する。
+    pub async fn delete_note(&self, note_id: i64) -> Result<Note, AdminError> {
+        let Some(note) = self.notes.find_including_trashed(note_id).await? else {
+            return Err(AdminError::NotFound);
+        };
+        // ゴミ箱内のメモなら何もしない（false が返る）
+        self.notes.delete_note(note.id, None).await?;
+        if !self.notes.purge_note(note.id, note.author_id).await? {
+            return Err(AdminError::NotFound);
+        }
+        Ok(note)
+    }
+}
@@ -0,0 +1,363 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::Notify;
+
+use crate::domain::attachment::{is_inline_type, normalize_filename, sniff_content_type};
+use crate::domain::image::{
+    THUMBNAIL_WIDTHS, image_dimensions, render_thumbnails, strip_metadata, thumbnail_width,
+    thumbnail_widths,
+};
+use crate::domain::model::Attachment;
+use crate::middleware::auth::token::random_token;
+use crate::repository::attachment::{AttachmentRepository, NewAttachment};
+use crate::repository::user::RepoError;
+use crate::service::blob::{BlobError, BlobStore};
+
+#[derive(Debug, Error)]
+pub enum AttachmentError {
+    #[error("not found")]
+    NotFound,
+
+    /// 1 ファイルの大きさの上限を超えている
+    #[error("attachment too large")]
+    TooLarge,
+
+    /// アップロードしたユーザーの添付ファイルの合計が上限を超える
+    #[error("attachment quota exceeded")]
+    QuotaExceeded,
+
+    #[error("invalid filename")]
+    InvalidFilename,
+
+    #[error(transparent)]
+    Repo(#[from] RepoError),
+
+    #[error(transparent)]
+    Blob(#[from] BlobError),
+}
+
+#[derive(Debug, Error)]
+pub enum AttachmentConfigError {
+    #[error("invalid ATTACHMENT_MAX_BYTES env")]
+    InvalidMaxBytes,
+    #[error("invalid ATTACHMENT_QUOTA_BYTES env")]
+    InvalidQuota,
+}
+
+/// メモの添付ファイルの保存・取得・削除。
+///
+/// 本体は `BlobStore` に、メタデータは `AttachmentRepository` に保存する。閲覧・編集の権限は
+/// 呼び出し側（メモの権限）で確認しておく。容量の上限はアップロードしたユーザーごとに数える。
+/// 画像はメタデータを取り除いてから保存し、サムネイルは `ThumbnailWorker` が後から作る。
+pub struct AttachmentService {
+    attachments: Arc<dyn AttachmentRepository>,
+    blobs: Arc<dyn BlobStore>,
+    max_bytes: u64,
+    quota_bytes: u64,
+    /// 画像がアップロードされたことを `ThumbnailWorker` に知らせる
+    uploaded_images: Notify,
+}
+
+impl AttachmentService {
+    const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+    const DEFAULT_QUOTA_BYTES: u64 = 100 * 1024 * 1024;
+    /// `purge_orphans` の 1 回で削除する件数
+    const ORPHAN_BATCH: i64 = 100;
+    /// `process_thumbnails` の 1 回で処理する件数
+    const THUMBNAIL_BATCH: i64 = 10;
+
+    pub fn new(attachments: Arc<dyn AttachmentRepository>, blobs: Arc<dyn BlobStore>) -> Self {
+        Self {
+            attachments,
+            blobs,
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+            quota_bytes: Self::DEFAULT_QUOTA_BYTES,
+            uploaded_images: Notify::new(),
+        }
+    }
+
+    /// 1 ファイルの大きさと、ユーザーごとの合計の上限（バイト）。
+    pub fn with_limits(mut self, max_bytes: u64, quota_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self.quota_bytes = quota_bytes;
+        self
+    }
+
+    /// `ATTACHMENT_MAX_BYTES`（既定 10 MiB）と `ATTACHMENT_QUOTA_BYTES`（既定 100 MiB）から設定する。
+    pub fn from_env(
+        attachments: Arc<dyn AttachmentRepository>,
+        blobs: Arc<dyn BlobStore>,
+    ) -> Result<Self, AttachmentConfigError> {
+        let bytes = |name: &str, default: u64, err: AttachmentConfigError| match std::env::var(name)
+        {
+            Ok(v) => match v.parse::<u64>() {
+                Ok(n) if n > 0 && i64::try_from(n).is_ok() => Ok(n),
+                _ => Err(err),
+            },
+            Err(_) => Ok(default),
+        };
+        let max_bytes = bytes(
+            "ATTACHMENT_MAX_BYTES",
+            Self::DEFAULT_MAX_BYTES,
+            AttachmentConfigError::InvalidMaxBytes,
+        )?;
+        let quota_bytes = bytes(
+            "ATTACHMENT_QUOTA_BYTES",
+            Self::DEFAULT_QUOTA_BYTES,
+            AttachmentConfigError::InvalidQuota,
+        )?;
+        Ok(Self::new(attachments, blobs).with_limits(max_bytes, quota_bytes))
+    }
+
+    /// 1 ファイルの大きさの上限（バイト）。
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// 本体を保存してからメタデータを追加する。追加できなければ保存した本体は削除する。
+    /// 画像は EXIF などのメタデータを取り除いたものを保存する（大きさの上限は元のファイルで数える）。
+    pub async fn upload(
+        &self,
+        note_id: i64,
+        user_id: i64,
+        filename: &str,
+        data: &[u8],
+    ) -> Result<Attachment, AttachmentError> {
+        if data.len() as u64 > self.max_bytes {
+            return Err(AttachmentError::TooLarge);
+        }
+        let filename = normalize_filename(filename).ok_or(AttachmentError::InvalidFilename)?;
+        let content_type = sniff_content_type(data);
+        let (data, dimensions) = if is_inline_type(content_type) {
+            let stripped = strip_metadata(data);
+            let dimensions = image_dimensions(&stripped);
+            (stripped, dimensions)
+        } else {
+            (data.into(), None)
+        };
+        let storage_key = format!("notes/{note_id}/{}", random_token(16));
+        let sha256 = format!("{:x}", Sha256::digest(&data));
+        self.blobs.put(&storage_key, &data).await?;
+        let created = self
+            .attachments
+            .create_attachment(
+                &NewAttachment {
+                    note_id,
+                    user_id,
+                    filename: &filename,
+                    content_type,
+                    size: data.len() as i64,
+                    sha256: &sha256,
+                    width: dimensions.and_then(|(width, _)| i32::try_from(width).ok()),
+                    height: dimensions.and_then(|(_, height)| i32::try_from(height).ok()),
+                    storage_key: &storage_key,
+                },
+                self.quota_bytes as i64,
+            )
+            .await;
+        match created {
+            Ok(Some(attachment)) => {
+                if attachment.width.is_some() {
+                    self.uploaded_images.notify_one();
+                }
+                Ok(attachment)
+            }
+            Ok(None) => {
+                self.discard(&storage_key).await;
+                Err(AttachmentError::QuotaExceeded)
+            }
+            Err(e) => {
+                self.discard(&storage_key).await;
+                Err(e.into())
+            }
+        }
+    }
+
+    pub async fn list(&self, note_id: i64) -> Result<Vec<Attachment>, AttachmentError> {
+        Ok(self.attachments.list_attachments(note_id).await?)
+    }
+
+    pub async fn find(
+        &self,
+        note_id: i64,
+        attachment_id: i64,
+    ) -> Result<Attachment, AttachmentError> {
+        self.attachments
+            .find_attachment(note_id, attachment_id)
+            .await?
+            .ok_or(AttachmentError::NotFound)
+    }
+
+    /// メモを指定せずに ID だけで探す（サムネイルの URL 用）。
+    pub async fn get(&self, attachment_id: i64) -> Result<Attachment, AttachmentError> {
+        self.attachments
+            .get_attachment(attachment_id)
+            .await?
+            .ok_or(AttachmentError::NotFound)
+    }
+
+    /// 幅 `requested` に近いサムネイルとその幅。画像でなければ `NotFound`、
+    /// まだ作っていなければ `Ok(None)`。
+    pub async fn thumbnail(
+        &self,
+        attachment: &Attachment,
+        requested: u32,
+    ) -> Result<Option<(u32, Vec<u8>)>, AttachmentError> {
+        let image_width = attachment.width.ok_or(AttachmentError::NotFound)?;
+        let width = thumbnail_width(requested, image_width as u32);
+        match self
+            .blobs
+            .get(&thumbnail_key(&attachment.storage_key, width), None)
+            .await
+        {
+            Ok(data) => Ok(Some((width, data))),
+            Err(BlobError::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// サムネイルをまだ作っていない画像を処理し、処理を終えた（作れない画像だった場合も含む）件数を返す。
+    /// 保存に失敗したものは次回に回す。
+    pub async fn process_thumbnails(&self) -> usize {
+        let pending = match self
+            .attachments
+            .pending_thumbnails(Self::THUMBNAIL_BATCH)
+            .await
+        {
+            Ok(pending) => pending,
+            Err(e) => {
+                eprintln!("thumbnail generation failed: {e}");
+                return 0;
+            }
+        };
+        let mut processed = 0;
+        for attachment in pending {
+            if let Err(e) = self.generate_thumbnails(&attachment).await {
+                eprintln!("thumbnail generation failed: {e}");
+                continue;
+            }
+            match self.attachments.mark_thumbnails_done(attachment.id).await {
+                Ok(()) => processed += 1,
+                Err(e) => eprintln!("thumbnail generation failed: {e}"),
+            }
+        }
+        processed
+    }
+
+    /// 画像がアップロードされるまで待つ（`ThumbnailWorker` 用）。
+    pub async fn wait_for_uploaded_images(&self) {
+        self.uploaded_images.notified().await;
+    }
+
+    /// サムネイルを作って保存する。読み込めない画像（本体が無い場合も含む）は何もしない。
+    async fn generate_thumbnails(&self, attachment: &Attachment) -> Result<(), AttachmentError> {
+        let Some(image_width) = attachment.width else {
+            return Ok(());
+        };
+        let data = match self.read(attachment, None).await {
+            Ok(data) => data,
+            Err(AttachmentError::NotFound) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let widths: Vec<u32> = thumbnail_widths(image_width as u32).collect();
+        // 画像の展開・縮小は重いのでブロッキング処理用のスレッドで行う
+        let rendered = {
+            let widths = widths.clone();
+            tokio::task::spawn_blocking(move || render_thumbnails(&data, &widths))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|rendered| rendered.map_err(|e| e.to_string()))
+        };
+        let thumbnails = match rendered {
+            Ok(thumbnails) => thumbnails,
+            Err(e) => {
+                eprintln!(
+                    "thumbnail generation skipped for attachment {}: {e}",
+                    attachment.id
+                );
+                return Ok(());
+            }
+        };
+        for (width, thumbnail) in widths.into_iter().zip(thumbnails) {
+            self.blobs
+                .put(&thumbnail_key(&attachment.storage_key, width), &thumbnail)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 本体（`range` を指定するとその範囲だけ）を読み込む。
+    pub async fn read(
+        &self,
+        attachment: &Attachment,
+        range: Option<Range<u64>>,
+    ) -> Result<Vec<u8>, AttachmentError> {
+        match self.blobs.get(&attachment.storage_key, range).await {
+            Ok(data) => Ok(data),
+            Err(BlobError::NotFound) => Err(AttachmentError::NotFound),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// メタデータを削除してから本体を削除する（本体の削除に失敗しても添付ファイルは消えている）。
+    pub async fn delete(&self, note_id: i64, attachment_id: i64) -> Result<(), AttachmentError> {
+        let attachment = self
+            .attachments
+            .delete_attachment(note_id, attachment_id)
+            .await?
+            .ok_or(AttachmentError::NotFound)?;
+        self.discard_thumbnails(&attachment.storage_key).await;
+        self.discard(&attachment.storage_key).await;
+        Ok(())
+    }
+
+    /// 完全に削除されたメモに付いていた添付ファイルを本体ごと削除し、件数を返す。
+    /// 本体を削除できなかったものは次回に回す。
+    pub async fn purge_orphans(&self) -> u64 {
+        let orphans = match self
+            .attachments
+            .orphaned_attachments(Self::ORPHAN_BATCH)
+            .await
+        {
+            Ok(orphans) => orphans,
+            Err(e) => {
+                eprintln!("attachment purge failed: {e}");
+                return 0;
+            }
+        };
+        let mut purged = 0;
+        for (attachment_id, storage_key) in orphans {
+            self.discard_thumbnails(&storage_key).await;
+            if let Err(e) = self.blobs.delete(&storage_key).await {
+                eprintln!("attachment purge failed: {e}");
+                continue;
+            }
+            match self.attachments.delete_orphan(attachment_id).await {
+                Ok(true) => purged += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("attachment purge failed: {e}"),
+            }
+        }
+        purged
+    }
+
+    async fn discard(&self, storage_key: &str) {
+        if let Err(e) = self.blobs.delete(storage_key).await {
+            eprintln!("attachment blob delete failed: {e}");
+        }
+    }
+
+    async fn discard_thumbnails(&self, storage_key: &str) {
+        for width in THUMBNAIL_WIDTHS {
+            self.discard(&thumbnail_key(storage_key, width)).await;
+        }
+    }
+}
+
+/// サムネイルの `BlobStore` のキー（本体のキーに幅を付ける）。
+fn thumbnail_key(storage_key: &str, width: u32) -> String {
+    format!("{storage_key}-w{width}")
+}
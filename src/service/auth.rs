@@ -1,11 +1,14 @@
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use argon2::{Argon2, PasswordHasher};
 use password_hash::{PasswordHash, PasswordVerifier, SaltString, rand_core::OsRng};
 use thiserror::Error;
 
-use crate::domain::model::User;
+use crate::domain::model::{Role, User};
 use crate::repository::user::{RepoError, UserRepository};
+use crate::service::login_throttle::LockoutPolicy;
+use crate::service::two_factor::{TwoFactorError, TwoFactorService};
 
 /// 認証に関するユースケースを提供するサービス層。
 #[async_trait::async_trait]
@@ -26,7 +29,29 @@ pub trait AuthService: Send + Sync + 'static {
     /// - Err(Repo(_)) / Err(HashError): 内部エラー
     async fn signup(&self, email: &str, password: &str) -> Result<Option<User>, AuthServiceError>;
 
-    async fn login(&self, email: &str, password: &str) -> Result<Option<User>, AuthServiceError>;
+    /// パスワードを確認する。
+    ///
+    /// 2 段階認証が有効なユーザーはここではログインを完了せず、
+    /// `LoginOutcome::TwoFactorRequired` のチャレンジトークンを `login_2fa` に渡す必要がある。
+    async fn login(
+        &self,
+        email: &str,
+        password: &str,
+    ) -> Result<Option<LoginOutcome>, AuthServiceError>;
+
+    /// チャレンジトークンと確認コード（認証アプリのコードまたはリカバリーコード）でログインを完了する。
+    /// 一致しなければ `InvalidCredentials`。
+    async fn login_2fa(&self, challenge_token: &str, code: &str) -> Result<User, AuthServiceError>;
+}
+
+/// `AuthService::login` の結果。
+#[derive(Debug)]
+pub enum LoginOutcome {
+    Authenticated(User),
+    /// 2 段階認証が必要（チャレンジトークンは 5 分間・1 回限り）
+    TwoFactorRequired {
+        challenge_token: String,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -45,15 +70,49 @@ pub enum AuthServiceError {
 
     #[error("invalid credentials")]
     InvalidCredentials,
+
+    /// 連続した失敗でアカウントがロックされている（パスワードが正しくても返す）
+    #[error("account temporarily locked")]
+    Locked { retry_after: Duration },
+
+    /// 管理者がアカウントを無効にしている
+    #[error("account disabled")]
+    Disabled,
+}
+
+impl From<TwoFactorError> for AuthServiceError {
+    fn from(e: TwoFactorError) -> Self {
+        match e {
+            TwoFactorError::Repo(e) => AuthServiceError::Repo(e),
+            _ => AuthServiceError::InvalidCredentials,
+        }
+    }
 }
 
 pub struct AuthServiceImpl {
     user_repository: Arc<dyn UserRepository>,
+    two_factor: Option<Arc<TwoFactorService>>,
+    lockout: LockoutPolicy,
 }
 
 impl AuthServiceImpl {
     pub fn new(user_repository: Arc<dyn UserRepository>) -> Self {
-        Self { user_repository }
+        Self {
+            user_repository,
+            two_factor: None,
+            lockout: LockoutPolicy::default(),
+        }
+    }
+
+    pub fn with_lockout(mut self, lockout: LockoutPolicy) -> Self {
+        self.lockout = lockout;
+        self
+    }
+
+    /// 2 段階認証を有効にしたユーザーのログインでコードを求める。
+    pub fn with_two_factor(mut self, two_factor: Arc<TwoFactorService>) -> Self {
+        self.two_factor = Some(two_factor);
+        self
     }
 }
 
@@ -66,29 +125,72 @@ impl AuthService for AuthServiceImpl {
         if !is_valid_password(password) {
             return Err(AuthServiceError::InvalidPassword);
         }
-        let salt = SaltString::generate(&mut OsRng);
-        let hash = Argon2::default()
-            .hash_password(password.as_bytes(), &salt)
-            .map_err(|_| AuthServiceError::HashError)?
-            .to_string();
+        let hash = hash_password(password)?;
 
         let created = self.user_repository.create_user(email, &hash).await?;
         Ok(created)
     }
 
-    async fn login(&self, email: &str, password: &str) -> Result<Option<User>, AuthServiceError> {
+    async fn login(
+        &self,
+        email: &str,
+        password: &str,
+    ) -> Result<Option<LoginOutcome>, AuthServiceError> {
         let Some(user) = self.user_repository.find_by_email(email).await? else {
+            // 登録の有無を応答時間で推測させないよう、存在する場合と同じだけ検証に時間をかける
+            verify_dummy_password(password);
             return Err(AuthServiceError::InvalidCredentials);
         };
 
-        // パスワードの検証
-        let parsed = PasswordHash::new(&user.password_hash)
-            .map_err(|_| AuthServiceError::InvalidCredentials)?;
-        Argon2::default()
-            .verify_password(password.as_bytes(), &parsed)
-            .map_err(|_| AuthServiceError::InvalidCredentials)?;
+        // パスワードの検証（ロック中でも行い、応答時間をロックしていない場合とそろえる）
+        let verified = verify_password(&user, password);
+        let now = now();
+        // ロック中は正しいパスワードでも受け付けず、失敗の回数も数えない
+        if let Some(locked_until) = user.locked_until
+            && locked_until > now
+        {
+            return Err(AuthServiceError::Locked {
+                retry_after: Duration::from_secs((locked_until - now) as u64),
+            });
+        }
+        if let Err(e) = verified {
+            let failures = self.user_repository.record_login_failure(user.id).await?;
+            if let Some(secs) = self.lockout.lock_secs(failures) {
+                self.user_repository
+                    .lock_user_until(user.id, now + secs)
+                    .await?;
+            }
+            return Err(e);
+        }
+        if user.failed_login_count > 0 || user.locked_until.is_some() {
+            self.user_repository.reset_login_failures(user.id).await?;
+        }
+        // パスワードが正しい場合だけ知らせる（無効化されたことを第三者に推測させない）
+        if user.is_disabled() {
+            return Err(AuthServiceError::Disabled);
+        }
 
-        Ok(Some(user))
+        if let Some(two_factor) = &self.two_factor
+            && two_factor.is_enabled(user.id).await?
+        {
+            let challenge_token = two_factor.start_login(user.id).await?;
+            return Ok(Some(LoginOutcome::TwoFactorRequired { challenge_token }));
+        }
+        Ok(Some(LoginOutcome::Authenticated(user)))
+    }
+
+    async fn login_2fa(&self, challenge_token: &str, code: &str) -> Result<User, AuthServiceError> {
+        let Some(two_factor) = &self.two_factor else {
+            return Err(AuthServiceError::InvalidCredentials);
+        };
+        let user_id = two_factor.complete_login(challenge_token, code).await?;
+        let Some(user) = self.user_repository.find_by_id(user_id).await? else {
+            return Err(AuthServiceError::InvalidCredentials);
+        };
+        if user.is_disabled() {
+            return Err(AuthServiceError::Disabled);
+        }
+        Ok(user)
     }
 }
 
@@ -104,16 +206,38 @@ impl AuthService for MockAuthServiceSuccess {
             email: email.to_string(),
             password_hash: "x".into(),
             created_at: 0,
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role: Role::User,
+            disabled_at: None,
         }))
     }
 
-    async fn login(&self, _email: &str, _password: &str) -> Result<Option<User>, AuthServiceError> {
-        Ok(Some(User {
+    async fn login(
+        &self,
+        _email: &str,
+        _password: &str,
+    ) -> Result<Option<LoginOutcome>, AuthServiceError> {
+        Ok(Some(LoginOutcome::Authenticated(User {
             id: 1,
             email: _email.to_string(),
             password_hash: _password.into(),
             created_at: 0,
-        }))
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role: Role::User,
+            disabled_at: None,
+        })))
+    }
+
+    async fn login_2fa(
+        &self,
+        _challenge_token: &str,
+        _code: &str,
+    ) -> Result<User, AuthServiceError> {
+        Err(AuthServiceError::InvalidCredentials)
     }
 }
 
@@ -130,9 +254,59 @@ impl AuthService for MockAuthServiceConflict {
         Ok(None)
     }
 
-    async fn login(&self, _email: &str, _password: &str) -> Result<Option<User>, AuthServiceError> {
+    async fn login(
+        &self,
+        _email: &str,
+        _password: &str,
+    ) -> Result<Option<LoginOutcome>, AuthServiceError> {
         Ok(None)
     }
+
+    async fn login_2fa(
+        &self,
+        _challenge_token: &str,
+        _code: &str,
+    ) -> Result<User, AuthServiceError> {
+        Err(AuthServiceError::InvalidCredentials)
+    }
+}
+
+/// Argon2id でハッシュ化し、PHC 文字列を返す。
+pub(crate) fn hash_password(password: &str) -> Result<String, AuthServiceError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|_| AuthServiceError::HashError)?
+        .to_string())
+}
+
+/// 保存済みのハッシュと照合する。一致しなければ `InvalidCredentials`。
+pub(crate) fn verify_password(user: &User, password: &str) -> Result<(), AuthServiceError> {
+    verify_password_hash(&user.password_hash, password)
+}
+
+/// `hash_password` で作った PHC 文字列と照合する（共有リンクのパスワードにも使う）。
+pub(crate) fn verify_password_hash(hash: &str, password: &str) -> Result<(), AuthServiceError> {
+    let parsed = PasswordHash::new(hash).map_err(|_| AuthServiceError::InvalidCredentials)?;
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .map_err(|_| AuthServiceError::InvalidCredentials)
+}
+
+/// 存在しないユーザーのログインで、パスワードの検証と同じ処理を行う（結果は捨てる）。
+fn verify_dummy_password(password: &str) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    let hash = DUMMY_HASH.get_or_init(|| hash_password("dummy-password-0").unwrap_or_default());
+    if let Ok(parsed) = PasswordHash::new(hash) {
+        let _ = Argon2::default().verify_password(password.as_bytes(), &parsed);
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs() as i64
 }
 
 /// 簡易 email 検証。
@@ -165,7 +339,7 @@ fn is_valid_email(email: &str) -> bool {
 /// - 8文字以上
 /// - ASCII 英字を1文字以上含む
 /// - ASCII 数字を1文字以上含む
-fn is_valid_password(password: &str) -> bool {
+pub(crate) fn is_valid_password(password: &str) -> bool {
     if password.len() < 8 {
         return false;
     }
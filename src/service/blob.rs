@@ -0,0 +1,354 @@
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::middleware::auth::token::random_token;
+
+#[derive(Debug, Error)]
+pub enum BlobError {
+    #[error("blob not found")]
+    NotFound,
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("blob store error: {0}")]
+    Backend(String),
+
+    #[error("invalid blob store config: {0}")]
+    Config(String),
+}
+
+/// 添付ファイルの本体の保存先。`blob_store_from_env` でローカルのディレクトリか S3 互換のストレージを選ぶ。
+///
+/// キーは `/` 区切りの英数字・`-`・`_` だけ（`AttachmentService` が作る）。
+#[async_trait::async_trait]
+pub trait BlobStore: Send + Sync + 'static {
+    /// 同じキーがあれば置き換える。
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), BlobError>;
+    /// `range` を指定するとその範囲（バイト単位、終端は含まない）だけを返す。範囲は呼び出し側で
+    /// 本体の大きさに収まるようにしておく。
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<Vec<u8>, BlobError>;
+    /// 無いキーを削除してもエラーにしない。
+    async fn delete(&self, key: &str) -> Result<(), BlobError>;
+}
+
+/// `BLOB_STORE`（`local` / `s3`、既定 `local`）に応じた `BlobStore` を作る。
+pub fn blob_store_from_env() -> Result<Arc<dyn BlobStore>, BlobError> {
+    match std::env::var("BLOB_STORE").as_deref() {
+        Ok("local") | Err(_) => Ok(Arc::new(LocalBlobStore::from_env())),
+        Ok("s3") => Ok(Arc::new(S3BlobStore::from_env()?)),
+        Ok(other) => Err(BlobError::Config(format!("unknown BLOB_STORE {other:?}"))),
+    }
+}
+
+fn check_key(key: &str) -> Result<(), BlobError> {
+    let valid = key.split('/').all(|segment| {
+        !segment.is_empty()
+            && segment
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(BlobError::Backend(format!("invalid key {key:?}")))
+    }
+}
+
+/// ディレクトリの下にキーをパスとしてファイルで保存する。
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// `BLOB_DIR`（既定 `blobs`）に保存する。
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("BLOB_DIR").unwrap_or_else(|_| "blobs".into()))
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, BlobError> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), BlobError> {
+        let path = self.path(key)?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        // 書きかけのファイルを読まれないように、別名で書いてから置き換える
+        let tmp = path.with_extension(format!("tmp-{}", random_token(6)));
+        tokio::fs::write(&tmp, data).await?;
+        if let Err(e) = tokio::fs::rename(&tmp, &path).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<Vec<u8>, BlobError> {
+        let path = self.path(key)?;
+        let mut file = match tokio::fs::File::open(&path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(BlobError::NotFound),
+            Err(e) => return Err(e.into()),
+        };
+        let mut data = vec![];
+        match range {
+            Some(range) => {
+                file.seek(SeekFrom::Start(range.start)).await?;
+                file.take(range.end.saturating_sub(range.start))
+                    .read_to_end(&mut data)
+                    .await?;
+            }
+            None => {
+                file.read_to_end(&mut data).await?;
+            }
+        }
+        Ok(data)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), BlobError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// S3 互換のオブジェクトストレージ（パス形式の URL `{endpoint}/{bucket}/{key}`、署名は AWS Signature V4）。
+pub struct S3BlobStore {
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl S3BlobStore {
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    const MAX_RESPONSE_BYTES: usize = 1 << 30;
+
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        access_key_id: &str,
+        secret_access_key: &str,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            access_key_id: access_key_id.to_string(),
+            secret_access_key: secret_access_key.to_string(),
+        }
+    }
+
+    /// `S3_ENDPOINT`（例: `https://s3.ap-northeast-1.amazonaws.com`）・`S3_BUCKET`・`S3_ACCESS_KEY_ID`・
+    /// `S3_SECRET_ACCESS_KEY`（必須）、`S3_REGION`（既定 `us-east-1`）から設定する。
+    pub fn from_env() -> Result<Self, BlobError> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| BlobError::Config(format!("missing {name} env")))
+        };
+        let endpoint = var("S3_ENDPOINT")?;
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            return Err(BlobError::Config("invalid S3_ENDPOINT env".into()));
+        }
+        Ok(Self::new(
+            &endpoint,
+            &var("S3_BUCKET")?,
+            &std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".into()),
+            &var("S3_ACCESS_KEY_ID")?,
+            &var("S3_SECRET_ACCESS_KEY")?,
+        ))
+    }
+
+    /// `Authorization` ヘッダーの値。`headers` は署名するヘッダー（名前は小文字で名前順、
+    /// `host`・`x-amz-content-sha256`・`x-amz-date` を含む）。
+    pub fn authorization(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        amz_date: &str,
+    ) -> String {
+        let header = |name| {
+            headers
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map_or("", |(_, v)| *v)
+        };
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let mut canonical = format!("{method}\n{path}\n\n");
+        for (name, value) in headers {
+            canonical.push_str(&format!("{name}:{}\n", value.trim()));
+        }
+        canonical.push_str(&format!(
+            "\n{signed_headers}\n{}",
+            header("x-amz-content-sha256")
+        ));
+
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{:x}",
+            Sha256::digest(canonical.as_bytes())
+        );
+        let key = [date, &self.region, "s3", "aws4_request"].iter().fold(
+            format!("AWS4{}", self.secret_access_key).into_bytes(),
+            |key, part| hmac_sha256(&key, part.as_bytes()),
+        );
+        let signature = hmac_sha256(&key, string_to_sign.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            self.access_key_id
+        )
+    }
+
+    /// 署名したリクエストを送り、ステータスと本文を返す。
+    async fn send(
+        &self,
+        method: &str,
+        key: &str,
+        body: Vec<u8>,
+        range: Option<Range<u64>>,
+    ) -> Result<(u16, Vec<u8>), BlobError> {
+        check_key(key)?;
+        let path = format!("/{}/{key}", self.bucket);
+        let url = format!("{}{path}", self.endpoint);
+        let host = self
+            .endpoint
+            .split_once("://")
+            .map_or(self.endpoint.as_str(), |(_, host)| host)
+            .to_string();
+        let payload_hash = format!("{:x}", Sha256::digest(&body));
+        let amz_date = amz_date(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        );
+        // 範囲の終端は HTTP では含む
+        let range = range.map(|r| format!("bytes={}-{}", r.start, r.end.saturating_sub(1)));
+        let mut headers = vec![("host", host.as_str())];
+        if let Some(range) = &range {
+            headers.push(("range", range.as_str()));
+        }
+        headers.push(("x-amz-content-sha256", payload_hash.as_str()));
+        headers.push(("x-amz-date", amz_date.as_str()));
+        let authorization = self.authorization(method, &path, &headers, &amz_date);
+        let mut headers: Vec<(String, String)> = headers
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        headers.push(("authorization".into(), authorization));
+        let method = awc::http::Method::from_bytes(method.as_bytes())
+            .map_err(|e| BlobError::Backend(e.to_string()))?;
+
+        // awc のクライアントは `Send` でないので、現在のスレッドの別タスクで送る
+        actix_web::rt::spawn(async move {
+            let client = awc::Client::builder().timeout(Self::TIMEOUT).finish();
+            let mut req = client.request(method, url);
+            for (name, value) in headers {
+                req = req.insert_header((name, value));
+            }
+            let mut res = req
+                .send_body(body)
+                .await
+                .map_err(|e| BlobError::Backend(e.to_string()))?;
+            let body = res
+                .body()
+                .limit(Self::MAX_RESPONSE_BYTES)
+                .await
+                .map_err(|e| BlobError::Backend(e.to_string()))?;
+            Ok((res.status().as_u16(), body.to_vec()))
+        })
+        .await
+        .map_err(|e| BlobError::Backend(e.to_string()))?
+    }
+}
+
+#[async_trait::async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, data: &[u8]) -> Result<(), BlobError> {
+        match self.send("PUT", key, data.to_vec(), None).await? {
+            (200..=299, _) => Ok(()),
+            (status, body) => Err(s3_error(status, &body)),
+        }
+    }
+
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<Vec<u8>, BlobError> {
+        // 空の範囲は S3 では指定できないので送らない
+        if range.as_ref().is_some_and(|r| r.is_empty()) {
+            return Ok(vec![]);
+        }
+        match self.send("GET", key, vec![], range).await? {
+            (200..=299, body) => Ok(body),
+            (404, _) => Err(BlobError::NotFound),
+            (status, body) => Err(s3_error(status, &body)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), BlobError> {
+        match self.send("DELETE", key, vec![], None).await? {
+            (200..=299 | 404, _) => Ok(()),
+            (status, body) => Err(s3_error(status, &body)),
+        }
+    }
+}
+
+fn s3_error(status: u16, body: &[u8]) -> BlobError {
+    BlobError::Backend(format!("{status} {}", String::from_utf8_lossy(body)))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// UNIX 時刻（秒）を `x-amz-date` の形式（`YYYYMMDDTHHMMSSZ`、UTC）にする。
+pub fn amz_date(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+    // 1970-01-01 からの日数をグレゴリオ暦の年月日にする（3 月始まりで数える）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
@@ -0,0 +1,177 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::sync::broadcast;
+
+use crate::domain::model::{Note, Visibility};
+
+/// メモの変更の種類（通知の `type`）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteEventKind {
+    /// 作成した、またはゴミ箱から戻した
+    Created,
+    Updated,
+    /// ゴミ箱に移動した、または管理者が削除した
+    Deleted,
+}
+
+impl NoteEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "note.created",
+            Self::Updated => "note.updated",
+            Self::Deleted => "note.deleted",
+        }
+    }
+}
+
+/// `EventBus` に流れるメモの変更。
+#[derive(Debug, Clone)]
+pub struct NoteEvent {
+    /// 発行順に増える ID（SSE の `id`。再接続時にここから再開する）
+    pub id: u64,
+    pub kind: NoteEventKind,
+    /// 変更後のメモ（`Deleted` では削除する直前のメモ）
+    pub note: Note,
+    /// 更新で公開範囲が変わった場合の変更前の公開範囲
+    pub previous_visibility: Option<Visibility>,
+}
+
+/// `Subscription::recv` で受け取るもの。
+#[derive(Debug)]
+pub enum Received {
+    Event(Arc<NoteEvent>),
+    /// 通知を取りこぼした（指定された ID から再開できない、または受信が追いつかなかった）。
+    /// 受け取った側はメモを取得し直す必要がある。
+    Missed,
+}
+
+/// メモの変更をプロセス内で購読者に配る。
+///
+/// 直近の `replay_capacity` 件を保持し、`subscribe` に最後に受け取った ID を渡すと続きから再開できる。
+/// ID はマイクロ秒単位の起動時刻から始まるので、再起動前の ID は保持している範囲より古い扱いになる。
+/// 通知はプロセス内だけで配るので、複数のプロセスで動かす場合は同じプロセスでの変更しか届かない。
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<NoteEvent>>,
+    recent: Mutex<Recent>,
+    replay_capacity: usize,
+}
+
+struct Recent {
+    next_id: u64,
+    events: VecDeque<Arc<NoteEvent>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_REPLAY_CAPACITY)
+    }
+}
+
+impl EventBus {
+    const DEFAULT_REPLAY_CAPACITY: usize = 1000;
+    /// 購読者ごとに溜められる未受信の通知の数（超えると `Received::Missed`）
+    const CHANNEL_CAPACITY: usize = 256;
+
+    pub fn new(replay_capacity: usize) -> Self {
+        let next_id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or_default()
+            .max(1);
+        Self {
+            sender: broadcast::channel(Self::CHANNEL_CAPACITY).0,
+            recent: Mutex::new(Recent {
+                next_id,
+                events: VecDeque::with_capacity(replay_capacity),
+            }),
+            replay_capacity,
+        }
+    }
+
+    /// メモの変更を通知する（公開範囲が変わりうる更新には `publish_update` を使う）。
+    pub fn publish(&self, kind: NoteEventKind, note: Note) {
+        self.send(kind, note, None);
+    }
+
+    /// `before` から `after` への更新を通知する。
+    pub fn publish_update(&self, before: &Note, after: Note) {
+        let previous_visibility = Some(before.visibility).filter(|&v| v != after.visibility);
+        self.send(NoteEventKind::Updated, after, previous_visibility);
+    }
+
+    fn send(&self, kind: NoteEventKind, note: Note, previous_visibility: Option<Visibility>) {
+        let mut recent = self.recent.lock().unwrap();
+        let event = Arc::new(NoteEvent {
+            id: recent.next_id,
+            kind,
+            note,
+            previous_visibility,
+        });
+        recent.next_id += 1;
+        if recent.events.len() == self.replay_capacity {
+            recent.events.pop_front();
+        }
+        if self.replay_capacity > 0 {
+            recent.events.push_back(event.clone());
+        }
+        // 購読者がいなければ失敗するが、保持した分は後から再開した購読者に届く
+        let _ = self.sender.send(event);
+    }
+
+    /// 以降の通知を購読する。`last_event_id` を渡すと、保持している範囲ならその次の通知から受け取る
+    /// （範囲外なら最初に `Received::Missed` を受け取る）。
+    pub fn subscribe(&self, last_event_id: Option<u64>) -> Subscription {
+        // 保持している分の取り出しと購読の開始の間に通知が割り込まないようにロックしたまま行う
+        let recent = self.recent.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let (backlog, missed) = match last_event_id {
+            None => (VecDeque::new(), false),
+            Some(last) => {
+                let oldest = recent.events.front().map_or(recent.next_id, |e| e.id);
+                if last < oldest - 1 || last >= recent.next_id {
+                    (VecDeque::new(), true)
+                } else {
+                    let backlog = recent
+                        .events
+                        .iter()
+                        .filter(|e| e.id > last)
+                        .cloned()
+                        .collect();
+                    (backlog, false)
+                }
+            }
+        };
+        Subscription {
+            missed,
+            backlog,
+            receiver,
+        }
+    }
+}
+
+/// `EventBus::subscribe` で始めた購読。
+pub struct Subscription {
+    missed: bool,
+    backlog: VecDeque<Arc<NoteEvent>>,
+    receiver: broadcast::Receiver<Arc<NoteEvent>>,
+}
+
+impl Subscription {
+    /// 次の通知を待つ。
+    pub async fn recv(&mut self) -> Received {
+        if std::mem::take(&mut self.missed) {
+            return Received::Missed;
+        }
+        if let Some(event) = self.backlog.pop_front() {
+            return Received::Event(event);
+        }
+        match self.receiver.recv().await {
+            Ok(event) => Received::Event(event),
+            Err(broadcast::error::RecvError::Lagged(_)) => Received::Missed,
+            // `EventBus` が購読者より先に破棄されることは無い
+            Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ThrottleConfigError {
+    #[error("invalid {0} env")]
+    Invalid(&'static str),
+}
+
+/// 連続したログイン失敗によるアカウントのロック（`users.failed_login_count` / `locked_until` に記録する）。
+///
+/// `threshold` 回続けて失敗するとロックし、その後も失敗が続くたびにロック時間を 2 倍にする
+/// （`max_lock_secs` まで）。ログインに成功すると回数は 0 に戻る。
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    threshold: i64,
+    lock_secs: i64,
+    max_lock_secs: i64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self::new(10, 15 * 60, 24 * 60 * 60)
+    }
+}
+
+impl LockoutPolicy {
+    pub fn new(threshold: i64, lock_secs: i64, max_lock_secs: i64) -> Self {
+        Self {
+            threshold,
+            lock_secs,
+            max_lock_secs,
+        }
+    }
+
+    /// `LOGIN_LOCKOUT_THRESHOLD`（既定 10 回）、`LOGIN_LOCKOUT_SECS`（既定 15 分）、
+    /// `LOGIN_LOCKOUT_MAX_SECS`（既定 24 時間）から設定する。
+    pub fn from_env() -> Result<Self, ThrottleConfigError> {
+        let default = Self::default();
+        Ok(Self::new(
+            env_positive("LOGIN_LOCKOUT_THRESHOLD", default.threshold as u64)? as i64,
+            env_positive("LOGIN_LOCKOUT_SECS", default.lock_secs as u64)? as i64,
+            env_positive("LOGIN_LOCKOUT_MAX_SECS", default.max_lock_secs as u64)? as i64,
+        ))
+    }
+
+    /// `failures` 回目の失敗でロックする秒数（まだロックしないなら None）。
+    pub fn lock_secs(&self, failures: i64) -> Option<i64> {
+        if failures < self.threshold {
+            return None;
+        }
+        let exp = (failures - self.threshold).min(30) as u32;
+        Some(
+            self.lock_secs
+                .saturating_mul(1 << exp)
+                .min(self.max_lock_secs),
+        )
+    }
+}
+
+struct Entry {
+    failures: u32,
+    last_failure: Instant,
+    blocked_until: Option<Instant>,
+}
+
+/// IP アドレス・メールアドレスごとのログイン失敗の記録（プロセス内のメモリに保持する）。
+///
+/// `free_attempts` 回までは待たずに試せるが、それを超えると失敗のたびに
+/// 1 秒・2 秒・4 秒…と次の試行まで待たせる（`max_backoff` まで）。
+/// 最後の失敗から `max_backoff` 経つと記録を忘れる。
+pub struct LoginThrottle {
+    entries: Mutex<HashMap<String, Entry>>,
+    free_attempts: u32,
+    base_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl LoginThrottle {
+    const DEFAULT_FREE_ATTEMPTS: u64 = 5;
+    const DEFAULT_MAX_BACKOFF_SECS: u64 = 15 * 60;
+    /// これを超えたら期限切れの記録を掃除する
+    const MAX_ENTRIES: usize = 100_000;
+
+    pub fn new(free_attempts: u32, base_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            free_attempts,
+            base_backoff,
+            max_backoff,
+        }
+    }
+
+    /// `LOGIN_FREE_ATTEMPTS`（既定 5 回）、`LOGIN_BACKOFF_MAX_SECS`（既定 15 分）から設定する。
+    pub fn from_env() -> Result<Self, ThrottleConfigError> {
+        let free_attempts = env_positive("LOGIN_FREE_ATTEMPTS", Self::DEFAULT_FREE_ATTEMPTS)?;
+        let max_backoff = env_positive("LOGIN_BACKOFF_MAX_SECS", Self::DEFAULT_MAX_BACKOFF_SECS)?;
+        Ok(Self::new(
+            u32::try_from(free_attempts)
+                .map_err(|_| ThrottleConfigError::Invalid("LOGIN_FREE_ATTEMPTS"))?,
+            Duration::from_secs(1),
+            Duration::from_secs(max_backoff),
+        ))
+    }
+
+    /// いずれかのキーが待機中なら、試行できるまでの時間（最も長いもの）を返す。
+    pub fn check(&self, keys: &[String]) -> Result<(), Duration> {
+        let entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let wait = keys
+            .iter()
+            .filter_map(|key| entries.get(key)?.blocked_until)
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+            .max();
+        match wait {
+            Some(wait) => Err(wait),
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_failure(&self, keys: &[String]) {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        if entries.len() > Self::MAX_ENTRIES {
+            entries.retain(|_, e| now - e.last_failure <= self.max_backoff);
+        }
+        for key in keys {
+            let entry = entries.entry(key.clone()).or_insert(Entry {
+                failures: 0,
+                last_failure: now,
+                blocked_until: None,
+            });
+            if now - entry.last_failure > self.max_backoff {
+                entry.failures = 0;
+            }
+            entry.failures += 1;
+            entry.last_failure = now;
+            if entry.failures > self.free_attempts {
+                let exp = (entry.failures - self.free_attempts - 1).min(30);
+                let backoff = self
+                    .base_backoff
+                    .saturating_mul(1 << exp)
+                    .min(self.max_backoff);
+                entry.blocked_until = Some(now + backoff);
+            }
+        }
+    }
+
+    /// ログインに成功したキーの記録を消す。
+    pub fn reset(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+}
+
+fn env_positive(name: &'static str, default: u64) -> Result<u64, ThrottleConfigError> {
+    match std::env::var(name) {
+        Ok(v) => match v.parse::<u64>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(ThrottleConfigError::Invalid(name)),
+        },
+        Err(_) => Ok(default),
+    }
+}
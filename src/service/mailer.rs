@@ -0,0 +1,310 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::middleware::auth::token::random_token;
+
+/// 送信するメール（本文はプレーンテキスト）。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MailerError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("smtp error: {0}")]
+    Smtp(String),
+
+    #[error("invalid mailer config: {0}")]
+    Config(String),
+}
+
+/// メールの送信手段。`mailer_from_env` で SMTP かファイル（outbox）を選ぶ。
+#[async_trait::async_trait]
+pub trait Mailer: Send + Sync + 'static {
+    async fn send(&self, email: &Email) -> Result<(), MailerError>;
+}
+
+/// `MAILER`（`smtp` / `outbox`、既定 `outbox`）に応じた `Mailer` を作る。
+pub fn mailer_from_env() -> Result<Arc<dyn Mailer>, MailerError> {
+    match std::env::var("MAILER").as_deref() {
+        Ok("smtp") => Ok(Arc::new(SmtpMailer::from_env()?)),
+        Ok("outbox") | Err(_) => Ok(Arc::new(OutboxMailer::from_env())),
+        Ok(other) => Err(MailerError::Config(format!("unknown MAILER {other:?}"))),
+    }
+}
+
+/// 送信せずにディレクトリへ 1 通 1 ファイル（JSON）で書き出す。開発・テスト用。
+pub struct OutboxMailer {
+    dir: PathBuf,
+    seq: AtomicU64,
+}
+
+impl OutboxMailer {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    /// `MAIL_OUTBOX_DIR`（既定 `outbox`）に書き出す。
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("MAIL_OUTBOX_DIR").unwrap_or_else(|_| "outbox".into()))
+    }
+
+    /// 書き出したメールを古い順に返す（テストで使用）。
+    #[allow(dead_code)]
+    pub fn sent(&self) -> Result<Vec<Email>, MailerError> {
+        let mut paths = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .map(|e| e.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        paths.retain(|p| p.extension().is_some_and(|ext| ext == "json"));
+        paths.sort();
+        paths
+            .iter()
+            .map(|p| {
+                let json = std::fs::read(p)?;
+                serde_json::from_slice(&json).map_err(|e| MailerError::Io(std::io::Error::other(e)))
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for OutboxMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailerError> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::from_secs(0))
+            .as_millis();
+        // ファイル名の順序 = 送信順（同じミリ秒内は連番、別プロセスとの衝突は乱数で避ける）
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let path = self
+            .dir
+            .join(format!("{millis:020}-{seq:08}-{}.json", random_token(6)));
+        let json = serde_json::to_vec_pretty(email).map_err(std::io::Error::other)?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(path, json).await?;
+        Ok(())
+    }
+}
+
+/// SMTP の暗号化方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// 平文（ローカルの中継サーバー向け）
+    None,
+    /// 平文で接続して `STARTTLS` で切り替える（既定、587 番）
+    StartTls,
+    /// 最初から TLS で接続する（465 番）
+    Tls,
+}
+
+/// SMTP でメールを送る。送信はブロッキング I/O なので `spawn_blocking` で行う。
+#[derive(Clone)]
+pub struct SmtpMailer {
+    host: String,
+    port: u16,
+    tls: SmtpTls,
+    credentials: Option<(String, String)>,
+    from: String,
+}
+
+impl SmtpMailer {
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(host: &str, port: u16, tls: SmtpTls, from: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            tls,
+            credentials: None,
+            from: from.to_string(),
+        }
+    }
+
+    /// `AUTH PLAIN` で認証する。
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// `SMTP_HOST`・`MAIL_FROM`（必須）、`SMTP_TLS`（`starttls` / `tls` / `none`、既定 `starttls`）、
+    /// `SMTP_PORT`（既定は `SMTP_TLS` に応じて 587 / 465 / 25）、`SMTP_USERNAME`・`SMTP_PASSWORD` から設定する。
+    pub fn from_env() -> Result<Self, MailerError> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| MailerError::Config(format!("missing {name} env")))
+        };
+        let host = var("SMTP_HOST")?;
+        let from = var("MAIL_FROM")?;
+        let tls = match std::env::var("SMTP_TLS").as_deref() {
+            Ok("starttls") | Err(_) => SmtpTls::StartTls,
+            Ok("tls") => SmtpTls::Tls,
+            Ok("none") => SmtpTls::None,
+            Ok(other) => {
+                return Err(MailerError::Config(format!("unknown SMTP_TLS {other:?}")));
+            }
+        };
+        let port = match std::env::var("SMTP_PORT") {
+            Ok(v) => v
+                .parse::<u16>()
+                .map_err(|_| MailerError::Config("invalid SMTP_PORT env".into()))?,
+            Err(_) => match tls {
+                SmtpTls::None => 25,
+                SmtpTls::StartTls => 587,
+                SmtpTls::Tls => 465,
+            },
+        };
+        let mailer = Self::new(&host, port, tls, &from);
+        Ok(match (var("SMTP_USERNAME"), var("SMTP_PASSWORD")) {
+            (Ok(username), Ok(password)) => mailer.with_credentials(&username, &password),
+            _ => mailer,
+        })
+    }
+
+    fn send_blocking(&self, email: &Email) -> Result<(), MailerError> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))?;
+        tcp.set_read_timeout(Some(Self::TIMEOUT))?;
+        tcp.set_write_timeout(Some(Self::TIMEOUT))?;
+        match self.tls {
+            SmtpTls::None => self.transaction(SmtpConnection::open(tcp)?, email),
+            SmtpTls::Tls => self.transaction(SmtpConnection::open(self.tls_connect(tcp)?)?, email),
+            SmtpTls::StartTls => {
+                let mut conn = SmtpConnection::open(tcp)?;
+                conn.command("EHLO memo-app", 250)?;
+                conn.command("STARTTLS", 220)?;
+                let tls = self.tls_connect(conn.into_inner())?;
+                self.transaction(SmtpConnection::new(tls), email)
+            }
+        }
+    }
+
+    fn tls_connect(&self, tcp: TcpStream) -> Result<native_tls::TlsStream<TcpStream>, MailerError> {
+        let connector =
+            native_tls::TlsConnector::new().map_err(|e| MailerError::Smtp(e.to_string()))?;
+        connector
+            .connect(&self.host, tcp)
+            .map_err(|e| MailerError::Smtp(e.to_string()))
+    }
+
+    fn transaction<S: Read + Write>(
+        &self,
+        mut conn: SmtpConnection<S>,
+        email: &Email,
+    ) -> Result<(), MailerError> {
+        conn.command("EHLO memo-app", 250)?;
+        if let Some((username, password)) = &self.credentials {
+            let plain = STANDARD.encode(format!("\0{username}\0{password}"));
+            conn.command(&format!("AUTH PLAIN {plain}"), 235)?;
+        }
+        conn.command(&format!("MAIL FROM:<{}>", self.from), 250)?;
+        conn.command(&format!("RCPT TO:<{}>", email.to), 250)?;
+        conn.command("DATA", 354)?;
+        conn.command(&format!("{}\r\n.", self.message(email)), 250)?;
+        // 送信は完了しているので QUIT の失敗は無視する
+        let _ = conn.command("QUIT", 221);
+        Ok(())
+    }
+
+    /// 件名は RFC 2047、本文は base64 でエンコードする（行頭の `.` のエスケープも不要になる）。
+    fn message(&self, email: &Email) -> String {
+        let body = STANDARD.encode(email.body.as_bytes());
+        let lines: Vec<&str> = body
+            .as_bytes()
+            .chunks(76)
+            .map(|c| std::str::from_utf8(c).unwrap_or_default())
+            .collect();
+        format!(
+            "From: <{}>\r\nTo: <{}>\r\nSubject: =?UTF-8?B?{}?=\r\nMIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=UTF-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}",
+            self.from,
+            email.to,
+            STANDARD.encode(email.subject.as_bytes()),
+            lines.join("\r\n"),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailerError> {
+        // ヘッダーインジェクション対策
+        if [&self.from, &email.to]
+            .iter()
+            .any(|v| v.contains(['\r', '\n', '<', '>']))
+        {
+            return Err(MailerError::Smtp("invalid address".into()));
+        }
+        let mailer = self.clone();
+        let email = email.clone();
+        tokio::task::spawn_blocking(move || mailer.send_blocking(&email))
+            .await
+            .map_err(|e| MailerError::Smtp(e.to_string()))?
+    }
+}
+
+struct SmtpConnection<S: Read + Write> {
+    stream: BufReader<S>,
+}
+
+impl<S: Read + Write> SmtpConnection<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    /// 接続してサーバーの挨拶（220）を待つ。
+    fn open(stream: S) -> Result<Self, MailerError> {
+        let mut conn = Self::new(stream);
+        conn.expect(220)?;
+        Ok(conn)
+    }
+
+    fn command(&mut self, line: &str, expected: u16) -> Result<(), MailerError> {
+        let stream = self.stream.get_mut();
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        self.expect(expected)
+    }
+
+    /// 応答を読み、最終行のコードが `expected` でなければエラーにする（複数行の応答は `250-...` の形）。
+    fn expect(&mut self, expected: u16) -> Result<(), MailerError> {
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(MailerError::Smtp("connection closed".into()));
+            }
+            if line.as_bytes().get(3) == Some(&b'-') {
+                continue;
+            }
+            return match line.get(..3).and_then(|code| code.parse::<u16>().ok()) {
+                Some(code) if code == expected => Ok(()),
+                _ => Err(MailerError::Smtp(line.trim_end().to_string())),
+            };
+        }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+}
@@ -1 +1,15 @@
+pub mod account;
+pub mod admin;
+pub mod attachment;
 pub mod auth;
+pub mod blob;
+pub mod events;
+pub mod login_throttle;
+pub mod mailer;
+pub mod notebook;
+pub mod session;
+pub mod share;
+pub mod thumbnail;
+pub mod trash;
+pub mod two_factor;
+pub mod workspace;
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::domain::model::{Note, Notebook};
+use crate::repository::note::NoteRepository;
+use crate::repository::notebook::{NotebookDeleteMode, NotebookRepository};
+use crate::repository::user::RepoError;
+
+#[derive(Debug, Error)]
+pub enum NotebookError {
+    /// ノートブックかメモが存在しない、または自分のものではない（存在自体を秘匿する）
+    #[error("not found")]
+    NotFound,
+
+    /// ノートブックを自分自身か子孫の下に移そうとした
+    #[error("notebook cannot be moved into itself")]
+    Cycle,
+
+    #[error("invalid name")]
+    InvalidName,
+
+    /// ワークスペースのメモはノートブックに入れられない
+    #[error("workspace notes cannot be filed")]
+    WorkspaceNote,
+
+    #[error(transparent)]
+    Repo(RepoError),
+}
+
+impl From<RepoError> for NotebookError {
+    fn from(e: RepoError) -> Self {
+        match e {
+            RepoError::Conflict => NotebookError::Cycle,
+            e => NotebookError::Repo(e),
+        }
+    }
+}
+
+/// ノートブックの作成・変更と、メモの出し入れ。
+///
+/// ノートブックは作成したユーザーだけが使え、入れられるのは自分が作成した個人のメモだけ
+/// （ワークスペースのメモは入れられない）。他人のノートブックは存在しないものとして扱う。
+pub struct NotebookService {
+    notebooks: Arc<dyn NotebookRepository>,
+    notes: Arc<dyn NoteRepository>,
+}
+
+impl NotebookService {
+    const MAX_NAME_CHARS: usize = 100;
+
+    pub fn new(notebooks: Arc<dyn NotebookRepository>, notes: Arc<dyn NoteRepository>) -> Self {
+        Self { notebooks, notes }
+    }
+
+    /// `parent_id` を指定するとそのノートブックの下に作成する。
+    pub async fn create(
+        &self,
+        user_id: i64,
+        parent_id: Option<i64>,
+        name: &str,
+    ) -> Result<Notebook, NotebookError> {
+        let name = Self::validate_name(name)?;
+        if let Some(parent_id) = parent_id {
+            self.find(user_id, parent_id).await?;
+        }
+        Ok(self
+            .notebooks
+            .create_notebook(user_id, parent_id, name)
+            .await?)
+    }
+
+    pub async fn find(&self, user_id: i64, notebook_id: i64) -> Result<Notebook, NotebookError> {
+        self.notebooks
+            .find_notebook(user_id, notebook_id)
+            .await?
+            .ok_or(NotebookError::NotFound)
+    }
+
+    pub async fn list(&self, user_id: i64) -> Result<Vec<Notebook>, NotebookError> {
+        Ok(self.notebooks.list_notebooks(user_id).await?)
+    }
+
+    pub async fn rename(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        name: &str,
+    ) -> Result<Notebook, NotebookError> {
+        let name = Self::validate_name(name)?;
+        self.notebooks
+            .rename_notebook(user_id, notebook_id, name)
+            .await?
+            .ok_or(NotebookError::NotFound)
+    }
+
+    /// `parent_id` の下に移す（`None` ならトップレベルに移す）。
+    pub async fn move_to(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        parent_id: Option<i64>,
+    ) -> Result<Notebook, NotebookError> {
+        self.notebooks
+            .move_notebook(user_id, notebook_id, parent_id)
+            .await?
+            .ok_or(NotebookError::NotFound)
+    }
+
+    /// ノートブックを削除し、移した・ゴミ箱に入れたメモを返す。
+    pub async fn delete(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        mode: NotebookDeleteMode,
+    ) -> Result<Vec<Note>, NotebookError> {
+        self.notebooks
+            .delete_notebook(user_id, notebook_id, mode)
+            .await?
+            .ok_or(NotebookError::NotFound)
+    }
+
+    /// メモを `notebook_id` に入れ（`None` ならノートブックから出し）、更新後のメモを返す。
+    pub async fn file_note(
+        &self,
+        user_id: i64,
+        note_id: i64,
+        notebook_id: Option<i64>,
+    ) -> Result<Note, NotebookError> {
+        let note = self
+            .notes
+            .find_by_id(note_id)
+            .await?
+            .filter(|note| note.author_id == user_id)
+            .ok_or(NotebookError::NotFound)?;
+        if note.workspace_id.is_some() {
+            return Err(NotebookError::WorkspaceNote);
+        }
+        if let Some(notebook_id) = notebook_id {
+            self.find(user_id, notebook_id).await?;
+        }
+        if !self
+            .notebooks
+            .file_note(user_id, note_id, notebook_id)
+            .await?
+        {
+            return Err(NotebookError::NotFound);
+        }
+        self.notes
+            .find_by_id(note_id)
+            .await?
+            .ok_or(NotebookError::NotFound)
+    }
+
+    fn validate_name(name: &str) -> Result<&str, NotebookError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > Self::MAX_NAME_CHARS {
+            return Err(NotebookError::InvalidName);
+        }
+        Ok(name)
+    }
+}
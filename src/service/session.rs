@@ -0,0 +1,180 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::middleware::auth::model::JWTClaim;
+use crate::middleware::auth::token::{hash_token, random_token};
+use crate::repository::token::TokenRepository;
+use crate::repository::user::RepoError;
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    /// 存在しない・期限切れ・失効済みのリフレッシュトークン
+    #[error("invalid refresh token")]
+    InvalidToken,
+
+    /// ローテーション済みのトークンが再利用された（ファミリーごと失効させた）
+    #[error("refresh token reused")]
+    TokenReused,
+
+    #[error("invalid REFRESH_TOKEN_EXP_SECS env")]
+    InvalidExpiration,
+
+    #[error("invalid TOKEN_PURGE_INTERVAL_SECS env")]
+    InvalidPurgeInterval,
+
+    #[error(transparent)]
+    Repo(#[from] RepoError),
+}
+
+/// リフレッシュトークンの発行・ローテーションとログアウト。
+///
+/// リフレッシュトークンは使うたびに新しいものと交換する（ローテーション）。
+/// 交換済みのトークンが再び使われた場合は漏えいとみなし、同じログインから続く
+/// トークン（ファミリー）をすべて失効させる。
+pub struct SessionService {
+    tokens: Arc<dyn TokenRepository>,
+    refresh_exp_secs: i64,
+}
+
+impl SessionService {
+    const DEFAULT_REFRESH_EXP_SECS: i64 = 30 * 24 * 60 * 60;
+
+    pub fn new(tokens: Arc<dyn TokenRepository>, refresh_exp_secs: i64) -> Self {
+        Self {
+            tokens,
+            refresh_exp_secs,
+        }
+    }
+
+    /// `REFRESH_TOKEN_EXP_SECS`（既定 30 日）から設定する。
+    pub fn from_env(tokens: Arc<dyn TokenRepository>) -> Result<Self, SessionError> {
+        let exp = match std::env::var("REFRESH_TOKEN_EXP_SECS") {
+            Ok(v) => v
+                .parse::<i64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .ok_or(SessionError::InvalidExpiration)?,
+            Err(_) => Self::DEFAULT_REFRESH_EXP_SECS,
+        };
+        Ok(Self::new(tokens, exp))
+    }
+
+    /// ログイン時に新しいファミリーのリフレッシュトークンを発行する。
+    pub async fn start(&self, user_id: i64) -> Result<String, SessionError> {
+        self.issue(user_id, &random_token(16)).await
+    }
+
+    /// リフレッシュトークンを新しいものと交換し、`(user_id, 新しいトークン)` を返す。
+    pub async fn refresh(&self, refresh_token: &str) -> Result<(i64, String), SessionError> {
+        let Some(stored) = self
+            .tokens
+            .find_refresh_token(&hash_token(refresh_token))
+            .await?
+        else {
+            return Err(SessionError::InvalidToken);
+        };
+        if stored.revoked_at.is_some() || stored.expires_at <= now() {
+            return Err(SessionError::InvalidToken);
+        }
+        // 使用済みへの更新は条件付き UPDATE なので、同時に使われた場合も片方は再利用扱いになる
+        if stored.used_at.is_some() || !self.tokens.mark_refresh_token_used(stored.id).await? {
+            self.tokens.revoke_token_family(&stored.family_id).await?;
+            return Err(SessionError::TokenReused);
+        }
+        let next = self.issue(stored.user_id, &stored.family_id).await?;
+        Ok((stored.user_id, next))
+    }
+
+    /// ログアウト。アクセストークンを失効リストに載せ、
+    /// リフレッシュトークンが渡されればそのファミリーも失効させる。
+    pub async fn end(
+        &self,
+        claim: &JWTClaim,
+        refresh_token: Option<&str>,
+    ) -> Result<(), SessionError> {
+        if !claim.jti.is_empty() {
+            self.tokens
+                .revoke_access_token(&claim.jti, claim.exp)
+                .await?;
+        }
+        if let Some(refresh_token) = refresh_token
+            && let Some(stored) = self
+                .tokens
+                .find_refresh_token(&hash_token(refresh_token))
+                .await?
+            && stored.user_id == claim.sub
+        {
+            self.tokens.revoke_token_family(&stored.family_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn issue(&self, user_id: i64, family_id: &str) -> Result<String, SessionError> {
+        let token = random_token(32);
+        self.tokens
+            .create_refresh_token(
+                user_id,
+                family_id,
+                &hash_token(&token),
+                self.refresh_exp_secs,
+            )
+            .await?;
+        Ok(token)
+    }
+}
+
+/// 有効期限を過ぎた失効リストの行とリフレッシュトークンを定期的に削除するバックグラウンドタスク。
+/// 期限切れのトークンは削除しなくても受け付けないので、テーブルが増え続けないようにするためのもの。
+pub struct TokenPurger {
+    tokens: Arc<dyn TokenRepository>,
+    interval: Duration,
+}
+
+impl TokenPurger {
+    const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+    pub fn new(tokens: Arc<dyn TokenRepository>, interval: Duration) -> Self {
+        Self { tokens, interval }
+    }
+
+    /// `TOKEN_PURGE_INTERVAL_SECS`（既定 1 時間）から設定する。
+    pub fn from_env(tokens: Arc<dyn TokenRepository>) -> Result<Self, SessionError> {
+        let interval = match std::env::var("TOKEN_PURGE_INTERVAL_SECS") {
+            Ok(v) => match v.parse::<u64>() {
+                Ok(secs) if secs > 0 => secs,
+                _ => return Err(SessionError::InvalidPurgeInterval),
+            },
+            Err(_) => Self::DEFAULT_INTERVAL_SECS,
+        };
+        Ok(Self::new(tokens, Duration::from_secs(interval)))
+    }
+
+    /// 期限切れのトークンを 1 回だけ削除し、件数を返す。
+    pub async fn purge_once(&self) -> u64 {
+        match self.tokens.purge_expired(now()).await {
+            Ok(purged) => purged,
+            Err(e) => {
+                eprintln!("token purge failed: {e}");
+                0
+            }
+        }
+    }
+
+    /// `interval` ごとに `purge_once` を繰り返す（起動直後にも 1 回実行する）。
+    pub async fn run(self) {
+        let mut ticker = actix_web::rt::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            self.purge_once().await;
+        }
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs() as i64
+}
@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::domain::model::{NoteShare, NoteSharing, ShareLink, SharePermission};
+use crate::domain::policy::{NoteAction, can_access_note};
+use crate::middleware::auth::token::{hash_token, random_token};
+use crate::repository::note::NoteRepository;
+use crate::repository::share::{NewShareLink, ShareRepository};
+use crate::repository::user::{RepoError, UserRepository};
+use crate::repository::workspace::WorkspaceRepository;
+use crate::service::auth::{hash_password, verify_password_hash};
+
+#[derive(Debug, Error)]
+pub enum ShareError {
+    /// メモが存在しない、または閲覧できない（存在自体を秘匿する）
+    #[error("not found")]
+    NotFound,
+
+    /// 閲覧はできるが共有を管理する権限がない
+    #[error("forbidden")]
+    Forbidden,
+
+    /// 共有先のメールアドレスのユーザーがいない、または自分自身
+    #[error("unknown user")]
+    UnknownUser,
+
+    /// 有効期限・パスワードの指定が不正
+    #[error("invalid input")]
+    InvalidInput,
+
+    /// 共有リンクにパスワードが設定されていて、指定が無いか一致しない
+    #[error("password required")]
+    PasswordRequired,
+
+    #[error("hash error")]
+    Hash,
+
+    #[error(transparent)]
+    Repo(#[from] RepoError),
+}
+
+/// メモの個別の共有と共有リンク。
+///
+/// 共有の管理は作成者（ワークスペースのメモならオーナーと作成者本人の編集者）だけが行える。
+/// 共有リンクのトークンは作成時のレスポンスでのみ返し、DB にはハッシュのみ保存する。
+pub struct ShareService {
+    shares: Arc<dyn ShareRepository>,
+    notes: Arc<dyn NoteRepository>,
+    workspaces: Arc<dyn WorkspaceRepository>,
+    users: Arc<dyn UserRepository>,
+}
+
+impl ShareService {
+    const MAX_LINK_EXPIRES_IN_DAYS: i64 = 365;
+    const MAX_PASSWORD_CHARS: usize = 128;
+    const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+    pub fn new(
+        shares: Arc<dyn ShareRepository>,
+        notes: Arc<dyn NoteRepository>,
+        workspaces: Arc<dyn WorkspaceRepository>,
+        users: Arc<dyn UserRepository>,
+    ) -> Self {
+        Self {
+            shares,
+            notes,
+            workspaces,
+            users,
+        }
+    }
+
+    /// 共有しているユーザーと共有リンクの一覧。
+    pub async fn sharing(&self, user_id: i64, note_id: i64) -> Result<NoteSharing, ShareError> {
+        self.require_sharer(note_id, user_id).await?;
+        Ok(NoteSharing {
+            users: self.shares.list_shares(note_id).await?,
+            links: self.shares.list_links(note_id).await?,
+        })
+    }
+
+    /// `email` のユーザーとメモを共有する。既に共有していれば権限を置き換える。
+    pub async fn share_with(
+        &self,
+        user_id: i64,
+        note_id: i64,
+        email: &str,
+        permission: SharePermission,
+    ) -> Result<NoteShare, ShareError> {
+        self.require_sharer(note_id, user_id).await?;
+        let target = match self.users.find_by_email(email.trim()).await? {
+            Some(target) if target.id != user_id => target,
+            _ => return Err(ShareError::UnknownUser),
+        };
+        Ok(self
+            .shares
+            .share_note(note_id, target.id, permission, user_id)
+            .await?)
+    }
+
+    pub async fn unshare(
+        &self,
+        user_id: i64,
+        note_id: i64,
+        target_id: i64,
+    ) -> Result<(), ShareError> {
+        self.require_sharer(note_id, user_id).await?;
+        if !self.shares.unshare_note(note_id, target_id).await? {
+            return Err(ShareError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// 共有リンクを作成し、トークン本体と一緒に返す。
+    /// `expires_in_days` は 1..=365（`None` なら無期限）。
+    pub async fn create_link(
+        &self,
+        user_id: i64,
+        note_id: i64,
+        permission: SharePermission,
+        expires_in_days: Option<i64>,
+        password: Option<&str>,
+    ) -> Result<(ShareLink, String), ShareError> {
+        let ttl_secs = match expires_in_days {
+            Some(days @ 1..=Self::MAX_LINK_EXPIRES_IN_DAYS) => Some(days * Self::SECS_PER_DAY),
+            Some(_) => return Err(ShareError::InvalidInput),
+            None => None,
+        };
+        if password.is_some_and(|p| p.is_empty() || p.chars().count() > Self::MAX_PASSWORD_CHARS) {
+            return Err(ShareError::InvalidInput);
+        }
+        self.require_sharer(note_id, user_id).await?;
+
+        let password_hash = password
+            .map(hash_password)
+            .transpose()
+            .map_err(|_| ShareError::Hash)?;
+        let token = random_token(32);
+        let link = self
+            .shares
+            .create_link(
+                note_id,
+                user_id,
+                &NewShareLink {
+                    permission,
+                    token_hash: &hash_token(&token),
+                    password_hash: password_hash.as_deref(),
+                    ttl_secs,
+                },
+            )
+            .await?;
+        Ok((link, token))
+    }
+
+    pub async fn revoke_link(
+        &self,
+        user_id: i64,
+        note_id: i64,
+        link_id: i64,
+    ) -> Result<(), ShareError> {
+        self.require_sharer(note_id, user_id).await?;
+        if !self.shares.delete_link(note_id, link_id).await? {
+            return Err(ShareError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// `viewer` に個別に共有された権限と、共有リンク `token` の権限のうち強い方。
+    /// リンクが無効（存在しない・期限切れ・別のメモのもの）なら無視する。
+    /// パスワード付きのリンクで `password` が無いか一致しなければ `PasswordRequired`。
+    pub async fn permission_for(
+        &self,
+        note_id: i64,
+        viewer: Option<i64>,
+        token: Option<&str>,
+        password: Option<&str>,
+    ) -> Result<Option<SharePermission>, ShareError> {
+        let shared = match viewer {
+            Some(user_id) => self.shares.shared_permission(note_id, user_id).await?,
+            None => None,
+        };
+        let Some(token) = token else {
+            return Ok(shared);
+        };
+        let link = match self.shares.find_link(&hash_token(token)).await? {
+            Some(link) if link.note_id == note_id => link,
+            _ => return Ok(shared),
+        };
+        if let Some(hash) = link.password_hash.as_deref() {
+            let Some(password) = password else {
+                return Err(ShareError::PasswordRequired);
+            };
+            verify_password_hash(hash, password).map_err(|_| ShareError::PasswordRequired)?;
+        }
+        Ok(shared.max(Some(link.permission)))
+    }
+
+    /// 共有を管理できるか確かめる。閲覧もできないメモは `NotFound`、閲覧だけできるなら `Forbidden`。
+    async fn require_sharer(&self, note_id: i64, user_id: i64) -> Result<(), ShareError> {
+        let note = self
+            .notes
+            .find_by_id(note_id)
+            .await?
+            .ok_or(ShareError::NotFound)?;
+        let role = match note.workspace_id {
+            Some(workspace_id) => self.workspaces.member_role(workspace_id, user_id).await?,
+            None => None,
+        };
+        let shared = self.shares.shared_permission(note_id, user_id).await?;
+        let allowed = |action| can_access_note(&note, Some(user_id), role, shared, action);
+        if allowed(NoteAction::Share) {
+            Ok(())
+        } else if allowed(NoteAction::Read) {
+            Err(ShareError::Forbidden)
+        } else {
+            Err(ShareError::NotFound)
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::service::attachment::AttachmentService;
+
+/// 画像の添付ファイルのサムネイルを作るバックグラウンドタスク。
+/// アップロードされるとすぐに処理し、それとは別に `interval` ごとに未処理のもの
+/// （起動前にアップロードされたものや、保存に失敗したもの）を探す。
+pub struct ThumbnailWorker {
+    attachments: Arc<AttachmentService>,
+    interval: Duration,
+}
+
+impl ThumbnailWorker {
+    const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(attachments: Arc<AttachmentService>) -> Self {
+        Self {
+            attachments,
+            interval: Self::DEFAULT_INTERVAL,
+        }
+    }
+
+    /// 未処理のものが無くなるまで処理し、次のアップロードか `interval` の経過を待つ、を繰り返す。
+    pub async fn run(self) {
+        loop {
+            if self.attachments.process_thumbnails().await > 0 {
+                continue;
+            }
+            tokio::select! {
+                _ = self.attachments.wait_for_uploaded_images() => {}
+                _ = actix_web::rt::time::sleep(self.interval) => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::repository::note::NoteRepository;
+use crate::service::attachment::AttachmentService;
+
+#[derive(Debug, Error)]
+pub enum TrashConfigError {
+    #[error("invalid TRASH_RETENTION_DAYS env")]
+    InvalidRetention,
+    #[error("invalid TRASH_PURGE_INTERVAL_SECS env")]
+    InvalidInterval,
+}
+
+/// 保持期間を過ぎたゴミ箱内のメモを定期的に完全削除するバックグラウンドタスク。
+/// `with_attachments` を指定すると、完全に削除されたメモの添付ファイルの本体も削除する。
+pub struct TrashPurger {
+    note_repo: Arc<dyn NoteRepository>,
+    attachments: Option<Arc<AttachmentService>>,
+    retention: Duration,
+    interval: Duration,
+}
+
+impl TrashPurger {
+    const DEFAULT_RETENTION_DAYS: u64 = 30;
+    const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+    pub fn new(
+        note_repo: Arc<dyn NoteRepository>,
+        retention: Duration,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            note_repo,
+            attachments: None,
+            retention,
+            interval,
+        }
+    }
+
+    pub fn with_attachments(mut self, attachments: Arc<AttachmentService>) -> Self {
+        self.attachments = Some(attachments);
+        self
+    }
+
+    /// `TRASH_RETENTION_DAYS`（既定 30 日）と `TRASH_PURGE_INTERVAL_SECS`（既定 1 時間）から設定する。
+    pub fn from_env(note_repo: Arc<dyn NoteRepository>) -> Result<Self, TrashConfigError> {
+        let days = match std::env::var("TRASH_RETENTION_DAYS") {
+            Ok(v) => v
+                .parse::<u64>()
+                .map_err(|_| TrashConfigError::InvalidRetention)?,
+            Err(_) => Self::DEFAULT_RETENTION_DAYS,
+        };
+        // 秒に直した値は `purge_trash` に i64 で渡すので、その範囲に収まらなければ設定の誤りとする
+        let retention_secs = days
+            .checked_mul(24 * 60 * 60)
+            .filter(|secs| i64::try_from(*secs).is_ok())
+            .ok_or(TrashConfigError::InvalidRetention)?;
+        let interval = match std::env::var("TRASH_PURGE_INTERVAL_SECS") {
+            Ok(v) => match v.parse::<u64>() {
+                Ok(secs) if secs > 0 => secs,
+                _ => return Err(TrashConfigError::InvalidInterval),
+            },
+            Err(_) => Self::DEFAULT_INTERVAL_SECS,
+        };
+        Ok(Self::new(
+            note_repo,
+            Duration::from_secs(retention_secs),
+            Duration::from_secs(interval),
+        ))
+    }
+
+    /// 期限切れのメモを 1 回だけ削除し、件数を返す。
+    /// 続けて、（`DELETE /trash/{id}` などで）完全に削除されたメモの添付ファイルを削除する。
+    pub async fn purge_once(&self) -> u64 {
+        let purged = match self
+            .note_repo
+            .purge_trash(self.retention.as_secs() as i64)
+            .await
+        {
+            Ok(purged) => purged,
+            Err(e) => {
+                eprintln!("trash purge failed: {e}");
+                0
+            }
+        };
+        if let Some(attachments) = &self.attachments {
+            attachments.purge_orphans().await;
+        }
+        purged
+    }
+
+    /// `interval` ごとに `purge_once` を繰り返す（起動直後にも 1 回実行する）。
+    pub async fn run(self) {
+        let mut ticker = actix_web::rt::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            self.purge_once().await;
+        }
+    }
+}
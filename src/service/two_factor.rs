@@ -0,0 +1,207 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand_core::{OsRng, RngCore};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::domain::model::UserTokenPurpose;
+use crate::domain::totp::{TOTP_DIGITS, base32_decode, base32_encode, otpauth_uri, verify_totp};
+use crate::middleware::auth::token::{hash_token, random_token};
+use crate::repository::user::{
+    RepoError, TwoFactorRepository, UserRepository, UserTokenRepository,
+};
+
+#[derive(Debug, Error)]
+pub enum TwoFactorError {
+    #[error("two-factor authentication already enabled")]
+    AlreadyEnabled,
+
+    /// `enroll` の前に確認しようとした
+    #[error("two-factor authentication not enrolled")]
+    NotEnrolled,
+
+    /// コードが一致しない・使用済み、またはチャレンジトークンが無効
+    #[error("invalid code")]
+    InvalidCode,
+
+    #[error(transparent)]
+    Repo(#[from] RepoError),
+}
+
+/// `POST /me/2fa/totp` の結果。`otpauth_uri` を QR コードにして認証アプリで読み取る。
+#[derive(Debug, Clone, Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String, // base32（手入力用）
+    pub otpauth_uri: String,
+}
+
+/// TOTP による 2 段階認証。
+///
+/// 登録（`enroll`）→ 認証アプリのコードで確認（`confirm`）の 2 段階で有効にする。
+/// 有効なユーザーのログインはパスワード確認後にチャレンジトークンを発行し、
+/// `complete_login` でコード（またはリカバリーコード）を確認してから完了する。
+pub struct TwoFactorService {
+    totp: Arc<dyn TwoFactorRepository>,
+    users: Arc<dyn UserRepository>,
+    tokens: Arc<dyn UserTokenRepository>,
+    issuer: String,
+}
+
+impl TwoFactorService {
+    const SECRET_LEN: usize = 20; // RFC 4226 の推奨（160 ビット）
+    const RECOVERY_CODE_COUNT: usize = 10;
+    const CHALLENGE_EXP_SECS: i64 = 5 * 60;
+
+    pub fn new(
+        totp: Arc<dyn TwoFactorRepository>,
+        users: Arc<dyn UserRepository>,
+        tokens: Arc<dyn UserTokenRepository>,
+        issuer: &str,
+    ) -> Self {
+        Self {
+            totp,
+            users,
+            tokens,
+            issuer: issuer.to_string(),
+        }
+    }
+
+    /// `TOTP_ISSUER`（既定 `memo-app`、認証アプリに表示される名前）から設定する。
+    pub fn from_env(
+        totp: Arc<dyn TwoFactorRepository>,
+        users: Arc<dyn UserRepository>,
+        tokens: Arc<dyn UserTokenRepository>,
+    ) -> Self {
+        let issuer = std::env::var("TOTP_ISSUER").unwrap_or_else(|_| "memo-app".into());
+        Self::new(totp, users, tokens, &issuer)
+    }
+
+    /// 新しい秘密鍵を発行する。確認前にやり直した場合は前の秘密鍵は使えなくなる。
+    pub async fn enroll(&self, user_id: i64) -> Result<TotpEnrollment, TwoFactorError> {
+        let Some(user) = self.users.find_by_id(user_id).await? else {
+            return Err(RepoError::Internal.into());
+        };
+        let mut bytes = [0u8; Self::SECRET_LEN];
+        OsRng.fill_bytes(&mut bytes);
+        let secret = base32_encode(&bytes);
+        if !self.totp.start_totp_enrollment(user_id, &secret).await? {
+            return Err(TwoFactorError::AlreadyEnabled);
+        }
+        Ok(TotpEnrollment {
+            otpauth_uri: otpauth_uri(&self.issuer, &user.email, &secret),
+            secret,
+        })
+    }
+
+    /// 認証アプリのコードで登録を確認して有効にし、リカバリーコードを返す
+    /// （リカバリーコードはこのときしか返さない）。
+    pub async fn confirm(&self, user_id: i64, code: &str) -> Result<Vec<String>, TwoFactorError> {
+        let Some(totp) = self.totp.find_totp(user_id).await? else {
+            return Err(TwoFactorError::NotEnrolled);
+        };
+        if totp.enabled_at.is_some() {
+            return Err(TwoFactorError::AlreadyEnabled);
+        }
+        let secret = base32_decode(&totp.secret).ok_or(RepoError::Internal)?;
+        let Some(step) = verify_totp(&secret, code, now()) else {
+            return Err(TwoFactorError::InvalidCode);
+        };
+        let codes: Vec<String> = (0..Self::RECOVERY_CODE_COUNT)
+            .map(|_| new_recovery_code())
+            .collect();
+        let hashes: Vec<String> = codes
+            .iter()
+            .map(|c| hash_token(&normalize_recovery_code(c)))
+            .collect();
+        if !self.totp.enable_totp(user_id, step, &hashes).await? {
+            return Err(TwoFactorError::AlreadyEnabled);
+        }
+        Ok(codes)
+    }
+
+    pub async fn is_enabled(&self, user_id: i64) -> Result<bool, TwoFactorError> {
+        let totp = self.totp.find_totp(user_id).await?;
+        Ok(totp.is_some_and(|t| t.enabled_at.is_some()))
+    }
+
+    /// パスワード確認済みのユーザーにチャレンジトークンを発行する（5 分間・1 回限り）。
+    pub async fn start_login(&self, user_id: i64) -> Result<String, TwoFactorError> {
+        let token = random_token(32);
+        self.tokens
+            .create_user_token(
+                user_id,
+                UserTokenPurpose::LoginChallenge,
+                &hash_token(&token),
+                Self::CHALLENGE_EXP_SECS,
+            )
+            .await?;
+        Ok(token)
+    }
+
+    /// チャレンジトークンとコードを確認し、ログインするユーザーの ID を返す。
+    /// チャレンジトークンはコードが誤っていても使用済みになる（推測はパスワードからやり直し）。
+    pub async fn complete_login(
+        &self,
+        challenge_token: &str,
+        code: &str,
+    ) -> Result<i64, TwoFactorError> {
+        let Some(user_id) = self
+            .tokens
+            .consume_user_token(
+                UserTokenPurpose::LoginChallenge,
+                &hash_token(challenge_token),
+            )
+            .await?
+        else {
+            return Err(TwoFactorError::InvalidCode);
+        };
+        if !self.verify_code(user_id, code).await? {
+            return Err(TwoFactorError::InvalidCode);
+        }
+        Ok(user_id)
+    }
+
+    /// 数字だけのコードは認証アプリのコード、それ以外はリカバリーコードとして確認する。
+    async fn verify_code(&self, user_id: i64, code: &str) -> Result<bool, TwoFactorError> {
+        let code = code.trim();
+        if code.len() == TOTP_DIGITS as usize && code.bytes().all(|b| b.is_ascii_digit()) {
+            let Some(totp) = self.totp.find_totp(user_id).await? else {
+                return Ok(false);
+            };
+            if totp.enabled_at.is_none() {
+                return Ok(false);
+            }
+            let secret = base32_decode(&totp.secret).ok_or(RepoError::Internal)?;
+            return match verify_totp(&secret, code, now()) {
+                Some(step) => Ok(self.totp.use_totp_step(user_id, step).await?),
+                None => Ok(false),
+            };
+        }
+        let hash = hash_token(&normalize_recovery_code(code));
+        Ok(self.totp.use_recovery_code(user_id, &hash).await?)
+    }
+}
+
+/// `xxxx-xxxx` 形式（base32 の 8 文字 = 40 ビット）。
+fn new_recovery_code() -> String {
+    let mut bytes = [0u8; 5];
+    OsRng.fill_bytes(&mut bytes);
+    let code = base32_encode(&bytes).to_ascii_lowercase();
+    format!("{}-{}", &code[..4], &code[4..])
+}
+
+/// 大文字・小文字や区切りの `-`・空白の違いは区別しない。
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0))
+        .as_secs() as i64
+}
@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::domain::model::{
+    Workspace, WorkspaceInvitation, WorkspaceMember, WorkspaceMembership, WorkspaceRole,
+};
+use crate::middleware::auth::token::{hash_token, random_token};
+use crate::repository::user::{RepoError, UserRepository};
+use crate::repository::workspace::WorkspaceRepository;
+use crate::service::mailer::{Email, Mailer, MailerError};
+
+#[derive(Debug, Error)]
+pub enum WorkspaceError {
+    /// ワークスペースが存在しない、またはメンバーではない（存在自体を秘匿する）
+    #[error("not found")]
+    NotFound,
+
+    /// メンバーだが操作に必要な役割（オーナー）ではない
+    #[error("forbidden")]
+    Forbidden,
+
+    /// 最後のオーナーを外す・降格させることはできない
+    #[error("workspace must have an owner")]
+    LastOwner,
+
+    #[error("invalid name")]
+    InvalidName,
+
+    /// 存在しない・期限切れ・承諾済み、または宛先が自分ではない招待
+    #[error("invalid invitation")]
+    InvalidInvitation,
+
+    #[error(transparent)]
+    Mail(#[from] MailerError),
+
+    #[error(transparent)]
+    Repo(RepoError),
+}
+
+impl From<RepoError> for WorkspaceError {
+    fn from(e: RepoError) -> Self {
+        match e {
+            RepoError::Conflict => WorkspaceError::LastOwner,
+            e => WorkspaceError::Repo(e),
+        }
+    }
+}
+
+/// ワークスペースの作成とメンバーの管理。
+///
+/// メンバーの招待・役割の変更・削除はオーナーだけが行える（自分で抜けるのは誰でもできる）。
+/// 招待のトークンは 1 回限りで、メールで送った平文は保存せずハッシュのみ保存する。
+pub struct WorkspaceService {
+    workspaces: Arc<dyn WorkspaceRepository>,
+    users: Arc<dyn UserRepository>,
+    mailer: Arc<dyn Mailer>,
+}
+
+impl WorkspaceService {
+    const INVITATION_EXP_SECS: i64 = 7 * 24 * 60 * 60;
+    const MAX_NAME_CHARS: usize = 100;
+
+    pub fn new(
+        workspaces: Arc<dyn WorkspaceRepository>,
+        users: Arc<dyn UserRepository>,
+        mailer: Arc<dyn Mailer>,
+    ) -> Self {
+        Self {
+            workspaces,
+            users,
+            mailer,
+        }
+    }
+
+    pub async fn create(&self, user_id: i64, name: &str) -> Result<Workspace, WorkspaceError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > Self::MAX_NAME_CHARS {
+            return Err(WorkspaceError::InvalidName);
+        }
+        Ok(self.workspaces.create_workspace(user_id, name).await?)
+    }
+
+    pub async fn list(&self, user_id: i64) -> Result<Vec<WorkspaceMembership>, WorkspaceError> {
+        Ok(self.workspaces.list_workspaces(user_id).await?)
+    }
+
+    /// メンバー一覧。メンバーなら役割に関係なく見られる。
+    pub async fn members(
+        &self,
+        user_id: i64,
+        workspace_id: i64,
+    ) -> Result<Vec<WorkspaceMember>, WorkspaceError> {
+        self.role_of(workspace_id, user_id).await?;
+        Ok(self.workspaces.list_members(workspace_id).await?)
+    }
+
+    pub async fn set_member_role(
+        &self,
+        user_id: i64,
+        workspace_id: i64,
+        member_id: i64,
+        role: WorkspaceRole,
+    ) -> Result<(), WorkspaceError> {
+        self.require_owner(workspace_id, user_id).await?;
+        if !self
+            .workspaces
+            .set_member_role(workspace_id, member_id, role)
+            .await?
+        {
+            return Err(WorkspaceError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// メンバーを外す。自分自身なら役割に関係なく抜けられる。
+    pub async fn remove_member(
+        &self,
+        user_id: i64,
+        workspace_id: i64,
+        member_id: i64,
+    ) -> Result<(), WorkspaceError> {
+        if member_id == user_id {
+            self.role_of(workspace_id, user_id).await?;
+        } else {
+            self.require_owner(workspace_id, user_id).await?;
+        }
+        if !self
+            .workspaces
+            .remove_member(workspace_id, member_id)
+            .await?
+        {
+            return Err(WorkspaceError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// `email` 宛てに招待のトークンを送る。宛先が未登録でもよい（登録後に承諾できる）。
+    pub async fn invite(
+        &self,
+        user_id: i64,
+        workspace_id: i64,
+        email: &str,
+        role: WorkspaceRole,
+    ) -> Result<WorkspaceInvitation, WorkspaceError> {
+        self.require_owner(workspace_id, user_id).await?;
+        let token = random_token(32);
+        let invitation = self
+            .workspaces
+            .create_invitation(
+                workspace_id,
+                email.trim(),
+                role,
+                user_id,
+                &hash_token(&token),
+                Self::INVITATION_EXP_SECS,
+            )
+            .await?;
+        self.mailer
+            .send(&Email {
+                to: invitation.email.clone(),
+                subject: "ワークスペースへの招待".into(),
+                body: format!(
+                    "ワークスペースに招待されました。参加するには、このメールアドレスでログインして\n\
+                     7 日以内に次のトークンを POST /workspaces/invitations/accept に送ってください。\n\n\
+                     {token}\n"
+                ),
+            })
+            .await?;
+        Ok(invitation)
+    }
+
+    /// 招待を承諾し、参加したワークスペースの ID を返す。
+    /// 招待の宛先とログイン中のユーザーのメールアドレスが一致する必要がある。
+    pub async fn accept_invitation(
+        &self,
+        user_id: i64,
+        token: &str,
+    ) -> Result<i64, WorkspaceError> {
+        let Some(user) = self.users.find_by_id(user_id).await? else {
+            return Err(WorkspaceError::InvalidInvitation);
+        };
+        self.workspaces
+            .accept_invitation(&hash_token(token), user.id, &user.email)
+            .await?
+            .ok_or(WorkspaceError::InvalidInvitation)
+    }
+
+    async fn role_of(
+        &self,
+        workspace_id: i64,
+        user_id: i64,
+    ) -> Result<WorkspaceRole, WorkspaceError> {
+        self.workspaces
+            .member_role(workspace_id, user_id)
+            .await?
+            .ok_or(WorkspaceError::NotFound)
+    }
+
+    async fn require_owner(&self, workspace_id: i64, user_id: i64) -> Result<(), WorkspaceError> {
+        match self.role_of(workspace_id, user_id).await? {
+            WorkspaceRole::Owner => Ok(()),
+            _ => Err(WorkspaceError::Forbidden),
+        }
+    }
+}
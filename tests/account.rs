@@ -0,0 +1,427 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use memo_app::app::auth::{
+    confirm_password_reset, resend_email_verification, signup, verify_email,
+};
+use memo_app::app::model::{PasswordResetConfirmInput, SignupInput, VerifyEmailInput};
+use memo_app::domain::model::{Role, User, UserTokenPurpose};
+use memo_app::middleware::auth::token::{JwtTokenService, random_token};
+use memo_app::repository::user::{RepoError, UserRepository, UserTokenRepository};
+use memo_app::service::account::{AccountError, AccountService};
+use memo_app::service::auth::{AuthService, AuthServiceImpl};
+use memo_app::service::mailer::{Email, Mailer, OutboxMailer, SmtpMailer, SmtpTls};
+
+// ---- Mocks ----
+
+struct StoredToken {
+    user_id: i64,
+    purpose: UserTokenPurpose,
+    hash: String,
+    used: bool,
+}
+
+// メモリ上でユーザーと 1 回限りのトークンを保持する
+#[derive(Default)]
+struct MockAccountRepo {
+    users: Mutex<Vec<User>>,
+    tokens: Mutex<Vec<StoredToken>>,
+}
+
+#[async_trait]
+impl UserRepository for MockAccountRepo {
+    async fn create_user(
+        &self,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<Option<User>, RepoError> {
+        let mut users = self.users.lock().unwrap();
+        if users.iter().any(|u| u.email == email) {
+            return Ok(None);
+        }
+        let user = User {
+            id: users.len() as i64 + 1,
+            email: email.into(),
+            password_hash: password_hash.into(),
+            created_at: 0,
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role: Role::User,
+            disabled_at: None,
+        };
+        users.push(user.clone());
+        Ok(Some(user))
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.email == email).cloned())
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.id == user_id).cloned())
+    }
+
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<bool, RepoError> {
+        let mut users = self.users.lock().unwrap();
+        let Some(user) = users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        user.password_hash = password_hash.into();
+        Ok(true)
+    }
+
+    async fn mark_email_verified(&self, user_id: i64) -> Result<bool, RepoError> {
+        let mut users = self.users.lock().unwrap();
+        let Some(user) = users
+            .iter_mut()
+            .find(|u| u.id == user_id && u.email_verified_at.is_none())
+        else {
+            return Ok(false);
+        };
+        user.email_verified_at = Some(1);
+        Ok(true)
+    }
+
+    async fn record_login_failure(&self, user_id: i64) -> Result<i64, RepoError> {
+        let mut users = self.users.lock().unwrap();
+        let user = users.iter_mut().find(|u| u.id == user_id).unwrap();
+        user.failed_login_count += 1;
+        Ok(user.failed_login_count)
+    }
+
+    async fn lock_user_until(&self, user_id: i64, locked_until: i64) -> Result<(), RepoError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(user) = users.iter_mut().find(|u| u.id == user_id) {
+            user.locked_until = Some(locked_until);
+        }
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, user_id: i64) -> Result<(), RepoError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(user) = users.iter_mut().find(|u| u.id == user_id) {
+            user.failed_login_count = 0;
+            user.locked_until = None;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserTokenRepository for MockAccountRepo {
+    async fn create_user_token(
+        &self,
+        user_id: i64,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<(), RepoError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        for t in tokens
+            .iter_mut()
+            .filter(|t| t.user_id == user_id && t.purpose == purpose)
+        {
+            t.used = true;
+        }
+        tokens.push(StoredToken {
+            user_id,
+            purpose,
+            hash: token_hash.into(),
+            used: false,
+        });
+        Ok(())
+    }
+
+    async fn consume_user_token(
+        &self,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<i64>, RepoError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let Some(t) = tokens
+            .iter_mut()
+            .find(|t| t.hash == token_hash && t.purpose == purpose && !t.used)
+        else {
+            return Ok(None);
+        };
+        t.used = true;
+        Ok(Some(t.user_id))
+    }
+}
+
+struct Fixture {
+    repo: Arc<MockAccountRepo>,
+    outbox: Arc<OutboxMailer>,
+    auth: Arc<dyn AuthService>,
+    accounts: AccountService,
+}
+
+fn fixture() -> Fixture {
+    let repo = Arc::new(MockAccountRepo::default());
+    let outbox = Arc::new(OutboxMailer::new(
+        std::env::temp_dir().join(format!("memo-outbox-{}", random_token(8))),
+    ));
+    let auth: Arc<dyn AuthService> = Arc::new(AuthServiceImpl::new(repo.clone()));
+    let accounts = AccountService::new(repo.clone(), repo.clone(), outbox.clone());
+    Fixture {
+        repo,
+        outbox,
+        auth,
+        accounts,
+    }
+}
+
+/// メール本文の最後の行がトークン
+fn token_in(email: &Email) -> String {
+    email.body.trim_end().lines().last().unwrap().to_string()
+}
+
+// ---- Tests ----
+
+#[tokio::test]
+async fn password_reset_flow() {
+    let f = fixture();
+    f.auth.signup("a@example.com", "password123").await.unwrap();
+
+    // 未登録のアドレスでも成功扱いでメールは送らない
+    f.accounts
+        .request_password_reset("nobody@example.com")
+        .await
+        .unwrap();
+    assert!(f.outbox.sent().unwrap().is_empty());
+
+    f.accounts
+        .request_password_reset("a@example.com")
+        .await
+        .unwrap();
+    let sent = f.outbox.sent().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "a@example.com");
+    let token = token_in(&sent[0]);
+
+    // ポリシー違反ではトークンを消費しない
+    assert!(matches!(
+        f.accounts.confirm_password_reset(&token, "short").await,
+        Err(AccountError::InvalidPassword)
+    ));
+    f.accounts
+        .confirm_password_reset(&token, "newpassword1")
+        .await
+        .unwrap();
+    assert!(matches!(
+        f.accounts
+            .confirm_password_reset(&token, "newpassword2")
+            .await,
+        Err(AccountError::InvalidToken)
+    ));
+
+    assert!(f.auth.login("a@example.com", "password123").await.is_err());
+    assert!(f.auth.login("a@example.com", "newpassword1").await.is_ok());
+}
+
+#[tokio::test]
+async fn password_reset_token_is_replaced_by_newer_one() {
+    let f = fixture();
+    f.auth.signup("a@example.com", "password123").await.unwrap();
+
+    f.accounts
+        .request_password_reset("a@example.com")
+        .await
+        .unwrap();
+    f.accounts
+        .request_password_reset("a@example.com")
+        .await
+        .unwrap();
+    let sent = f.outbox.sent().unwrap();
+    assert_eq!(sent.len(), 2);
+
+    assert!(matches!(
+        f.accounts
+            .confirm_password_reset(&token_in(&sent[0]), "newpassword1")
+            .await,
+        Err(AccountError::InvalidToken)
+    ));
+    assert!(
+        f.accounts
+            .confirm_password_reset(&token_in(&sent[1]), "newpassword1")
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn change_password_requires_current_password() {
+    let f = fixture();
+    let user = f
+        .auth
+        .signup("a@example.com", "password123")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert!(matches!(
+        f.accounts
+            .change_password(user.id, "wrongpass1", "newpassword1")
+            .await,
+        Err(AccountError::InvalidCredentials)
+    ));
+    assert!(matches!(
+        f.accounts
+            .change_password(user.id, "password123", "short")
+            .await,
+        Err(AccountError::InvalidPassword)
+    ));
+    f.accounts
+        .change_password(user.id, "password123", "newpassword1")
+        .await
+        .unwrap();
+    assert!(f.auth.login("a@example.com", "newpassword1").await.is_ok());
+}
+
+#[actix_web::test]
+async fn signup_sends_verification_email() {
+    let f = fixture();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(f.auth.clone()))
+            .app_data(web::Data::new(f.accounts))
+            .app_data(web::Data::new(JwtTokenService::from_secret(
+                b"secret", 3600,
+            )))
+            .service(signup)
+            .service(verify_email)
+            .service(resend_email_verification)
+            .service(confirm_password_reset),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/auth/signup")
+        .set_json(SignupInput {
+            email: "a@example.com".into(),
+            password: "password123".into(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let sent = f.outbox.sent().unwrap();
+    assert_eq!(sent.len(), 1);
+    let token = token_in(&sent[0]);
+
+    // 別の用途のトークンとしては使えない
+    let req = test::TestRequest::post()
+        .uri("/auth/password-reset/confirm")
+        .set_json(PasswordResetConfirmInput {
+            token: token.clone(),
+            new_password: "newpassword1".into(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    for expected in [StatusCode::NO_CONTENT, StatusCode::BAD_REQUEST] {
+        let req = test::TestRequest::post()
+            .uri("/auth/verify-email")
+            .set_json(VerifyEmailInput {
+                token: token.clone(),
+            })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), expected);
+    }
+    assert!(f.repo.users.lock().unwrap()[0].email_verified_at.is_some());
+
+    // 確認済みなら再送しない
+    let access = JwtTokenService::from_secret(b"secret", 3600)
+        .generate(1)
+        .unwrap();
+    let req = test::TestRequest::post()
+        .uri("/me/email-verification")
+        .insert_header(("Authorization", format!("Bearer {}", access)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn smtp_mailer_delivers_message() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // 受け取ったコマンドと DATA の内容を記録する最小限の SMTP サーバー
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut commands = Vec::new();
+        let mut data = String::new();
+        writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            let line = line.trim_end().to_string();
+            let reply: &[u8] = match line.as_str() {
+                l if l.starts_with("EHLO") => b"250-localhost\r\n250 AUTH PLAIN\r\n",
+                l if l.starts_with("AUTH PLAIN") => b"235 ok\r\n",
+                "DATA" => {
+                    writer.write_all(b"354 go ahead\r\n").unwrap();
+                    loop {
+                        let mut l = String::new();
+                        reader.read_line(&mut l).unwrap();
+                        if l == ".\r\n" {
+                            break;
+                        }
+                        data.push_str(&l);
+                    }
+                    b"250 queued\r\n"
+                }
+                "QUIT" => {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    commands.push(line);
+                    break;
+                }
+                _ => b"250 ok\r\n",
+            };
+            writer.write_all(reply).unwrap();
+            commands.push(line);
+        }
+        (commands, data)
+    });
+
+    let mailer = SmtpMailer::new("127.0.0.1", port, SmtpTls::None, "noreply@example.com")
+        .with_credentials("user", "pass");
+    mailer
+        .send(&Email {
+            to: "a@example.com".into(),
+            subject: "件名".into(),
+            body: "本文\n".into(),
+        })
+        .await
+        .unwrap();
+
+    let (commands, data) = server.join().unwrap();
+    assert_eq!(
+        commands,
+        vec![
+            "EHLO memo-app".to_string(),
+            format!("AUTH PLAIN {}", STANDARD.encode("\0user\0pass")),
+            "MAIL FROM:<noreply@example.com>".into(),
+            "RCPT TO:<a@example.com>".into(),
+            "DATA".into(),
+            "QUIT".into(),
+        ]
+    );
+    assert!(data.contains(&format!("Subject: =?UTF-8?B?{}?=", STANDARD.encode("件名"))));
+    assert!(data.ends_with(&format!("\r\n\r\n{}\r\n", STANDARD.encode("本文\n"))));
+}
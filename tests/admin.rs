@@ -0,0 +1,750 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use memo_app::app::admin::{
+    delete_any_note, disable_user, enable_user, force_password_reset, get_user, list_users,
+};
+use memo_app::app::model::{UserPage, UserSummary};
+use memo_app::domain::model::{
+    ApiToken, Note, NoteSearchHit, RefreshToken, Role, Scope, TrashedNote, User, UserTokenPurpose,
+    Visibility,
+};
+use memo_app::middleware::auth::token::{JwtTokenService, random_token};
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::token::TokenRepository;
+use memo_app::repository::user::{
+    ApiTokenRepository, RepoError, UserAdminRepository, UserRepository, UserTokenRepository,
+};
+use memo_app::service::account::AccountService;
+use memo_app::service::admin::AdminService;
+use memo_app::service::auth::{AuthService, AuthServiceError, AuthServiceImpl};
+use memo_app::service::mailer::OutboxMailer;
+
+// ---- Mocks ----
+
+// 管理者（id=1）と一般ユーザー（id=2）を保持する
+struct MockAdminRepo {
+    users: Mutex<Vec<User>>,
+}
+
+impl MockAdminRepo {
+    fn new() -> Self {
+        use argon2::{Argon2, PasswordHasher};
+        use password_hash::{SaltString, rand_core::OsRng};
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(b"password123", &salt)
+            .unwrap()
+            .to_string();
+        let user = |id: i64, email: &str, role: Role| User {
+            id,
+            email: email.into(),
+            password_hash: password_hash.clone(),
+            created_at: 0,
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role,
+            disabled_at: None,
+        };
+        Self {
+            users: Mutex::new(vec![
+                user(1, "admin@example.com", Role::Admin),
+                user(2, "b@example.com", Role::User),
+            ]),
+        }
+    }
+}
+
+#[async_trait]
+impl UserRepository for MockAdminRepo {
+    async fn create_user(
+        &self,
+        _email: &str,
+        _password_hash: &str,
+    ) -> Result<Option<User>, RepoError> {
+        Ok(None)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.email == email).cloned())
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.id == user_id).cloned())
+    }
+
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<bool, RepoError> {
+        let mut users = self.users.lock().unwrap();
+        let Some(user) = users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(false);
+        };
+        user.password_hash = password_hash.into();
+        Ok(true)
+    }
+
+    async fn mark_email_verified(&self, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn record_login_failure(&self, _user_id: i64) -> Result<i64, RepoError> {
+        Ok(1)
+    }
+
+    async fn lock_user_until(&self, _user_id: i64, _locked_until: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserAdminRepository for MockAdminRepo {
+    async fn list_users(
+        &self,
+        query: Option<&str>,
+        after: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<User>, RepoError> {
+        let users = self.users.lock().unwrap();
+        Ok(users
+            .iter()
+            .filter(|u| query.is_none_or(|q| u.email.contains(q)))
+            .filter(|u| u.id > after.unwrap_or(0))
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn set_user_disabled(
+        &self,
+        user_id: i64,
+        disabled: bool,
+    ) -> Result<Option<User>, RepoError> {
+        let mut users = self.users.lock().unwrap();
+        let Some(user) = users.iter_mut().find(|u| u.id == user_id) else {
+            return Ok(None);
+        };
+        user.disabled_at = disabled.then_some(100);
+        Ok(Some(user.clone()))
+    }
+}
+
+#[async_trait]
+impl UserTokenRepository for MockAdminRepo {
+    async fn create_user_token(
+        &self,
+        _user_id: i64,
+        _purpose: UserTokenPurpose,
+        _token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn consume_user_token(
+        &self,
+        _purpose: UserTokenPurpose,
+        _token_hash: &str,
+    ) -> Result<Option<i64>, RepoError> {
+        Ok(None)
+    }
+}
+
+// 失効させたトークンの種類と対象ユーザーを記録する（API トークンも扱う）
+#[derive(Default)]
+struct MockTokenRepo {
+    revoked: Mutex<Vec<(&'static str, i64)>>,
+}
+
+#[async_trait]
+impl TokenRepository for MockTokenRepo {
+    async fn create_refresh_token(
+        &self,
+        _user_id: i64,
+        _family_id: &str,
+        _token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn find_refresh_token(
+        &self,
+        _token_hash: &str,
+    ) -> Result<Option<RefreshToken>, RepoError> {
+        Ok(None)
+    }
+
+    async fn mark_refresh_token_used(&self, _id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn revoke_token_family(&self, _family_id: &str) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: i64) -> Result<u64, RepoError> {
+        self.revoked.lock().unwrap().push(("refresh", user_id));
+        Ok(1)
+    }
+
+    async fn revoke_access_token(&self, _jti: &str, _expires_at: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn revoke_user_access_tokens(&self, user_id: i64) -> Result<(), RepoError> {
+        self.revoked.lock().unwrap().push(("access", user_id));
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(
+        &self,
+        _jti: &str,
+        _user_id: i64,
+        _issued_at: i64,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_expired(&self, _now: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+}
+
+#[async_trait]
+impl ApiTokenRepository for MockTokenRepo {
+    async fn create_api_token(
+        &self,
+        _user_id: i64,
+        _name: &str,
+        _token_hash: &str,
+        _scopes: &[Scope],
+        _ttl_secs: Option<i64>,
+    ) -> Result<ApiToken, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn list_api_tokens(&self, _user_id: i64) -> Result<Vec<ApiToken>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_api_token(&self, _user_id: i64, _token_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn delete_user_api_tokens(&self, user_id: i64) -> Result<u64, RepoError> {
+        self.revoked.lock().unwrap().push(("api", user_id));
+        Ok(1)
+    }
+
+    async fn authenticate_api_token(
+        &self,
+        _token_hash: &str,
+    ) -> Result<Option<ApiToken>, RepoError> {
+        Ok(None)
+    }
+}
+
+// ユーザー 2 のメモ（id=5）が 1 件あり、削除・完全削除の呼び出しを記録する
+#[derive(Default)]
+struct MockNoteRepo {
+    calls: Mutex<Vec<(&'static str, i64, Option<i64>)>>,
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok((note_id == 5).then(|| Note {
+            id: 5,
+            author_id: 2,
+            workspace_id: None,
+            notebook_id: None,
+            title: "spam".into(),
+            content: "c".into(),
+            visibility: Visibility::Public,
+            created_at: 1,
+            updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        }))
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(None)
+    }
+
+    async fn delete_note(
+        &self,
+        note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        self.calls.lock().unwrap().push(("delete", note_id, None));
+        Ok(true)
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(("purge", note_id, Some(user_id)));
+        Ok(true)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+struct Fixture {
+    users: Arc<MockAdminRepo>,
+    tokens: Arc<MockTokenRepo>,
+    notes: Arc<MockNoteRepo>,
+    outbox: Arc<OutboxMailer>,
+    admin: web::Data<AdminService>,
+}
+
+fn fixture() -> Fixture {
+    let users = Arc::new(MockAdminRepo::new());
+    let tokens = Arc::new(MockTokenRepo::default());
+    let notes = Arc::new(MockNoteRepo::default());
+    let outbox = Arc::new(OutboxMailer::new(
+        std::env::temp_dir().join(format!("memo-outbox-{}", random_token(8))),
+    ));
+    let accounts = Arc::new(AccountService::new(
+        users.clone(),
+        users.clone(),
+        outbox.clone(),
+    ));
+    let admin = web::Data::new(AdminService::new(
+        users.clone(),
+        users.clone(),
+        tokens.clone(),
+        tokens.clone(),
+        notes.clone(),
+        accounts,
+    ));
+    Fixture {
+        users,
+        tokens,
+        notes,
+        outbox,
+        admin,
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer(user_id: i64) -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(user_id).unwrap()),
+    )
+}
+
+macro_rules! admin_app {
+    ($f:expr) => {{
+        let users: Arc<dyn UserRepository> = $f.users.clone();
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(users))
+                .app_data(web::Data::new(jwt()))
+                .app_data($f.admin.clone())
+                .service(list_users)
+                .service(get_user)
+                .service(disable_user)
+                .service(enable_user)
+                .service(force_password_reset)
+                .service(delete_any_note),
+        )
+        .await
+    }};
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn admin_endpoints_require_admin_role() {
+    let f = fixture();
+    let app = admin_app!(f);
+
+    let req = test::TestRequest::get().uri("/admin/users").to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    let req = test::TestRequest::get()
+        .uri("/admin/users")
+        .insert_header(bearer(2))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    let req = test::TestRequest::delete()
+        .uri("/admin/notes/5")
+        .insert_header(bearer(2))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert!(f.notes.calls.lock().unwrap().is_empty());
+
+    let req = test::TestRequest::get()
+        .uri("/admin/users?limit=1")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let page: UserPage = test::read_body_json(resp).await;
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].role, Role::Admin);
+    assert_eq!(page.next_after, Some(1));
+
+    let req = test::TestRequest::get()
+        .uri("/admin/users?q=b%40example")
+        .insert_header(bearer(1))
+        .to_request();
+    let page: UserPage = test::read_body_json(test::call_service(&app, req).await).await;
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].id, 2);
+    assert_eq!(page.next_after, None);
+}
+
+#[actix_web::test]
+async fn disabled_user_cannot_log_in() {
+    let f = fixture();
+    let app = admin_app!(f);
+
+    // 自分自身は無効にできない
+    let req = test::TestRequest::post()
+        .uri("/admin/users/1/disable")
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/admin/users/2/disable")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let user: UserSummary = test::read_body_json(resp).await;
+    assert!(user.disabled_at.is_some());
+    assert_eq!(
+        *f.tokens.revoked.lock().unwrap(),
+        vec![("refresh", 2), ("access", 2), ("api", 2)]
+    );
+
+    let auth = AuthServiceImpl::new(f.users.clone());
+    assert!(matches!(
+        auth.login("b@example.com", "password123").await,
+        Err(AuthServiceError::Disabled)
+    ));
+    // パスワードが誤っていれば無効かどうかは知らせない
+    assert!(matches!(
+        auth.login("b@example.com", "wrongpass1").await,
+        Err(AuthServiceError::InvalidCredentials)
+    ));
+
+    let req = test::TestRequest::post()
+        .uri("/admin/users/2/enable")
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert!(auth.login("b@example.com", "password123").await.is_ok());
+
+    let req = test::TestRequest::post()
+        .uri("/admin/users/99/disable")
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn force_password_reset_invalidates_password() {
+    let f = fixture();
+    let app = admin_app!(f);
+
+    let req = test::TestRequest::post()
+        .uri("/admin/users/2/password-reset")
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+
+    let sent = f.outbox.sent().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "b@example.com");
+    assert_eq!(
+        *f.tokens.revoked.lock().unwrap(),
+        vec![("refresh", 2), ("access", 2), ("api", 2)]
+    );
+    let auth = AuthServiceImpl::new(f.users.clone());
+    assert!(matches!(
+        auth.login("b@example.com", "password123").await,
+        Err(AuthServiceError::InvalidCredentials)
+    ));
+}
+
+#[actix_web::test]
+async fn admin_can_delete_any_note() {
+    let f = fixture();
+    let app = admin_app!(f);
+
+    let req = test::TestRequest::delete()
+        .uri("/admin/notes/5")
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    // 作成者のゴミ箱を経由して完全に削除する
+    assert_eq!(
+        *f.notes.calls.lock().unwrap(),
+        vec![("delete", 5, None), ("purge", 5, Some(2))]
+    );
+
+    let req = test::TestRequest::delete()
+        .uri("/admin/notes/9")
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、無効化・パスワードの強制リセット後は発行済みのトークンが使えないことを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn disabling_user_revokes_issued_tokens_in_sqlite() {
+    use memo_app::app::model::CreatedApiToken;
+    use memo_app::app::notes::create_note;
+    use memo_app::app::tokens::{create_api_token, list_api_tokens};
+    use memo_app::repository::note::SqliteNoteRepository;
+    use memo_app::repository::token::SqliteTokenRepository;
+    use memo_app::repository::user::SqliteUserRepository;
+
+    let pool = common::sqlite_pool().await;
+    let admin_id = common::insert_user(&pool, "admin@example.com").await;
+    sqlx::query("UPDATE users SET role = 'admin' WHERE id = ?")
+        .bind(admin_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let disabled_id = common::insert_user(&pool, "b@example.com").await;
+    let reset_id = common::insert_user(&pool, "c@example.com").await;
+
+    let users = Arc::new(SqliteUserRepository::new(pool.clone()));
+    let tokens: Arc<dyn TokenRepository> = Arc::new(SqliteTokenRepository::new(pool.clone()));
+    let notes: Arc<dyn NoteRepository> = Arc::new(SqliteNoteRepository::new(pool.clone()));
+    let outbox = Arc::new(OutboxMailer::new(
+        std::env::temp_dir().join(format!("memo-outbox-{}", random_token(8))),
+    ));
+    let accounts = Arc::new(AccountService::new(users.clone(), users.clone(), outbox));
+    let admin = web::Data::new(AdminService::new(
+        users.clone(),
+        users.clone(),
+        tokens.clone(),
+        users.clone(),
+        notes.clone(),
+        accounts,
+    ));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(users.clone() as Arc<dyn UserRepository>))
+            .app_data(web::Data::new(users.clone() as Arc<dyn ApiTokenRepository>))
+            .app_data(web::Data::new(tokens))
+            .app_data(web::Data::new(notes))
+            .app_data(web::Data::new(jwt()))
+            .app_data(admin)
+            .service(disable_user)
+            .service(force_password_reset)
+            .service(create_api_token)
+            .service(list_api_tokens)
+            .service(create_note),
+    )
+    .await;
+
+    // 無効にするユーザーの API トークン（メモの読み書き）と JWT、リセットするユーザーの JWT
+    let jwt_of = |user_id| format!("Bearer {}", jwt().generate(user_id).unwrap());
+    let (disabled_jwt, reset_jwt) = (jwt_of(disabled_id), jwt_of(reset_id));
+    let req = test::TestRequest::post()
+        .uri("/me/tokens")
+        .insert_header(("Authorization", disabled_jwt.clone()))
+        .set_json(serde_json::json!({"name": "ci", "scopes": ["notes:read", "notes:write"]}))
+        .to_request();
+    let created: CreatedApiToken = test::call_and_read_body_json(&app, req).await;
+    let pat = format!("Bearer {}", created.token);
+
+    let create_note_with = |auth: &str| {
+        test::TestRequest::post()
+            .uri("/notes")
+            .insert_header(("Authorization", auth.to_string()))
+            .set_json(serde_json::json!({"title": "t", "content": "c"}))
+            .to_request()
+    };
+    let list_tokens_with = |auth: &str| {
+        test::TestRequest::get()
+            .uri("/me/tokens")
+            .insert_header(("Authorization", auth.to_string()))
+            .to_request()
+    };
+    let status = |resp: actix_web::dev::ServiceResponse| resp.status();
+    assert_eq!(
+        status(test::call_service(&app, create_note_with(&pat)).await),
+        StatusCode::CREATED
+    );
+    assert_eq!(
+        status(test::call_service(&app, list_tokens_with(&disabled_jwt)).await),
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(test::call_service(&app, list_tokens_with(&reset_jwt)).await),
+        StatusCode::OK
+    );
+
+    let admin_jwt = jwt_of(admin_id);
+    let req = test::TestRequest::post()
+        .uri(&format!("/admin/users/{disabled_id}/disable"))
+        .insert_header(("Authorization", admin_jwt.clone()))
+        .to_request();
+    assert_eq!(status(test::call_service(&app, req).await), StatusCode::OK);
+    assert_eq!(
+        status(test::call_service(&app, create_note_with(&pat)).await),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(test::call_service(&app, list_tokens_with(&disabled_jwt)).await),
+        StatusCode::UNAUTHORIZED
+    );
+    // API トークンは削除されている（有効に戻しても使えない）
+    assert!(users.list_api_tokens(disabled_id).await.unwrap().is_empty());
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/admin/users/{reset_id}/password-reset"))
+        .insert_header(("Authorization", admin_jwt.clone()))
+        .to_request();
+    assert_eq!(
+        status(test::call_service(&app, req).await),
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        status(test::call_service(&app, list_tokens_with(&reset_jwt)).await),
+        StatusCode::UNAUTHORIZED
+    );
+    // 他のユーザーのトークンは影響を受けない
+    assert_eq!(
+        status(test::call_service(&app, list_tokens_with(&admin_jwt)).await),
+        StatusCode::OK
+    );
+}
+
+/// 実際の SQLite で、管理者がゴミ箱内のメモも完全に削除できることを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn admin_purges_trashed_notes_in_sqlite() {
+    use memo_app::repository::note::SqliteNoteRepository;
+    use memo_app::repository::token::SqliteTokenRepository;
+    use memo_app::repository::user::SqliteUserRepository;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let users = Arc::new(SqliteUserRepository::new(pool.clone()));
+    let notes: Arc<dyn NoteRepository> = Arc::new(SqliteNoteRepository::new(pool.clone()));
+    let outbox = Arc::new(OutboxMailer::new(
+        std::env::temp_dir().join(format!("memo-outbox-{}", random_token(8))),
+    ));
+    let admin = AdminService::new(
+        users.clone(),
+        users.clone(),
+        Arc::new(SqliteTokenRepository::new(pool.clone())),
+        users.clone(),
+        notes.clone(),
+        Arc::new(AccountService::new(users.clone(), users, outbox)),
+    );
+
+    let mut ids = Vec::new();
+    for title in ["live", "trashed"] {
+        let note = notes
+            .create_note(
+                author,
+                &NewNote {
+                    title,
+                    content: "c",
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        ids.push(note.id);
+    }
+    assert!(notes.delete_note(ids[1], None).await.unwrap());
+
+    for id in ids {
+        assert_eq!(admin.delete_note(id).await.unwrap().id, id);
+        assert!(notes.find_including_trashed(id).await.unwrap().is_none());
+        assert!(admin.delete_note(id).await.is_err());
+    }
+    assert!(notes.list_trash(author).await.unwrap().is_empty());
+}
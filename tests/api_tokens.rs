@@ -0,0 +1,444 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use memo_app::app::model::{CreateApiTokenInput, CreateNoteInput, CreatedApiToken};
+use memo_app::app::notes::{create_note, list_notes};
+use memo_app::app::tokens::{create_api_token, delete_api_token, list_api_tokens};
+use memo_app::domain::model::{ApiToken, Note, NoteSearchHit, Scope, TrashedNote, Visibility};
+use memo_app::middleware::auth::token::{JwtTokenService, hash_token};
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::user::{ApiTokenRepository, RepoError};
+
+// ---- Mocks ----
+
+// メモリ上で API トークンとそのハッシュを保持する
+#[derive(Default)]
+struct MockApiTokenRepo {
+    tokens: Mutex<Vec<(ApiToken, String)>>,
+}
+
+#[async_trait]
+impl ApiTokenRepository for MockApiTokenRepo {
+    async fn create_api_token(
+        &self,
+        user_id: i64,
+        name: &str,
+        token_hash: &str,
+        scopes: &[Scope],
+        ttl_secs: Option<i64>,
+    ) -> Result<ApiToken, RepoError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let token = ApiToken {
+            id: tokens.len() as i64 + 1,
+            user_id,
+            name: name.into(),
+            scopes: scopes.to_vec(),
+            created_at: 1,
+            expires_at: ttl_secs.map(|s| 1 + s),
+            last_used_at: None,
+        };
+        tokens.push((token.clone(), token_hash.into()));
+        Ok(token)
+    }
+
+    async fn list_api_tokens(&self, user_id: i64) -> Result<Vec<ApiToken>, RepoError> {
+        Ok(self
+            .tokens
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(t, _)| t.user_id == user_id)
+            .map(|(t, _)| t.clone())
+            .collect())
+    }
+
+    async fn delete_api_token(&self, user_id: i64, token_id: i64) -> Result<bool, RepoError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let before = tokens.len();
+        tokens.retain(|(t, _)| !(t.user_id == user_id && t.id == token_id));
+        Ok(tokens.len() != before)
+    }
+
+    async fn delete_user_api_tokens(&self, user_id: i64) -> Result<u64, RepoError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let before = tokens.len();
+        tokens.retain(|(t, _)| t.user_id != user_id);
+        Ok((before - tokens.len()) as u64)
+    }
+
+    async fn authenticate_api_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<ApiToken>, RepoError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let Some((t, _)) = tokens.iter_mut().find(|(_, h)| h == token_hash) else {
+            return Ok(None);
+        };
+        t.last_used_at = Some(2);
+        Ok(Some(t.clone()))
+    }
+}
+
+struct MockNoteRepo;
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, user_id: i64, note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Ok(Note {
+            id: 1,
+            author_id: user_id,
+            workspace_id: None,
+            notebook_id: None,
+            title: note.title.into(),
+            content: note.content.into(),
+            visibility: note.visibility,
+            created_at: 1,
+            updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        })
+    }
+
+    async fn find_by_id(&self, _note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(None)
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer(token: &str) -> (&'static str, String) {
+    ("Authorization", format!("Bearer {}", token))
+}
+
+fn new_note() -> CreateNoteInput {
+    CreateNoteInput {
+        title: "t".into(),
+        content: "c".into(),
+        visibility: Visibility::Private,
+        tags: vec![],
+        workspace_id: None,
+        notebook_id: None,
+    }
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn api_token_lifecycle() {
+    let repo: Arc<dyn ApiTokenRepository> = Arc::new(MockApiTokenRepo::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(create_api_token)
+            .service(list_api_tokens)
+            .service(delete_api_token),
+    )
+    .await;
+    let access = jwt().generate(7).unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/me/tokens")
+        .insert_header(bearer(&access))
+        .set_json(CreateApiTokenInput {
+            name: "ci".into(),
+            scopes: vec![Scope::NotesRead],
+            expires_in_days: Some(30),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created: CreatedApiToken = test::read_body_json(resp).await;
+    assert!(created.token.starts_with("memo_pat_"));
+    assert_eq!(created.api_token.expires_at, Some(1 + 30 * 24 * 60 * 60));
+
+    // 発行したトークン自身ではトークンを管理できない
+    let req = test::TestRequest::get()
+        .uri("/me/tokens")
+        .insert_header(bearer(&created.token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri("/me/tokens")
+        .insert_header(bearer(&access))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let tokens: Vec<ApiToken> = test::read_body_json(resp).await;
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].name, "ci");
+    assert_eq!(tokens[0].last_used_at, Some(2));
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/me/tokens/{}", created.api_token.id))
+        .insert_header(bearer(&access))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    // 削除後は認証できない
+    let req = test::TestRequest::get()
+        .uri("/me/tokens")
+        .insert_header(bearer(&created.token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn create_api_token_validates_input() {
+    let repo: Arc<dyn ApiTokenRepository> = Arc::new(MockApiTokenRepo::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(create_api_token),
+    )
+    .await;
+    let access = jwt().generate(7).unwrap();
+
+    for input in [
+        CreateApiTokenInput {
+            name: " ".into(),
+            scopes: vec![Scope::NotesRead],
+            expires_in_days: None,
+        },
+        CreateApiTokenInput {
+            name: "ci".into(),
+            scopes: vec![],
+            expires_in_days: None,
+        },
+        CreateApiTokenInput {
+            name: "ci".into(),
+            scopes: vec![Scope::NotesRead],
+            expires_in_days: Some(0),
+        },
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/me/tokens")
+            .insert_header(bearer(&access))
+            .set_json(input)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[actix_web::test]
+async fn notes_handlers_enforce_token_scopes() {
+    let repo = Arc::new(MockApiTokenRepo::default());
+    let api_tokens: Arc<dyn ApiTokenRepository> = repo.clone();
+    let notes: Arc<dyn NoteRepository> = Arc::new(MockNoteRepo);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(api_tokens))
+            .app_data(web::Data::new(notes))
+            .app_data(web::Data::new(jwt()))
+            .service(create_note)
+            .service(list_notes),
+    )
+    .await;
+
+    let read_only = "memo_pat_read";
+    let read_write = "memo_pat_write";
+    repo.create_api_token(7, "r", &hash_token(read_only), &[Scope::NotesRead], None)
+        .await
+        .unwrap();
+    repo.create_api_token(
+        7,
+        "rw",
+        &hash_token(read_write),
+        &[Scope::NotesRead, Scope::NotesWrite],
+        None,
+    )
+    .await
+    .unwrap();
+
+    let req = test::TestRequest::get()
+        .uri("/notes")
+        .insert_header(bearer(read_only))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/notes")
+        .insert_header(bearer(read_only))
+        .set_json(new_note())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri("/notes")
+        .insert_header(bearer(read_write))
+        .set_json(new_note())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::post()
+        .uri("/notes")
+        .insert_header(bearer("memo_pat_unknown"))
+        .set_json(new_note())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、API トークンの認証が有効期限・アカウントの無効化・削除に従うことを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn api_token_queries_in_sqlite() {
+    use memo_app::repository::user::{SqliteUserRepository, UserAdminRepository};
+
+    let pool = common::sqlite_pool().await;
+    let owner = common::insert_user(&pool, "a@example.com").await;
+    let other = common::insert_user(&pool, "b@example.com").await;
+    let repo = SqliteUserRepository::new(pool.clone());
+    let scopes = [Scope::NotesRead];
+
+    let forever = repo
+        .create_api_token(owner, "ci", "hash-forever", &scopes, None)
+        .await
+        .unwrap();
+    assert!(forever.expires_at.is_none() && forever.last_used_at.is_none());
+    let expiring = repo
+        .create_api_token(owner, "tmp", "hash-expiring", &scopes, Some(3600))
+        .await
+        .unwrap();
+    assert_eq!(expiring.expires_at, Some(expiring.created_at + 3600));
+    repo.create_api_token(other, "other", "hash-other", &scopes, None)
+        .await
+        .unwrap();
+
+    let used = repo
+        .authenticate_api_token("hash-forever")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (used.id, used.user_id, used.scopes),
+        (forever.id, owner, vec![Scope::NotesRead])
+    );
+    assert!(used.last_used_at.is_some());
+    assert!(
+        repo.authenticate_api_token("hash-unknown")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    // 期限切れ
+    sqlx::query("UPDATE api_tokens SET expires_at = strftime('%s','now') - 1 WHERE id = ?")
+        .bind(expiring.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(
+        repo.authenticate_api_token("hash-expiring")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // 一覧と削除は持ち主のトークンだけが対象
+    let listed: Vec<i64> = repo
+        .list_api_tokens(owner)
+        .await
+        .unwrap()
+        .iter()
+        .map(|t| t.id)
+        .collect();
+    assert_eq!(listed, [expiring.id, forever.id]);
+    assert!(!repo.delete_api_token(other, forever.id).await.unwrap());
+
+    // 持ち主のアカウントを無効にすると使えず、有効に戻すと使える
+    repo.set_user_disabled(owner, true).await.unwrap();
+    assert!(
+        repo.authenticate_api_token("hash-forever")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    repo.set_user_disabled(owner, false).await.unwrap();
+    assert!(
+        repo.authenticate_api_token("hash-forever")
+            .await
+            .unwrap()
+            .is_some()
+    );
+
+    assert!(repo.delete_api_token(owner, forever.id).await.unwrap());
+    assert!(
+        repo.authenticate_api_token("hash-forever")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(repo.delete_user_api_tokens(owner).await.unwrap(), 1);
+    assert!(repo.list_api_tokens(owner).await.unwrap().is_empty());
+    assert_eq!(repo.list_api_tokens(other).await.unwrap().len(), 1);
+}
@@ -0,0 +1,186 @@
+use std::io::Cursor;
+
+use image::{ImageFormat, RgbImage};
+use memo_app::domain::attachment::{is_inline_type, normalize_filename, sniff_content_type};
+use memo_app::domain::image::{
+    image_dimensions, render_thumbnails, strip_metadata, thumbnail_width, thumbnail_widths,
+};
+
+/// 向き（`orientation`）と、その後ろに位置情報らしき文字列を持つ EXIF（TIFF 形式）。
+fn exif(orientation: u8) -> Vec<u8> {
+    let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0".to_vec();
+    tiff.push(orientation);
+    tiff.extend_from_slice(&[0; 6]);
+    tiff.extend_from_slice(b"GPS 35.6812N 139.7671E");
+    tiff
+}
+
+fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+    let mut out = Vec::new();
+    RgbImage::new(width, height)
+        .write_to(&mut Cursor::new(&mut out), format)
+        .unwrap();
+    out
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    data.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn sniff_content_type_ignores_declared_type() {
+    for (data, expected) in [
+        (&b"\x89PNG\r\n\x1a\n...."[..], "image/png"),
+        (b"\xff\xd8\xff\xe0....", "image/jpeg"),
+        (b"GIF89a....", "image/gif"),
+        (b"RIFF\x10\0\0\0WEBPVP8 ", "image/webp"),
+        (b"RIFF\x10\0\0\0WAVEfmt ", "application/octet-stream"),
+        (b"%PDF-1.7\n", "application/pdf"),
+        (b"PK\x03\x04....", "application/zip"),
+        (b"\x1f\x8b\x08", "application/gzip"),
+        // HTML・SVG もテキストとして返す
+        (b"<svg onload=alert(1)>", "text/plain; charset=utf-8"),
+        ("メモ\r\n\tok".as_bytes(), "text/plain; charset=utf-8"),
+        (b"", "text/plain; charset=utf-8"),
+        (b"\0\x01\x02", "application/octet-stream"),
+        (b"\xff\xfe", "application/octet-stream"),
+    ] {
+        assert_eq!(sniff_content_type(data), expected, "{data:?}");
+    }
+    assert!(is_inline_type("image/png"));
+    assert!(!is_inline_type("text/plain; charset=utf-8"));
+}
+
+#[test]
+fn normalize_filename_strips_paths_and_controls() {
+    assert_eq!(
+        normalize_filename("report.pdf").as_deref(),
+        Some("report.pdf")
+    );
+    assert_eq!(
+        normalize_filename("../../etc/passwd").as_deref(),
+        Some("passwd")
+    );
+    assert_eq!(
+        normalize_filename("C:\\tmp\\a b.txt").as_deref(),
+        Some("a b.txt")
+    );
+    assert_eq!(normalize_filename(" x\r\n.txt ").as_deref(), Some("x.txt"));
+    assert_eq!(
+        normalize_filename(&"あ".repeat(300))
+            .unwrap()
+            .chars()
+            .count(),
+        255
+    );
+    for name in ["", "  ", "dir/", "..", "\u{7}"] {
+        assert_eq!(normalize_filename(name), None, "{name:?}");
+    }
+}
+
+#[test]
+fn strip_metadata_keeps_only_orientation_in_jpeg() {
+    let jpeg = encode(40, 10, ImageFormat::Jpeg);
+    let mut app1 = b"\xff\xe1\0\0Exif\0\0".to_vec();
+    app1.extend(exif(6));
+    let len = (app1.len() - 2) as u16;
+    app1[2..4].copy_from_slice(&len.to_be_bytes());
+    let app13 = b"\xff\xed\0\x10Photoshop 3.0\0";
+    let mut photo = jpeg[..2].to_vec();
+    photo.extend(&app1);
+    photo.extend(app13);
+    photo.extend(&jpeg[2..]);
+
+    assert_eq!(image_dimensions(&photo), Some((10, 40)));
+    let stripped = strip_metadata(&photo);
+    assert!(contains(&photo, b"GPS") && !contains(&stripped, b"GPS"));
+    assert!(!contains(&stripped, b"Photoshop"));
+    // 向きは残るので、表示上の大きさは変わらない
+    assert_eq!(image_dimensions(&stripped), Some((10, 40)));
+    assert!(stripped.ends_with(&jpeg[jpeg.len() - 100..]));
+
+    // 向きを変えない EXIF は丸ごと取り除く
+    let mut upright = jpeg[..2].to_vec();
+    let mut app1 = app1.clone();
+    app1[10 + 19] = 1;
+    upright.extend(&app1);
+    upright.extend(&jpeg[2..]);
+    assert_eq!(strip_metadata(&upright).as_ref(), jpeg.as_slice());
+
+    // メタデータの無いもの・解釈できないものはそのまま
+    assert_eq!(strip_metadata(&jpeg).as_ref(), jpeg.as_slice());
+    assert_eq!(strip_metadata(b"\xff\xd8\xff").as_ref(), b"\xff\xd8\xff");
+    assert_eq!(strip_metadata(b"hello").as_ref(), b"hello");
+}
+
+#[test]
+fn strip_metadata_removes_webp_chunks_and_fixes_header() {
+    // 拡張形式（VP8X）の WebP に EXIF と XMP のチャンクを付ける
+    let simple = encode(30, 20, ImageFormat::WebP);
+    let image_chunk = &simple[12..];
+    let mut vp8x = b"VP8X\x0a\0\0\0\x0c\0\0\0".to_vec();
+    vp8x.extend_from_slice(&29u32.to_le_bytes()[..3]);
+    vp8x.extend_from_slice(&19u32.to_le_bytes()[..3]);
+    let mut exif_chunk = b"EXIF".to_vec();
+    let tiff = exif(8);
+    exif_chunk.extend_from_slice(&(tiff.len() as u32).to_le_bytes());
+    exif_chunk.extend(&tiff);
+    let xmp = b"XMP \x05\0\0\0<xmp>\0";
+    let mut webp = b"RIFF\0\0\0\0WEBP".to_vec();
+    webp.extend(&vp8x);
+    webp.extend_from_slice(image_chunk);
+    webp.extend(&exif_chunk);
+    webp.extend_from_slice(xmp);
+    let size = (webp.len() - 8) as u32;
+    webp[4..8].copy_from_slice(&size.to_le_bytes());
+
+    assert_eq!(image_dimensions(&webp), Some((20, 30)));
+    let stripped = strip_metadata(&webp);
+    assert!(!contains(&stripped, b"GPS") && !contains(&stripped, b"<xmp>"));
+    assert_eq!(
+        u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize,
+        stripped.len() - 8
+    );
+    // XMP のフラグだけが落ちる
+    assert_eq!(stripped[20], 0x08);
+    assert_eq!(image_dimensions(&stripped), Some((20, 30)));
+}
+
+#[test]
+fn thumbnail_widths_round_up_without_enlarging() {
+    for (requested, image_width, expected) in [
+        (100, 2000, 160),
+        (160, 2000, 160),
+        (161, 2000, 320),
+        (5000, 2000, 1280),
+        (1000, 500, 640),
+        (1000, 100, 160),
+        (100, 100, 160),
+    ] {
+        assert_eq!(
+            thumbnail_width(requested, image_width),
+            expected,
+            "{requested} for {image_width}"
+        );
+    }
+    assert_eq!(thumbnail_widths(100).collect::<Vec<_>>(), vec![160]);
+    assert_eq!(
+        thumbnail_widths(500).collect::<Vec<_>>(),
+        vec![160, 320, 640]
+    );
+    assert_eq!(
+        thumbnail_widths(5000).collect::<Vec<_>>(),
+        vec![160, 320, 640, 1280]
+    );
+
+    let thumbnails = render_thumbnails(&encode(500, 100, ImageFormat::Jpeg), &[160, 640]).unwrap();
+    let sizes: Vec<_> = thumbnails
+        .iter()
+        .map(|t| {
+            assert_eq!(image::guess_format(t).unwrap(), ImageFormat::Jpeg);
+            image_dimensions(t).unwrap()
+        })
+        .collect();
+    assert_eq!(sizes, vec![(160, 32), (500, 100)]);
+    assert!(render_thumbnails(b"\x89PNG\r\n\x1a\nbroken", &[160]).is_err());
+}
@@ -0,0 +1,681 @@
+use std::collections::HashSet;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use image::{ImageFormat, RgbImage};
+use memo_app::app::attachments::{
+    attachment_thumbnail, delete_attachment, download_attachment, list_attachments,
+    upload_attachment,
+};
+use memo_app::app::notes::get_note;
+use memo_app::domain::model::{Attachment, Note, NoteSearchHit, TrashedNote, Visibility};
+use memo_app::middleware::auth::token::{JwtTokenService, random_token};
+use memo_app::repository::attachment::{AttachmentRepository, NewAttachment};
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::user::RepoError;
+use memo_app::service::attachment::AttachmentService;
+use memo_app::service::blob::LocalBlobStore;
+
+// ---- Mocks ----
+
+// ユーザー 1 の private なメモ（id=1）だけが存在する。本文は添付ファイルの画像を参照する
+struct MockNoteRepo;
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok((note_id == 1).then(|| Note {
+            id: 1,
+            author_id: 1,
+            workspace_id: None,
+            notebook_id: None,
+            title: "n1".into(),
+            content: "![a](/notes/1/attachments/1) ![b](/notes/1/attachments/2) \
+                      ![c](/notes/2/attachments/1) [d](/notes/1/attachments/1)"
+                .into(),
+            visibility: Visibility::Private,
+            created_at: 1,
+            updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        }))
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(None)
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+#[derive(Default)]
+struct MockAttachmentRepo {
+    attachments: Mutex<Vec<Attachment>>,
+    thumbnails_done: Mutex<HashSet<i64>>,
+}
+
+#[async_trait]
+impl AttachmentRepository for MockAttachmentRepo {
+    async fn create_attachment(
+        &self,
+        attachment: &NewAttachment<'_>,
+        quota: i64,
+    ) -> Result<Option<Attachment>, RepoError> {
+        let mut attachments = self.attachments.lock().unwrap();
+        let used: i64 = attachments
+            .iter()
+            .filter(|a| a.user_id == Some(attachment.user_id))
+            .map(|a| a.size)
+            .sum();
+        if used + attachment.size > quota {
+            return Ok(None);
+        }
+        let created = Attachment {
+            id: attachments.len() as i64 + 1,
+            note_id: attachment.note_id,
+            user_id: Some(attachment.user_id),
+            filename: attachment.filename.into(),
+            content_type: attachment.content_type.into(),
+            size: attachment.size,
+            sha256: attachment.sha256.into(),
+            width: attachment.width,
+            height: attachment.height,
+            created_at: 1,
+            storage_key: attachment.storage_key.into(),
+        };
+        attachments.push(created.clone());
+        Ok(Some(created))
+    }
+
+    async fn list_attachments(&self, note_id: i64) -> Result<Vec<Attachment>, RepoError> {
+        let attachments = self.attachments.lock().unwrap();
+        Ok(attachments
+            .iter()
+            .filter(|a| a.note_id == note_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_attachment(
+        &self,
+        note_id: i64,
+        attachment_id: i64,
+    ) -> Result<Option<Attachment>, RepoError> {
+        let attachments = self.attachments.lock().unwrap();
+        Ok(attachments
+            .iter()
+            .find(|a| a.note_id == note_id && a.id == attachment_id)
+            .cloned())
+    }
+
+    async fn get_attachment(&self, attachment_id: i64) -> Result<Option<Attachment>, RepoError> {
+        let attachments = self.attachments.lock().unwrap();
+        Ok(attachments.iter().find(|a| a.id == attachment_id).cloned())
+    }
+
+    async fn delete_attachment(
+        &self,
+        note_id: i64,
+        attachment_id: i64,
+    ) -> Result<Option<Attachment>, RepoError> {
+        let mut attachments = self.attachments.lock().unwrap();
+        let index = attachments
+            .iter()
+            .position(|a| a.note_id == note_id && a.id == attachment_id);
+        Ok(index.map(|i| attachments.remove(i)))
+    }
+
+    async fn orphaned_attachments(&self, _limit: i64) -> Result<Vec<(i64, String)>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_orphan(&self, _attachment_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn pending_thumbnails(&self, limit: i64) -> Result<Vec<Attachment>, RepoError> {
+        let attachments = self.attachments.lock().unwrap();
+        let done = self.thumbnails_done.lock().unwrap();
+        Ok(attachments
+            .iter()
+            .filter(|a| a.width.is_some() && !done.contains(&a.id))
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn mark_thumbnails_done(&self, attachment_id: i64) -> Result<(), RepoError> {
+        self.thumbnails_done.lock().unwrap().insert(attachment_id);
+        Ok(())
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn blob_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("memo-attachments-{}", random_token(8)))
+}
+
+fn bearer(user_id: i64) -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(user_id).unwrap()),
+    )
+}
+
+/// `file` フィールドに `data` を入れた `multipart/form-data` の本文。
+fn multipart(filename: &str, data: &[u8]) -> (String, Vec<u8>) {
+    let mut body = format!(
+        "--BOUNDARY\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+         Content-Type: text/plain\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(data);
+    body.extend_from_slice(b"\r\n--BOUNDARY--\r\n");
+    ("multipart/form-data; boundary=BOUNDARY".into(), body)
+}
+
+macro_rules! app {
+    ($dir:expr, $max:expr, $quota:expr) => {{
+        let service = AttachmentService::new(
+            Arc::new(MockAttachmentRepo::default()),
+            Arc::new(LocalBlobStore::new($dir)),
+        )
+        .with_limits($max, $quota);
+        app!(web::Data::new(service))
+    }};
+    ($service:expr) => {{
+        let note_repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepo);
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(note_repo))
+                .app_data($service)
+                .app_data(web::Data::new(jwt()))
+                .service(upload_attachment)
+                .service(list_attachments)
+                .service(download_attachment)
+                .service(delete_attachment)
+                .service(attachment_thumbnail)
+                .service(get_note),
+        )
+        .await
+    }};
+}
+
+/// PNG のチャンク（長さ・種類・データ・CRC）。
+fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(&crc.finalize().to_be_bytes());
+    chunk
+}
+
+/// 幅 800・高さ 200 の PNG に、向き（90 度回転）と位置情報らしきものを持つ EXIF と
+/// テキストのチャンクを付けたもの。表示上は幅 200・高さ 800 になる。
+fn photo_png() -> Vec<u8> {
+    let mut png = Vec::new();
+    RgbImage::new(800, 200)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let mut exif = b"MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0".to_vec();
+    exif.extend_from_slice(b"GPS 35.6812N 139.7671E");
+    // IHDR（シグネチャ 8 バイト + チャンク 25 バイト）の直後に入れる
+    let mut chunks = png_chunk(b"eXIf", &exif);
+    chunks.extend(png_chunk(b"tEXt", b"Comment\0GPS 35.6812N 139.7671E"));
+    png.splice(33..33, chunks);
+    png
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn upload_download_and_delete_attachment() {
+    let dir = blob_dir();
+    let app = app!(&dir, 1024, 4096);
+
+    // 申告された名前・タイプに関係なく、中身から PNG と判定する
+    let png = b"\x89PNG\r\n\x1a\n0123456789";
+    let (content_type, body) = multipart("../shot.txt", png);
+    let req = test::TestRequest::post()
+        .uri("/notes/1/attachments")
+        .insert_header(bearer(1))
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let attachment: Attachment = test::read_body_json(resp).await;
+    assert_eq!(attachment.filename, "shot.txt");
+    assert_eq!(attachment.content_type, "image/png");
+    assert_eq!(attachment.size, png.len() as i64);
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1/attachments")
+        .insert_header(bearer(1))
+        .to_request();
+    let list: Vec<Attachment> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(list.iter().map(|a| a.id).collect::<Vec<_>>(), vec![1]);
+
+    let uri = format!("/notes/1/attachments/{}", attachment.id);
+    let req = test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+    assert_eq!(resp.headers().get("accept-ranges").unwrap(), "bytes");
+    let disposition = resp.headers().get("content-disposition").unwrap();
+    assert!(disposition.to_str().unwrap().starts_with("inline"));
+    let etag = resp.headers().get("etag").unwrap().clone();
+    assert_eq!(test::read_body(resp).await.as_ref(), png);
+
+    // 範囲の指定（先頭から・末尾から）
+    for (range, expected, content_range) in [
+        ("bytes=8-11", &png[8..12], "bytes 8-11/18"),
+        ("bytes=-3", &png[15..], "bytes 15-17/18"),
+        ("bytes=16-", &png[16..], "bytes 16-17/18"),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(bearer(1))
+            .insert_header(("Range", range))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT, "{range}");
+        assert_eq!(resp.headers().get("content-range").unwrap(), content_range);
+        assert_eq!(test::read_body(resp).await.as_ref(), expected);
+    }
+
+    let req = test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(bearer(1))
+        .insert_header(("Range", "bytes=100-"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(resp.headers().get("content-range").unwrap(), "bytes */18");
+
+    // `If-Range` が一致しなければ全体を返す
+    for (if_range, status) in [
+        (etag.to_str().unwrap(), StatusCode::PARTIAL_CONTENT),
+        ("\"stale\"", StatusCode::OK),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(bearer(1))
+            .insert_header(("Range", "bytes=0-1"))
+            .insert_header(("If-Range", if_range))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), status, "{if_range}");
+    }
+
+    let req = test::TestRequest::delete()
+        .uri(&uri)
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let req = test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    // 本体も削除されている
+    assert_eq!(std::fs::read_dir(dir.join("notes/1")).unwrap().count(), 0);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[actix_web::test]
+async fn upload_enforces_size_limit_and_quota() {
+    let dir = blob_dir();
+    let app = app!(&dir, 8, 12);
+
+    let upload = |data: &'static [u8]| {
+        let (content_type, body) = multipart("a.txt", data);
+        test::TestRequest::post()
+            .uri("/notes/1/attachments")
+            .insert_header(bearer(1))
+            .insert_header(("Content-Type", content_type))
+            .set_payload(body)
+            .to_request()
+    };
+    let resp = test::call_service(&app, upload(b"123456789")).await;
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    let resp = test::call_service(&app, upload(b"12345678")).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let attachment: Attachment = test::read_body_json(resp).await;
+    assert_eq!(attachment.content_type, "text/plain; charset=utf-8");
+
+    // 合計が 12 バイトを超える
+    let resp = test::call_service(&app, upload(b"12345")).await;
+    assert_eq!(resp.status(), StatusCode::INSUFFICIENT_STORAGE);
+    // 上限を超えた分の本体は残らない
+    assert_eq!(std::fs::read_dir(dir.join("notes/1")).unwrap().count(), 1);
+
+    let resp = test::call_service(&app, upload(b"1234")).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[actix_web::test]
+async fn attachments_follow_note_permissions() {
+    let dir = blob_dir();
+    let app = app!(&dir, 1024, 4096);
+
+    // 閲覧できないメモ（他人の private なメモ・存在しないメモ）は 404
+    let (content_type, body) = multipart("a.txt", b"hello");
+    let req = test::TestRequest::post()
+        .uri("/notes/1/attachments")
+        .insert_header(bearer(2))
+        .insert_header(("Content-Type", content_type.clone()))
+        .set_payload(body.clone())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    for uri in ["/notes/1/attachments", "/notes/5/attachments"] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(bearer(2))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+    }
+    let req = test::TestRequest::get()
+        .uri("/notes/1/attachments")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // ファイルが無ければ 400
+    let req = test::TestRequest::post()
+        .uri("/notes/1/attachments")
+        .insert_header(bearer(1))
+        .insert_header(("Content-Type", content_type))
+        .set_payload(b"--BOUNDARY--\r\n".to_vec())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[actix_web::test]
+async fn images_get_thumbnails_without_metadata() {
+    let dir = blob_dir();
+    let service = web::Data::new(AttachmentService::new(
+        Arc::new(MockAttachmentRepo::default()),
+        Arc::new(LocalBlobStore::new(&dir)),
+    ));
+    let app = app!(service.clone());
+
+    let upload = |filename: &str, data: &[u8]| {
+        let (content_type, body) = multipart(filename, data);
+        test::TestRequest::post()
+            .uri("/notes/1/attachments")
+            .insert_header(bearer(1))
+            .insert_header(("Content-Type", content_type))
+            .set_payload(body)
+            .to_request()
+    };
+    let photo: Attachment =
+        test::call_and_read_body_json(&app, upload("photo.png", &photo_png())).await;
+    assert_eq!((photo.width, photo.height), (Some(200), Some(800)));
+    let text: Attachment = test::call_and_read_body_json(&app, upload("a.txt", b"hello")).await;
+    assert_eq!((text.width, text.height), (None, None));
+
+    // 保存したものからは EXIF の向き以外のメタデータが消えている
+    let req = test::TestRequest::get()
+        .uri("/notes/1/attachments/1")
+        .insert_header(bearer(1))
+        .to_request();
+    let stored = test::call_and_read_body(&app, req).await;
+    assert_eq!(stored.len() as i64, photo.size);
+    assert!(!stored.windows(3).any(|w| w == b"GPS"));
+    assert!(!stored.windows(4).any(|w| w == b"tEXt"));
+    assert!(stored.windows(4).any(|w| w == b"eXIf"));
+
+    // サムネイルを作るまでは元の画像にリダイレクトする
+    let req = test::TestRequest::get()
+        .uri("/attachments/1/thumb?w=100")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(
+        resp.headers().get("location").unwrap(),
+        "/notes/1/attachments/1?w=100"
+    );
+    assert_eq!(service.process_thumbnails().await, 1);
+    assert_eq!(service.process_thumbnails().await, 0);
+
+    // 幅は用意してある幅に丸め、元の画像より大きくはしない。向きは画素に反映する
+    for (uri, expected) in [
+        ("/attachments/1/thumb?w=100", (160, 640)),
+        ("/attachments/1/thumb?w=1000", (200, 800)),
+        ("/attachments/1/thumb", (200, 800)),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(bearer(1))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{uri}");
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+        let thumb = image::load_from_memory(&test::read_body(resp).await).unwrap();
+        assert_eq!((thumb.width(), thumb.height()), expected, "{uri}");
+    }
+
+    // 画像でない・閲覧できない・幅が 0 の場合
+    for (uri, user, status) in [
+        ("/attachments/2/thumb", 1, StatusCode::NOT_FOUND),
+        ("/attachments/9/thumb", 1, StatusCode::NOT_FOUND),
+        ("/attachments/1/thumb", 2, StatusCode::NOT_FOUND),
+        ("/attachments/1/thumb?w=0", 1, StatusCode::BAD_REQUEST),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(bearer(user))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), status, "{uri} as {user}");
+    }
+
+    // HTML に変換した本文では、このメモの添付画像だけをサムネイルにする
+    let req = test::TestRequest::get()
+        .uri("/notes/1?format=html")
+        .insert_header(bearer(1))
+        .to_request();
+    let html = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    assert!(
+        html.contains(r#"src="/attachments/1/thumb?w=640""#),
+        "{html}"
+    );
+    assert!(html.contains(r#"src="/notes/1/attachments/2""#), "{html}");
+    assert!(html.contains(r#"src="/notes/2/attachments/1""#), "{html}");
+    assert!(html.contains(r#"href="/notes/1/attachments/1""#), "{html}");
+
+    // 削除するとサムネイルも消える
+    let req = test::TestRequest::delete()
+        .uri("/notes/1/attachments/1")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(std::fs::read_dir(dir.join("notes/1")).unwrap().count(), 1);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、添付ファイルのメタデータの各クエリ（容量の上限、メモでの絞り込み、
+/// サムネイルの待ち行列、完全に削除されたメモの添付ファイル）を確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn attachment_queries_in_sqlite() {
+    use memo_app::repository::attachment::SqliteAttachmentRepository;
+    use memo_app::repository::note::SqliteNoteRepository;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let notes = SqliteNoteRepository::new(pool.clone());
+    let repo = SqliteAttachmentRepository::new(pool.clone());
+    let new_note = NewNote {
+        title: "t",
+        content: "c",
+        ..Default::default()
+    };
+    let note = notes.create_note(author, &new_note).await.unwrap();
+    let other = notes.create_note(author, &new_note).await.unwrap();
+    let attachment = |note_id, storage_key, size, width| NewAttachment {
+        note_id,
+        user_id: author,
+        filename: "f",
+        content_type: "application/octet-stream",
+        size,
+        sha256: "00",
+        width,
+        height: width,
+        storage_key,
+    };
+
+    let image = repo
+        .create_attachment(&attachment(note.id, "k1", 60, Some(10)), 100)
+        .await
+        .unwrap()
+        .unwrap();
+    // 合計が上限を超えるものは追加しない（他のメモの添付ファイルも合計に含める）
+    assert!(
+        repo.create_attachment(&attachment(other.id, "k2", 41, None), 100)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let file = repo
+        .create_attachment(&attachment(other.id, "k2", 40, None), 100)
+        .await
+        .unwrap()
+        .unwrap();
+    let listed: Vec<i64> = repo
+        .list_attachments(note.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|a| a.id)
+        .collect();
+    assert_eq!(listed, [image.id]);
+    assert!(
+        repo.find_attachment(note.id, file.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(
+        repo.get_attachment(file.id).await.unwrap().unwrap().note_id,
+        other.id
+    );
+
+    // サムネイルを作るのは画像だけで、作り終えたら待ち行列から外れる
+    let pending: Vec<i64> = repo
+        .pending_thumbnails(10)
+        .await
+        .unwrap()
+        .iter()
+        .map(|a| a.id)
+        .collect();
+    assert_eq!(pending, [image.id]);
+    repo.mark_thumbnails_done(image.id).await.unwrap();
+    assert!(repo.pending_thumbnails(10).await.unwrap().is_empty());
+
+    // 完全に削除したメモの添付ファイルだけが後片付けの対象になる
+    assert!(notes.delete_note(other.id, None).await.unwrap());
+    assert!(repo.orphaned_attachments(10).await.unwrap().is_empty());
+    assert!(notes.purge_note(other.id, author).await.unwrap());
+    assert_eq!(
+        repo.orphaned_attachments(10).await.unwrap(),
+        [(file.id, "k2".to_string())]
+    );
+    assert!(repo.get_attachment(file.id).await.unwrap().is_none());
+    assert!(!repo.delete_orphan(image.id).await.unwrap());
+    assert!(repo.delete_orphan(file.id).await.unwrap());
+
+    assert!(
+        repo.delete_attachment(other.id, image.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let deleted = repo
+        .delete_attachment(note.id, image.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(deleted.storage_key, "k1");
+    assert!(repo.list_attachments(note.id).await.unwrap().is_empty());
+}
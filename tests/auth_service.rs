@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use memo_app::domain::model::User;
+use memo_app::domain::model::{Role, User};
 use memo_app::repository::user::{
     MockRepoConflict, MockRepoSuccess, MockRepoWithUser, RepoError, UserRepository,
 };
@@ -21,6 +21,28 @@ impl UserRepository for MockRepoError {
     async fn find_by_email(&self, _email: &str) -> Result<Option<User>, RepoError> {
         Err(RepoError::Internal)
     }
+    async fn find_by_id(&self, _user_id: i64) -> Result<Option<User>, RepoError> {
+        Err(RepoError::Internal)
+    }
+    async fn update_password(
+        &self,
+        _user_id: i64,
+        _password_hash: &str,
+    ) -> Result<bool, RepoError> {
+        Err(RepoError::Internal)
+    }
+    async fn mark_email_verified(&self, _user_id: i64) -> Result<bool, RepoError> {
+        Err(RepoError::Internal)
+    }
+    async fn record_login_failure(&self, _user_id: i64) -> Result<i64, RepoError> {
+        Err(RepoError::Internal)
+    }
+    async fn lock_user_until(&self, _user_id: i64, _locked_until: i64) -> Result<(), RepoError> {
+        Err(RepoError::Internal)
+    }
+    async fn reset_login_failures(&self, _user_id: i64) -> Result<(), RepoError> {
+        Err(RepoError::Internal)
+    }
 }
 
 #[tokio::test]
@@ -88,6 +110,11 @@ async fn login_returns_user_when_credentials_valid() {
         email: "a@example.com".into(),
         password_hash: phc("password123"),
         created_at: 0,
+        email_verified_at: None,
+        failed_login_count: 0,
+        locked_until: None,
+        role: Role::User,
+        disabled_at: None,
     };
     let repo = Arc::new(MockRepoWithUser { user });
     let service = AuthServiceImpl::new(repo);
@@ -103,6 +130,11 @@ async fn login_returns_error_when_password_wrong() {
         email: "a@example.com".into(),
         password_hash: phc("password123"),
         created_at: 0,
+        email_verified_at: None,
+        failed_login_count: 0,
+        locked_until: None,
+        role: Role::User,
+        disabled_at: None,
     };
     let repo = Arc::new(MockRepoWithUser { user });
     let service = AuthServiceImpl::new(repo);
@@ -118,6 +150,11 @@ async fn login_returns_error_when_user_not_found() {
         email: "b@example.com".into(),
         password_hash: phc("password123"),
         created_at: 0,
+        email_verified_at: None,
+        failed_login_count: 0,
+        locked_until: None,
+        role: Role::User,
+        disabled_at: None,
     };
     let repo = Arc::new(MockRepoWithUser { user });
     let service = AuthServiceImpl::new(repo);
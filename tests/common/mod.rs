@@ -0,0 +1,43 @@
+//! 実際の SQLite（インメモリ）を使うテストの共通部分。
+
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqlitePoolOptions;
+
+const SCHEMA: &str = include_str!("../fixtures/sqlite/schema.sql");
+
+/// `tests/fixtures/sqlite/schema.sql` を流したインメモリのデータベース。
+/// 接続ごとに別のデータベースになるので、接続は 1 本に限る。
+pub async fn sqlite_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("sqlite connect");
+    for statement in SCHEMA.split(';').filter(|s| !s.trim().is_empty()) {
+        sqlx::query(statement)
+            .execute(&pool)
+            .await
+            .expect("sqlite schema");
+    }
+    pool
+}
+
+/// パスワード `password123` のユーザーを作り、ID を返す。
+#[allow(dead_code)]
+pub async fn insert_user(pool: &SqlitePool, email: &str) -> i64 {
+    use argon2::{Argon2, PasswordHasher};
+    use password_hash::{SaltString, rand_core::OsRng};
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(b"password123", &salt)
+        .unwrap()
+        .to_string();
+    sqlx::query_scalar(
+        "INSERT INTO users (email, password_hash, created_at) VALUES (?, ?, 0) RETURNING id",
+    )
+    .bind(email)
+    .bind(password_hash)
+    .fetch_one(pool)
+    .await
+    .expect("insert user")
+}
@@ -0,0 +1,807 @@
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::body::MessageBody;
+use actix_web::{App, HttpServer, http::StatusCode, web};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use memo_app::app::events::{note_events, note_events_ws};
+use memo_app::app::model::{CreateNoteInput, NoteEventMessage, UpdateNoteInput};
+use memo_app::app::notes::{create_note, delete_note, update_note};
+use memo_app::domain::model::{Note, NoteSearchHit, TrashedNote, Visibility};
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::user::RepoError;
+use memo_app::service::events::{EventBus, NoteEventKind, Received};
+
+// ---- Mocks ----
+
+// 作成・更新（タイトルと公開範囲）・削除だけを保持する
+#[derive(Default)]
+struct MockNoteRepo {
+    notes: Mutex<Vec<Note>>,
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, user_id: i64, note: &NewNote<'_>) -> Result<Note, RepoError> {
+        let mut notes = self.notes.lock().unwrap();
+        let created = Note {
+            id: notes.len() as i64 + 1,
+            author_id: user_id,
+            workspace_id: None,
+            notebook_id: None,
+            title: note.title.to_string(),
+            content: note.content.to_string(),
+            visibility: note.visibility,
+            created_at: 1,
+            updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        };
+        notes.push(created.clone());
+        Ok(created)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        let notes = self.notes.lock().unwrap();
+        Ok(notes.iter().find(|n| n.id == note_id).cloned())
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        note_id: i64,
+        _user_id: i64,
+        changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        let mut notes = self.notes.lock().unwrap();
+        let Some(note) = notes.iter_mut().find(|n| n.id == note_id) else {
+            return Ok(None);
+        };
+        if let Some(title) = changes.title {
+            note.title = title.to_string();
+        }
+        if let Some(visibility) = changes.visibility {
+            note.visibility = visibility;
+        }
+        note.version += 1;
+        Ok(Some(note.clone().into()))
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_note(
+        &self,
+        note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        let mut notes = self.notes.lock().unwrap();
+        let before = notes.len();
+        notes.retain(|n| n.id != note_id);
+        Ok(notes.len() < before)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer(user_id: i64) -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(user_id).unwrap()),
+    )
+}
+
+fn note(id: i64, author_id: i64, visibility: Visibility) -> Note {
+    Note {
+        id,
+        author_id,
+        workspace_id: None,
+        notebook_id: None,
+        title: "t".into(),
+        content: "c".into(),
+        visibility,
+        created_at: 1,
+        updated_at: 1,
+        version: 1,
+        tags: vec![],
+        pinned: false,
+        starred: false,
+        archived_at: None,
+    }
+}
+
+/// SSE の次のイベント（キープアライブは含まない）を `(event, data)` で返す。
+async fn next_event<B: MessageBody>(body: &mut Pin<Box<B>>) -> (String, String) {
+    let chunk = actix_web::rt::time::timeout(
+        Duration::from_secs(5),
+        std::future::poll_fn(|cx| body.as_mut().poll_next(cx)),
+    )
+    .await
+    .expect("no event")
+    .expect("stream ended")
+    .map_err(Into::into)
+    .unwrap();
+    let frame = String::from_utf8(chunk.to_vec()).unwrap();
+    let field = |name: &str| {
+        frame
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .unwrap_or_default()
+            .to_string()
+    };
+    (field("event: "), field("data: "))
+}
+
+fn message(data: &str) -> NoteEventMessage {
+    serde_json::from_str(data).unwrap()
+}
+
+macro_rules! events_app {
+    ($repo:expr, $bus:expr) => {
+        actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new($repo.clone() as Arc<dyn NoteRepository>))
+                .app_data(web::Data::new(jwt()))
+                .app_data($bus.clone())
+                .service(create_note)
+                .service(update_note)
+                .service(delete_note)
+                .service(note_events)
+                .service(note_events_ws),
+        )
+        .await
+    };
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn bus_replays_events_after_last_event_id() {
+    let bus = EventBus::new(2);
+    for id in 1..=3 {
+        bus.publish(NoteEventKind::Created, note(id, 1, Visibility::Public));
+    }
+    let received_note = |received: Received| match received {
+        Received::Event(event) => event.note.id,
+        Received::Missed => 0,
+    };
+
+    // 保持しているのは直近の 2 件（メモ 2・3）
+    let mut live = bus.subscribe(None);
+    let mut replay = bus.subscribe(None);
+    bus.publish(NoteEventKind::Created, note(4, 1, Visibility::Public));
+    let Received::Event(fourth) = live.recv().await else {
+        panic!("missed");
+    };
+    assert_eq!(fourth.note.id, 4);
+    assert_eq!(received_note(replay.recv().await), 4);
+
+    let mut resumed = bus.subscribe(Some(fourth.id - 2));
+    assert_eq!(received_note(resumed.recv().await), 3);
+    assert_eq!(received_note(resumed.recv().await), 4);
+    // 保持している範囲より古い ID（再起動前の ID を含む）や未発行の ID からは再開できない
+    for last in [fourth.id - 3, 1, fourth.id + 1] {
+        assert!(matches!(
+            bus.subscribe(Some(last)).recv().await,
+            Received::Missed
+        ));
+    }
+    assert!(
+        actix_web::rt::time::timeout(
+            Duration::from_millis(50),
+            bus.subscribe(Some(fourth.id)).recv()
+        )
+        .await
+        .is_err()
+    );
+}
+
+#[actix_web::test]
+async fn sse_streams_changes_of_listed_notes() {
+    let repo = Arc::new(MockNoteRepo::default());
+    let bus = web::Data::new(EventBus::default());
+    let app = events_app!(repo, bus);
+
+    // 作成者はトークンをクエリで、他のユーザーはヘッダーで渡す
+    let token = jwt().generate(1).unwrap();
+    let resp = actix_web::test::call_service(
+        &app,
+        actix_web::test::TestRequest::get()
+            .uri(&format!("/events?access_token={token}"))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    let mut author = Box::pin(resp.into_body());
+    let resp = actix_web::test::call_service(
+        &app,
+        actix_web::test::TestRequest::get()
+            .uri("/events")
+            .insert_header(bearer(2))
+            .to_request(),
+    )
+    .await;
+    let mut other = Box::pin(resp.into_body());
+
+    for visibility in [Visibility::Private, Visibility::Public] {
+        let create = actix_web::test::TestRequest::post()
+            .uri("/notes")
+            .insert_header(bearer(1))
+            .set_json(CreateNoteInput {
+                title: "t".into(),
+                content: "c".into(),
+                visibility,
+                tags: vec![],
+                workspace_id: None,
+                notebook_id: None,
+            })
+            .to_request();
+        let resp = actix_web::test::call_service(&app, create).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+    let (event, data) = next_event(&mut author).await;
+    assert_eq!(event, "note.created");
+    let first = message(&data);
+    assert_eq!((first.note_id, first.note.unwrap().id), (1, 1));
+    assert_eq!(message(&next_event(&mut author).await.1).note_id, 2);
+    // 他人の非公開のメモは届かない
+    let (event, data) = next_event(&mut other).await;
+    assert_eq!(
+        (event.as_str(), message(&data).note_id),
+        ("note.created", 2)
+    );
+
+    // `public` でなくなったメモは、他のユーザーには削除として届く（内容は含めない）
+    let update = actix_web::test::TestRequest::put()
+        .uri("/notes/2")
+        .insert_header(bearer(1))
+        .set_json(UpdateNoteInput {
+            title: Some("secret".into()),
+            content: None,
+            visibility: Some(Visibility::Private),
+            tags: None,
+        })
+        .to_request();
+    assert_eq!(
+        actix_web::test::call_service(&app, update).await.status(),
+        StatusCode::OK
+    );
+    let (event, data) = next_event(&mut author).await;
+    assert_eq!(event, "note.updated");
+    assert_eq!(message(&data).note.unwrap().title, "secret");
+    let (event, data) = next_event(&mut other).await;
+    assert_eq!(event, "note.deleted");
+    let deleted = message(&data);
+    assert_eq!(deleted.note_id, 2);
+    assert!(deleted.note.is_none());
+    assert!(!data.contains("secret"));
+
+    let delete = actix_web::test::TestRequest::delete()
+        .uri("/notes/1")
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(
+        actix_web::test::call_service(&app, delete).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let (event, data) = next_event(&mut author).await;
+    assert_eq!(
+        (event.as_str(), message(&data).note_id),
+        ("note.deleted", 1)
+    );
+
+    // 再接続すると `Last-Event-ID` の次から届く
+    let resp = actix_web::test::call_service(
+        &app,
+        actix_web::test::TestRequest::get()
+            .uri("/events")
+            .insert_header(bearer(1))
+            .insert_header(("Last-Event-ID", first.id.to_string()))
+            .to_request(),
+    )
+    .await;
+    let mut resumed = Box::pin(resp.into_body());
+    let ids: Vec<_> = [
+        next_event(&mut resumed).await,
+        next_event(&mut resumed).await,
+        next_event(&mut resumed).await,
+    ]
+    .iter()
+    .map(|(event, data)| (event.clone(), message(data).note_id))
+    .collect();
+    assert_eq!(
+        ids,
+        [
+            ("note.created".to_string(), 2),
+            ("note.updated".to_string(), 2),
+            ("note.deleted".to_string(), 1)
+        ]
+    );
+    // 続きを送れない ID なら取り直しを促す
+    let resp = actix_web::test::call_service(
+        &app,
+        actix_web::test::TestRequest::get()
+            .uri("/events?last_event_id=1")
+            .insert_header(bearer(1))
+            .to_request(),
+    )
+    .await;
+    let (event, data) = next_event(&mut Box::pin(resp.into_body())).await;
+    assert_eq!(
+        (event.as_str(), data.as_str()),
+        ("reset", r#"{"type":"reset"}"#)
+    );
+}
+
+#[actix_web::test]
+async fn events_require_a_valid_token() {
+    let repo = Arc::new(MockNoteRepo::default());
+    let bus = web::Data::new(EventBus::default());
+    let app = events_app!(repo, bus);
+
+    for uri in [
+        "/events",
+        "/events?access_token=",
+        "/events?access_token=invalid",
+        "/events/ws",
+    ] {
+        let req = actix_web::test::TestRequest::get().uri(uri).to_request();
+        let resp = actix_web::test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED, "{uri}");
+    }
+    let req = actix_web::test::TestRequest::get()
+        .uri("/events")
+        .insert_header(bearer(1))
+        .insert_header(("Last-Event-ID", "abc"))
+        .to_request();
+    let resp = actix_web::test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn websocket_streams_the_same_events() {
+    let bus = web::Data::new(EventBus::default());
+    let data = bus.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(jwt()))
+            .app_data(data.clone())
+            .service(note_events_ws)
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    bus.publish(NoteEventKind::Created, note(1, 1, Visibility::Public));
+    let token = jwt().generate(2).unwrap();
+    let (resp, mut socket) = awc::Client::new()
+        .ws(format!("ws://{addr}/events/ws?access_token={token}"))
+        .connect()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+    bus.publish(NoteEventKind::Created, note(2, 1, Visibility::Private));
+    bus.publish(NoteEventKind::Deleted, note(1, 1, Visibility::Public));
+    let frame = actix_web::rt::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let awc::ws::Frame::Text(text) = frame else {
+        panic!("unexpected frame: {frame:?}");
+    };
+    let deleted: NoteEventMessage = serde_json::from_slice(&text).unwrap();
+    assert_eq!(
+        (deleted.kind.as_str(), deleted.note_id),
+        ("note.deleted", 1)
+    );
+
+    // `?last_event_id=` で続きから受け取る
+    let (_, mut resumed) = awc::Client::new()
+        .ws(format!(
+            "ws://{addr}/events/ws?access_token={token}&last_event_id={}",
+            deleted.id - 2
+        ))
+        .connect()
+        .await
+        .unwrap();
+    let Some(Ok(awc::ws::Frame::Text(text))) = resumed.next().await else {
+        panic!("no event");
+    };
+    let replayed: NoteEventMessage = serde_json::from_slice(&text).unwrap();
+    assert_eq!((replayed.id, replayed.note_id), (deleted.id, 1));
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、個別に共有された非公開のメモの変更が共有相手にも届くことを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn shared_notes_appear_in_events_in_sqlite() {
+    use memo_app::domain::model::SharePermission;
+    use memo_app::repository::note::SqliteNoteRepository;
+    use memo_app::repository::share::{ShareRepository, SqliteShareRepository};
+    use memo_app::repository::user::{SqliteUserRepository, UserRepository};
+    use memo_app::repository::workspace::{SqliteWorkspaceRepository, WorkspaceRepository};
+    use memo_app::service::share::ShareService;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let reader = common::insert_user(&pool, "b@example.com").await;
+    let stranger = common::insert_user(&pool, "c@example.com").await;
+
+    let notes: Arc<dyn NoteRepository> = Arc::new(SqliteNoteRepository::new(pool.clone()));
+    let share_repo = Arc::new(SqliteShareRepository::new(pool.clone()));
+    let workspaces: Arc<dyn WorkspaceRepository> =
+        Arc::new(SqliteWorkspaceRepository::new(pool.clone()));
+    let users: Arc<dyn UserRepository> = Arc::new(SqliteUserRepository::new(pool.clone()));
+    let shares = ShareService::new(share_repo.clone(), notes.clone(), workspaces.clone(), users);
+
+    let secret = notes
+        .create_note(
+            author,
+            &NewNote {
+                title: "secret",
+                content: "c",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let public = notes
+        .create_note(
+            author,
+            &NewNote {
+                title: "public",
+                content: "c",
+                visibility: Visibility::Public,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    share_repo
+        .share_note(secret.id, reader, SharePermission::Read, author)
+        .await
+        .unwrap();
+
+    let bus = web::Data::new(EventBus::default());
+    let app = actix_web::test::init_service(
+        App::new()
+            .app_data(web::Data::new(notes.clone()))
+            .app_data(web::Data::new(workspaces))
+            .app_data(web::Data::new(shares))
+            .app_data(web::Data::new(jwt()))
+            .app_data(bus.clone())
+            .service(update_note)
+            .service(note_events),
+    )
+    .await;
+    let subscribe = |user_id| {
+        actix_web::test::TestRequest::get()
+            .uri("/events")
+            .insert_header(bearer(user_id))
+            .to_request()
+    };
+    let resp = actix_web::test::call_service(&app, subscribe(reader)).await;
+    let mut shared = Box::pin(resp.into_body());
+    let resp = actix_web::test::call_service(&app, subscribe(stranger)).await;
+    let mut other = Box::pin(resp.into_body());
+
+    for note_id in [secret.id, public.id] {
+        let update = actix_web::test::TestRequest::put()
+            .uri(&format!("/notes/{note_id}"))
+            .insert_header(bearer(author))
+            .set_json(UpdateNoteInput {
+                title: Some("edited".into()),
+                content: None,
+                visibility: None,
+                tags: None,
+            })
+            .to_request();
+        assert_eq!(
+            actix_web::test::call_service(&app, update).await.status(),
+            StatusCode::OK
+        );
+    }
+    // `GET /notes` には出ないが、`GET /notes/{id}` で閲覧できるので届く
+    let (event, data) = next_event(&mut shared).await;
+    assert_eq!(event, "note.updated");
+    let received = message(&data);
+    assert_eq!(received.note_id, secret.id);
+    assert_eq!(received.note.unwrap().title, "edited");
+    assert_eq!(message(&next_event(&mut shared).await.1).note_id, public.id);
+    // 共有されていないユーザーには公開のメモの変更だけが届く
+    assert_eq!(message(&next_event(&mut other).await.1).note_id, public.id);
+}
+
+/// 実際の SQLite で、タイトルの変更で `[[...]]` を書き換えたメモも更新として届くことを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn relinked_notes_appear_in_events_in_sqlite() {
+    use memo_app::repository::note::SqliteNoteRepository;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let notes: Arc<dyn NoteRepository> = Arc::new(SqliteNoteRepository::new(pool.clone()));
+    let target = notes
+        .create_note(
+            author,
+            &NewNote {
+                title: "old",
+                content: "c",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let source = notes
+        .create_note(
+            author,
+            &NewNote {
+                title: "source",
+                content: "see [[old]]",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    // 書き換えで更新日時が変わったことが分かるよう、古い日時にしておく
+    sqlx::query("UPDATE notes SET updated_at = 1 WHERE id = ?")
+        .bind(source.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let bus = web::Data::new(EventBus::default());
+    let app = actix_web::test::init_service(
+        App::new()
+            .app_data(web::Data::new(notes.clone()))
+            .app_data(web::Data::new(jwt()))
+            .app_data(bus.clone())
+            .service(update_note)
+            .service(note_events),
+    )
+    .await;
+    let resp = actix_web::test::call_service(
+        &app,
+        actix_web::test::TestRequest::get()
+            .uri("/events")
+            .insert_header(bearer(author))
+            .to_request(),
+    )
+    .await;
+    let mut body = Box::pin(resp.into_body());
+
+    let rename = actix_web::test::TestRequest::put()
+        .uri(&format!("/notes/{}", target.id))
+        .insert_header(bearer(author))
+        .set_json(UpdateNoteInput {
+            title: Some("new".into()),
+            content: None,
+            visibility: None,
+            tags: None,
+        })
+        .to_request();
+    assert_eq!(
+        actix_web::test::call_service(&app, rename).await.status(),
+        StatusCode::OK
+    );
+    let (event, data) = next_event(&mut body).await;
+    assert_eq!(
+        (event.as_str(), message(&data).note_id),
+        ("note.updated", target.id)
+    );
+    let (event, data) = next_event(&mut body).await;
+    assert_eq!(event, "note.updated");
+    let relinked = message(&data).note.unwrap();
+    assert_eq!(relinked.id, source.id);
+    assert_eq!(relinked.content, "see [[new]]");
+    assert_eq!(relinked.version, source.version + 1);
+    assert!(relinked.updated_at > 1);
+}
+
+/// 実際の SQLite で、ノートブックの削除で移した・ゴミ箱に入れたメモも通知されることを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn notebook_deletion_appears_in_events_in_sqlite() {
+    use memo_app::app::notebooks::delete_notebook;
+    use memo_app::repository::note::SqliteNoteRepository;
+    use memo_app::repository::notebook::{NotebookRepository, SqliteNotebookRepository};
+    use memo_app::service::notebook::NotebookService;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let notes: Arc<dyn NoteRepository> = Arc::new(SqliteNoteRepository::new(pool.clone()));
+    let notebooks: Arc<dyn NotebookRepository> =
+        Arc::new(SqliteNotebookRepository::new(pool.clone()));
+    let parent = notebooks
+        .create_notebook(author, None, "parent")
+        .await
+        .unwrap();
+    let moved = notebooks
+        .create_notebook(author, Some(parent.id), "moved")
+        .await
+        .unwrap();
+    let trashed = notebooks
+        .create_notebook(author, None, "trashed")
+        .await
+        .unwrap();
+    let mut filed = Vec::new();
+    for notebook_id in [moved.id, trashed.id] {
+        let note = notes
+            .create_note(
+                author,
+                &NewNote {
+                    title: "t",
+                    content: "c",
+                    notebook_id: Some(notebook_id),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        filed.push(note);
+    }
+
+    let bus = web::Data::new(EventBus::default());
+    let app = actix_web::test::init_service(
+        App::new()
+            .app_data(web::Data::new(notes.clone()))
+            .app_data(web::Data::new(NotebookService::new(notebooks, notes)))
+            .app_data(web::Data::new(jwt()))
+            .app_data(bus.clone())
+            .service(delete_notebook)
+            .service(note_events),
+    )
+    .await;
+    let resp = actix_web::test::call_service(
+        &app,
+        actix_web::test::TestRequest::get()
+            .uri("/events")
+            .insert_header(bearer(author))
+            .to_request(),
+    )
+    .await;
+    let mut body = Box::pin(resp.into_body());
+
+    let delete = |uri: String| {
+        actix_web::test::TestRequest::delete()
+            .uri(&uri)
+            .insert_header(bearer(author))
+            .to_request()
+    };
+    let resp =
+        actix_web::test::call_service(&app, delete(format!("/notebooks/{}", moved.id))).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let (event, data) = next_event(&mut body).await;
+    assert_eq!(event, "note.updated");
+    let updated = message(&data).note.unwrap();
+    assert_eq!(updated.id, filed[0].id);
+    assert_eq!(updated.notebook_id, Some(parent.id));
+    assert_eq!(updated.version, filed[0].version + 1);
+
+    let resp = actix_web::test::call_service(
+        &app,
+        delete(format!("/notebooks/{}?mode=cascade", trashed.id)),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let (event, data) = next_event(&mut body).await;
+    assert_eq!(
+        (event.as_str(), message(&data).note_id),
+        ("note.deleted", filed[1].id)
+    );
+}
+
+/// 実際の SQLite で、接続中に失効した JWT・削除された API トークンの接続が
+/// キープアライブの時点で閉じられることを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn revoked_tokens_end_the_stream_in_sqlite() {
+    use memo_app::domain::model::Scope;
+    use memo_app::middleware::auth::token::{API_TOKEN_PREFIX, hash_token, random_token};
+    use memo_app::repository::token::{SqliteTokenRepository, TokenRepository};
+    use memo_app::repository::user::{ApiTokenRepository, SqliteUserRepository};
+
+    let pool = common::sqlite_pool().await;
+    let user = common::insert_user(&pool, "a@example.com").await;
+    let tokens: Arc<dyn TokenRepository> = Arc::new(SqliteTokenRepository::new(pool.clone()));
+    let api_tokens: Arc<dyn ApiTokenRepository> = Arc::new(SqliteUserRepository::new(pool.clone()));
+    let pat = format!("{API_TOKEN_PREFIX}{}", random_token(32));
+    let api_token = api_tokens
+        .create_api_token(user, "feed", &hash_token(&pat), &[Scope::NotesRead], None)
+        .await
+        .unwrap();
+    let jwt_token = jwt().generate(user).unwrap();
+
+    let app = actix_web::test::init_service(
+        App::new()
+            .app_data(web::Data::new(tokens.clone()))
+            .app_data(web::Data::new(api_tokens.clone()))
+            .app_data(web::Data::new(jwt()))
+            .app_data(web::Data::new(EventBus::default()))
+            .service(note_events),
+    )
+    .await;
+    let subscribe = |token: &str| {
+        actix_web::test::TestRequest::get()
+            .uri("/events")
+            .insert_header(("Authorization", format!("Bearer {token}")))
+            .to_request()
+    };
+    let resp = actix_web::test::call_service(&app, subscribe(&jwt_token)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut by_jwt = Box::pin(resp.into_body());
+    let resp = actix_web::test::call_service(&app, subscribe(&pat)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut by_pat = Box::pin(resp.into_body());
+
+    let claim = jwt().verify(&jwt_token).unwrap();
+    tokens
+        .revoke_access_token(&claim.jti, claim.exp)
+        .await
+        .unwrap();
+    assert!(
+        api_tokens
+            .delete_api_token(user, api_token.id)
+            .await
+            .unwrap()
+    );
+    for body in [&mut by_jwt, &mut by_pat] {
+        let end = actix_web::rt::time::timeout(
+            Duration::from_secs(20),
+            std::future::poll_fn(|cx| body.as_mut().poll_next(cx)),
+        )
+        .await
+        .expect("stream still open");
+        assert!(end.is_none());
+    }
+}
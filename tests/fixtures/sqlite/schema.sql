@@ -0,0 +1,189 @@
+-- テスト用の SQLite スキーマ（db/migrations と db/sqlite を SQLite の型で書き直したもの）。
+-- マイグレーションで列を足したらここにも足すこと。日時は UNIX 秒の INTEGER。
+CREATE TABLE users (
+  id                  INTEGER PRIMARY KEY,
+  email               TEXT    NOT NULL UNIQUE,
+  password_hash       TEXT    NOT NULL,
+  created_at          INTEGER NOT NULL,
+  email_verified_at   INTEGER,
+  failed_login_count  INTEGER NOT NULL DEFAULT 0,
+  locked_until        INTEGER,
+  role                TEXT    NOT NULL DEFAULT 'user',
+  disabled_at         INTEGER,
+  tokens_valid_after  INTEGER
+);
+
+CREATE TABLE user_tokens (
+  id          INTEGER PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  purpose     TEXT    NOT NULL,
+  token_hash  TEXT    NOT NULL UNIQUE,
+  created_at  INTEGER NOT NULL,
+  expires_at  INTEGER NOT NULL,
+  used_at     INTEGER
+);
+
+CREATE TABLE workspaces (
+  id          INTEGER PRIMARY KEY,
+  name        TEXT    NOT NULL,
+  created_at  INTEGER NOT NULL
+);
+
+CREATE TABLE workspace_members (
+  workspace_id  INTEGER NOT NULL,
+  user_id       INTEGER NOT NULL,
+  role          TEXT    NOT NULL,
+  created_at    INTEGER NOT NULL,
+  PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE TABLE workspace_invitations (
+  id            INTEGER PRIMARY KEY,
+  workspace_id  INTEGER NOT NULL,
+  email         TEXT    NOT NULL,
+  role          TEXT    NOT NULL,
+  token_hash    TEXT    NOT NULL UNIQUE,
+  invited_by    INTEGER,
+  created_at    INTEGER NOT NULL,
+  expires_at    INTEGER NOT NULL,
+  accepted_at   INTEGER
+);
+
+CREATE TABLE notebooks (
+  id          INTEGER PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  parent_id   INTEGER,
+  name        TEXT    NOT NULL,
+  created_at  INTEGER NOT NULL,
+  updated_at  INTEGER NOT NULL
+);
+
+CREATE TABLE notes (
+  id            INTEGER PRIMARY KEY,
+  user_id       INTEGER NOT NULL,
+  title         TEXT    NOT NULL,
+  content       TEXT    NOT NULL,
+  visibility    TEXT    NOT NULL DEFAULT 'private',
+  created_at    INTEGER NOT NULL,
+  updated_at    INTEGER NOT NULL,
+  version       INTEGER NOT NULL DEFAULT 1,
+  deleted_at    INTEGER,
+  workspace_id  INTEGER,
+  notebook_id   INTEGER,
+  pinned        INTEGER NOT NULL DEFAULT 0,
+  starred       INTEGER NOT NULL DEFAULT 0,
+  archived_at   INTEGER
+);
+
+CREATE TABLE note_shares (
+  note_id     INTEGER NOT NULL,
+  user_id     INTEGER NOT NULL,
+  permission  TEXT    NOT NULL,
+  granted_by  INTEGER,
+  created_at  INTEGER NOT NULL,
+  PRIMARY KEY (note_id, user_id)
+);
+
+CREATE TABLE note_share_links (
+  id             INTEGER PRIMARY KEY,
+  note_id        INTEGER NOT NULL,
+  token_hash     TEXT    NOT NULL UNIQUE,
+  permission     TEXT    NOT NULL,
+  password_hash  TEXT,
+  created_by     INTEGER,
+  created_at     INTEGER NOT NULL,
+  expires_at     INTEGER
+);
+
+CREATE TABLE tags (
+  id          INTEGER PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  name        TEXT    NOT NULL,
+  created_at  INTEGER NOT NULL,
+  UNIQUE (user_id, name)
+);
+
+CREATE TABLE note_tags (
+  note_id  INTEGER NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+  tag_id   INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+  PRIMARY KEY (note_id, tag_id)
+);
+
+CREATE TABLE note_revisions (
+  id          INTEGER PRIMARY KEY,
+  note_id     INTEGER NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+  revision    INTEGER NOT NULL,
+  title       TEXT    NOT NULL,
+  content     TEXT    NOT NULL,
+  editor_id   INTEGER,
+  created_at  INTEGER NOT NULL,
+  UNIQUE (note_id, revision)
+);
+
+CREATE TABLE refresh_tokens (
+  id          INTEGER PRIMARY KEY,
+  user_id     INTEGER NOT NULL,
+  family_id   TEXT    NOT NULL,
+  token_hash  TEXT    NOT NULL UNIQUE,
+  expires_at  INTEGER NOT NULL,
+  created_at  INTEGER NOT NULL,
+  used_at     INTEGER,
+  revoked_at  INTEGER
+);
+
+CREATE TABLE revoked_tokens (
+  jti         TEXT    PRIMARY KEY,
+  expires_at  INTEGER NOT NULL
+);
+
+CREATE TABLE api_tokens (
+  id            INTEGER PRIMARY KEY,
+  user_id       INTEGER NOT NULL,
+  name          TEXT    NOT NULL,
+  token_hash    TEXT    NOT NULL UNIQUE,
+  scopes        TEXT    NOT NULL,
+  created_at    INTEGER NOT NULL,
+  expires_at    INTEGER,
+  last_used_at  INTEGER
+);
+
+CREATE TABLE user_totp (
+  user_id         INTEGER PRIMARY KEY,
+  secret          TEXT    NOT NULL,
+  created_at      INTEGER NOT NULL,
+  enabled_at      INTEGER,
+  last_used_step  INTEGER
+);
+
+CREATE TABLE totp_recovery_codes (
+  id         INTEGER PRIMARY KEY,
+  user_id    INTEGER NOT NULL,
+  code_hash  TEXT    NOT NULL,
+  used_at    INTEGER,
+  UNIQUE (user_id, code_hash)
+);
+
+CREATE TABLE note_links (
+  source_id     INTEGER NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+  position      INTEGER NOT NULL,
+  target_id     INTEGER,
+  target_title  TEXT,
+  PRIMARY KEY (source_id, position)
+);
+
+CREATE TABLE note_attachments (
+  id             INTEGER PRIMARY KEY,
+  note_id        INTEGER REFERENCES notes(id) ON DELETE SET NULL,
+  user_id        INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  filename       TEXT    NOT NULL,
+  content_type   TEXT    NOT NULL,
+  size           INTEGER NOT NULL,
+  sha256         TEXT    NOT NULL,
+  storage_key    TEXT    NOT NULL UNIQUE,
+  created_at     INTEGER NOT NULL,
+  width          INTEGER,
+  height         INTEGER,
+  thumbnails_at  INTEGER
+);
+
+CREATE VIRTUAL TABLE notes_fts USING fts5(title, content);
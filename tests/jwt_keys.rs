@@ -0,0 +1,165 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{App, http::StatusCode, web};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use memo_app::app::auth::jwks;
+use memo_app::middleware::auth::model::JWTClaim;
+use memo_app::middleware::auth::token::{JwtKey, JwtTokenService, TokenError};
+
+const RSA_OLD: &[u8] = include_bytes!("fixtures/jwt/rsa-old.pem");
+const RSA_OLD_PUB: &[u8] = include_bytes!("fixtures/jwt/rsa-old.pub.pem");
+const RSA_NEW: &[u8] = include_bytes!("fixtures/jwt/rsa-new.pem");
+const RSA_NEW_PUB: &[u8] = include_bytes!("fixtures/jwt/rsa-new.pub.pem");
+const ED25519: &[u8] = include_bytes!("fixtures/jwt/ed25519.pem");
+const ED25519_PUB: &[u8] = include_bytes!("fixtures/jwt/ed25519.pub.pem");
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+fn rsa_old() -> JwtKey {
+    JwtKey::from_pem("old", Algorithm::RS256, Some(RSA_OLD), RSA_OLD_PUB).unwrap()
+}
+
+fn rsa_new() -> JwtKey {
+    JwtKey::from_pem("new", Algorithm::RS256, Some(RSA_NEW), RSA_NEW_PUB).unwrap()
+}
+
+#[test]
+fn rs256_and_eddsa_tokens_carry_kid() {
+    let ed = JwtKey::from_pem("ed", Algorithm::EdDSA, Some(ED25519), ED25519_PUB).unwrap();
+    for (key, kid, alg) in [
+        (rsa_old(), "old", Algorithm::RS256),
+        (ed, "ed", Algorithm::EdDSA),
+    ] {
+        let svc = JwtTokenService::from_keys(vec![key], 3600).unwrap();
+        let token = svc.generate(42).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some(kid));
+        assert_eq!(header.alg, alg);
+        assert_eq!(svc.verify(&token).unwrap().sub, 42);
+    }
+}
+
+#[test]
+fn from_pem_rejects_mismatched_key() {
+    assert!(matches!(
+        JwtKey::from_pem("x", Algorithm::EdDSA, None, RSA_OLD_PUB),
+        Err(TokenError::InvalidKey(_))
+    ));
+    assert!(matches!(
+        JwtKey::from_pem("x", Algorithm::HS256, None, RSA_OLD_PUB),
+        Err(TokenError::InvalidKey(_))
+    ));
+    assert!(matches!(
+        JwtTokenService::from_keys(vec![rsa_old(), rsa_old()], 3600),
+        Err(TokenError::InvalidKey(_))
+    ));
+}
+
+#[test]
+fn rotation_signs_with_newest_active_key() {
+    let now = now();
+    let old_token = JwtTokenService::from_keys(vec![rsa_old()], 3600)
+        .unwrap()
+        .generate(1)
+        .unwrap();
+
+    // new は有効化済み、old は検証専用に残っている
+    let svc = JwtTokenService::from_keys(
+        vec![
+            rsa_old().with_schedule(0, Some(now + 3600)),
+            rsa_new().with_schedule(now - 10, None),
+        ],
+        3600,
+    )
+    .unwrap();
+    let token = svc.generate(1).unwrap();
+    assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some("new"));
+    assert!(svc.verify(&old_token).is_ok());
+
+    // 有効化前の鍵では署名しない
+    let svc = JwtTokenService::from_keys(
+        vec![rsa_old(), rsa_new().with_schedule(now + 3600, None)],
+        3600,
+    )
+    .unwrap();
+    let token = svc.generate(1).unwrap();
+    assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some("old"));
+
+    // 退役した鍵のトークンは受け付けない
+    let svc = JwtTokenService::from_keys(
+        vec![rsa_old().with_schedule(0, Some(now - 1)), rsa_new()],
+        3600,
+    )
+    .unwrap();
+    assert!(matches!(svc.verify(&old_token), Err(TokenError::Decode)));
+
+    // 署名できる鍵が無い
+    let svc = JwtTokenService::from_keys(
+        vec![JwtKey::from_pem("old", Algorithm::RS256, None, RSA_OLD_PUB).unwrap()],
+        3600,
+    )
+    .unwrap();
+    assert!(matches!(svc.generate(1), Err(TokenError::NoSigningKey)));
+    assert!(svc.verify(&old_token).is_ok());
+}
+
+#[actix_web::test]
+async fn jwks_publishes_public_keys() {
+    let now = now();
+    let svc = JwtTokenService::from_keys(
+        vec![
+            rsa_old().with_schedule(0, Some(now - 1)),
+            rsa_new(),
+            JwtKey::from_pem("ed", Algorithm::EdDSA, Some(ED25519), ED25519_PUB)
+                .unwrap()
+                .with_schedule(now + 3600, None),
+        ],
+        3600,
+    )
+    .unwrap();
+    let token = svc.generate(7).unwrap();
+    let app =
+        actix_web::test::init_service(App::new().app_data(web::Data::new(svc)).service(jwks)).await;
+
+    let req = actix_web::test::TestRequest::get()
+        .uri("/.well-known/jwks.json")
+        .to_request();
+    let resp = actix_web::test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let set: JwkSet = actix_web::test::read_body_json(resp).await;
+
+    // 退役済みの old は含まず、有効化前の ed は事前に公開する
+    let mut kids: Vec<_> = set
+        .keys
+        .iter()
+        .filter_map(|k| k.common.key_id.clone())
+        .collect();
+    kids.sort();
+    assert_eq!(kids, vec!["ed", "new"]);
+
+    // 公開鍵だけでトークンを検証できる
+    let jwk = set.find("new").unwrap();
+    let claim = decode::<JWTClaim>(
+        &token,
+        &DecodingKey::from_jwk(jwk).unwrap(),
+        &Validation::new(Algorithm::RS256),
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(claim.sub, 7);
+
+    // 共有鍵（HS256）は公開しない
+    assert!(
+        JwtTokenService::from_secret(b"secret", 3600)
+            .jwks()
+            .keys
+            .is_empty()
+    );
+}
@@ -23,6 +23,17 @@ fn generate_and_verify_returns_claim() {
     assert!(claim.exp > claim.iat);
 }
 
+#[test]
+fn generate_assigns_unique_jti() {
+    let svc = JwtTokenService::from_secret(b"secret", 3600);
+
+    let a = svc.verify(&svc.generate(1).unwrap()).unwrap();
+    let b = svc.verify(&svc.generate(1).unwrap()).unwrap();
+
+    assert!(!a.jti.is_empty());
+    assert_ne!(a.jti, b.jti);
+}
+
 #[test]
 fn verify_fails_when_expired() {
     let secret = b"secret";
@@ -34,6 +45,8 @@ fn verify_fails_when_expired() {
         sub: 1,
         iat: past - 10,
         exp: past,
+        jti: "expired".into(),
+        scope: None,
     };
     let token = encode(
         &Header::new(Algorithm::HS256),
@@ -0,0 +1,313 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use memo_app::app::graph::note_graph;
+use memo_app::app::links::{list_backlinks, list_links};
+use memo_app::domain::graph::{GraphEdgeKind, GraphNode, NoteGraph};
+use memo_app::domain::model::{Note, NoteLink, NoteSearchHit, TrashedNote, Visibility};
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::link::LinkRepository;
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::user::RepoError;
+
+// ---- Mocks ----
+
+// ユーザー 1 の private なメモ（id=1）だけが存在する
+struct MockNoteRepo;
+
+fn note(id: i64) -> Note {
+    Note {
+        id,
+        author_id: 1,
+        workspace_id: None,
+        notebook_id: None,
+        title: format!("n{id}"),
+        content: "[[n2]] [[#9]]".into(),
+        visibility: Visibility::Private,
+        created_at: 1,
+        updated_at: 1,
+        version: 1,
+        tags: vec![],
+        pinned: false,
+        starred: false,
+        archived_at: None,
+    }
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok((note_id == 1).then(|| note(1)))
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(None)
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+// メモ 1 は n2（id=2）と存在しない #9 にリンクしていて、メモ 3 からリンクされている。
+// グラフはメモ 1 → 2 のリンク、2 と 3 の共通のタグ、孤立したメモ 4。
+// 呼び出された `viewer` を記録する
+#[derive(Default)]
+struct MockLinkRepo {
+    viewers: Mutex<Vec<Option<i64>>>,
+}
+
+#[async_trait]
+impl LinkRepository for MockLinkRepo {
+    async fn links_from(
+        &self,
+        _note_id: i64,
+        viewer: Option<i64>,
+    ) -> Result<Vec<NoteLink>, RepoError> {
+        self.viewers.lock().unwrap().push(viewer);
+        Ok(vec![
+            NoteLink {
+                target: "n2".into(),
+                note_id: Some(2),
+                title: Some("n2".into()),
+            },
+            NoteLink {
+                target: "#9".into(),
+                note_id: None,
+                title: None,
+            },
+        ])
+    }
+
+    async fn backlinks(&self, _note_id: i64, viewer: Option<i64>) -> Result<Vec<Note>, RepoError> {
+        self.viewers.lock().unwrap().push(viewer);
+        Ok(vec![note(3)])
+    }
+
+    async fn note_graph(&self, user_id: i64) -> Result<NoteGraph, RepoError> {
+        self.viewers.lock().unwrap().push(Some(user_id));
+        let node = |id: i64, tags: &[&str]| GraphNode {
+            id,
+            title: format!("n{id}"),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        };
+        Ok(NoteGraph::new(
+            vec![
+                node(1, &[]),
+                node(2, &["rust"]),
+                node(3, &["rust"]),
+                node(4, &[]),
+            ],
+            &[(1, 2)],
+        ))
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn list_links_reports_dangling_links() {
+    let note_repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepo);
+    let links = Arc::new(MockLinkRepo::default());
+    let link_repo: Arc<dyn LinkRepository> = links.clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(note_repo))
+            .app_data(web::Data::new(link_repo))
+            .app_data(web::Data::new(jwt()))
+            .service(list_links),
+    )
+    .await;
+
+    let token = jwt().generate(1).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/notes/1/links")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let all: Vec<NoteLink> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(all.len(), 2);
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1/links?dangling=true")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let dangling: Vec<NoteLink> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(dangling, vec![all[1].clone()]);
+    assert_eq!(*links.viewers.lock().unwrap(), vec![Some(1), Some(1)]);
+
+    // 閲覧できないメモは 404（リンクは返さない）
+    let other = jwt().generate(2).unwrap();
+    for (uri, token) in [
+        ("/notes/1/links", Some(other)),
+        ("/notes/1/links", None),
+        ("/notes/5/links", Some(token)),
+    ] {
+        let mut req = test::TestRequest::get().uri(uri);
+        if let Some(token) = token {
+            req = req.insert_header(("Authorization", format!("Bearer {}", token)));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+    }
+    assert_eq!(links.viewers.lock().unwrap().len(), 2);
+}
+
+#[actix_web::test]
+async fn list_backlinks_returns_linking_notes() {
+    let note_repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepo);
+    let links = Arc::new(MockLinkRepo::default());
+    let link_repo: Arc<dyn LinkRepository> = links.clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(note_repo))
+            .app_data(web::Data::new(link_repo))
+            .app_data(web::Data::new(jwt()))
+            .service(list_backlinks),
+    )
+    .await;
+
+    let token = jwt().generate(1).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/notes/1/backlinks")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let notes: Vec<Note> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(notes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![3]);
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1/backlinks")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(*links.viewers.lock().unwrap(), vec![Some(1)]);
+}
+
+#[actix_web::test]
+async fn note_graph_returns_json_and_dot() {
+    let links = Arc::new(MockLinkRepo::default());
+    let link_repo: Arc<dyn LinkRepository> = links.clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(link_repo))
+            .app_data(web::Data::new(jwt()))
+            .service(note_graph),
+    )
+    .await;
+
+    let token = jwt().generate(1).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/graph")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let graph: NoteGraph = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(graph.nodes.len(), 4);
+    assert_eq!(
+        graph
+            .edges
+            .iter()
+            .map(|e| (e.source, e.target, e.kind))
+            .collect::<Vec<_>>(),
+        vec![(1, 2, GraphEdgeKind::Link), (2, 3, GraphEdgeKind::Tag)]
+    );
+
+    // メモ 1 から 1 本でたどれるのは 2 だけ、2 本なら 3 まで
+    let req = test::TestRequest::get()
+        .uri("/graph?note=1")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let graph: NoteGraph = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        graph.nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/graph?note=1&depth=2&format=dot")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/vnd.graphviz; charset=utf-8"
+    );
+    let body = test::read_body(resp).await;
+    let dot = std::str::from_utf8(&body).unwrap();
+    assert!(dot.starts_with("digraph notes {"));
+    assert!(dot.contains("n1 -> n2;"));
+    assert!(dot.contains("n3 [label=\"n3\"];"));
+    assert!(!dot.contains("n4"));
+
+    for (uri, status) in [
+        ("/graph?note=9", StatusCode::NOT_FOUND),
+        ("/graph?note=1&depth=6", StatusCode::BAD_REQUEST),
+        ("/graph?format=svg", StatusCode::BAD_REQUEST),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), status, "{uri}");
+    }
+
+    let req = test::TestRequest::get().uri("/graph").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,388 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::{App, http::StatusCode, http::header, web};
+use async_trait::async_trait;
+use memo_app::app::auth::login;
+use memo_app::app::model::LoginInput;
+use memo_app::domain::model::{Role, User};
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::token::TokenRepository;
+use memo_app::repository::user::{RepoError, UserRepository};
+use memo_app::service::auth::{AuthService, AuthServiceError, AuthServiceImpl};
+use memo_app::service::login_throttle::{LockoutPolicy, LoginThrottle};
+use memo_app::service::session::SessionService;
+
+// ---- Mocks ----
+
+// 1 ユーザーを保持し、ログイン失敗の回数とロックを記録する
+struct MockLockoutRepo {
+    user: Mutex<User>,
+}
+
+impl MockLockoutRepo {
+    fn new(password: &str) -> Self {
+        use argon2::{Argon2, PasswordHasher};
+        use password_hash::{SaltString, rand_core::OsRng};
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .unwrap()
+            .to_string();
+        Self {
+            user: Mutex::new(User {
+                id: 1,
+                email: "a@example.com".into(),
+                password_hash,
+                created_at: 0,
+                email_verified_at: None,
+                failed_login_count: 0,
+                locked_until: None,
+                role: Role::User,
+                disabled_at: None,
+            }),
+        }
+    }
+
+    fn user(&self) -> User {
+        self.user.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl UserRepository for MockLockoutRepo {
+    async fn create_user(
+        &self,
+        _email: &str,
+        _password_hash: &str,
+    ) -> Result<Option<User>, RepoError> {
+        Ok(None)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError> {
+        let user = self.user();
+        Ok((user.email == email).then_some(user))
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+        let user = self.user();
+        Ok((user.id == user_id).then_some(user))
+    }
+
+    async fn update_password(
+        &self,
+        _user_id: i64,
+        _password_hash: &str,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn mark_email_verified(&self, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn record_login_failure(&self, _user_id: i64) -> Result<i64, RepoError> {
+        let mut user = self.user.lock().unwrap();
+        user.failed_login_count += 1;
+        Ok(user.failed_login_count)
+    }
+
+    async fn lock_user_until(&self, _user_id: i64, locked_until: i64) -> Result<(), RepoError> {
+        self.user.lock().unwrap().locked_until = Some(locked_until);
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, _user_id: i64) -> Result<(), RepoError> {
+        let mut user = self.user.lock().unwrap();
+        user.failed_login_count = 0;
+        user.locked_until = None;
+        Ok(())
+    }
+}
+
+// ログイン完了時のリフレッシュトークン発行だけ受け付ける
+struct MockTokenRepo;
+
+#[async_trait]
+impl TokenRepository for MockTokenRepo {
+    async fn create_refresh_token(
+        &self,
+        _user_id: i64,
+        _family_id: &str,
+        _token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn find_refresh_token(
+        &self,
+        _token_hash: &str,
+    ) -> Result<Option<memo_app::domain::model::RefreshToken>, RepoError> {
+        Ok(None)
+    }
+
+    async fn mark_refresh_token_used(&self, _id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn revoke_token_family(&self, _family_id: &str) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn revoke_user_refresh_tokens(&self, _user_id: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn revoke_access_token(&self, _jti: &str, _expires_at: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn revoke_user_access_tokens(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(
+        &self,
+        _jti: &str,
+        _user_id: i64,
+        _issued_at: i64,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_expired(&self, _now: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+}
+
+fn keys(xs: &[&str]) -> Vec<String> {
+    xs.iter().map(|s| s.to_string()).collect()
+}
+
+// ---- Tests ----
+
+#[test]
+fn throttle_backs_off_after_free_attempts() {
+    let throttle = LoginThrottle::new(2, Duration::from_secs(10), Duration::from_secs(60));
+    let ip = keys(&["ip:1"]);
+
+    throttle.record_failure(&ip);
+    throttle.record_failure(&ip);
+    assert!(throttle.check(&ip).is_ok());
+
+    throttle.record_failure(&ip);
+    let wait = throttle.check(&ip).unwrap_err();
+    assert!(wait > Duration::from_secs(9) && wait <= Duration::from_secs(10));
+    throttle.record_failure(&ip);
+    assert!(throttle.check(&ip).unwrap_err() > Duration::from_secs(19));
+
+    // 他のキーには影響しない。どれか 1 つでも待機中なら待たせる
+    assert!(throttle.check(&keys(&["ip:2"])).is_ok());
+    assert!(throttle.check(&keys(&["ip:2", "ip:1"])).is_err());
+
+    throttle.reset("ip:1");
+    assert!(throttle.check(&ip).is_ok());
+}
+
+#[test]
+fn lockout_doubles_up_to_max() {
+    let policy = LockoutPolicy::new(3, 60, 300);
+    assert_eq!(policy.lock_secs(2), None);
+    assert_eq!(policy.lock_secs(3), Some(60));
+    assert_eq!(policy.lock_secs(4), Some(120));
+    assert_eq!(policy.lock_secs(5), Some(240));
+    assert_eq!(policy.lock_secs(6), Some(300));
+    assert_eq!(policy.lock_secs(100), Some(300));
+}
+
+#[tokio::test]
+async fn account_is_locked_after_repeated_failures() {
+    let repo = Arc::new(MockLockoutRepo::new("password123"));
+    let service = AuthServiceImpl::new(repo.clone()).with_lockout(LockoutPolicy::new(3, 60, 300));
+
+    // 成功すると失敗の回数は 0 に戻る
+    for _ in 0..2 {
+        assert!(service.login("a@example.com", "wrongpass").await.is_err());
+    }
+    assert!(service.login("a@example.com", "password123").await.is_ok());
+    assert_eq!(repo.user().failed_login_count, 0);
+
+    for _ in 0..2 {
+        assert!(matches!(
+            service.login("a@example.com", "wrongpass").await,
+            Err(AuthServiceError::InvalidCredentials)
+        ));
+    }
+    assert!(repo.user().locked_until.is_none());
+    assert!(matches!(
+        service.login("a@example.com", "wrongpass").await,
+        Err(AuthServiceError::InvalidCredentials)
+    ));
+    assert!(repo.user().locked_until.is_some());
+
+    // ロック中は正しいパスワードでも受け付けず、解けるまでの時間を返す
+    let locked_until = repo.user().locked_until;
+    for password in ["password123", "wrongpass"] {
+        assert!(matches!(
+            service.login("a@example.com", password).await,
+            Err(AuthServiceError::Locked { retry_after })
+                if retry_after > Duration::ZERO && retry_after <= Duration::from_secs(60)
+        ));
+    }
+    assert_eq!(repo.user().failed_login_count, 3);
+    assert_eq!(repo.user().locked_until, locked_until);
+
+    // 存在しないメールアドレスはロックされず、常に認証エラー
+    for _ in 0..5 {
+        assert!(matches!(
+            service.login("nobody@example.com", "password123").await,
+            Err(AuthServiceError::InvalidCredentials)
+        ));
+    }
+}
+
+#[actix_web::test]
+async fn login_endpoint_returns_429_with_retry_after() {
+    let repo = Arc::new(MockLockoutRepo::new("password123"));
+    let auth: Arc<dyn AuthService> = Arc::new(AuthServiceImpl::new(repo));
+    let app = actix_web::test::init_service(
+        App::new()
+            .app_data(web::Data::new(auth))
+            .app_data(web::Data::new(JwtTokenService::from_secret(
+                b"secret", 3600,
+            )))
+            .app_data(web::Data::new(SessionService::new(
+                Arc::new(MockTokenRepo),
+                3600,
+            )))
+            .app_data(web::Data::new(LoginThrottle::new(
+                1,
+                Duration::from_secs(30),
+                Duration::from_secs(60),
+            )))
+            .service(login),
+    )
+    .await;
+    let attempt = |email: &str, password: &str| {
+        actix_web::test::TestRequest::post()
+            .uri("/auth/login")
+            .peer_addr("192.0.2.1:1234".parse().unwrap())
+            .set_json(LoginInput {
+                email: email.into(),
+                password: password.into(),
+            })
+            .to_request()
+    };
+
+    let resp = actix_web::test::call_service(&app, attempt("a@example.com", "wrongpass")).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = actix_web::test::call_service(&app, attempt("b@example.com", "wrongpass")).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // 同じ IP からは正しいパスワードでも待たされる
+    let resp = actix_web::test::call_service(&app, attempt("a@example.com", "password123")).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = resp
+        .headers()
+        .get(header::RETRY_AFTER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=30).contains(&retry_after));
+}
+
+#[actix_web::test]
+async fn locked_account_gets_429_with_retry_after() {
+    let repo = Arc::new(MockLockoutRepo::new("password123"));
+    let auth: Arc<dyn AuthService> =
+        Arc::new(AuthServiceImpl::new(repo.clone()).with_lockout(LockoutPolicy::new(1, 120, 300)));
+    let app = actix_web::test::init_service(
+        App::new()
+            .app_data(web::Data::new(auth))
+            .app_data(web::Data::new(JwtTokenService::from_secret(
+                b"secret", 3600,
+            )))
+            .app_data(web::Data::new(SessionService::new(
+                Arc::new(MockTokenRepo),
+                3600,
+            )))
+            .service(login),
+    )
+    .await;
+    let attempt = |password: &str| {
+        actix_web::test::TestRequest::post()
+            .uri("/auth/login")
+            .set_json(LoginInput {
+                email: "a@example.com".into(),
+                password: password.into(),
+            })
+            .to_request()
+    };
+
+    let resp = actix_web::test::call_service(&app, attempt("wrongpass")).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert!(repo.user().locked_until.is_some());
+
+    let resp = actix_web::test::call_service(&app, attempt("password123")).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = resp
+        .headers()
+        .get(header::RETRY_AFTER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=120).contains(&retry_after));
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、失敗の回数とロックが `users` に記録され、ロック中は正しいパスワードでも
+/// 受け付けないことを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[tokio::test]
+async fn lockout_is_recorded_in_sqlite() {
+    use memo_app::repository::user::SqliteUserRepository;
+
+    let pool = common::sqlite_pool().await;
+    let user_id = common::insert_user(&pool, "a@example.com").await;
+    let repo = Arc::new(SqliteUserRepository::new(pool.clone()));
+    let service = AuthServiceImpl::new(repo.clone()).with_lockout(LockoutPolicy::new(2, 60, 300));
+    let state = || async {
+        let user = repo.find_by_id(user_id).await.unwrap().unwrap();
+        (user.failed_login_count, user.locked_until)
+    };
+
+    assert!(service.login("a@example.com", "wrongpass").await.is_err());
+    assert_eq!(state().await, (1, None));
+    assert!(service.login("a@example.com", "password123").await.is_ok());
+    assert_eq!(state().await, (0, None));
+
+    for _ in 0..2 {
+        assert!(service.login("a@example.com", "wrongpass").await.is_err());
+    }
+    let (failures, locked_until) = state().await;
+    assert_eq!(failures, 2);
+    assert!(locked_until.is_some());
+    for password in ["password123", "wrongpass"] {
+        assert!(matches!(
+            service.login("a@example.com", password).await,
+            Err(AuthServiceError::Locked { .. })
+        ));
+    }
+    assert_eq!(state().await, (2, locked_until));
+
+    // ロックが切れた後に成功すれば、回数もロックも消える
+    sqlx::query("UPDATE users SET locked_until = 1 WHERE id = ?")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(service.login("a@example.com", "password123").await.is_ok());
+    assert_eq!(state().await, (0, None));
+}
@@ -0,0 +1,65 @@
+use memo_app::domain::markdown::{render_html, render_html_with};
+
+#[test]
+fn render_html_supports_gfm_tables_and_task_lists() {
+    let html = render_html(
+        "# Title\n\n| a | b |\n|:--|:-:|\n| 1 | 2 |\n\n- [x] done\n- [ ] todo\n\n~~old~~ **new**\n",
+    );
+    assert!(html.contains("<h1>Title</h1>"), "{html}");
+    assert!(
+        html.contains("<th style=\"text-align: left\">a</th>"),
+        "{html}"
+    );
+    assert!(
+        html.contains("<td style=\"text-align: center\">2</td>"),
+        "{html}"
+    );
+    assert!(html.contains("<input"), "{html}");
+    assert!(html.contains("type=\"checkbox\""), "{html}");
+    assert!(html.contains("checked"), "{html}");
+    assert!(
+        html.contains("<del>old</del> <strong>new</strong>"),
+        "{html}"
+    );
+}
+
+#[test]
+fn render_html_strips_scripts_and_unsafe_attributes() {
+    let html = render_html(
+        "<script>alert(1)</script>\n\n\
+         <img src=x onerror=alert(1)>\n\n\
+         [link](javascript:alert(1)) <a href=\"https://example.com\">ok</a>\n\n\
+         <input type=\"text\" value=\"x\"> <table><tr><td style=\"position: fixed\">t</td></tr></table>\n",
+    );
+    for bad in [
+        "<script",
+        "onerror",
+        "javascript:",
+        "type=\"text\"",
+        "position",
+    ] {
+        assert!(!html.contains(bad), "{bad} in {html}");
+    }
+    assert!(html.contains("href=\"https://example.com\""), "{html}");
+    assert!(html.contains("rel=\"noopener noreferrer\""), "{html}");
+}
+
+#[test]
+fn render_html_with_rewrites_only_image_urls() {
+    let html = render_html_with(
+        "![shot](/a/1 \"t\") [link](/a/1) ![other](/b/2) ![evil](/a/evil)",
+        |url| match url {
+            "/a/1" => Some("/thumb/1?w=640&x=1".to_string()),
+            "/a/evil" => Some("javascript:alert(1)".to_string()),
+            _ => None,
+        },
+    );
+    assert!(
+        html.contains(r#"<img src="/thumb/1?w=640&amp;x=1" alt="shot" title="t">"#),
+        "{html}"
+    );
+    assert!(html.contains(r#"<a href="/a/1""#), "{html}");
+    assert!(html.contains(r#"<img src="/b/2" alt="other">"#), "{html}");
+    // 置き換えた後の URL も無害化する
+    assert!(!html.contains("javascript"), "{html}");
+}
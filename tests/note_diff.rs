@@ -0,0 +1,24 @@
+use memo_app::domain::diff::{DiffOp, line_diff};
+
+#[test]
+fn line_diff_marks_changed_lines() {
+    let lines = line_diff("a\nb\nc\n", "a\nB\nc\nd\n");
+    let ops: Vec<(DiffOp, &str)> = lines.iter().map(|l| (l.op, l.text.as_str())).collect();
+    assert_eq!(
+        ops,
+        vec![
+            (DiffOp::Equal, "a"),
+            (DiffOp::Delete, "b"),
+            (DiffOp::Insert, "B"),
+            (DiffOp::Equal, "c"),
+            (DiffOp::Insert, "d"),
+        ]
+    );
+}
+
+#[test]
+fn line_diff_of_identical_text_is_all_equal() {
+    let lines = line_diff("x\r\ny\r\n", "x\r\ny\r\n");
+    assert!(lines.iter().all(|l| l.op == DiffOp::Equal));
+    assert_eq!(lines[1].text, "y");
+}
@@ -0,0 +1,86 @@
+use memo_app::domain::graph::{GraphEdge, GraphEdgeKind, GraphNode, NoteGraph};
+
+fn node(id: i64, title: &str, tags: &[&str]) -> GraphNode {
+    GraphNode {
+        id,
+        title: title.into(),
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+    }
+}
+
+#[test]
+fn new_builds_link_and_tag_edges() {
+    let graph = NoteGraph::new(
+        vec![
+            node(3, "c", &["db", "rust"]),
+            node(1, "a", &["rust"]),
+            node(2, "b", &["db", "rust"]),
+        ],
+        // 重複・自分自身・グラフに無いメモへのリンクは除く
+        &[(1, 2), (1, 2), (2, 2), (3, 9), (3, 1)],
+    );
+    assert_eq!(
+        graph.nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    let tag = |source, target, tags: &[&str]| GraphEdge {
+        source,
+        target,
+        kind: GraphEdgeKind::Tag,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+    };
+    let link = |source, target| GraphEdge {
+        source,
+        target,
+        kind: GraphEdgeKind::Link,
+        tags: vec![],
+    };
+    assert_eq!(
+        graph.edges,
+        vec![
+            link(1, 2),
+            link(3, 1),
+            tag(1, 2, &["rust"]),
+            tag(1, 3, &["rust"]),
+            tag(2, 3, &["db", "rust"]),
+        ]
+    );
+}
+
+#[test]
+fn neighborhood_limits_depth_in_both_directions() {
+    // 1 → 2 → 3 → 4、5 は孤立
+    let graph = NoteGraph::new(
+        (1..=5).map(|id| node(id, "n", &[])).collect(),
+        &[(1, 2), (2, 3), (3, 4)],
+    );
+    let ids = |depth| {
+        graph
+            .neighborhood(3, depth)
+            .unwrap()
+            .nodes
+            .iter()
+            .map(|n| n.id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids(0), vec![3]);
+    assert_eq!(ids(1), vec![2, 3, 4]);
+    assert_eq!(ids(2), vec![1, 2, 3, 4]);
+    assert_eq!(graph.neighborhood(3, 1).unwrap().edges.len(), 2);
+    assert!(graph.neighborhood(9, 1).is_none());
+}
+
+#[test]
+fn to_dot_escapes_labels() {
+    let graph = NoteGraph::new(
+        vec![
+            node(1, "say \"hi\"\\", &["a"]),
+            node(2, "two\nlines", &["a"]),
+        ],
+        &[(2, 1)],
+    );
+    assert_eq!(
+        graph.to_dot(),
+        "digraph notes {\n  n1 [label=\"say \\\"hi\\\"\\\\\"];\n  n2 [label=\"two lines\"];\n  n2 -> n1;\n  n1 -> n2 [dir=none, style=dashed, label=\"a\"];\n}\n"
+    );
+}
@@ -0,0 +1,30 @@
+use memo_app::domain::link::{LinkTarget, parse_links, rename_links};
+
+#[test]
+fn parse_links_reads_titles_and_ids_in_order() {
+    let links = parse_links(
+        "See [[ Rust ]] and [[#12]], [[Rust]] again.\n\
+         [[#x]] [[]] [[a\nb]] [[[c]] [[d",
+    );
+    assert_eq!(
+        links,
+        vec![
+            LinkTarget::Title("Rust".into()),
+            LinkTarget::Id(12),
+            LinkTarget::Title("#x".into()),
+            LinkTarget::Title("c".into()),
+        ]
+    );
+}
+
+#[test]
+fn rename_links_rewrites_only_matching_titles() {
+    assert_eq!(
+        rename_links("[[Old]] [[ Old ]] [[Older]] [[#1]]", "Old", "New").as_deref(),
+        Some("[[New]] [[New]] [[Older]] [[#1]]")
+    );
+    assert_eq!(rename_links("[[Other]]", "Old", "New"), None);
+    // リンクとして書けないタイトルには書き換えない
+    assert_eq!(rename_links("[[Old]]", "Old", "a]]b"), None);
+    assert_eq!(rename_links("[[Old]]", "Old", " New"), None);
+}
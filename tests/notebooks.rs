@@ -0,0 +1,457 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::test::{TestRequest, call_and_read_body_json, call_service, init_service};
+use actix_web::{App, http::StatusCode, web};
+use async_trait::async_trait;
+use memo_app::app::model::NotePage;
+use memo_app::app::notebooks::{
+    create_notebook, delete_notebook, file_note, list_notebook_notes, list_notebooks,
+    move_notebook, rename_notebook,
+};
+use memo_app::app::notes::create_note;
+use memo_app::domain::model::{Note, NoteSearchHit, Notebook, TrashedNote, Visibility};
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::notebook::{NotebookDeleteMode, NotebookRepository};
+use memo_app::repository::user::RepoError;
+use memo_app::service::notebook::NotebookService;
+
+// ---- Mocks ----
+
+type Notes = Arc<Mutex<Vec<Note>>>;
+
+fn note(id: i64, author_id: i64, workspace_id: Option<i64>) -> Note {
+    Note {
+        id,
+        author_id,
+        workspace_id,
+        notebook_id: None,
+        title: "t".into(),
+        content: "c".into(),
+        visibility: Visibility::Private,
+        created_at: 1,
+        updated_at: 1,
+        version: 1,
+        tags: vec![],
+        pinned: false,
+        starred: false,
+        archived_at: None,
+    }
+}
+
+// メモ 1: ユーザー 1 の個人のメモ、メモ 2: ユーザー 2 の個人のメモ、
+// メモ 3: ユーザー 1 が作成したワークスペース 10 のメモ
+struct MockNoteRepo {
+    notes: Notes,
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, user_id: i64, new: &NewNote<'_>) -> Result<Note, RepoError> {
+        let mut notes = self.notes.lock().unwrap();
+        let mut created = note(notes.len() as i64 + 1, user_id, new.workspace_id);
+        created.notebook_id = new.notebook_id;
+        notes.push(created.clone());
+        Ok(created)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        let notes = self.notes.lock().unwrap();
+        Ok(notes.iter().find(|n| n.id == note_id).cloned())
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(None)
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_notes(&self, query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        let notes = self.notes.lock().unwrap();
+        Ok(notes
+            .iter()
+            .filter(|n| query.notebook.is_none_or(|id| n.notebook_id == Some(id)))
+            .take(query.limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+struct MockNotebookRepo {
+    notebooks: Mutex<Vec<(i64, Notebook)>>, // (所有者, ノートブック)
+    notes: Notes,
+    deleted: Mutex<Vec<(i64, NotebookDeleteMode)>>,
+}
+
+impl MockNotebookRepo {
+    fn owned(&self, user_id: i64, notebook_id: i64) -> Option<Notebook> {
+        let notebooks = self.notebooks.lock().unwrap();
+        notebooks
+            .iter()
+            .find(|(owner, nb)| *owner == user_id && nb.id == notebook_id)
+            .map(|(_, nb)| nb.clone())
+    }
+}
+
+#[async_trait]
+impl NotebookRepository for MockNotebookRepo {
+    async fn create_notebook(
+        &self,
+        user_id: i64,
+        parent_id: Option<i64>,
+        name: &str,
+    ) -> Result<Notebook, RepoError> {
+        let mut notebooks = self.notebooks.lock().unwrap();
+        let notebook = Notebook {
+            id: notebooks.len() as i64 + 1,
+            parent_id,
+            name: name.into(),
+            created_at: 0,
+            updated_at: 0,
+        };
+        notebooks.push((user_id, notebook.clone()));
+        Ok(notebook)
+    }
+
+    async fn find_notebook(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+    ) -> Result<Option<Notebook>, RepoError> {
+        Ok(self.owned(user_id, notebook_id))
+    }
+
+    async fn list_notebooks(&self, user_id: i64) -> Result<Vec<Notebook>, RepoError> {
+        let notebooks = self.notebooks.lock().unwrap();
+        Ok(notebooks
+            .iter()
+            .filter(|(owner, _)| *owner == user_id)
+            .map(|(_, nb)| nb.clone())
+            .collect())
+    }
+
+    async fn rename_notebook(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        name: &str,
+    ) -> Result<Option<Notebook>, RepoError> {
+        let mut notebooks = self.notebooks.lock().unwrap();
+        Ok(notebooks
+            .iter_mut()
+            .find(|(owner, nb)| *owner == user_id && nb.id == notebook_id)
+            .map(|(_, nb)| {
+                nb.name = name.into();
+                nb.clone()
+            }))
+    }
+
+    async fn move_notebook(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        parent_id: Option<i64>,
+    ) -> Result<Option<Notebook>, RepoError> {
+        if self.owned(user_id, notebook_id).is_none() {
+            return Ok(None);
+        }
+        // 移動先から親をたどって自分自身に行き着けば循環する
+        let mut ancestor = parent_id;
+        while let Some(id) = ancestor {
+            if id == notebook_id {
+                return Err(RepoError::Conflict);
+            }
+            let Some(nb) = self.owned(user_id, id) else {
+                return Ok(None);
+            };
+            ancestor = nb.parent_id;
+        }
+        let mut notebooks = self.notebooks.lock().unwrap();
+        Ok(notebooks
+            .iter_mut()
+            .find(|(_, nb)| nb.id == notebook_id)
+            .map(|(_, nb)| {
+                nb.parent_id = parent_id;
+                nb.clone()
+            }))
+    }
+
+    async fn delete_notebook(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        mode: NotebookDeleteMode,
+    ) -> Result<Option<Vec<Note>>, RepoError> {
+        if self.owned(user_id, notebook_id).is_none() {
+            return Ok(None);
+        }
+        self.deleted.lock().unwrap().push((notebook_id, mode));
+        let mut notebooks = self.notebooks.lock().unwrap();
+        notebooks.retain(|(_, nb)| nb.id != notebook_id);
+        Ok(Some(vec![]))
+    }
+
+    async fn file_note(
+        &self,
+        user_id: i64,
+        note_id: i64,
+        notebook_id: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        let mut notes = self.notes.lock().unwrap();
+        let Some(note) = notes
+            .iter_mut()
+            .find(|n| n.id == note_id && n.author_id == user_id)
+        else {
+            return Ok(false);
+        };
+        note.notebook_id = notebook_id;
+        note.version += 1;
+        Ok(true)
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer(user_id: i64) -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(user_id).unwrap()),
+    )
+}
+
+macro_rules! notebook_app {
+    ($repo:expr, $notes:expr) => {{
+        let notes: Arc<dyn NoteRepository> = Arc::new(MockNoteRepo {
+            notes: $notes.clone(),
+        });
+        let service = NotebookService::new($repo.clone(), notes.clone());
+        init_service(
+            App::new()
+                .app_data(web::Data::new(notes))
+                .app_data(web::Data::new(jwt()))
+                .app_data(web::Data::new(service))
+                .service(create_note)
+                .service(create_notebook)
+                .service(list_notebooks)
+                .service(rename_notebook)
+                .service(move_notebook)
+                .service(delete_notebook)
+                .service(list_notebook_notes)
+                .service(file_note),
+        )
+        .await
+    }};
+}
+
+fn fixture() -> (Arc<MockNotebookRepo>, Notes) {
+    let notes: Notes = Arc::new(Mutex::new(vec![
+        note(1, 1, None),
+        note(2, 2, None),
+        note(3, 1, Some(10)),
+    ]));
+    let repo = Arc::new(MockNotebookRepo {
+        notebooks: Mutex::new(vec![]),
+        notes: notes.clone(),
+        deleted: Mutex::new(vec![]),
+    });
+    (repo, notes)
+}
+
+fn create(user_id: i64, name: &str, parent_id: Option<i64>) -> TestRequest {
+    TestRequest::post()
+        .uri("/notebooks")
+        .insert_header(bearer(user_id))
+        .set_json(serde_json::json!({ "name": name, "parent_id": parent_id }))
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn notebooks_nest_and_cannot_form_cycles() {
+    let (repo, notes) = fixture();
+    let app = notebook_app!(repo, notes);
+
+    let root: Notebook =
+        call_and_read_body_json(&app, create(1, " 仕事 ", None).to_request()).await;
+    assert_eq!(root.name, "仕事");
+    let child: Notebook =
+        call_and_read_body_json(&app, create(1, "会議", Some(root.id)).to_request()).await;
+    assert_eq!(child.parent_id, Some(root.id));
+    // 空の名前・他人のノートブックの下には作れない
+    let resp = call_service(&app, create(1, "  ", None).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = call_service(&app, create(2, "x", Some(root.id)).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let move_to = |user_id, id, parent_id: Option<i64>| {
+        TestRequest::put()
+            .uri(&format!("/notebooks/{id}/parent"))
+            .insert_header(bearer(user_id))
+            .set_json(serde_json::json!({ "parent_id": parent_id }))
+            .to_request()
+    };
+    let resp = call_service(&app, move_to(1, root.id, Some(child.id))).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let resp = call_service(&app, move_to(1, root.id, Some(root.id))).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let resp = call_service(&app, move_to(2, child.id, None)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let moved: Notebook = call_and_read_body_json(&app, move_to(1, child.id, None)).await;
+    assert_eq!(moved.parent_id, None);
+
+    let req = TestRequest::put()
+        .uri(&format!("/notebooks/{}", root.id))
+        .insert_header(bearer(1))
+        .set_json(serde_json::json!({ "name": "個人" }))
+        .to_request();
+    let renamed: Notebook = call_and_read_body_json(&app, req).await;
+    assert_eq!(renamed.name, "個人");
+
+    // 一覧は自分のノートブックだけ
+    let req = TestRequest::get()
+        .uri("/notebooks")
+        .insert_header(bearer(2))
+        .to_request();
+    let listed: Vec<Notebook> = call_and_read_body_json(&app, req).await;
+    assert!(listed.is_empty());
+}
+
+#[actix_web::test]
+async fn only_own_personal_notes_can_be_filed() {
+    let (repo, notes) = fixture();
+    let app = notebook_app!(repo, notes);
+    let notebook: Notebook = call_and_read_body_json(&app, create(1, "n", None).to_request()).await;
+
+    let file = |user_id, note_id, notebook_id: Option<i64>| {
+        TestRequest::put()
+            .uri(&format!("/notes/{note_id}/notebook"))
+            .insert_header(bearer(user_id))
+            .set_json(serde_json::json!({ "notebook_id": notebook_id }))
+            .to_request()
+    };
+    let filed: Note = call_and_read_body_json(&app, file(1, 1, Some(notebook.id))).await;
+    assert_eq!(filed.notebook_id, Some(notebook.id));
+    // 他人のメモ・他人のノートブック・ワークスペースのメモ
+    let resp = call_service(&app, file(1, 2, Some(notebook.id))).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = call_service(&app, file(2, 2, Some(notebook.id))).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = call_service(&app, file(1, 3, Some(notebook.id))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let list = |user_id| {
+        TestRequest::get()
+            .uri(&format!("/notebooks/{}/notes?limit=10", notebook.id))
+            .insert_header(bearer(user_id))
+            .to_request()
+    };
+    let page: NotePage = call_and_read_body_json(&app, list(1)).await;
+    assert_eq!(page.items.iter().map(|n| n.id).collect::<Vec<_>>(), [1]);
+    let resp = call_service(&app, list(2)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let unfiled: Note = call_and_read_body_json(&app, file(1, 1, None)).await;
+    assert_eq!(unfiled.notebook_id, None);
+    let page: NotePage = call_and_read_body_json(&app, list(1)).await;
+    assert!(page.items.is_empty());
+}
+
+#[actix_web::test]
+async fn creating_a_note_in_a_notebook_checks_the_owner() {
+    let (repo, notes) = fixture();
+    let app = notebook_app!(repo, notes);
+    let notebook: Notebook = call_and_read_body_json(&app, create(1, "n", None).to_request()).await;
+
+    let post_note = |user_id, body: serde_json::Value| {
+        TestRequest::post()
+            .uri("/notes")
+            .insert_header(bearer(user_id))
+            .set_json(body)
+            .to_request()
+    };
+    let body = serde_json::json!({ "title": "t", "content": "c", "notebook_id": notebook.id });
+    let created: Note = call_and_read_body_json(&app, post_note(1, body.clone())).await;
+    assert_eq!(created.notebook_id, Some(notebook.id));
+    let resp = call_service(&app, post_note(2, body)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body = serde_json::json!({
+        "title": "t", "content": "c", "workspace_id": 10, "notebook_id": notebook.id
+    });
+    let resp = call_service(&app, post_note(1, body)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn deleting_a_notebook_defaults_to_reparent() {
+    let (repo, notes) = fixture();
+    let app = notebook_app!(repo, notes);
+    let a: Notebook = call_and_read_body_json(&app, create(1, "a", None).to_request()).await;
+    let b: Notebook = call_and_read_body_json(&app, create(1, "b", None).to_request()).await;
+
+    let delete = |user_id, uri: String| {
+        TestRequest::delete()
+            .uri(&uri)
+            .insert_header(bearer(user_id))
+            .to_request()
+    };
+    let resp = call_service(&app, delete(2, format!("/notebooks/{}", a.id))).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = call_service(&app, delete(1, format!("/notebooks/{}?mode=purge", a.id))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = call_service(&app, delete(1, format!("/notebooks/{}", a.id))).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = call_service(&app, delete(1, format!("/notebooks/{}?mode=cascade", b.id))).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        *repo.deleted.lock().unwrap(),
+        [
+            (a.id, NotebookDeleteMode::Reparent),
+            (b.id, NotebookDeleteMode::Cascade)
+        ]
+    );
+}
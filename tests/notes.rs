@@ -1,12 +1,17 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use actix_web::{App, http::StatusCode, test, web};
 use async_trait::async_trait;
-use memo_app::app::model::{CreateNoteInput, UpdateNoteInput};
-use memo_app::app::notes::{create_note, delete_note, get_note, update_note};
-use memo_app::domain::model::Note;
+use memo_app::app::model::{CreateNoteInput, NotePage, UpdateNoteInput};
+use memo_app::app::notes::{
+    archive_note, create_note, delete_note, get_note, list_notes, pin_note, search_notes,
+    star_note, unarchive_note, unpin_note, update_note,
+};
+use memo_app::domain::model::{Note, NoteSearchHit, TrashedNote, Visibility};
 use memo_app::middleware::auth::token::JwtTokenService;
-use memo_app::repository::note::NoteRepository;
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
 use memo_app::repository::user::RepoError;
 
 // ---- Mocks ----
@@ -15,19 +20,22 @@ struct MockNoteRepoCreateOk;
 
 #[async_trait]
 impl NoteRepository for MockNoteRepoCreateOk {
-    async fn create_note(
-        &self,
-        user_id: i64,
-        title: &str,
-        content: &str,
-    ) -> Result<Note, RepoError> {
+    async fn create_note(&self, user_id: i64, note: &NewNote<'_>) -> Result<Note, RepoError> {
         Ok(Note {
             id: 1,
             author_id: user_id,
-            title: title.to_string(),
-            content: content.to_string(),
+            workspace_id: None,
+            notebook_id: None,
+            title: note.title.to_string(),
+            content: note.content.to_string(),
+            visibility: note.visibility,
             created_at: 1,
             updated_at: 1,
+            version: 1,
+            tags: note.tags.to_vec(),
+            pinned: false,
+            starred: false,
+            archived_at: None,
         })
     }
 
@@ -35,35 +43,62 @@ impl NoteRepository for MockNoteRepoCreateOk {
         Ok(None)
     }
 
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
     async fn update_note(
         &self,
         _note_id: i64,
         _user_id: i64,
-        _title: Option<&str>,
-        _content: Option<&str>,
-    ) -> Result<Option<Note>, RepoError> {
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
         Ok(None)
     }
 
-    async fn list_notes(&self) -> Result<Vec<Note>, RepoError> {
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
         Ok(vec![])
     }
 
-    async fn delete_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
         Ok(false)
     }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
 }
 
 struct MockNoteRepoFindSome;
 
 #[async_trait]
 impl NoteRepository for MockNoteRepoFindSome {
-    async fn create_note(
-        &self,
-        _user_id: i64,
-        _title: &str,
-        _content: &str,
-    ) -> Result<Note, RepoError> {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
         Err(RepoError::Internal)
     }
 
@@ -71,42 +106,77 @@ impl NoteRepository for MockNoteRepoFindSome {
         Ok(Some(Note {
             id: note_id,
             author_id: 7,
+            workspace_id: None,
+            notebook_id: None,
             title: "t".into(),
             content: "c".into(),
+            visibility: Visibility::Public,
             created_at: 1,
             updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
         }))
     }
 
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
     async fn update_note(
         &self,
         _note_id: i64,
         _user_id: i64,
-        _title: Option<&str>,
-        _content: Option<&str>,
-    ) -> Result<Option<Note>, RepoError> {
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
         Ok(None)
     }
 
-    async fn list_notes(&self) -> Result<Vec<Note>, RepoError> {
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
         Ok(vec![])
     }
 
-    async fn delete_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
         Ok(false)
     }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
 }
 
 struct MockNoteRepoFindNone;
 
 #[async_trait]
 impl NoteRepository for MockNoteRepoFindNone {
-    async fn create_note(
-        &self,
-        _user_id: i64,
-        _title: &str,
-        _content: &str,
-    ) -> Result<Note, RepoError> {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
         Err(RepoError::Internal)
     }
 
@@ -114,144 +184,284 @@ impl NoteRepository for MockNoteRepoFindNone {
         Ok(None)
     }
 
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
     async fn update_note(
         &self,
         _note_id: i64,
         _user_id: i64,
-        _title: Option<&str>,
-        _content: Option<&str>,
-    ) -> Result<Option<Note>, RepoError> {
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
         Ok(None)
     }
 
-    async fn list_notes(&self) -> Result<Vec<Note>, RepoError> {
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
         Ok(vec![])
     }
 
-    async fn delete_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
         Ok(false)
     }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
 }
 
 struct MockNoteRepoUpdateOk;
 
 #[async_trait]
 impl NoteRepository for MockNoteRepoUpdateOk {
-    async fn create_note(
-        &self,
-        _user_id: i64,
-        _title: &str,
-        _content: &str,
-    ) -> Result<Note, RepoError> {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
         Err(RepoError::Internal)
     }
     async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
         Ok(Some(Note {
             id: note_id,
             author_id: 42,
+            workspace_id: None,
+            notebook_id: None,
             title: "t".into(),
             content: "c".into(),
+            visibility: Visibility::Private,
             created_at: 1,
             updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
         }))
     }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
     async fn update_note(
         &self,
         note_id: i64,
         user_id: i64,
-        title: Option<&str>,
-        content: Option<&str>,
-    ) -> Result<Option<Note>, RepoError> {
-        Ok(Some(Note {
-            id: note_id,
-            author_id: user_id,
-            title: title.unwrap_or("orig").to_string(),
-            content: content.unwrap_or("orig").to_string(),
-            created_at: 1,
-            updated_at: 2,
-        }))
+        changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(Some(
+            Note {
+                id: note_id,
+                author_id: user_id,
+                workspace_id: None,
+                notebook_id: None,
+                title: changes.title.unwrap_or("orig").to_string(),
+                content: changes.content.unwrap_or("orig").to_string(),
+                visibility: changes.visibility.unwrap_or_default(),
+                created_at: 1,
+                updated_at: 2,
+                version: 1,
+                tags: changes.tags.map(|t| t.to_vec()).unwrap_or_default(),
+                pinned: false,
+                starred: false,
+                archived_at: None,
+            }
+            .into(),
+        ))
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
     }
 
-    async fn list_notes(&self) -> Result<Vec<Note>, RepoError> {
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
         Ok(vec![])
     }
 
-    async fn delete_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
         Ok(false)
     }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
 }
 
 struct MockNoteRepoUpdateNone;
 
 #[async_trait]
 impl NoteRepository for MockNoteRepoUpdateNone {
-    async fn create_note(
-        &self,
-        _user_id: i64,
-        _title: &str,
-        _content: &str,
-    ) -> Result<Note, RepoError> {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
         Err(RepoError::Internal)
     }
     async fn find_by_id(&self, _note_id: i64) -> Result<Option<Note>, RepoError> {
         Ok(None)
     }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
     async fn update_note(
         &self,
         _note_id: i64,
         _user_id: i64,
-        _title: Option<&str>,
-        _content: Option<&str>,
-    ) -> Result<Option<Note>, RepoError> {
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
         Ok(None)
     }
 
-    async fn list_notes(&self) -> Result<Vec<Note>, RepoError> {
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
         Ok(vec![])
     }
 
-    async fn delete_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
         Ok(false)
     }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
 }
 
 struct MockNoteRepoDeleteOk;
 
 #[async_trait]
 impl NoteRepository for MockNoteRepoDeleteOk {
-    async fn create_note(
-        &self,
-        _user_id: i64,
-        _title: &str,
-        _content: &str,
-    ) -> Result<Note, RepoError> {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
         Err(RepoError::Internal)
     }
     async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
         Ok(Some(Note {
             id: note_id,
             author_id: 1,
+            workspace_id: None,
+            notebook_id: None,
             title: "t".into(),
             content: "c".into(),
+            visibility: Visibility::Private,
             created_at: 1,
             updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
         }))
     }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
     async fn update_note(
         &self,
         _note_id: i64,
         _user_id: i64,
-        _title: Option<&str>,
-        _content: Option<&str>,
-    ) -> Result<Option<Note>, RepoError> {
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
         Ok(None)
     }
-    async fn list_notes(&self) -> Result<Vec<Note>, RepoError> {
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
         Ok(vec![])
     }
-    async fn delete_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
         Ok(true)
     }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
 }
 
 #[actix_web::test]
@@ -296,6 +506,208 @@ async fn delete_note_returns_404_when_absent() {
     assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
 
+// ユーザー 1 のメモ（id=1）。読み込み時のバージョンは 3 で、
+// 保存されているバージョン `stored` と異なれば他の更新が割り込んだことになる
+struct MockNoteRepoVersioned {
+    stored: i64,
+}
+
+impl MockNoteRepoVersioned {
+    fn note(&self, version: i64) -> Note {
+        Note {
+            id: 1,
+            author_id: 1,
+            workspace_id: None,
+            notebook_id: None,
+            title: "t".into(),
+            content: "c".into(),
+            visibility: Visibility::Private,
+            created_at: 1,
+            updated_at: 1,
+            version,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        }
+    }
+
+    fn check(&self, expected_version: Option<i64>) -> Result<(), RepoError> {
+        match expected_version {
+            Some(v) if v != self.stored => Err(RepoError::VersionMismatch),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepoVersioned {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, _note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(Some(self.note(3)))
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        self.check(changes.expected_version)?;
+        Ok(Some(self.note(self.stored + 1).into()))
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        self.check(expected_version)?;
+        Ok(true)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+// ユーザー 1 の非公開メモ（id=1）。固定・スター・アーカイブの変更を保存し、
+// `list_notes` に渡された `(archived, starred)` を記録する
+#[derive(Default)]
+struct MockNoteRepoStates {
+    note: Mutex<Option<Note>>,
+    listed: Mutex<Vec<(bool, bool)>>,
+}
+
+impl MockNoteRepoStates {
+    fn current(&self) -> Note {
+        self.note.lock().unwrap().clone().unwrap_or(Note {
+            id: 1,
+            author_id: 1,
+            workspace_id: None,
+            notebook_id: None,
+            title: "t".into(),
+            content: "c".into(),
+            visibility: Visibility::Private,
+            created_at: 1,
+            updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        })
+    }
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepoStates {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok((note_id == 1).then(|| self.current()))
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        assert!(changes.title.is_none() && changes.content.is_none());
+        let mut note = self.current();
+        note.pinned = changes.pinned.unwrap_or(note.pinned);
+        note.starred = changes.starred.unwrap_or(note.starred);
+        note.archived_at = match changes.archived {
+            Some(true) => note.archived_at.or(Some(100)),
+            Some(false) => None,
+            None => note.archived_at,
+        };
+        note.version += 1;
+        *self.note.lock().unwrap() = Some(note.clone());
+        Ok(Some(note.into()))
+    }
+
+    async fn list_notes(&self, query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        self.listed
+            .lock()
+            .unwrap()
+            .push((query.archived, query.starred));
+        Ok(vec![])
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
 fn jwt() -> JwtTokenService {
     JwtTokenService::from_secret(b"test-secret", 3600)
 }
@@ -319,6 +731,10 @@ async fn create_note_returns_201() {
     let payload = CreateNoteInput {
         title: "Hello".into(),
         content: "World".into(),
+        visibility: Visibility::Private,
+        tags: vec!["greeting".into()],
+        workspace_id: None,
+        notebook_id: None,
     };
 
     let req = test::TestRequest::post()
@@ -333,6 +749,7 @@ async fn create_note_returns_201() {
     assert_eq!(created.author_id, user_id);
     assert_eq!(created.title, "Hello");
     assert_eq!(created.content, "World");
+    assert_eq!(created.tags, vec!["greeting".to_string()]);
 }
 
 #[actix_web::test]
@@ -344,6 +761,55 @@ async fn get_note_returns_200_public() {
     let req = test::TestRequest::get().uri("/notes/1").to_request();
     let resp = test::call_service(&app, req).await;
     assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("ETag").unwrap(), "\"1\"");
+}
+
+#[actix_web::test]
+async fn get_note_renders_html_on_request() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoFindSome);
+
+    let app = test::init_service(App::new().app_data(web::Data::new(repo)).service(get_note)).await;
+
+    for (uri, accept) in [
+        ("/notes/1?format=html", None),
+        (
+            "/notes/1",
+            Some("text/html,application/xhtml+xml,*/*;q=0.8"),
+        ),
+        ("/notes/1?format=html", Some("application/json")),
+    ] {
+        let mut req = test::TestRequest::get().uri(uri);
+        if let Some(accept) = accept {
+            req = req.insert_header(("Accept", accept));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{uri} {accept:?}");
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"1\"");
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "<p>c</p>\n");
+    }
+
+    for (uri, accept) in [
+        ("/notes/1", "application/json, text/html;q=0.5"),
+        ("/notes/1?format=json", "text/html"),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Accept", accept))
+            .to_request();
+        let note: Note = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(note.content, "c");
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1?format=pdf")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 }
 
 #[actix_web::test]
@@ -374,6 +840,8 @@ async fn update_note_returns_200_for_owner() {
     let payload = UpdateNoteInput {
         title: Some("New".into()),
         content: None,
+        visibility: None,
+        tags: None,
     };
 
     let req = test::TestRequest::put()
@@ -406,6 +874,8 @@ async fn update_note_returns_404_when_not_owner_or_absent() {
     let payload = UpdateNoteInput {
         title: None,
         content: Some("C".into()),
+        visibility: None,
+        tags: None,
     };
 
     let req = test::TestRequest::put()
@@ -416,3 +886,876 @@ async fn update_note_returns_404_when_not_owner_or_absent() {
     let resp = test::call_service(&app, req).await;
     assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
+
+struct MockNoteRepoPrivate;
+
+#[async_trait]
+impl NoteRepository for MockNoteRepoPrivate {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(Some(Note {
+            id: note_id,
+            author_id: 7,
+            workspace_id: None,
+            notebook_id: None,
+            title: "secret".into(),
+            content: "c".into(),
+            visibility: Visibility::Private,
+            created_at: 1,
+            updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        }))
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(None)
+    }
+
+    // 閲覧者本人のメモだけを返す（未ログインなら空）
+    async fn list_notes(&self, query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(query
+            .viewer
+            .into_iter()
+            .map(|author_id| Note {
+                id: 1,
+                author_id,
+                workspace_id: None,
+                notebook_id: None,
+                title: "mine".into(),
+                content: "c".into(),
+                visibility: Visibility::Private,
+                created_at: 1,
+                updated_at: 1,
+                version: 1,
+                tags: query.tags.clone(),
+                pinned: false,
+                starred: false,
+                archived_at: None,
+            })
+            .collect())
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    // 閲覧者本人のメモのみヒットする
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        viewer: Option<i64>,
+        query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(viewer
+            .into_iter()
+            .map(|author_id| NoteSearchHit {
+                note: Note {
+                    id: 1,
+                    author_id,
+                    workspace_id: None,
+                    notebook_id: None,
+                    title: "mine".into(),
+                    content: query.to_string(),
+                    visibility: Visibility::Private,
+                    created_at: 1,
+                    updated_at: 1,
+                    version: 1,
+                    tags: vec![],
+                    pinned: false,
+                    starred: false,
+                    archived_at: None,
+                },
+                rank: 1.0,
+                snippet: format!("<mark>{query}</mark>"),
+            })
+            .collect())
+    }
+}
+
+#[actix_web::test]
+async fn get_private_note_returns_404_for_anonymous_and_other_users() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoPrivate);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(get_note),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/notes/1").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let token = jwt().generate(8).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/notes/1")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn get_private_note_returns_200_for_owner() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoPrivate);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(get_note),
+    )
+    .await;
+
+    let token = jwt().generate(7).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/notes/1")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let note: Note = test::read_body_json(resp).await;
+    assert_eq!(note.visibility, Visibility::Private);
+}
+
+#[actix_web::test]
+async fn list_notes_passes_caller_as_viewer() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoPrivate);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(list_notes),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/notes").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let page: NotePage = test::read_body_json(resp).await;
+    assert!(page.items.is_empty());
+    assert!(page.next_cursor.is_none());
+
+    let token = jwt().generate(3).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/notes")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let page: NotePage = test::read_body_json(resp).await;
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].author_id, 3);
+}
+
+// ID 1..=10 のメモを降順で持つリポジトリ（キーセットページングを模倣）
+struct MockNoteRepoPaged;
+
+#[async_trait]
+impl NoteRepository for MockNoteRepoPaged {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, _note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(None)
+    }
+
+    async fn list_notes(&self, query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        let start = query.after.as_ref().map(|c| c.id - 1).unwrap_or(10);
+        Ok((1..=start)
+            .rev()
+            .take(query.limit as usize)
+            .map(|id| Note {
+                id,
+                author_id: 1,
+                workspace_id: None,
+                notebook_id: None,
+                title: format!("n{id}"),
+                content: "c".into(),
+                visibility: Visibility::Public,
+                created_at: id,
+                updated_at: id,
+                version: 1,
+                tags: vec![],
+                pinned: false,
+                starred: false,
+                archived_at: None,
+            })
+            .collect())
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+#[actix_web::test]
+async fn list_notes_pages_through_results_with_cursor() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoPaged);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .service(list_notes),
+    )
+    .await;
+
+    let mut seen = vec![];
+    let mut uri = "/notes?limit=4".to_string();
+    loop {
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let page: NotePage = test::read_body_json(resp).await;
+        assert!(page.items.len() <= 4);
+        seen.extend(page.items.iter().map(|n| n.id));
+        match page.next_cursor {
+            Some(cursor) => uri = format!("/notes?limit=4&cursor={cursor}"),
+            None => break,
+        }
+    }
+    assert_eq!(seen, (1..=10).rev().collect::<Vec<_>>());
+}
+
+#[actix_web::test]
+async fn list_notes_rejects_invalid_cursor_and_limit() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoPaged);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .service(list_notes),
+    )
+    .await;
+
+    use base64::Engine;
+    let cursor = |raw: &str| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw);
+    for uri in [
+        "/notes?cursor=not-a-cursor".to_string(),
+        // ID だけの古い形式、並び替えキーが日時でない、固定状態が 0/1 でない
+        format!("/notes?cursor={}", cursor("5")),
+        format!("/notes?cursor={}", cursor("0|n5|5")),
+        format!("/notes?cursor={}", cursor("2|5|5")),
+        "/notes?limit=0".to_string(),
+        "/notes?limit=1000".to_string(),
+        "/notes?sort=author".to_string(),
+    ] {
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[actix_web::test]
+async fn search_notes_returns_hits_visible_to_caller() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoPrivate);
+
+    // main.rs と同じく get_note より先に登録する
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(search_notes)
+            .service(get_note),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/notes/search?q=rust")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let hits: Vec<NoteSearchHit> = test::read_body_json(resp).await;
+    assert!(hits.is_empty());
+
+    let token = jwt().generate(3).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/notes/search?q=rust")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let hits: Vec<NoteSearchHit> = test::read_body_json(resp).await;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].note.author_id, 3);
+    assert_eq!(hits[0].snippet, "<mark>rust</mark>");
+}
+
+#[actix_web::test]
+async fn search_notes_requires_query() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoPrivate);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .service(search_notes),
+    )
+    .await;
+
+    for uri in [
+        "/notes/search",
+        "/notes/search?q=",
+        "/notes/search?q=%20%20",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[actix_web::test]
+async fn list_notes_accepts_repeated_tag_filters() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoPrivate);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(list_notes),
+    )
+    .await;
+
+    let token = jwt().generate(3).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/notes?tag=work&tag=%20rust%20&tag=work&tag_mode=any")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let page: NotePage = test::read_body_json(resp).await;
+    // 正規化（trim・重複除去）された上でリポジトリに渡る
+    assert_eq!(page.items[0].tags, vec!["work".to_string(), "rust".into()]);
+
+    for uri in ["/notes?tag=a&tag_mode=none", "/notes?tag=a%2Fb"] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
+
+#[actix_web::test]
+async fn create_note_rejects_invalid_tags() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoCreateOk);
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(create_note),
+    )
+    .await;
+
+    let token = jwt().generate(1).unwrap();
+    let payload = CreateNoteInput {
+        title: "Hello".into(),
+        content: "World".into(),
+        visibility: Visibility::Private,
+        tags: vec!["   ".into()],
+        workspace_id: None,
+        notebook_id: None,
+    };
+    let req = test::TestRequest::post()
+        .uri("/notes")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(&payload)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn update_note_honors_if_match() {
+    let token = jwt().generate(1).unwrap();
+    let payload = UpdateNoteInput {
+        title: Some("New".into()),
+        content: None,
+        visibility: None,
+        tags: None,
+    };
+
+    // stored=4: 読み込み後に別の更新が入ったケース
+    for (stored, if_match, expected) in [
+        (3, None, StatusCode::OK),
+        (3, Some("\"3\""), StatusCode::OK),
+        (3, Some("*"), StatusCode::OK),
+        (3, Some("\"2\""), StatusCode::PRECONDITION_FAILED),
+        (3, Some("W/\"3\""), StatusCode::PRECONDITION_FAILED),
+        (4, Some("\"3\""), StatusCode::PRECONDITION_FAILED),
+    ] {
+        let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoVersioned { stored });
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(repo))
+                .app_data(web::Data::new(jwt()))
+                .service(update_note),
+        )
+        .await;
+
+        let mut req = test::TestRequest::put()
+            .uri("/notes/1")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(&payload);
+        if let Some(if_match) = if_match {
+            req = req.insert_header(("If-Match", if_match));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(
+            resp.status(),
+            expected,
+            "stored={stored} if-match={if_match:?}"
+        );
+        if expected == StatusCode::OK {
+            assert_eq!(resp.headers().get("ETag").unwrap(), "\"4\"");
+        }
+    }
+}
+
+#[actix_web::test]
+async fn delete_note_honors_if_match() {
+    let token = jwt().generate(1).unwrap();
+
+    for (stored, if_match, expected) in [
+        (3, "\"3\"", StatusCode::NO_CONTENT),
+        (3, "\"1\", \"3\"", StatusCode::NO_CONTENT),
+        (3, "\"2\"", StatusCode::PRECONDITION_FAILED),
+        (4, "\"3\"", StatusCode::PRECONDITION_FAILED),
+    ] {
+        let repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepoVersioned { stored });
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(repo))
+                .app_data(web::Data::new(jwt()))
+                .service(delete_note),
+        )
+        .await;
+
+        let req = test::TestRequest::delete()
+            .uri("/notes/1")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .insert_header(("If-Match", if_match))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.status(),
+            expected,
+            "stored={stored} if-match={if_match}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn pin_star_and_archive_toggle_note_state() {
+    let repo = Arc::new(MockNoteRepoStates::default());
+    let dyn_repo: Arc<dyn NoteRepository> = repo.clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(dyn_repo))
+            .app_data(web::Data::new(jwt()))
+            .service(pin_note)
+            .service(unpin_note)
+            .service(star_note)
+            .service(archive_note)
+            .service(unarchive_note),
+    )
+    .await;
+
+    let owner = jwt().generate(1).unwrap();
+    let send = |req: test::TestRequest, token: &str| {
+        req.insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request()
+    };
+
+    let req = send(test::TestRequest::put().uri("/notes/1/pin"), &owner);
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("ETag").unwrap(), "\"2\"");
+    let note: Note = test::read_body_json(resp).await;
+    assert!(note.pinned && !note.starred);
+
+    let req = send(test::TestRequest::put().uri("/notes/1/star"), &owner);
+    let note: Note = test::call_and_read_body_json(&app, req).await;
+    assert!(note.pinned && note.starred);
+
+    let req = send(test::TestRequest::delete().uri("/notes/1/pin"), &owner);
+    let note: Note = test::call_and_read_body_json(&app, req).await;
+    assert!(!note.pinned && note.starred);
+
+    // アーカイブし直しても最初の日時のまま
+    for _ in 0..2 {
+        let req = send(test::TestRequest::put().uri("/notes/1/archive"), &owner);
+        let note: Note = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(note.archived_at, Some(100));
+    }
+    let req = send(test::TestRequest::delete().uri("/notes/1/archive"), &owner);
+    let note: Note = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(note.archived_at, None);
+
+    // 古い If-Match は 412、他人の非公開メモと存在しないメモは 404
+    let req = send(
+        test::TestRequest::put()
+            .uri("/notes/1/pin")
+            .insert_header(("If-Match", "\"1\"")),
+        &owner,
+    );
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+    let other = jwt().generate(2).unwrap();
+    for (uri, token) in [("/notes/1/pin", &other), ("/notes/9/pin", &owner)] {
+        let req = send(test::TestRequest::put().uri(uri), token);
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+    }
+    assert_eq!(repo.current().version, 7);
+}
+
+#[actix_web::test]
+async fn list_notes_passes_archived_and_starred_filters() {
+    let repo = Arc::new(MockNoteRepoStates::default());
+    let dyn_repo: Arc<dyn NoteRepository> = repo.clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(dyn_repo))
+            .app_data(web::Data::new(jwt()))
+            .service(list_notes),
+    )
+    .await;
+
+    let token = jwt().generate(1).unwrap();
+    for uri in [
+        "/notes",
+        "/notes?archived=true",
+        "/notes?starred=true&archived=false",
+    ] {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK, "{uri}");
+    }
+    assert_eq!(
+        *repo.listed.lock().unwrap(),
+        vec![(false, false), (true, false), (false, true)]
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/notes?archived=yes")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、カーソルが前ページ最後のメモの更新・削除に影響されないことと、
+/// 固定したメモが先頭に並ぶことを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn list_notes_pages_with_stale_cursor_in_sqlite() {
+    use memo_app::repository::note::SqliteNoteRepository;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let repo: Arc<dyn NoteRepository> = Arc::new(SqliteNoteRepository::new(pool.clone()));
+    let mut ids = vec![];
+    for i in 1..=5 {
+        let title = format!("n{i}");
+        let note = repo
+            .create_note(
+                author,
+                &NewNote {
+                    title: &title,
+                    content: "c",
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        sqlx::query("UPDATE notes SET updated_at = ? WHERE id = ?")
+            .bind(i * 100)
+            .bind(note.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        ids.push(note.id);
+    }
+    sqlx::query("UPDATE notes SET pinned = 1 WHERE id = ?")
+        .bind(ids[1])
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo.clone()))
+            .app_data(web::Data::new(jwt()))
+            .service(list_notes),
+    )
+    .await;
+    let token = jwt().generate(author).unwrap();
+    let page = |cursor: Option<String>| {
+        let uri = match cursor {
+            Some(cursor) => format!("/notes?sort=updated_at&limit=2&cursor={cursor}"),
+            None => "/notes?sort=updated_at&limit=2".to_string(),
+        };
+        test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request()
+    };
+    let read = |page: &NotePage| page.items.iter().map(|n| n.id).collect::<Vec<_>>();
+
+    let first: NotePage = test::call_and_read_body_json(&app, page(None)).await;
+    assert_eq!(read(&first), [ids[1], ids[4]]);
+    // 前ページ最後のメモが更新されて先頭へ移っても、続きの位置は変わらない
+    sqlx::query("UPDATE notes SET updated_at = 900 WHERE id = ?")
+        .bind(ids[4])
+        .execute(&pool)
+        .await
+        .unwrap();
+    let second: NotePage = test::call_and_read_body_json(&app, page(first.next_cursor)).await;
+    assert_eq!(read(&second), [ids[3], ids[2]]);
+    // 削除されても同じ
+    assert!(repo.delete_note(ids[2], None).await.unwrap());
+    let third: NotePage = test::call_and_read_body_json(&app, page(second.next_cursor)).await;
+    assert_eq!(read(&third), [ids[0]]);
+    assert!(third.next_cursor.is_none());
+}
+
+/// 実際の SQLite で、`update_note` / `delete_note` がバージョンを検査することを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn update_note_checks_version_in_sqlite() {
+    use memo_app::repository::note::SqliteNoteRepository;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let repo = SqliteNoteRepository::new(pool.clone());
+    let note = repo
+        .create_note(
+            author,
+            &NewNote {
+                title: "t",
+                content: "v1",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(note.version, 1);
+
+    let changes = |content, expected_version| NoteChanges {
+        content: Some(content),
+        expected_version,
+        ..Default::default()
+    };
+    let updated = repo
+        .update_note(note.id, author, &changes("v2", Some(1)))
+        .await
+        .unwrap()
+        .unwrap()
+        .note;
+    assert_eq!((updated.content.as_str(), updated.version), ("v2", 2));
+    // 古いバージョンでは更新せず、内容も変わらない
+    assert!(matches!(
+        repo.update_note(note.id, author, &changes("v3", Some(1)))
+            .await,
+        Err(RepoError::VersionMismatch)
+    ));
+    let stored = repo.find_by_id(note.id).await.unwrap().unwrap();
+    assert_eq!((stored.content.as_str(), stored.version), ("v2", 2));
+    // バージョンを指定しなければ検査しない
+    let updated = repo
+        .update_note(note.id, author, &changes("v3", None))
+        .await
+        .unwrap()
+        .unwrap()
+        .note;
+    assert_eq!(updated.version, 3);
+    assert!(
+        repo.update_note(note.id + 1, author, &changes("x", Some(1)))
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    assert!(matches!(
+        repo.delete_note(note.id, Some(2)).await,
+        Err(RepoError::VersionMismatch)
+    ));
+    assert!(repo.delete_note(note.id, Some(3)).await.unwrap());
+    // ゴミ箱のメモは更新できない（存在しないものとして扱う）
+    assert!(
+        repo.update_note(note.id, author, &changes("v4", None))
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+/// 実際の SQLite（FTS5）で、検索が閲覧範囲・更新・削除に従い、タイトルの一致を上位にすることを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn search_uses_fts5_in_sqlite() {
+    use memo_app::repository::note::SqliteNoteRepository;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let other = common::insert_user(&pool, "b@example.com").await;
+    let repo = SqliteNoteRepository::new(pool.clone());
+    let create = |user_id, title: &'static str, content: &'static str, visibility| {
+        let repo = &repo;
+        async move {
+            repo.create_note(
+                user_id,
+                &NewNote {
+                    title,
+                    content,
+                    visibility,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+        }
+    };
+    let in_content = create(author, "memo", "learning rust today", Visibility::Private).await;
+    let in_title = create(author, "rust", "notes", Visibility::Private).await;
+    let hidden = create(other, "rust secret", "x", Visibility::Private).await;
+    let public = create(other, "public", "rust for everyone", Visibility::Public).await;
+
+    let ids = |hits: Vec<NoteSearchHit>| hits.iter().map(|h| h.note.id).collect::<Vec<_>>();
+    let hits = repo.search(Some(author), "rust", 10).await.unwrap();
+    assert_eq!(hits[0].note.id, in_title.id);
+    assert!(hits[0].rank > hits[1].rank);
+    let found = ids(hits);
+    assert_eq!(found.len(), 3);
+    assert!(found.contains(&in_content.id) && found.contains(&public.id));
+    assert!(!found.contains(&hidden.id));
+    let hits = repo.search(Some(author), "learning", 10).await.unwrap();
+    assert_eq!(hits[0].snippet, "<mark>learning</mark> rust today");
+    // 未ログインでは `public` なメモだけ
+    assert_eq!(
+        ids(repo.search(None, "rust", 10).await.unwrap()),
+        [public.id]
+    );
+    // FTS5 の構文になる文字を含んでもエラーにしない
+    assert!(repo.search(Some(author), "\"rust OR", 10).await.is_ok());
+    assert!(
+        repo.search(Some(author), "  ", 10)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // 更新・削除は索引に反映される
+    repo.update_note(
+        in_content.id,
+        author,
+        &NoteChanges {
+            content: Some("learning go today"),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert!(!ids(repo.search(Some(author), "rust", 10).await.unwrap()).contains(&in_content.id));
+    assert_eq!(
+        ids(repo.search(Some(author), "go", 10).await.unwrap()),
+        [in_content.id]
+    );
+    assert!(repo.delete_note(in_title.id, None).await.unwrap());
+    assert_eq!(
+        ids(repo.search(Some(author), "rust", 10).await.unwrap()),
+        [public.id]
+    );
+}
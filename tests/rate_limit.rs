@@ -0,0 +1,159 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::{Method, StatusCode, header};
+use actix_web::{App, HttpResponse, web};
+use memo_app::middleware::auth::extractor::AuthenticatedUser;
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::middleware::rate_limit::{
+    InMemoryRateLimitStore, RateLimitPolicy, RateLimitStore, RateLimiter, TokenBucket,
+};
+
+async fn create_note(user: AuthenticatedUser) -> HttpResponse {
+    HttpResponse::Created().body(user.0.sub.to_string())
+}
+
+async fn ok() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+fn header_value<B>(resp: &ServiceResponse<B>, name: &str) -> String {
+    resp.headers()
+        .get(name)
+        .unwrap_or_else(|| panic!("missing {name}"))
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn policy_parses_requests_per_seconds() {
+    assert_eq!(
+        RateLimitPolicy::parse("60/30").unwrap(),
+        RateLimitPolicy::new(60, Duration::from_secs(30))
+    );
+    for invalid in ["60", "0/10", "10/0", "a/10", ""] {
+        assert!(RateLimitPolicy::parse(invalid).is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn token_bucket_refills_over_time() {
+    let policy = RateLimitPolicy::new(2, Duration::from_secs(10));
+    let start = Instant::now();
+    let mut bucket = TokenBucket::full(&policy, start);
+
+    let first = bucket.take(&policy, start);
+    assert!(first.allowed);
+    assert_eq!(first.remaining, 1);
+    assert_eq!(first.reset_after, Duration::from_secs(5));
+    assert!(bucket.take(&policy, start).allowed);
+
+    let denied = bucket.take(&policy, start);
+    assert!(!denied.allowed);
+    assert_eq!(denied.remaining, 0);
+    assert_eq!(denied.retry_after, Some(Duration::from_secs(5)));
+
+    // 5 秒で 1 つ補充される（容量を超えては貯まらない）
+    assert!(bucket.take(&policy, start + Duration::from_secs(5)).allowed);
+    assert!(!bucket.take(&policy, start + Duration::from_secs(5)).allowed);
+    let later = bucket.take(&policy, start + Duration::from_secs(60));
+    assert!(later.allowed);
+    assert_eq!(later.remaining, 1);
+}
+
+#[actix_web::test]
+async fn limits_by_ip_and_returns_429_with_headers() {
+    let store: Arc<dyn RateLimitStore> = Arc::new(InMemoryRateLimitStore::new());
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(
+                RateLimiter::new(store)
+                    .group(
+                        "write",
+                        RateLimitPolicy::new(2, Duration::from_secs(60)),
+                        [(Method::POST, "/notes")],
+                    )
+                    .default_policy(RateLimitPolicy::new(100, Duration::from_secs(60))),
+            )
+            .route("/notes", web::post().to(ok))
+            .route("/notes", web::get().to(ok)),
+    )
+    .await;
+    let post = |ip: &str| {
+        actix_web::test::TestRequest::post()
+            .uri("/notes")
+            .peer_addr(format!("{ip}:1234").parse().unwrap())
+            .to_request()
+    };
+
+    let resp = actix_web::test::call_service(&app, post("192.0.2.1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(header_value(&resp, "ratelimit-limit"), "2");
+    assert_eq!(header_value(&resp, "ratelimit-remaining"), "1");
+    assert_eq!(header_value(&resp, "ratelimit-policy"), "2;w=60");
+    let resp = actix_web::test::call_service(&app, post("192.0.2.1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = actix_web::test::call_service(&app, post("192.0.2.1")).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header_value(&resp, "ratelimit-remaining"), "0");
+    let retry_after: u64 = header_value(&resp, header::RETRY_AFTER.as_str())
+        .parse()
+        .unwrap();
+    assert!((1..=30).contains(&retry_after));
+
+    // 別の IP・別のグループは別の枠
+    let resp = actix_web::test::call_service(&app, post("192.0.2.2")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let req = actix_web::test::TestRequest::get()
+        .uri("/notes")
+        .peer_addr("192.0.2.1:1234".parse().unwrap())
+        .to_request();
+    let resp = actix_web::test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(header_value(&resp, "ratelimit-limit"), "100");
+}
+
+#[actix_web::test]
+async fn authenticated_requests_are_limited_per_user() {
+    let jwt = JwtTokenService::from_secret(b"secret", 3600);
+    let alice = jwt.generate(1).unwrap();
+    let bob = jwt.generate(2).unwrap();
+    let store: Arc<dyn RateLimitStore> = Arc::new(InMemoryRateLimitStore::new());
+    let app = actix_web::test::init_service(
+        App::new()
+            .wrap(RateLimiter::new(store).group(
+                "write",
+                RateLimitPolicy::new(1, Duration::from_secs(60)),
+                [(Method::POST, "/notes")],
+            ))
+            .app_data(web::Data::new(jwt))
+            .route("/notes", web::post().to(create_note)),
+    )
+    .await;
+    let post = |token: &str| {
+        actix_web::test::TestRequest::post()
+            .uri("/notes")
+            .peer_addr("192.0.2.1:1234".parse().unwrap())
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .to_request()
+    };
+
+    // 同じ IP でもユーザーごとに数える
+    let resp = actix_web::test::call_service(&app, post(&alice)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(actix_web::test::read_body(resp).await, "1");
+    let resp = actix_web::test::call_service(&app, post(&bob)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(actix_web::test::read_body(resp).await, "2");
+    let resp = actix_web::test::call_service(&app, post(&alice)).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // 検証できないトークンは IP で数え、ハンドラで 401 になる
+    let resp = actix_web::test::call_service(&app, post("invalid")).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = actix_web::test::call_service(&app, post("invalid")).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+}
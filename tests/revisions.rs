@@ -0,0 +1,400 @@
+use std::sync::Arc;
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use memo_app::app::model::NoteDiff;
+use memo_app::app::revisions::{diff_revisions, get_revision, list_revisions, restore_revision};
+use memo_app::domain::diff::DiffOp;
+use memo_app::domain::model::{
+    Note, NoteRevision, NoteRevisionSummary, NoteSearchHit, TrashedNote, Visibility,
+};
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::revision::RevisionRepository;
+use memo_app::repository::user::RepoError;
+
+// ---- Mocks ----
+
+// ユーザー 1 の private なメモ（id=1）だけが存在する
+struct MockNoteRepo;
+
+fn note(title: &str, content: &str) -> Note {
+    Note {
+        id: 1,
+        author_id: 1,
+        workspace_id: None,
+        notebook_id: None,
+        title: title.into(),
+        content: content.into(),
+        visibility: Visibility::Private,
+        created_at: 1,
+        updated_at: 2,
+        version: 1,
+        tags: vec![],
+        pinned: false,
+        starred: false,
+        archived_at: None,
+    }
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok((note_id == 1).then(|| note("v2", "a\nc\n")))
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(Some(
+            note(
+                changes.title.unwrap_or("v2"),
+                changes.content.unwrap_or("a\nc\n"),
+            )
+            .into(),
+        ))
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+// メモ 1 に revision 1（"v1"）と 2（"v2"）がある
+struct MockRevisionRepo;
+
+fn revision(revision: i64) -> NoteRevision {
+    let (title, content) = match revision {
+        1 => ("v1", "a\nb\n"),
+        _ => ("v2", "a\nc\n"),
+    };
+    NoteRevision {
+        note_id: 1,
+        revision,
+        title: title.into(),
+        content: content.into(),
+        editor_id: Some(1),
+        created_at: revision,
+    }
+}
+
+#[async_trait]
+impl RevisionRepository for MockRevisionRepo {
+    async fn list_revisions(&self, _note_id: i64) -> Result<Vec<NoteRevisionSummary>, RepoError> {
+        Ok([2, 1]
+            .into_iter()
+            .map(|rev| {
+                let r = revision(rev);
+                NoteRevisionSummary {
+                    revision: r.revision,
+                    title: r.title,
+                    editor_id: r.editor_id,
+                    created_at: r.created_at,
+                }
+            })
+            .collect())
+    }
+
+    async fn find_revision(
+        &self,
+        note_id: i64,
+        rev: i64,
+    ) -> Result<Option<NoteRevision>, RepoError> {
+        Ok((note_id == 1 && (1..=2).contains(&rev)).then(|| revision(rev)))
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer(user_id: i64) -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(user_id).unwrap()),
+    )
+}
+
+macro_rules! app {
+    () => {{
+        let note_repo: Arc<dyn NoteRepository> = Arc::new(MockNoteRepo);
+        let revision_repo: Arc<dyn RevisionRepository> = Arc::new(MockRevisionRepo);
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(note_repo))
+                .app_data(web::Data::new(revision_repo))
+                .app_data(web::Data::new(jwt()))
+                .service(list_revisions)
+                .service(get_revision)
+                .service(diff_revisions)
+                .service(restore_revision),
+        )
+        .await
+    }};
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn revisions_are_hidden_with_private_note() {
+    let app = app!();
+
+    for uri in [
+        "/notes/1/revisions",
+        "/notes/1/revisions/1",
+        "/notes/1/diff?from=1&to=2",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .insert_header(bearer(2))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+    }
+}
+
+#[actix_web::test]
+async fn list_and_get_revisions() {
+    let app = app!();
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1/revisions")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let revisions: Vec<NoteRevisionSummary> = test::read_body_json(resp).await;
+    assert_eq!(
+        revisions.iter().map(|r| r.revision).collect::<Vec<_>>(),
+        vec![2, 1]
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1/revisions/1")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let rev: NoteRevision = test::read_body_json(resp).await;
+    assert_eq!(rev.content, "a\nb\n");
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1/revisions/9")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn diff_between_revisions() {
+    let app = app!();
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1/diff?from=1&to=2")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let diff: NoteDiff = test::read_body_json(resp).await;
+    assert_eq!(
+        (diff.from_title.as_str(), diff.to_title.as_str()),
+        ("v1", "v2")
+    );
+    let ops: Vec<(DiffOp, &str)> = diff.lines.iter().map(|l| (l.op, l.text.as_str())).collect();
+    assert_eq!(
+        ops,
+        vec![
+            (DiffOp::Equal, "a"),
+            (DiffOp::Delete, "b"),
+            (DiffOp::Insert, "c")
+        ]
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/notes/1/diff?from=1&to=9")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn restore_revision_requires_owner() {
+    let app = app!();
+
+    let req = test::TestRequest::post()
+        .uri("/notes/1/revisions/1/restore")
+        .insert_header(bearer(2))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    // 閲覧できないメモは 403 ではなく 404（存在を秘匿する）
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::post()
+        .uri("/notes/1/revisions/9/restore")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::post()
+        .uri("/notes/1/revisions/1/restore")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let note: Note = test::read_body_json(resp).await;
+    assert_eq!(note.title, "v1");
+    assert_eq!(note.content, "a\nb\n");
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、復元が `PUT /notes/{id}` と同じく共有の権限と `If-Match` に従うことを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn restore_revision_checks_shares_and_if_match_in_sqlite() {
+    use memo_app::domain::model::SharePermission;
+    use memo_app::repository::note::SqliteNoteRepository;
+    use memo_app::repository::revision::SqliteRevisionRepository;
+    use memo_app::repository::share::{ShareRepository, SqliteShareRepository};
+    use memo_app::repository::user::{SqliteUserRepository, UserRepository};
+    use memo_app::repository::workspace::{SqliteWorkspaceRepository, WorkspaceRepository};
+    use memo_app::service::share::ShareService;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let writer = common::insert_user(&pool, "b@example.com").await;
+    let stranger = common::insert_user(&pool, "c@example.com").await;
+
+    let notes: Arc<dyn NoteRepository> = Arc::new(SqliteNoteRepository::new(pool.clone()));
+    let revisions: Arc<dyn RevisionRepository> =
+        Arc::new(SqliteRevisionRepository::new(pool.clone()));
+    let share_repo = Arc::new(SqliteShareRepository::new(pool.clone()));
+    let workspaces: Arc<dyn WorkspaceRepository> =
+        Arc::new(SqliteWorkspaceRepository::new(pool.clone()));
+    let users: Arc<dyn UserRepository> = Arc::new(SqliteUserRepository::new(pool.clone()));
+    let shares = ShareService::new(share_repo.clone(), notes.clone(), workspaces.clone(), users);
+
+    let created = notes
+        .create_note(
+            author,
+            &NewNote {
+                title: "v1",
+                content: "a\nb\n",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let changes = NoteChanges {
+        title: Some("v2"),
+        content: Some("a\nc\n"),
+        ..Default::default()
+    };
+    let current = notes
+        .update_note(created.id, author, &changes)
+        .await
+        .unwrap()
+        .unwrap()
+        .note;
+    share_repo
+        .share_note(created.id, writer, SharePermission::Write, author)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(notes.clone()))
+            .app_data(web::Data::new(revisions.clone()))
+            .app_data(web::Data::new(workspaces))
+            .app_data(web::Data::new(shares))
+            .app_data(web::Data::new(jwt()))
+            .service(restore_revision),
+    )
+    .await;
+    let restore = |user_id: i64, if_match: Option<i64>| {
+        let mut req = test::TestRequest::post()
+            .uri(&format!("/notes/{}/revisions/1/restore", created.id))
+            .insert_header(bearer(user_id));
+        if let Some(version) = if_match {
+            req = req.insert_header(("If-Match", format!("\"{version}\"")));
+        }
+        req.to_request()
+    };
+
+    let resp = test::call_service(&app, restore(stranger, None)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    // 読み込んだ後に更新されていれば上書きしない
+    let resp = test::call_service(&app, restore(writer, Some(created.version))).await;
+    assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(
+        notes.find_by_id(created.id).await.unwrap().unwrap().title,
+        "v2"
+    );
+
+    // `write` で共有された人は復元できる
+    let resp = test::call_service(&app, restore(writer, Some(current.version))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let restored: Note = test::read_body_json(resp).await;
+    assert_eq!(
+        (restored.title.as_str(), restored.content.as_str()),
+        ("v1", "a\nb\n")
+    );
+    assert_eq!(restored.version, current.version + 1);
+    let history = revisions.list_revisions(created.id).await.unwrap();
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[0].editor_id, Some(writer));
+}
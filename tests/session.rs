@@ -0,0 +1,351 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use memo_app::app::auth::{logout, me, refresh};
+use memo_app::app::model::{LoginOutput, LogoutInput, RefreshInput};
+use memo_app::domain::model::RefreshToken;
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::token::TokenRepository;
+use memo_app::repository::user::RepoError;
+use memo_app::service::session::{SessionError, SessionService};
+
+// ---- Mocks ----
+
+struct StoredToken {
+    token: RefreshToken,
+    hash: String,
+}
+
+// メモリ上でリフレッシュトークンと失効リストを保持する
+#[derive(Default)]
+struct MockTokenRepo {
+    refresh: Mutex<Vec<StoredToken>>,
+    revoked: Mutex<Vec<String>>,
+}
+
+impl MockTokenRepo {
+    fn revoked_count(&self) -> usize {
+        self.refresh
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|t| t.token.revoked_at.is_some())
+            .count()
+    }
+}
+
+#[async_trait]
+impl TokenRepository for MockTokenRepo {
+    async fn create_refresh_token(
+        &self,
+        user_id: i64,
+        family_id: &str,
+        token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<(), RepoError> {
+        let mut stored = self.refresh.lock().unwrap();
+        let id = stored.len() as i64 + 1;
+        stored.push(StoredToken {
+            token: RefreshToken {
+                id,
+                user_id,
+                family_id: family_id.into(),
+                expires_at: i64::MAX,
+                used_at: None,
+                revoked_at: None,
+            },
+            hash: token_hash.into(),
+        });
+        Ok(())
+    }
+
+    async fn find_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshToken>, RepoError> {
+        Ok(self
+            .refresh
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|t| t.hash == token_hash)
+            .map(|t| t.token.clone()))
+    }
+
+    async fn mark_refresh_token_used(&self, id: i64) -> Result<bool, RepoError> {
+        let mut stored = self.refresh.lock().unwrap();
+        let Some(t) = stored.iter_mut().find(|t| t.token.id == id) else {
+            return Ok(false);
+        };
+        if t.token.used_at.is_some() || t.token.revoked_at.is_some() {
+            return Ok(false);
+        }
+        t.token.used_at = Some(1);
+        Ok(true)
+    }
+
+    async fn revoke_token_family(&self, family_id: &str) -> Result<u64, RepoError> {
+        let mut count = 0;
+        for t in self.refresh.lock().unwrap().iter_mut() {
+            if t.token.family_id == family_id && t.token.revoked_at.is_none() {
+                t.token.revoked_at = Some(1);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: i64) -> Result<u64, RepoError> {
+        let mut count = 0;
+        for t in self.refresh.lock().unwrap().iter_mut() {
+            if t.token.user_id == user_id && t.token.revoked_at.is_none() {
+                t.token.revoked_at = Some(1);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn revoke_access_token(&self, jti: &str, _expires_at: i64) -> Result<(), RepoError> {
+        self.revoked.lock().unwrap().push(jti.into());
+        Ok(())
+    }
+
+    async fn revoke_user_access_tokens(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(
+        &self,
+        jti: &str,
+        _user_id: i64,
+        _issued_at: i64,
+    ) -> Result<bool, RepoError> {
+        Ok(self.revoked.lock().unwrap().iter().any(|j| j == jti))
+    }
+
+    async fn purge_expired(&self, _now: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn refresh_rotates_token() {
+    let repo = Arc::new(MockTokenRepo::default());
+    let sessions = SessionService::new(repo.clone(), 3600);
+
+    let first = sessions.start(7).await.unwrap();
+    let (user_id, second) = sessions.refresh(&first).await.unwrap();
+    assert_eq!(user_id, 7);
+    assert_ne!(first, second);
+
+    let (_, third) = sessions.refresh(&second).await.unwrap();
+    assert_ne!(second, third);
+    assert!(matches!(
+        sessions.refresh("unknown").await,
+        Err(SessionError::InvalidToken)
+    ));
+}
+
+#[actix_web::test]
+async fn reused_refresh_token_revokes_whole_family() {
+    let repo = Arc::new(MockTokenRepo::default());
+    let sessions = SessionService::new(repo.clone(), 3600);
+
+    let other = sessions.start(7).await.unwrap();
+    let first = sessions.start(7).await.unwrap();
+    let (_, second) = sessions.refresh(&first).await.unwrap();
+
+    // 交換済みの first を再利用すると、同じファミリーの second も使えなくなる
+    assert!(matches!(
+        sessions.refresh(&first).await,
+        Err(SessionError::TokenReused)
+    ));
+    assert!(matches!(
+        sessions.refresh(&second).await,
+        Err(SessionError::InvalidToken)
+    ));
+    assert_eq!(repo.revoked_count(), 2);
+
+    // 別のログイン（ファミリー）には影響しない
+    assert!(sessions.refresh(&other).await.is_ok());
+}
+
+#[actix_web::test]
+async fn logout_revokes_access_and_refresh_tokens() {
+    let repo = Arc::new(MockTokenRepo::default());
+    let tokens: Arc<dyn TokenRepository> = repo.clone();
+    let sessions = web::Data::new(SessionService::new(repo.clone(), 3600));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(tokens))
+            .app_data(web::Data::new(jwt()))
+            .app_data(sessions.clone())
+            .service(me)
+            .service(refresh)
+            .service(logout),
+    )
+    .await;
+
+    let access = jwt().generate(7).unwrap();
+    let refresh_token = sessions.start(7).await.unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/me")
+        .insert_header(("Authorization", format!("Bearer {}", access)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/auth/logout")
+        .insert_header(("Authorization", format!("Bearer {}", access)))
+        .set_json(LogoutInput {
+            refresh_token: Some(refresh_token.clone()),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::post()
+        .uri("/me")
+        .insert_header(("Authorization", format!("Bearer {}", access)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::post()
+        .uri("/auth/refresh")
+        .set_json(RefreshInput { refresh_token })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn refresh_endpoint_issues_new_tokens() {
+    let repo = Arc::new(MockTokenRepo::default());
+    let sessions = web::Data::new(SessionService::new(repo, 3600));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(jwt()))
+            .app_data(sessions.clone())
+            .service(refresh),
+    )
+    .await;
+
+    let refresh_token = sessions.start(7).await.unwrap();
+    let req = test::TestRequest::post()
+        .uri("/auth/refresh")
+        .set_json(RefreshInput {
+            refresh_token: refresh_token.clone(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let out: LoginOutput = test::read_body_json(resp).await;
+    assert_eq!(jwt().verify(&out.token).unwrap().sub, 7);
+    assert_ne!(out.refresh_token, refresh_token);
+
+    // 2 回目は再利用として拒否される
+    let req = test::TestRequest::post()
+        .uri("/auth/refresh")
+        .set_json(RefreshInput { refresh_token })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、期限切れのリフレッシュトークンと失効リストの行だけが削除されることを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn expired_tokens_are_purged_in_sqlite() {
+    use memo_app::repository::token::SqliteTokenRepository;
+    use memo_app::service::session::TokenPurger;
+    use std::time::Duration;
+
+    let pool = common::sqlite_pool().await;
+    let user_id = common::insert_user(&pool, "a@example.com").await;
+    let tokens: Arc<dyn TokenRepository> = Arc::new(SqliteTokenRepository::new(pool.clone()));
+    tokens
+        .create_refresh_token(user_id, "f1", "expired", -60)
+        .await
+        .unwrap();
+    tokens
+        .create_refresh_token(user_id, "f2", "live", 3600)
+        .await
+        .unwrap();
+    tokens.revoke_access_token("old", 1).await.unwrap();
+    tokens.revoke_access_token("new", i64::MAX).await.unwrap();
+
+    let purger = TokenPurger::new(tokens.clone(), Duration::from_secs(3600));
+    assert_eq!(purger.purge_once().await, 2);
+    assert_eq!(purger.purge_once().await, 0);
+
+    assert!(
+        tokens
+            .find_refresh_token("expired")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(tokens.find_refresh_token("live").await.unwrap().is_some());
+    assert!(
+        !tokens
+            .is_access_token_revoked("old", user_id, i64::MAX)
+            .await
+            .unwrap()
+    );
+    assert!(
+        tokens
+            .is_access_token_revoked("new", user_id, i64::MAX)
+            .await
+            .unwrap()
+    );
+}
+
+/// 実際の SQLite で、全トークンの失効が同じ秒に発行したトークンにも及び、
+/// 次の秒に発行したトークンには及ばないことを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn revoking_all_tokens_covers_the_current_second_in_sqlite() {
+    use memo_app::repository::token::SqliteTokenRepository;
+
+    let pool = common::sqlite_pool().await;
+    let user_id = common::insert_user(&pool, "a@example.com").await;
+    let tokens = SqliteTokenRepository::new(pool.clone());
+    let before: i64 = sqlx::query_scalar("SELECT CAST(strftime('%s','now') AS INTEGER)")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    tokens.revoke_user_access_tokens(user_id).await.unwrap();
+    let after: i64 = sqlx::query_scalar("SELECT CAST(strftime('%s','now') AS INTEGER)")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+    for issued_at in before..=after {
+        assert!(
+            tokens
+                .is_access_token_revoked("jti", user_id, issued_at)
+                .await
+                .unwrap()
+        );
+    }
+    assert!(
+        !tokens
+            .is_access_token_revoked("jti", user_id, after + 1)
+            .await
+            .unwrap()
+    );
+}
@@ -0,0 +1,772 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::{App, http::StatusCode, web};
+use async_trait::async_trait;
+use memo_app::app::model::CreatedShareLink;
+use memo_app::app::notes::{SHARE_PASSWORD_HEADER, delete_note, get_note, update_note};
+use memo_app::app::shares::{
+    create_share_link, list_shares, revoke_share_link, share_note, unshare_note,
+};
+use memo_app::domain::model::{
+    Note, NoteSearchHit, NoteShare, NoteSharing, Role, ShareLink, SharePermission, TrashedNote,
+    User, Visibility, Workspace, WorkspaceInvitation, WorkspaceMember, WorkspaceMembership,
+    WorkspaceRole,
+};
+use memo_app::domain::policy::{NoteAction, can_access_note};
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::share::{NewShareLink, ResolvedShareLink, ShareRepository};
+use memo_app::repository::user::{RepoError, UserRepository};
+use memo_app::repository::workspace::WorkspaceRepository;
+use memo_app::service::login_throttle::LoginThrottle;
+use memo_app::service::share::ShareService;
+
+// ---- Mocks ----
+
+const NOTE: i64 = 1;
+
+// ユーザー 1〜3（a〜c@example.com）
+struct MockUserRepo;
+
+impl MockUserRepo {
+    fn user(id: i64) -> Option<User> {
+        let email = match id {
+            1 => "a@example.com",
+            2 => "b@example.com",
+            3 => "c@example.com",
+            _ => return None,
+        };
+        Some(User {
+            id,
+            email: email.into(),
+            password_hash: "!".into(),
+            created_at: 0,
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role: Role::User,
+            disabled_at: None,
+        })
+    }
+}
+
+#[async_trait]
+impl UserRepository for MockUserRepo {
+    async fn create_user(
+        &self,
+        _email: &str,
+        _password_hash: &str,
+    ) -> Result<Option<User>, RepoError> {
+        Ok(None)
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError> {
+        Ok((1..=3)
+            .filter_map(Self::user)
+            .find(|u| u.email.eq_ignore_ascii_case(email)))
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+        Ok(Self::user(user_id))
+    }
+
+    async fn update_password(
+        &self,
+        _user_id: i64,
+        _password_hash: &str,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn mark_email_verified(&self, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn record_login_failure(&self, _user_id: i64) -> Result<i64, RepoError> {
+        Ok(1)
+    }
+
+    async fn lock_user_until(&self, _user_id: i64, _locked_until: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+}
+
+// 個人のメモだけを扱うので、誰もワークスペースのメンバーではない
+struct MockWorkspaceRepo;
+
+#[async_trait]
+impl WorkspaceRepository for MockWorkspaceRepo {
+    async fn create_workspace(&self, _owner_id: i64, _name: &str) -> Result<Workspace, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn list_workspaces(&self, _user_id: i64) -> Result<Vec<WorkspaceMembership>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn member_role(
+        &self,
+        _workspace_id: i64,
+        _user_id: i64,
+    ) -> Result<Option<WorkspaceRole>, RepoError> {
+        Ok(None)
+    }
+
+    async fn list_members(&self, _workspace_id: i64) -> Result<Vec<WorkspaceMember>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn set_member_role(
+        &self,
+        _workspace_id: i64,
+        _user_id: i64,
+        _role: WorkspaceRole,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn remove_member(&self, _workspace_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn create_invitation(
+        &self,
+        _workspace_id: i64,
+        _email: &str,
+        _role: WorkspaceRole,
+        _invited_by: i64,
+        _token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<WorkspaceInvitation, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn accept_invitation(
+        &self,
+        _token_hash: &str,
+        _user_id: i64,
+        _email: &str,
+    ) -> Result<Option<i64>, RepoError> {
+        Ok(None)
+    }
+}
+
+// ユーザー 1 の private なメモ 1 だけがある
+struct MockNoteRepo {
+    note: Mutex<Note>,
+    deleted: Mutex<bool>,
+}
+
+impl MockNoteRepo {
+    fn new() -> Self {
+        Self {
+            note: Mutex::new(Note {
+                id: NOTE,
+                author_id: 1,
+                workspace_id: None,
+                notebook_id: None,
+                title: "t".into(),
+                content: "c".into(),
+                visibility: Visibility::Private,
+                created_at: 1,
+                updated_at: 1,
+                version: 1,
+                tags: vec![],
+                pinned: false,
+                starred: false,
+                archived_at: None,
+            }),
+            deleted: Mutex::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        let note = self.note.lock().unwrap();
+        Ok((note.id == note_id && !*self.deleted.lock().unwrap()).then(|| note.clone()))
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        note_id: i64,
+        _user_id: i64,
+        changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        let mut note = self.note.lock().unwrap();
+        if note.id != note_id {
+            return Ok(None);
+        }
+        if let Some(content) = changes.content {
+            note.content = content.into();
+        }
+        note.version += 1;
+        Ok(Some(note.clone().into()))
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        *self.deleted.lock().unwrap() = true;
+        Ok(true)
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+struct Link {
+    link: ShareLink,
+    token_hash: String,
+    password_hash: Option<String>,
+}
+
+#[derive(Default)]
+struct MockShareRepo {
+    shares: Mutex<Vec<(i64, i64, SharePermission)>>,
+    links: Mutex<Vec<Link>>,
+}
+
+#[async_trait]
+impl ShareRepository for MockShareRepo {
+    async fn share_note(
+        &self,
+        note_id: i64,
+        user_id: i64,
+        permission: SharePermission,
+        _granted_by: i64,
+    ) -> Result<NoteShare, RepoError> {
+        let mut shares = self.shares.lock().unwrap();
+        shares.retain(|(n, u, _)| !(*n == note_id && *u == user_id));
+        shares.push((note_id, user_id, permission));
+        Ok(NoteShare {
+            user_id,
+            email: MockUserRepo::user(user_id).unwrap().email,
+            permission,
+            created_at: 0,
+        })
+    }
+
+    async fn unshare_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
+        let mut shares = self.shares.lock().unwrap();
+        let before = shares.len();
+        shares.retain(|(n, u, _)| !(*n == note_id && *u == user_id));
+        Ok(shares.len() < before)
+    }
+
+    async fn list_shares(&self, note_id: i64) -> Result<Vec<NoteShare>, RepoError> {
+        let shares = self.shares.lock().unwrap();
+        Ok(shares
+            .iter()
+            .filter(|(n, _, _)| *n == note_id)
+            .map(|(_, u, permission)| NoteShare {
+                user_id: *u,
+                email: MockUserRepo::user(*u).unwrap().email,
+                permission: *permission,
+                created_at: 0,
+            })
+            .collect())
+    }
+
+    async fn shared_permission(
+        &self,
+        note_id: i64,
+        user_id: i64,
+    ) -> Result<Option<SharePermission>, RepoError> {
+        let shares = self.shares.lock().unwrap();
+        Ok(shares
+            .iter()
+            .find(|(n, u, _)| *n == note_id && *u == user_id)
+            .map(|(_, _, permission)| *permission))
+    }
+
+    async fn create_link(
+        &self,
+        note_id: i64,
+        created_by: i64,
+        link: &NewShareLink<'_>,
+    ) -> Result<ShareLink, RepoError> {
+        let mut links = self.links.lock().unwrap();
+        let created = ShareLink {
+            id: links.len() as i64 + 1,
+            note_id,
+            permission: link.permission,
+            has_password: link.password_hash.is_some(),
+            created_by: Some(created_by),
+            created_at: 0,
+            expires_at: link.ttl_secs,
+        };
+        links.push(Link {
+            link: created.clone(),
+            token_hash: link.token_hash.into(),
+            password_hash: link.password_hash.map(String::from),
+        });
+        Ok(created)
+    }
+
+    async fn list_links(&self, note_id: i64) -> Result<Vec<ShareLink>, RepoError> {
+        let links = self.links.lock().unwrap();
+        Ok(links
+            .iter()
+            .filter(|l| l.link.note_id == note_id)
+            .map(|l| l.link.clone())
+            .collect())
+    }
+
+    async fn delete_link(&self, note_id: i64, link_id: i64) -> Result<bool, RepoError> {
+        let mut links = self.links.lock().unwrap();
+        let before = links.len();
+        links.retain(|l| !(l.link.note_id == note_id && l.link.id == link_id));
+        Ok(links.len() < before)
+    }
+
+    async fn find_link(&self, token_hash: &str) -> Result<Option<ResolvedShareLink>, RepoError> {
+        let links = self.links.lock().unwrap();
+        Ok(links
+            .iter()
+            .find(|l| l.token_hash == token_hash)
+            .map(|l| ResolvedShareLink {
+                note_id: l.link.note_id,
+                permission: l.link.permission,
+                password_hash: l.password_hash.clone(),
+            }))
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer(user_id: i64) -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(user_id).unwrap()),
+    )
+}
+
+macro_rules! share_app {
+    ($($data:expr),* $(,)?) => {{
+        let notes: Arc<dyn NoteRepository> = Arc::new(MockNoteRepo::new());
+        let workspaces: Arc<dyn WorkspaceRepository> = Arc::new(MockWorkspaceRepo);
+        let service = ShareService::new(
+            Arc::new(MockShareRepo::default()),
+            notes.clone(),
+            workspaces.clone(),
+            Arc::new(MockUserRepo),
+        );
+        actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(notes))
+                .app_data(web::Data::new(workspaces))
+                .app_data(web::Data::new(jwt()))
+                .app_data(web::Data::new(service))
+                $(.app_data($data))*
+                .service(get_note)
+                .service(update_note)
+                .service(delete_note)
+                .service(list_shares)
+                .service(share_note)
+                .service(unshare_note)
+                .service(create_share_link)
+                .service(revoke_share_link),
+        )
+        .await
+    }};
+}
+
+// ---- Tests ----
+
+#[test]
+fn policy_for_shared_notes() {
+    use NoteAction::*;
+    let note = Note {
+        id: NOTE,
+        author_id: 1,
+        workspace_id: None,
+        notebook_id: None,
+        title: "t".into(),
+        content: "c".into(),
+        visibility: Visibility::Private,
+        created_at: 1,
+        updated_at: 1,
+        version: 1,
+        tags: vec![],
+        pinned: false,
+        starred: false,
+        archived_at: None,
+    };
+    let allowed = |share, action| can_access_note(&note, Some(2), None, share, action);
+
+    assert!(!allowed(None, Read));
+    assert!(allowed(Some(SharePermission::Read), Read));
+    assert!(!allowed(Some(SharePermission::Read), Update));
+    assert!(allowed(Some(SharePermission::Write), Update));
+    // 共有された人は削除・再共有できない
+    assert!(!allowed(Some(SharePermission::Write), Delete));
+    assert!(!allowed(Some(SharePermission::Write), Share));
+    // 共有リンクは未ログインでも効く
+    assert!(can_access_note(
+        &note,
+        None,
+        None,
+        Some(SharePermission::Read),
+        Read
+    ));
+}
+
+#[actix_web::test]
+async fn shared_user_access_follows_permission() {
+    use actix_web::test::{TestRequest, call_service};
+    let app = share_app!();
+    let share = |user_id, email: &str, permission: &str| {
+        TestRequest::post()
+            .uri("/notes/1/shares")
+            .insert_header(bearer(user_id))
+            .set_json(serde_json::json!({ "email": email, "permission": permission }))
+            .to_request()
+    };
+    let get = |user_id| {
+        TestRequest::get()
+            .uri("/notes/1")
+            .insert_header(bearer(user_id))
+            .to_request()
+    };
+    let put = |user_id| {
+        TestRequest::put()
+            .uri("/notes/1")
+            .insert_header(bearer(user_id))
+            .set_json(serde_json::json!({ "content": "edited" }))
+            .to_request()
+    };
+
+    assert_eq!(
+        call_service(&app, get(2)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    // 共有を管理できるのは作成者だけ。閲覧もできないメモは 404
+    let resp = call_service(&app, share(2, "c@example.com", "read")).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = call_service(&app, share(1, "nobody@example.com", "read")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = call_service(&app, share(1, "b@example.com", "read")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(call_service(&app, get(2)).await.status(), StatusCode::OK);
+    assert_eq!(
+        call_service(&app, put(2)).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call_service(&app, get(3)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    // 閲覧できるようになったので 404 ではなく 403
+    let resp = call_service(&app, share(2, "c@example.com", "read")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // 共有し直すと権限が置き換わる
+    let resp = call_service(&app, share(1, "b@example.com", "write")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(call_service(&app, put(2)).await.status(), StatusCode::OK);
+    let req = TestRequest::delete()
+        .uri("/notes/1")
+        .insert_header(bearer(2))
+        .to_request();
+    assert_eq!(
+        call_service(&app, req).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    let req = TestRequest::get()
+        .uri("/notes/1/shares")
+        .insert_header(bearer(1))
+        .to_request();
+    let sharing: NoteSharing = actix_web::test::call_and_read_body_json(&app, req).await;
+    assert_eq!(sharing.users.len(), 1);
+    assert_eq!(sharing.users[0].permission, SharePermission::Write);
+
+    let req = TestRequest::delete()
+        .uri("/notes/1/shares/2")
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(
+        call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    assert_eq!(
+        call_service(&app, get(2)).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn share_link_grants_access_until_revoked() {
+    use actix_web::test::{TestRequest, call_and_read_body_json, call_service};
+    let app = share_app!();
+    let create = |permission: &str| {
+        TestRequest::post()
+            .uri("/notes/1/share-links")
+            .insert_header(bearer(1))
+            .set_json(serde_json::json!({ "permission": permission, "expires_in_days": 7 }))
+            .to_request()
+    };
+    let read: CreatedShareLink = call_and_read_body_json(&app, create("read")).await;
+    let write: CreatedShareLink = call_and_read_body_json(&app, create("write")).await;
+    assert_eq!(read.link.expires_at, Some(7 * 24 * 60 * 60));
+
+    // 未ログインでもリンクで閲覧できる
+    let req = TestRequest::get()
+        .uri(&format!("/notes/1?share={}", read.token))
+        .to_request();
+    assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+    let req = TestRequest::get()
+        .uri("/notes/1?share=not-a-token")
+        .to_request();
+    assert_eq!(
+        call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let put = |token: &str| {
+        TestRequest::put()
+            .uri(&format!("/notes/1?share={token}"))
+            .insert_header(bearer(3))
+            .set_json(serde_json::json!({ "content": "via link" }))
+            .to_request()
+    };
+    assert_eq!(
+        call_service(&app, put(&read.token)).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        call_service(&app, put(&write.token)).await.status(),
+        StatusCode::OK
+    );
+    // 編集リンクでもログインは必要
+    let req = TestRequest::put()
+        .uri(&format!("/notes/1?share={}", write.token))
+        .set_json(serde_json::json!({ "content": "anonymous" }))
+        .to_request();
+    assert_eq!(
+        call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let req = TestRequest::delete()
+        .uri(&format!("/notes/1/share-links/{}", read.link.id))
+        .insert_header(bearer(1))
+        .to_request();
+    assert_eq!(
+        call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+    let req = TestRequest::get()
+        .uri(&format!("/notes/1?share={}", read.token))
+        .to_request();
+    assert_eq!(
+        call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn password_protected_share_link() {
+    use actix_web::test::{TestRequest, call_and_read_body_json, call_service};
+    let app = share_app!();
+    let req = TestRequest::post()
+        .uri("/notes/1/share-links")
+        .insert_header(bearer(1))
+        .set_json(serde_json::json!({ "permission": "read", "expires_in_days": 0 }))
+        .to_request();
+    assert_eq!(
+        call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+
+    let req = TestRequest::post()
+        .uri("/notes/1/share-links")
+        .insert_header(bearer(1))
+        .set_json(serde_json::json!({ "permission": "read", "password": "open sesame" }))
+        .to_request();
+    let created: CreatedShareLink = call_and_read_body_json(&app, req).await;
+    assert!(created.link.has_password);
+    assert_eq!(created.link.expires_at, None);
+
+    let get = |password: Option<&str>| {
+        let mut req = TestRequest::get().uri(&format!("/notes/1?share={}", created.token));
+        if let Some(password) = password {
+            req = req.insert_header((SHARE_PASSWORD_HEADER, password));
+        }
+        req.to_request()
+    };
+    assert_eq!(
+        call_service(&app, get(None)).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call_service(&app, get(Some("wrong"))).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        call_service(&app, get(Some("open sesame"))).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[actix_web::test]
+async fn share_link_password_attempts_are_throttled() {
+    use actix_web::http::header;
+    use actix_web::test::{TestRequest, call_and_read_body_json, call_service};
+    let app = share_app!(web::Data::new(LoginThrottle::new(
+        1,
+        Duration::from_secs(30),
+        Duration::from_secs(60),
+    )));
+    let req = TestRequest::post()
+        .uri("/notes/1/share-links")
+        .insert_header(bearer(1))
+        .set_json(serde_json::json!({ "permission": "read", "password": "open sesame" }))
+        .to_request();
+    let created: CreatedShareLink = call_and_read_body_json(&app, req).await;
+
+    let get = |password: &str, peer: &str| {
+        TestRequest::get()
+            .uri(&format!("/notes/1?share={}", created.token))
+            .peer_addr(peer.parse().unwrap())
+            .insert_header((SHARE_PASSWORD_HEADER, password))
+            .to_request()
+    };
+    for peer in ["192.0.2.1:1234", "192.0.2.2:1234"] {
+        assert_eq!(
+            call_service(&app, get("wrong", peer)).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    // 同じリンクへは、別の IP アドレスからでも正しいパスワードでも待たされる
+    let resp = call_service(&app, get("open sesame", "192.0.2.3:1234")).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = resp
+        .headers()
+        .get(header::RETRY_AFTER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=30).contains(&retry_after));
+}
+
+#[cfg(not(feature = "postgres"))]
+mod common;
+
+/// 実際の SQLite で、共有リンクの検索が有効期限と取り消しに従い、共有の権限が置き換わることを確かめる。
+#[cfg(not(feature = "postgres"))]
+#[actix_web::test]
+async fn share_link_and_share_queries_in_sqlite() {
+    use memo_app::repository::share::SqliteShareRepository;
+
+    let pool = common::sqlite_pool().await;
+    let author = common::insert_user(&pool, "a@example.com").await;
+    let reader = common::insert_user(&pool, "b@example.com").await;
+    let repo = SqliteShareRepository::new(pool.clone());
+    let link = |token_hash, password_hash, ttl_secs| NewShareLink {
+        permission: SharePermission::Read,
+        token_hash,
+        password_hash,
+        ttl_secs,
+    };
+
+    let forever = repo
+        .create_link(NOTE, author, &link("hash-forever", None, None))
+        .await
+        .unwrap();
+    assert!(forever.expires_at.is_none() && !forever.has_password);
+    let expiring = repo
+        .create_link(NOTE, author, &link("hash-expiring", Some("pw"), Some(60)))
+        .await
+        .unwrap();
+    assert!(expiring.has_password);
+    assert_eq!(expiring.expires_at, Some(expiring.created_at + 60));
+
+    let found = repo.find_link("hash-expiring").await.unwrap().unwrap();
+    assert_eq!(found.note_id, NOTE);
+    assert_eq!(found.password_hash.as_deref(), Some("pw"));
+    sqlx::query("UPDATE note_share_links SET expires_at = strftime('%s','now') WHERE id = ?")
+        .bind(expiring.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    // 期限の時刻ちょうどで無効になる。一覧には残る
+    assert!(repo.find_link("hash-expiring").await.unwrap().is_none());
+    assert_eq!(repo.list_links(NOTE).await.unwrap().len(), 2);
+    assert!(repo.find_link("hash-unknown").await.unwrap().is_none());
+
+    // 別のメモのリンクとしては取り消せない
+    assert!(!repo.delete_link(NOTE + 1, forever.id).await.unwrap());
+    assert!(repo.delete_link(NOTE, forever.id).await.unwrap());
+    assert!(repo.find_link("hash-forever").await.unwrap().is_none());
+
+    repo.share_note(NOTE, reader, SharePermission::Read, author)
+        .await
+        .unwrap();
+    let share = repo
+        .share_note(NOTE, reader, SharePermission::Write, author)
+        .await
+        .unwrap();
+    assert_eq!(
+        (share.user_id, share.email.as_str()),
+        (reader, "b@example.com")
+    );
+    assert_eq!(repo.list_shares(NOTE).await.unwrap().len(), 1);
+    assert_eq!(
+        repo.shared_permission(NOTE, reader).await.unwrap(),
+        Some(SharePermission::Write)
+    );
+    assert!(repo.unshare_note(NOTE, reader).await.unwrap());
+    assert_eq!(repo.shared_permission(NOTE, reader).await.unwrap(), None);
+}
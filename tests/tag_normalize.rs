@@ -0,0 +1,25 @@
+use memo_app::domain::tag::{InvalidTag, normalize_tags};
+
+#[test]
+fn normalize_tags_trims_and_dedupes() {
+    let tags = vec![" rust ".to_string(), "work".into(), "rust".into()];
+    assert_eq!(
+        normalize_tags(&tags).unwrap(),
+        vec!["rust".to_string(), "work".into()]
+    );
+}
+
+#[test]
+fn normalize_tags_rejects_invalid_names() {
+    assert_eq!(normalize_tags(&["".into()]), Err(InvalidTag::Empty));
+    assert!(matches!(
+        normalize_tags(&["a/b".into()]),
+        Err(InvalidTag::InvalidChar(_))
+    ));
+    assert!(matches!(
+        normalize_tags(&["x".repeat(65)]),
+        Err(InvalidTag::TooLong(_))
+    ));
+    let many: Vec<String> = (0..33).map(|i| format!("t{i}")).collect();
+    assert_eq!(normalize_tags(&many), Err(InvalidTag::TooMany));
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use memo_app::app::model::{MergeTagsInput, RenameTagInput};
+use memo_app::app::tags::{delete_tag, list_tags, merge_tags, rename_tag};
+use memo_app::domain::model::TagCount;
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::tag::TagRepository;
+use memo_app::repository::user::RepoError;
+
+// ---- Mocks ----
+
+// "work"(2件) と "taken"(0件) を持つユーザーを模倣する
+struct MockTagRepo;
+
+#[async_trait]
+impl TagRepository for MockTagRepo {
+    async fn list_tags(&self, _user_id: i64) -> Result<Vec<TagCount>, RepoError> {
+        Ok(vec![
+            TagCount {
+                name: "taken".into(),
+                count: 0,
+            },
+            TagCount {
+                name: "work".into(),
+                count: 2,
+            },
+        ])
+    }
+
+    async fn rename_tag(&self, _user_id: i64, from: &str, to: &str) -> Result<bool, RepoError> {
+        match (from, to) {
+            (_, "taken") => Err(RepoError::Conflict),
+            ("work", _) => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
+    async fn merge_tags(
+        &self,
+        _user_id: i64,
+        sources: &[String],
+        _target: &str,
+    ) -> Result<u64, RepoError> {
+        Ok(sources.len() as u64)
+    }
+
+    async fn delete_tag(&self, _user_id: i64, name: &str) -> Result<bool, RepoError> {
+        Ok(name == "work")
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer() -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(1).unwrap()),
+    )
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn list_tags_returns_counts() {
+    let repo: Arc<dyn TagRepository> = Arc::new(MockTagRepo);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(list_tags),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/tags").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::get()
+        .uri("/tags")
+        .insert_header(bearer())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let tags: Vec<TagCount> = test::read_body_json(resp).await;
+    assert_eq!(tags[1].name, "work");
+    assert_eq!(tags[1].count, 2);
+}
+
+#[actix_web::test]
+async fn rename_tag_maps_repository_results() {
+    let repo: Arc<dyn TagRepository> = Arc::new(MockTagRepo);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(rename_tag),
+    )
+    .await;
+
+    for (uri, name, expected) in [
+        ("/tags/work", "job", StatusCode::NO_CONTENT),
+        ("/tags/missing", "job", StatusCode::NOT_FOUND),
+        ("/tags/work", "taken", StatusCode::CONFLICT),
+        ("/tags/work", " ", StatusCode::BAD_REQUEST),
+    ] {
+        let req = test::TestRequest::put()
+            .uri(uri)
+            .insert_header(bearer())
+            .set_json(RenameTagInput { name: name.into() })
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), expected, "{uri} -> {name}");
+    }
+}
+
+#[actix_web::test]
+async fn merge_and_delete_tags() {
+    let repo: Arc<dyn TagRepository> = Arc::new(MockTagRepo);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(merge_tags)
+            .service(delete_tag),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/tags/merge")
+        .insert_header(bearer())
+        .set_json(MergeTagsInput {
+            sources: vec!["job".into(), "work".into()],
+            target: "work".into(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::post()
+        .uri("/tags/merge")
+        .insert_header(bearer())
+        .set_json(MergeTagsInput {
+            sources: vec![],
+            target: "work".into(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::delete()
+        .uri("/tags/work")
+        .insert_header(bearer())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::delete()
+        .uri("/tags/none")
+        .insert_header(bearer())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
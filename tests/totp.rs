@@ -0,0 +1,60 @@
+use memo_app::domain::totp::{
+    base32_decode, base32_encode, otpauth_uri, totp_code, totp_step, verify_totp,
+};
+
+// RFC 6238 Appendix B（SHA-1）の秘密鍵
+const SECRET: &[u8] = b"12345678901234567890";
+
+#[test]
+fn base32_matches_rfc4648_vectors() {
+    for (raw, encoded) in [
+        ("f", "MY"),
+        ("fo", "MZXQ"),
+        ("foo", "MZXW6"),
+        ("foob", "MZXW6YQ"),
+        ("fooba", "MZXW6YTB"),
+        ("foobar", "MZXW6YTBOI"),
+    ] {
+        assert_eq!(base32_encode(raw.as_bytes()), encoded);
+        assert_eq!(base32_decode(encoded).unwrap(), raw.as_bytes());
+    }
+    assert_eq!(base32_decode("mzxw6ytboi======").unwrap(), b"foobar");
+    assert_eq!(base32_decode("MZ1W"), None);
+}
+
+#[test]
+fn totp_code_matches_rfc6238_vectors() {
+    // RFC の 8 桁の値の下 6 桁
+    for (time, code) in [
+        (59, 287082),
+        (1111111109, 81804),
+        (1111111111, 50471),
+        (1234567890, 5924),
+        (2000000000, 279037),
+    ] {
+        assert_eq!(totp_code(SECRET, totp_step(time)), code, "t={time}");
+    }
+}
+
+#[test]
+fn verify_totp_allows_one_step_of_clock_skew() {
+    let now = 1111111111;
+    let step = totp_step(now);
+    let code = |step| format!("{:06}", totp_code(SECRET, step));
+
+    assert_eq!(verify_totp(SECRET, &code(step), now), Some(step));
+    assert_eq!(verify_totp(SECRET, &code(step - 1), now), Some(step - 1));
+    assert_eq!(verify_totp(SECRET, &code(step + 1), now), Some(step + 1));
+    assert_eq!(verify_totp(SECRET, &code(step - 2), now), None);
+    assert_eq!(verify_totp(SECRET, "12345", now), None);
+    assert_eq!(verify_totp(SECRET, "abcdef", now), None);
+}
+
+#[test]
+fn otpauth_uri_escapes_label() {
+    assert_eq!(
+        otpauth_uri("memo app", "a+b@example.com", "MZXW6"),
+        "otpauth://totp/memo%20app:a%2Bb%40example.com?secret=MZXW6&issuer=memo%20app\
+         &algorithm=SHA1&digits=6&period=30"
+    );
+}
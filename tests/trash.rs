@@ -0,0 +1,204 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use memo_app::app::trash::{list_trash, purge_note, restore_note};
+use memo_app::domain::model::{Note, NoteSearchHit, TrashedNote, Visibility};
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::user::RepoError;
+use memo_app::service::trash::TrashPurger;
+
+// ---- Mocks ----
+
+// ユーザー 1 のゴミ箱にメモ（id=1）が 1 件ある
+#[derive(Default)]
+struct MockTrashRepo {
+    purged_with: Mutex<Option<i64>>,
+}
+
+fn note() -> Note {
+    Note {
+        id: 1,
+        author_id: 1,
+        workspace_id: None,
+        notebook_id: None,
+        title: "t".into(),
+        content: "c".into(),
+        visibility: Visibility::Private,
+        created_at: 1,
+        updated_at: 1,
+        version: 2,
+        tags: vec![],
+        pinned: false,
+        starred: false,
+        archived_at: None,
+    }
+}
+
+#[async_trait]
+impl NoteRepository for MockTrashRepo {
+    async fn create_note(&self, _user_id: i64, _note: &NewNote<'_>) -> Result<Note, RepoError> {
+        Err(RepoError::Internal)
+    }
+
+    async fn find_by_id(&self, _note_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        _note_id: i64,
+        _user_id: i64,
+        _changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        Ok(None)
+    }
+
+    async fn delete_note(
+        &self,
+        _note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn list_trash(&self, user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        if user_id != 1 {
+            return Ok(vec![]);
+        }
+        Ok(vec![TrashedNote {
+            note: note(),
+            deleted_at: 10,
+        }])
+    }
+
+    async fn restore_note(&self, note_id: i64, user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok((note_id == 1 && user_id == 1).then(note))
+    }
+
+    async fn purge_note(&self, note_id: i64, user_id: i64) -> Result<bool, RepoError> {
+        Ok(note_id == 1 && user_id == 1)
+    }
+
+    async fn purge_trash(&self, retention_secs: i64) -> Result<u64, RepoError> {
+        *self.purged_with.lock().unwrap() = Some(retention_secs);
+        Ok(3)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer(user_id: i64) -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(user_id).unwrap()),
+    )
+}
+
+// ---- Tests ----
+
+#[actix_web::test]
+async fn list_trash_returns_callers_trashed_notes() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockTrashRepo::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(list_trash),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/trash").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::get()
+        .uri("/trash")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let trashed: Vec<TrashedNote> = test::read_body_json(resp).await;
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].note.id, 1);
+    assert_eq!(trashed[0].deleted_at, 10);
+}
+
+#[actix_web::test]
+async fn restore_and_purge_only_own_trash() {
+    let repo: Arc<dyn NoteRepository> = Arc::new(MockTrashRepo::default());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(repo))
+            .app_data(web::Data::new(jwt()))
+            .service(restore_note)
+            .service(purge_note),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/notes/1/restore")
+        .insert_header(bearer(2))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::post()
+        .uri("/notes/1/restore")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let restored: Note = test::read_body_json(resp).await;
+    assert_eq!(restored.id, 1);
+
+    let req = test::TestRequest::delete()
+        .uri("/trash/1")
+        .insert_header(bearer(2))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::delete()
+        .uri("/trash/1")
+        .insert_header(bearer(1))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn purger_passes_retention_to_repository() {
+    let repo = Arc::new(MockTrashRepo::default());
+    let purger = TrashPurger::new(
+        repo.clone(),
+        Duration::from_secs(7 * 24 * 60 * 60),
+        Duration::from_secs(60),
+    );
+
+    assert_eq!(purger.purge_once().await, 3);
+    assert_eq!(*repo.purged_with.lock().unwrap(), Some(7 * 24 * 60 * 60));
+}
@@ -0,0 +1,501 @@
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{App, http::StatusCode, test, web};
+use async_trait::async_trait;
+use memo_app::app::auth::{login, login_2fa};
+use memo_app::app::model::{
+    ConfirmTotpInput, LoginInput, LoginOutput, LoginTwoFactorInput, RecoveryCodesOutput,
+    TwoFactorChallenge,
+};
+use memo_app::app::two_factor::{confirm_totp, enroll_totp};
+use memo_app::domain::model::{RefreshToken, Role, TotpCredential, User, UserTokenPurpose};
+use memo_app::domain::totp::{base32_decode, totp_code, totp_step};
+use memo_app::middleware::auth::token::JwtTokenService;
+use memo_app::repository::token::TokenRepository;
+use memo_app::repository::user::{
+    RepoError, TwoFactorRepository, UserRepository, UserTokenRepository,
+};
+use memo_app::service::auth::{AuthService, AuthServiceError, AuthServiceImpl, LoginOutcome};
+use memo_app::service::session::SessionService;
+use memo_app::service::two_factor::{TwoFactorError, TwoFactorService};
+
+// ---- Mocks ----
+
+struct StoredToken {
+    user_id: i64,
+    purpose: UserTokenPurpose,
+    hash: String,
+    used: bool,
+}
+
+struct StoredRecoveryCode {
+    user_id: i64,
+    hash: String,
+    used: bool,
+}
+
+// メモリ上でユーザー・1 回限りのトークン・TOTP の設定を保持する
+#[derive(Default)]
+struct MockTwoFactorRepo {
+    users: Mutex<Vec<User>>,
+    tokens: Mutex<Vec<StoredToken>>,
+    totp: Mutex<Vec<TotpCredential>>,
+    recovery_codes: Mutex<Vec<StoredRecoveryCode>>,
+}
+
+#[async_trait]
+impl UserRepository for MockTwoFactorRepo {
+    async fn create_user(
+        &self,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<Option<User>, RepoError> {
+        let mut users = self.users.lock().unwrap();
+        if users.iter().any(|u| u.email == email) {
+            return Ok(None);
+        }
+        let user = User {
+            id: users.len() as i64 + 1,
+            email: email.into(),
+            password_hash: password_hash.into(),
+            created_at: 0,
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role: Role::User,
+            disabled_at: None,
+        };
+        users.push(user.clone());
+        Ok(Some(user))
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, RepoError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.email == email).cloned())
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+        let users = self.users.lock().unwrap();
+        Ok(users.iter().find(|u| u.id == user_id).cloned())
+    }
+
+    async fn update_password(
+        &self,
+        _user_id: i64,
+        _password_hash: &str,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn mark_email_verified(&self, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn record_login_failure(&self, user_id: i64) -> Result<i64, RepoError> {
+        let mut users = self.users.lock().unwrap();
+        let user = users.iter_mut().find(|u| u.id == user_id).unwrap();
+        user.failed_login_count += 1;
+        Ok(user.failed_login_count)
+    }
+
+    async fn lock_user_until(&self, user_id: i64, locked_until: i64) -> Result<(), RepoError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(user) = users.iter_mut().find(|u| u.id == user_id) {
+            user.locked_until = Some(locked_until);
+        }
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, user_id: i64) -> Result<(), RepoError> {
+        let mut users = self.users.lock().unwrap();
+        if let Some(user) = users.iter_mut().find(|u| u.id == user_id) {
+            user.failed_login_count = 0;
+            user.locked_until = None;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserTokenRepository for MockTwoFactorRepo {
+    async fn create_user_token(
+        &self,
+        user_id: i64,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<(), RepoError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        for t in tokens
+            .iter_mut()
+            .filter(|t| t.user_id == user_id && t.purpose == purpose)
+        {
+            t.used = true;
+        }
+        tokens.push(StoredToken {
+            user_id,
+            purpose,
+            hash: token_hash.into(),
+            used: false,
+        });
+        Ok(())
+    }
+
+    async fn consume_user_token(
+        &self,
+        purpose: UserTokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<i64>, RepoError> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let Some(t) = tokens
+            .iter_mut()
+            .find(|t| t.hash == token_hash && t.purpose == purpose && !t.used)
+        else {
+            return Ok(None);
+        };
+        t.used = true;
+        Ok(Some(t.user_id))
+    }
+}
+
+#[async_trait]
+impl TwoFactorRepository for MockTwoFactorRepo {
+    async fn start_totp_enrollment(&self, user_id: i64, secret: &str) -> Result<bool, RepoError> {
+        let mut totp = self.totp.lock().unwrap();
+        match totp.iter_mut().find(|t| t.user_id == user_id) {
+            Some(t) if t.enabled_at.is_some() => return Ok(false),
+            Some(t) => t.secret = secret.into(),
+            None => totp.push(TotpCredential {
+                user_id,
+                secret: secret.into(),
+                enabled_at: None,
+                last_used_step: None,
+            }),
+        }
+        Ok(true)
+    }
+
+    async fn find_totp(&self, user_id: i64) -> Result<Option<TotpCredential>, RepoError> {
+        let totp = self.totp.lock().unwrap();
+        Ok(totp.iter().find(|t| t.user_id == user_id).cloned())
+    }
+
+    async fn enable_totp(
+        &self,
+        user_id: i64,
+        step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<bool, RepoError> {
+        let mut totp = self.totp.lock().unwrap();
+        let Some(t) = totp
+            .iter_mut()
+            .find(|t| t.user_id == user_id && t.enabled_at.is_none())
+        else {
+            return Ok(false);
+        };
+        t.enabled_at = Some(1);
+        t.last_used_step = Some(step);
+        let mut codes = self.recovery_codes.lock().unwrap();
+        codes.retain(|c| c.user_id != user_id);
+        codes.extend(recovery_code_hashes.iter().map(|hash| StoredRecoveryCode {
+            user_id,
+            hash: hash.clone(),
+            used: false,
+        }));
+        Ok(true)
+    }
+
+    async fn use_totp_step(&self, user_id: i64, step: i64) -> Result<bool, RepoError> {
+        let mut totp = self.totp.lock().unwrap();
+        let Some(t) = totp.iter_mut().find(|t| {
+            t.user_id == user_id
+                && t.enabled_at.is_some()
+                && t.last_used_step.is_none_or(|last| last < step)
+        }) else {
+            return Ok(false);
+        };
+        t.last_used_step = Some(step);
+        Ok(true)
+    }
+
+    async fn use_recovery_code(&self, user_id: i64, code_hash: &str) -> Result<bool, RepoError> {
+        let mut codes = self.recovery_codes.lock().unwrap();
+        let Some(c) = codes
+            .iter_mut()
+            .find(|c| c.user_id == user_id && c.hash == code_hash && !c.used)
+        else {
+            return Ok(false);
+        };
+        c.used = true;
+        Ok(true)
+    }
+}
+
+// ログイン完了時のリフレッシュトークン発行だけ受け付ける
+struct MockTokenRepo;
+
+#[async_trait]
+impl TokenRepository for MockTokenRepo {
+    async fn create_refresh_token(
+        &self,
+        _user_id: i64,
+        _family_id: &str,
+        _token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn find_refresh_token(
+        &self,
+        _token_hash: &str,
+    ) -> Result<Option<RefreshToken>, RepoError> {
+        Ok(None)
+    }
+
+    async fn mark_refresh_token_used(&self, _id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn revoke_token_family(&self, _family_id: &str) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn revoke_user_refresh_tokens(&self, _user_id: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn revoke_access_token(&self, _jti: &str, _expires_at: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn revoke_user_access_tokens(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(
+        &self,
+        _jti: &str,
+        _user_id: i64,
+        _issued_at: i64,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_expired(&self, _now: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+}
+
+struct Fixture {
+    auth: Arc<dyn AuthService>,
+    two_factor: Arc<TwoFactorService>,
+}
+
+fn fixture() -> Fixture {
+    let repo = Arc::new(MockTwoFactorRepo::default());
+    let two_factor = Arc::new(TwoFactorService::new(
+        repo.clone(),
+        repo.clone(),
+        repo.clone(),
+        "memo-app",
+    ));
+    let auth: Arc<dyn AuthService> =
+        Arc::new(AuthServiceImpl::new(repo).with_two_factor(two_factor.clone()));
+    Fixture { auth, two_factor }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// `otpauth://` URI の秘密鍵で `step` のコードを作る。
+fn code_at(otpauth_uri: &str, step: i64) -> String {
+    let secret = otpauth_uri
+        .split(['?', '&'])
+        .find_map(|p| p.strip_prefix("secret="))
+        .unwrap();
+    format!("{:06}", totp_code(&base32_decode(secret).unwrap(), step))
+}
+
+async fn challenge(auth: &Arc<dyn AuthService>) -> String {
+    match auth.login("a@example.com", "password123").await {
+        Ok(Some(LoginOutcome::TwoFactorRequired { challenge_token })) => challenge_token,
+        other => panic!("expected challenge, got {other:?}"),
+    }
+}
+
+// ---- Tests ----
+
+#[tokio::test]
+async fn enrollment_requires_valid_code_and_returns_recovery_codes() {
+    let f = fixture();
+    let user = f
+        .auth
+        .signup("a@example.com", "password123")
+        .await
+        .unwrap()
+        .unwrap();
+
+    // 確認前はログインにコードは不要
+    assert!(matches!(
+        f.auth.login("a@example.com", "password123").await,
+        Ok(Some(LoginOutcome::Authenticated(_)))
+    ));
+    assert!(matches!(
+        f.two_factor.confirm(user.id, "123456").await,
+        Err(TwoFactorError::NotEnrolled)
+    ));
+
+    let enrollment = f.two_factor.enroll(user.id).await.unwrap();
+    assert!(
+        enrollment
+            .otpauth_uri
+            .starts_with("otpauth://totp/memo-app:a%40example.com?secret=")
+    );
+    let step = totp_step(now());
+    let wrong = format!(
+        "{:06}",
+        (code_at(&enrollment.otpauth_uri, step)
+            .parse::<u32>()
+            .unwrap()
+            + 1)
+            % 1_000_000
+    );
+    assert!(matches!(
+        f.two_factor.confirm(user.id, &wrong).await,
+        Err(TwoFactorError::InvalidCode)
+    ));
+    assert!(!f.two_factor.is_enabled(user.id).await.unwrap());
+
+    let codes = f
+        .two_factor
+        .confirm(user.id, &code_at(&enrollment.otpauth_uri, step))
+        .await
+        .unwrap();
+    assert_eq!(codes.len(), 10);
+    assert!(f.two_factor.is_enabled(user.id).await.unwrap());
+    assert!(matches!(
+        f.two_factor.enroll(user.id).await,
+        Err(TwoFactorError::AlreadyEnabled)
+    ));
+}
+
+#[tokio::test]
+async fn login_requires_second_factor_once_enabled() {
+    let f = fixture();
+    let user = f
+        .auth
+        .signup("a@example.com", "password123")
+        .await
+        .unwrap()
+        .unwrap();
+    let enrollment = f.two_factor.enroll(user.id).await.unwrap();
+    let step = totp_step(now());
+    let recovery_codes = f
+        .two_factor
+        .confirm(user.id, &code_at(&enrollment.otpauth_uri, step))
+        .await
+        .unwrap();
+
+    // 確認に使ったコードは再利用できず、チャレンジトークンも使えなくなる
+    let token = challenge(&f.auth).await;
+    let code = code_at(&enrollment.otpauth_uri, step);
+    assert!(matches!(
+        f.auth.login_2fa(&token, &code).await,
+        Err(AuthServiceError::InvalidCredentials)
+    ));
+    let next = code_at(&enrollment.otpauth_uri, step + 1);
+    assert!(matches!(
+        f.auth.login_2fa(&token, &next).await,
+        Err(AuthServiceError::InvalidCredentials)
+    ));
+
+    let token = challenge(&f.auth).await;
+    assert_eq!(f.auth.login_2fa(&token, &next).await.unwrap().id, user.id);
+
+    // リカバリーコードは大文字・区切りなしでも通り、1 回限り
+    let recovery = recovery_codes[0].replace('-', "").to_uppercase();
+    let token = challenge(&f.auth).await;
+    assert_eq!(
+        f.auth.login_2fa(&token, &recovery).await.unwrap().id,
+        user.id
+    );
+    let token = challenge(&f.auth).await;
+    assert!(f.auth.login_2fa(&token, &recovery_codes[0]).await.is_err());
+}
+
+#[actix_web::test]
+async fn two_step_login_over_http() {
+    let f = fixture();
+    let user = f
+        .auth
+        .signup("a@example.com", "password123")
+        .await
+        .unwrap()
+        .unwrap();
+    let jwt = JwtTokenService::from_secret(b"secret", 3600);
+    let bearer = format!("Bearer {}", jwt.generate(user.id).unwrap());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(f.auth.clone()))
+            .app_data(web::Data::from(f.two_factor.clone()))
+            .app_data(web::Data::new(jwt))
+            .app_data(web::Data::new(SessionService::new(
+                Arc::new(MockTokenRepo),
+                3600,
+            )))
+            .service(login)
+            .service(login_2fa)
+            .service(enroll_totp)
+            .service(confirm_totp),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/me/2fa/totp")
+        .insert_header(("Authorization", bearer.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let uri = body["otpauth_uri"].as_str().unwrap().to_string();
+
+    let step = totp_step(now());
+    let req = test::TestRequest::post()
+        .uri("/me/2fa/totp/confirm")
+        .insert_header(("Authorization", bearer.clone()))
+        .set_json(ConfirmTotpInput {
+            code: code_at(&uri, step),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let _: RecoveryCodesOutput = test::read_body_json(resp).await;
+
+    let req = test::TestRequest::post()
+        .uri("/auth/login")
+        .set_json(LoginInput {
+            email: "a@example.com".into(),
+            password: "password123".into(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let TwoFactorChallenge { challenge_token } = test::read_body_json(resp).await;
+
+    let req = test::TestRequest::post()
+        .uri("/auth/login/2fa")
+        .set_json(LoginTwoFactorInput {
+            challenge_token,
+            code: code_at(&uri, step + 1),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let _: LoginOutput = test::read_body_json(resp).await;
+}
@@ -0,0 +1,704 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::{App, http::StatusCode, web};
+use async_trait::async_trait;
+use memo_app::app::model::{AcceptedInvitation, CreateNoteInput};
+use memo_app::app::notes::{create_note, delete_note, get_note, update_note};
+use memo_app::app::workspaces::{
+    accept_invitation, invite_member, list_members, list_workspaces, remove_member, set_member_role,
+};
+use memo_app::domain::model::{
+    Note, NoteSearchHit, Role, TrashedNote, User, Visibility, Workspace, WorkspaceInvitation,
+    WorkspaceMember, WorkspaceMembership, WorkspaceRole,
+};
+use memo_app::domain::policy::{NoteAction, can_access_note, lists_note};
+use memo_app::middleware::auth::token::{JwtTokenService, random_token};
+use memo_app::repository::note::{
+    NewNote, NoteChanges, NoteListQuery, NoteRepository, UpdatedNote,
+};
+use memo_app::repository::user::{RepoError, UserRepository};
+use memo_app::repository::workspace::WorkspaceRepository;
+use memo_app::service::mailer::OutboxMailer;
+use memo_app::service::workspace::WorkspaceService;
+
+// ---- Mocks ----
+
+const WORKSPACE: i64 = 10;
+
+// ユーザー 1〜4（a〜d@example.com）
+struct MockUserRepo;
+
+#[async_trait]
+impl UserRepository for MockUserRepo {
+    async fn create_user(
+        &self,
+        _email: &str,
+        _password_hash: &str,
+    ) -> Result<Option<User>, RepoError> {
+        Ok(None)
+    }
+
+    async fn find_by_email(&self, _email: &str) -> Result<Option<User>, RepoError> {
+        Ok(None)
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, RepoError> {
+        let email = match user_id {
+            1 => "a@example.com",
+            2 => "b@example.com",
+            3 => "c@example.com",
+            4 => "d@example.com",
+            _ => return Ok(None),
+        };
+        Ok(Some(User {
+            id: user_id,
+            email: email.into(),
+            password_hash: "!".into(),
+            created_at: 0,
+            email_verified_at: None,
+            failed_login_count: 0,
+            locked_until: None,
+            role: Role::User,
+            disabled_at: None,
+        }))
+    }
+
+    async fn update_password(
+        &self,
+        _user_id: i64,
+        _password_hash: &str,
+    ) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn mark_email_verified(&self, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn record_login_failure(&self, _user_id: i64) -> Result<i64, RepoError> {
+        Ok(1)
+    }
+
+    async fn lock_user_until(&self, _user_id: i64, _locked_until: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+
+    async fn reset_login_failures(&self, _user_id: i64) -> Result<(), RepoError> {
+        Ok(())
+    }
+}
+
+struct Invitation {
+    token_hash: String,
+    workspace_id: i64,
+    email: String,
+    role: WorkspaceRole,
+    accepted: bool,
+}
+
+// ワークスペース 10 にオーナー（1）・編集者（2）・閲覧者（3）がいる。ユーザー 4 はメンバーではない
+struct MockWorkspaceRepo {
+    members: Mutex<Vec<(i64, i64, WorkspaceRole)>>,
+    invitations: Mutex<Vec<Invitation>>,
+}
+
+impl MockWorkspaceRepo {
+    fn new() -> Self {
+        Self {
+            members: Mutex::new(vec![
+                (WORKSPACE, 1, WorkspaceRole::Owner),
+                (WORKSPACE, 2, WorkspaceRole::Editor),
+                (WORKSPACE, 3, WorkspaceRole::Viewer),
+            ]),
+            invitations: Mutex::new(vec![]),
+        }
+    }
+
+    fn is_last_owner(
+        members: &[(i64, i64, WorkspaceRole)],
+        workspace_id: i64,
+        user_id: i64,
+    ) -> bool {
+        let owners: Vec<i64> = members
+            .iter()
+            .filter(|(w, _, role)| *w == workspace_id && *role == WorkspaceRole::Owner)
+            .map(|(_, u, _)| *u)
+            .collect();
+        owners == [user_id]
+    }
+}
+
+#[async_trait]
+impl WorkspaceRepository for MockWorkspaceRepo {
+    async fn create_workspace(&self, owner_id: i64, name: &str) -> Result<Workspace, RepoError> {
+        self.members
+            .lock()
+            .unwrap()
+            .push((11, owner_id, WorkspaceRole::Owner));
+        Ok(Workspace {
+            id: 11,
+            name: name.into(),
+            created_at: 0,
+        })
+    }
+
+    async fn list_workspaces(&self, user_id: i64) -> Result<Vec<WorkspaceMembership>, RepoError> {
+        let members = self.members.lock().unwrap();
+        Ok(members
+            .iter()
+            .filter(|(_, u, _)| *u == user_id)
+            .map(|(w, _, role)| WorkspaceMembership {
+                workspace: Workspace {
+                    id: *w,
+                    name: "team".into(),
+                    created_at: 0,
+                },
+                role: *role,
+            })
+            .collect())
+    }
+
+    async fn member_role(
+        &self,
+        workspace_id: i64,
+        user_id: i64,
+    ) -> Result<Option<WorkspaceRole>, RepoError> {
+        let members = self.members.lock().unwrap();
+        Ok(members
+            .iter()
+            .find(|(w, u, _)| *w == workspace_id && *u == user_id)
+            .map(|(_, _, role)| *role))
+    }
+
+    async fn list_members(&self, workspace_id: i64) -> Result<Vec<WorkspaceMember>, RepoError> {
+        let members = self.members.lock().unwrap();
+        Ok(members
+            .iter()
+            .filter(|(w, _, _)| *w == workspace_id)
+            .map(|(_, u, role)| WorkspaceMember {
+                user_id: *u,
+                email: format!("user{u}@example.com"),
+                role: *role,
+                joined_at: 0,
+            })
+            .collect())
+    }
+
+    async fn set_member_role(
+        &self,
+        workspace_id: i64,
+        user_id: i64,
+        role: WorkspaceRole,
+    ) -> Result<bool, RepoError> {
+        let mut members = self.members.lock().unwrap();
+        if role != WorkspaceRole::Owner && Self::is_last_owner(&members, workspace_id, user_id) {
+            return Err(RepoError::Conflict);
+        }
+        match members
+            .iter_mut()
+            .find(|(w, u, _)| *w == workspace_id && *u == user_id)
+        {
+            Some(member) => {
+                member.2 = role;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn remove_member(&self, workspace_id: i64, user_id: i64) -> Result<bool, RepoError> {
+        let mut members = self.members.lock().unwrap();
+        if Self::is_last_owner(&members, workspace_id, user_id) {
+            return Err(RepoError::Conflict);
+        }
+        let before = members.len();
+        members.retain(|(w, u, _)| !(*w == workspace_id && *u == user_id));
+        Ok(members.len() < before)
+    }
+
+    async fn create_invitation(
+        &self,
+        workspace_id: i64,
+        email: &str,
+        role: WorkspaceRole,
+        invited_by: i64,
+        token_hash: &str,
+        _ttl_secs: i64,
+    ) -> Result<WorkspaceInvitation, RepoError> {
+        self.invitations.lock().unwrap().push(Invitation {
+            token_hash: token_hash.into(),
+            workspace_id,
+            email: email.into(),
+            role,
+            accepted: false,
+        });
+        Ok(WorkspaceInvitation {
+            id: 1,
+            workspace_id,
+            email: email.into(),
+            role,
+            invited_by: Some(invited_by),
+            created_at: 0,
+            expires_at: 3600,
+        })
+    }
+
+    async fn accept_invitation(
+        &self,
+        token_hash: &str,
+        user_id: i64,
+        email: &str,
+    ) -> Result<Option<i64>, RepoError> {
+        let mut invitations = self.invitations.lock().unwrap();
+        let Some(invitation) = invitations.iter_mut().find(|i| {
+            i.token_hash == token_hash && i.email.eq_ignore_ascii_case(email) && !i.accepted
+        }) else {
+            return Ok(None);
+        };
+        invitation.accepted = true;
+        self.members
+            .lock()
+            .unwrap()
+            .push((invitation.workspace_id, user_id, invitation.role));
+        Ok(Some(invitation.workspace_id))
+    }
+}
+
+// ワークスペース 10 の private なメモ: 100（作成者 2）, 101（作成者 1）
+struct MockNoteRepo {
+    notes: Mutex<Vec<Note>>,
+    deleted: Mutex<Vec<i64>>,
+}
+
+impl MockNoteRepo {
+    fn new() -> Self {
+        let note = |id, author_id| Note {
+            id,
+            author_id,
+            workspace_id: Some(WORKSPACE),
+            notebook_id: None,
+            title: "t".into(),
+            content: "c".into(),
+            visibility: Visibility::Private,
+            created_at: 1,
+            updated_at: 1,
+            version: 1,
+            tags: vec![],
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        };
+        Self {
+            notes: Mutex::new(vec![note(100, 2), note(101, 1)]),
+            deleted: Mutex::new(vec![]),
+        }
+    }
+}
+
+#[async_trait]
+impl NoteRepository for MockNoteRepo {
+    async fn create_note(&self, user_id: i64, note: &NewNote<'_>) -> Result<Note, RepoError> {
+        let created = Note {
+            id: 200,
+            author_id: user_id,
+            workspace_id: note.workspace_id,
+            notebook_id: note.notebook_id,
+            title: note.title.into(),
+            content: note.content.into(),
+            visibility: note.visibility,
+            created_at: 1,
+            updated_at: 1,
+            version: 1,
+            tags: note.tags.to_vec(),
+            pinned: false,
+            starred: false,
+            archived_at: None,
+        };
+        self.notes.lock().unwrap().push(created.clone());
+        Ok(created)
+    }
+
+    async fn find_by_id(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        let notes = self.notes.lock().unwrap();
+        Ok(notes.iter().find(|n| n.id == note_id).cloned())
+    }
+
+    async fn find_including_trashed(&self, note_id: i64) -> Result<Option<Note>, RepoError> {
+        self.find_by_id(note_id).await
+    }
+
+    async fn update_note(
+        &self,
+        note_id: i64,
+        _user_id: i64,
+        changes: &NoteChanges<'_>,
+    ) -> Result<Option<UpdatedNote>, RepoError> {
+        let mut notes = self.notes.lock().unwrap();
+        let Some(note) = notes.iter_mut().find(|n| n.id == note_id) else {
+            return Ok(None);
+        };
+        if let Some(content) = changes.content {
+            note.content = content.into();
+        }
+        note.version += 1;
+        Ok(Some(note.clone().into()))
+    }
+
+    async fn delete_note(
+        &self,
+        note_id: i64,
+        _expected_version: Option<i64>,
+    ) -> Result<bool, RepoError> {
+        self.deleted.lock().unwrap().push(note_id);
+        Ok(true)
+    }
+
+    async fn list_notes(&self, _query: &NoteListQuery) -> Result<Vec<Note>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn list_trash(&self, _user_id: i64) -> Result<Vec<TrashedNote>, RepoError> {
+        Ok(vec![])
+    }
+
+    async fn restore_note(&self, _note_id: i64, _user_id: i64) -> Result<Option<Note>, RepoError> {
+        Ok(None)
+    }
+
+    async fn purge_note(&self, _note_id: i64, _user_id: i64) -> Result<bool, RepoError> {
+        Ok(false)
+    }
+
+    async fn purge_trash(&self, _retention_secs: i64) -> Result<u64, RepoError> {
+        Ok(0)
+    }
+
+    async fn search(
+        &self,
+        _viewer: Option<i64>,
+        _query: &str,
+        _limit: i64,
+    ) -> Result<Vec<NoteSearchHit>, RepoError> {
+        Ok(vec![])
+    }
+}
+
+struct Fixture {
+    notes: Arc<MockNoteRepo>,
+    workspaces: Arc<MockWorkspaceRepo>,
+    outbox: Arc<OutboxMailer>,
+    service: web::Data<WorkspaceService>,
+}
+
+fn fixture() -> Fixture {
+    let notes = Arc::new(MockNoteRepo::new());
+    let workspaces = Arc::new(MockWorkspaceRepo::new());
+    let outbox = Arc::new(OutboxMailer::new(
+        std::env::temp_dir().join(format!("memo-outbox-{}", random_token(8))),
+    ));
+    let service = web::Data::new(WorkspaceService::new(
+        workspaces.clone(),
+        Arc::new(MockUserRepo),
+        outbox.clone(),
+    ));
+    Fixture {
+        notes,
+        workspaces,
+        outbox,
+        service,
+    }
+}
+
+fn jwt() -> JwtTokenService {
+    JwtTokenService::from_secret(b"test-secret", 3600)
+}
+
+fn bearer(user_id: i64) -> (&'static str, String) {
+    (
+        "Authorization",
+        format!("Bearer {}", jwt().generate(user_id).unwrap()),
+    )
+}
+
+macro_rules! workspace_app {
+    ($f:expr) => {{
+        let notes: Arc<dyn NoteRepository> = $f.notes.clone();
+        let workspaces: Arc<dyn WorkspaceRepository> = $f.workspaces.clone();
+        actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(notes))
+                .app_data(web::Data::new(workspaces))
+                .app_data(web::Data::new(jwt()))
+                .app_data($f.service.clone())
+                .service(get_note)
+                .service(create_note)
+                .service(update_note)
+                .service(delete_note)
+                .service(accept_invitation)
+                .service(list_workspaces)
+                .service(list_members)
+                .service(set_member_role)
+                .service(remove_member)
+                .service(invite_member),
+        )
+        .await
+    }};
+}
+
+fn note(author_id: i64, workspace_id: Option<i64>, visibility: Visibility) -> Note {
+    Note {
+        id: 1,
+        author_id,
+        workspace_id,
+        notebook_id: None,
+        title: "t".into(),
+        content: "c".into(),
+        visibility,
+        created_at: 1,
+        updated_at: 1,
+        version: 1,
+        tags: vec![],
+        pinned: false,
+        starred: false,
+        archived_at: None,
+    }
+}
+
+// ---- Tests ----
+
+#[test]
+fn policy_for_personal_notes() {
+    use NoteAction::*;
+    let private = note(1, None, Visibility::Private);
+    let public = note(1, None, Visibility::Public);
+
+    for action in [Read, Update, Delete, Share] {
+        assert!(can_access_note(&private, Some(1), None, None, action));
+        assert!(!can_access_note(&private, Some(2), None, None, action));
+    }
+    assert!(can_access_note(&public, None, None, None, Read));
+    assert!(!can_access_note(&public, Some(2), None, None, Update));
+    // 個人のメモには役割は関係しない
+    assert!(!can_access_note(
+        &private,
+        Some(2),
+        Some(WorkspaceRole::Owner),
+        None,
+        Read
+    ));
+}
+
+#[test]
+fn policy_for_workspace_notes() {
+    use NoteAction::*;
+    use WorkspaceRole::*;
+    let private = note(2, Some(WORKSPACE), Visibility::Private);
+    let unlisted = note(2, Some(WORKSPACE), Visibility::Unlisted);
+
+    let allowed =
+        |viewer, role, action| can_access_note(&private, Some(viewer), role, None, action);
+    assert!(allowed(3, Some(Viewer), Read));
+    assert!(!allowed(3, Some(Viewer), Update));
+    assert!(allowed(4, Some(Editor), Update));
+    assert!(!allowed(4, Some(Editor), Delete)); // 他人のメモは削除できない
+    assert!(allowed(2, Some(Editor), Delete));
+    assert!(allowed(1, Some(Owner), Delete));
+    // メンバーでなくなった作成者は編集できない
+    assert!(!allowed(2, None, Read));
+    assert!(!allowed(2, None, Update));
+
+    assert!(can_access_note(&unlisted, None, None, None, Read));
+    assert!(!can_access_note(&unlisted, Some(4), None, None, Update));
+}
+
+#[test]
+fn listed_notes_follow_visibility_and_membership() {
+    use Visibility::*;
+    let personal = note(1, None, Private);
+    let shared = note(2, Some(WORKSPACE), Private);
+
+    assert!(lists_note(&personal, Private, 1, None));
+    assert!(!lists_note(&personal, Private, 2, None));
+    // `unlisted` は閲覧できても一覧には出ない
+    assert!(!lists_note(&personal, Unlisted, 2, None));
+    assert!(lists_note(&personal, Public, 2, None));
+    assert!(lists_note(&shared, Private, 3, Some(WorkspaceRole::Viewer)));
+    // メンバーでなくなった作成者の一覧には出ない
+    assert!(!lists_note(&shared, Private, 2, None));
+}
+
+#[actix_web::test]
+async fn workspace_note_access_follows_member_roles() {
+    let f = fixture();
+    let app = workspace_app!(f);
+    let get = |user_id| {
+        actix_web::test::TestRequest::get()
+            .uri("/notes/100")
+            .insert_header(bearer(user_id))
+            .to_request()
+    };
+    let put = |user_id| {
+        actix_web::test::TestRequest::put()
+            .uri("/notes/100")
+            .insert_header(bearer(user_id))
+            .set_json(serde_json::json!({ "content": "edited" }))
+            .to_request()
+    };
+    let delete = |user_id, note_id| {
+        actix_web::test::TestRequest::delete()
+            .uri(&format!("/notes/{note_id}"))
+            .insert_header(bearer(user_id))
+            .to_request()
+    };
+
+    let resp = actix_web::test::call_service(&app, get(3)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = actix_web::test::call_service(&app, get(4)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = actix_web::test::call_service(&app, put(3)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = actix_web::test::call_service(&app, put(4)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = actix_web::test::call_service(&app, put(1)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let updated: Note = actix_web::test::read_body_json(resp).await;
+    assert_eq!(updated.content, "edited");
+
+    // 編集者は他人のメモを削除できず、オーナーは削除できる
+    let resp = actix_web::test::call_service(&app, delete(2, 101)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = actix_web::test::call_service(&app, delete(2, 100)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = actix_web::test::call_service(&app, delete(1, 101)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    assert_eq!(*f.notes.deleted.lock().unwrap(), vec![100, 101]);
+}
+
+#[actix_web::test]
+async fn creating_workspace_notes_requires_editor_role() {
+    let f = fixture();
+    let app = workspace_app!(f);
+    let post = |user_id| {
+        actix_web::test::TestRequest::post()
+            .uri("/notes")
+            .insert_header(bearer(user_id))
+            .set_json(CreateNoteInput {
+                title: "t".into(),
+                content: "c".into(),
+                visibility: Visibility::Private,
+                tags: vec![],
+                workspace_id: Some(WORKSPACE),
+                notebook_id: None,
+            })
+            .to_request()
+    };
+
+    let resp = actix_web::test::call_service(&app, post(3)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = actix_web::test::call_service(&app, post(4)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = actix_web::test::call_service(&app, post(2)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created: Note = actix_web::test::read_body_json(resp).await;
+    assert_eq!(created.workspace_id, Some(WORKSPACE));
+    assert_eq!(created.author_id, 2);
+}
+
+#[actix_web::test]
+async fn owner_invites_by_email_and_invitee_accepts() {
+    let f = fixture();
+    let app = workspace_app!(f);
+    let invite = |user_id| {
+        actix_web::test::TestRequest::post()
+            .uri("/workspaces/10/invitations")
+            .insert_header(bearer(user_id))
+            .set_json(serde_json::json!({ "email": "d@example.com", "role": "editor" }))
+            .to_request()
+    };
+    let accept = |user_id, token: &str| {
+        actix_web::test::TestRequest::post()
+            .uri("/workspaces/invitations/accept")
+            .insert_header(bearer(user_id))
+            .set_json(serde_json::json!({ "token": token }))
+            .to_request()
+    };
+
+    let resp = actix_web::test::call_service(&app, invite(2)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = actix_web::test::call_service(&app, invite(1)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let sent = f.outbox.sent().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "d@example.com");
+    let token = sent[0].body.trim_end().lines().last().unwrap().to_string();
+
+    // 宛先以外のユーザーは承諾できない
+    let resp = actix_web::test::call_service(&app, accept(2, &token)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = actix_web::test::call_service(&app, accept(4, &token)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let accepted: AcceptedInvitation = actix_web::test::read_body_json(resp).await;
+    assert_eq!(accepted.workspace_id, WORKSPACE);
+    let resp = actix_web::test::call_service(&app, accept(4, &token)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = actix_web::test::TestRequest::get()
+        .uri("/workspaces")
+        .insert_header(bearer(4))
+        .to_request();
+    let memberships: Vec<WorkspaceMembership> =
+        actix_web::test::read_body_json(actix_web::test::call_service(&app, req).await).await;
+    assert_eq!(memberships.len(), 1);
+    assert_eq!(memberships[0].workspace.id, WORKSPACE);
+    assert_eq!(memberships[0].role, WorkspaceRole::Editor);
+}
+
+#[actix_web::test]
+async fn workspace_always_keeps_an_owner() {
+    let f = fixture();
+    let app = workspace_app!(f);
+    let set_role = |user_id, member_id, role: &str| {
+        actix_web::test::TestRequest::put()
+            .uri(&format!("/workspaces/10/members/{member_id}"))
+            .insert_header(bearer(user_id))
+            .set_json(serde_json::json!({ "role": role }))
+            .to_request()
+    };
+    let remove = |user_id, member_id| {
+        actix_web::test::TestRequest::delete()
+            .uri(&format!("/workspaces/10/members/{member_id}"))
+            .insert_header(bearer(user_id))
+            .to_request()
+    };
+
+    let resp = actix_web::test::call_service(&app, remove(1, 1)).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let resp = actix_web::test::call_service(&app, set_role(1, 1, "editor")).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let resp = actix_web::test::call_service(&app, set_role(2, 3, "editor")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // オーナーを引き継げば抜けられる
+    let resp = actix_web::test::call_service(&app, set_role(1, 2, "owner")).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = actix_web::test::call_service(&app, remove(1, 1)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    // 閲覧者は他人を外せないが、自分は抜けられる
+    let resp = actix_web::test::call_service(&app, remove(3, 2)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = actix_web::test::call_service(&app, remove(3, 3)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = actix_web::test::TestRequest::get()
+        .uri("/workspaces/10/members")
+        .insert_header(bearer(3))
+        .to_request();
+    let resp = actix_web::test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        *f.workspaces.members.lock().unwrap(),
+        vec![(WORKSPACE, 2, WorkspaceRole::Owner)]
+    );
+}